@@ -0,0 +1,81 @@
+// Counting allocations and peak heap use, so that batch designs which trade
+// memory for speed can be compared on both.
+//
+// Including this module makes `Counting` the bench's global allocator.  It
+// hands everything on to the system allocator, counting each allocation and
+// reallocation and keeping track of the bytes live at once.  `measure` runs
+// a closure and returns how many allocations it made and how far above what
+// was already live the heap rose meanwhile.  The counts are global, so they
+// also cover any other threads allocating at the same time, which for a
+// bench split across threads is what's wanted.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+pub struct Counting;
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grew(bytes: usize) {
+    let live: usize = LIVE.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    PEAK.fetch_max(live, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr: *mut u8 = System.alloc(layout);
+
+        if !ptr.is_null() {
+            grew(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new: *mut u8 = System.realloc(ptr, layout, new_size);
+
+        if !new.is_null() {
+            LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+            grew(new_size);
+        }
+        new
+    }
+}
+
+/// What a closure allocated while it ran.
+#[derive(Copy, Clone, Debug)]
+pub struct Footprint {
+    /// Allocations and reallocations made.
+    pub allocations: usize,
+    /// The most bytes live at once, beyond those live when it started.
+    pub peak_bytes: usize,
+}
+
+/// Run `f`, returning what it returned and what it allocated.
+pub fn measure<T, F: FnOnce() -> T>(f: F) -> (T, Footprint) {
+    let live: usize = LIVE.load(Ordering::Relaxed);
+    let allocations: usize = ALLOCATIONS.load(Ordering::Relaxed);
+
+    PEAK.store(live, Ordering::Relaxed);
+    let result: T = f();
+
+    (result, Footprint {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        peak_bytes: PEAK.load(Ordering::Relaxed).saturating_sub(live),
+    })
+}
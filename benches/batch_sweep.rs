@@ -0,0 +1,195 @@
+// Per-item cost of batch validation and verification, across batch sizes.
+//
+//    cargo bench --bench batch_sweep [-- [--json FILE] [--label TEXT]]
+//
+// prints one row per batch size, giving the median nanoseconds spent per key
+// or per signature by:
+//
+//    validate        traits::validate_batch on distinct keys
+//    deduplicated    batch::validate_deduplicated on the same keys
+//    verify          ed25519::verify, once per signature
+//    expanded        ExpandedPublicKey::new for the batch, then its verify,
+//                    which is what a batch under one key should cost
+//
+// Each cell is the median of five samples, each timed over at least 1024
+// items, so that small batches aren't dominated by timer noise.  A second
+// table gives the allocations each strategy makes per item, and the peak heap
+// use above the baseline, over one more run of a single batch, outside the
+// timing.  With `--json`, the same medians, in cycles as well, and the
+// allocation counts are also written to FILE as described in `report`.
+
+extern crate curve25519_dalek;
+extern crate tor22006;
+
+mod alloc;
+mod report;
+
+use std::env;
+use std::process;
+use std::time::Duration;
+use std::time::Instant;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::scalar::Scalar;
+
+use tor22006::batch::validate_deduplicated;
+use tor22006::ed25519::verify;
+use tor22006::ed25519::ExpandedPublicKey;
+use tor22006::key::CheckedKey;
+use tor22006::key::UncheckedKey;
+use tor22006::policy::KeyPolicy;
+use tor22006::policy::VerificationProfile;
+use tor22006::traits::validate_batch;
+
+use alloc::Footprint;
+use report::Measurement;
+use report::Report;
+
+static USAGE: &str = "usage: batch_sweep [--json FILE] [--label TEXT]";
+
+static SIZES: [usize; 5] = [1, 8, 64, 512, 4096];
+
+// The median nanoseconds and cycles per item, and what one batch allocated.
+type Cost = (f64, Option<f64>, Footprint);
+
+// RFC 8032 §7.1, test 1.
+static PUBLIC_KEY: [u8; 32] = [
+    0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7,
+    0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+    0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+    0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a ];
+static SIGNATURE: [u8; 64] = [
+    0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+    0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+    0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+    0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+    0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+    0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+    0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+    0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b ];
+
+// Run `f`, which handles a batch of `size` items, until at least 1024 items
+// have been handled, five times over, and return the median nanoseconds and
+// cycles taken per item, and what a single batch allocated.
+fn per_item<F: FnMut() -> usize>(size: usize, mut f: F) -> Cost {
+    let rounds: usize = 1024_usize.div_ceil(size);
+    let mut ns: Vec<f64> = Vec::new();
+    let mut cycles: Vec<f64> = Vec::new();
+    let (_, footprint): (usize, Footprint) = alloc::measure(&mut f);
+
+    for _ in 0..5 {
+        let start: Instant = Instant::now();
+        let start_cycles: Option<u64> = report::cycles();
+        let mut accepted: usize = 0;
+
+        for _ in 0..rounds {
+            accepted += f();
+        }
+        let elapsed: Duration = start.elapsed();
+        if let (Some(start), Some(end)) = (start_cycles, report::cycles()) {
+            cycles.push((end - start) as f64 / (rounds * size) as f64);
+        }
+
+        // Every item in these batches is valid.
+        assert_eq!(accepted, rounds * size);
+        ns.push((elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64) / (rounds * size) as f64);
+    }
+
+    (report::median(&mut ns), match cycles.is_empty() {
+        true  => None,
+        false => Some(report::median(&mut cycles)),
+    }, footprint)
+}
+
+fn parse_args() -> Option<(Option<String>, Option<String>)> {
+    let mut json: Option<String> = None;
+    let mut label: Option<String> = None;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json"  => json = Some(args.next()?),
+            "--label" => label = Some(args.next()?),
+            // Passed by `cargo bench` to every bench target.
+            "--bench" => {},
+            _         => return None,
+        }
+    }
+    Some((json, label))
+}
+
+fn main() {
+    let (json, label): (Option<String>, Option<String>) = match parse_args() {
+        Some(args) => args,
+        None       => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        },
+    };
+    let mut report: Report = Report::new("batch_sweep", label);
+
+    let keys: Vec<CompressedEdwardsY> = (1..SIZES[SIZES.len() - 1] as u64 + 1)
+        .map(|i| (&Scalar::from_u64(i) * &constants::ED25519_BASEPOINT).compress_edwards())
+        .collect();
+    let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+    let checked: CheckedKey<CompressedEdwardsY> = UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap();
+    let mut footprints: Vec<(usize, [Footprint; 4])> = Vec::new();
+
+    println!("{:>6} {:>14} {:>14} {:>14} {:>14}", "batch", "validate", "deduplicated", "verify", "expanded");
+
+    for &size in SIZES.iter() {
+        let batch: &[CompressedEdwardsY] = &keys[..size];
+
+        let cells: [(&'static str, Cost); 4] = [
+            ("validate", per_item(size, || {
+                validate_batch(batch).iter().filter(|p| p.is_some()).count()
+            })),
+            ("deduplicated", per_item(size, || {
+                validate_deduplicated(batch).iter().filter(|p| p.is_some()).count()
+            })),
+            ("verify", per_item(size, || {
+                (0..size).filter(|_| verify(&key, b"", &SIGNATURE, VerificationProfile::Strict)).count()
+            })),
+            ("expanded", per_item(size, || {
+                let expanded: ExpandedPublicKey = ExpandedPublicKey::new(&checked);
+
+                (0..size).filter(|_| expanded.verify(b"", &SIGNATURE, VerificationProfile::Strict)).count()
+            })),
+        ];
+
+        println!("{:>6} {:>14.0} {:>14.0} {:>14.0} {:>14.0}",
+                 size, (cells[0].1).0, (cells[1].1).0, (cells[2].1).0, (cells[3].1).0);
+        footprints.push((size, [(cells[0].1).2, (cells[1].1).2, (cells[2].1).2, (cells[3].1).2]));
+
+        for &(strategy, (ns, cycles, footprint)) in cells.iter() {
+            report.push(Measurement {
+                strategy: strategy.to_string(),
+                batch: Some(size),
+                ns_per_item: ns,
+                cycles_per_item: cycles,
+                allocations_per_item: Some(footprint.allocations as f64 / size as f64),
+                peak_bytes: Some(footprint.peak_bytes),
+            });
+        }
+    }
+
+    println!();
+    println!("{:>6} {:>14} {:>14} {:>14} {:>14}  (allocations per item / peak bytes per batch)",
+             "batch", "validate", "deduplicated", "verify", "expanded");
+
+    for &(size, ref footprints) in footprints.iter() {
+        let cells: Vec<String> = footprints.iter()
+            .map(|footprint| format!("{:.2}/{}", footprint.allocations as f64 / size as f64, footprint.peak_bytes))
+            .collect();
+
+        println!("{:>6} {:>14} {:>14} {:>14} {:>14}", size, cells[0], cells[1], cells[2], cells[3]);
+    }
+
+    if let Some(path) = json {
+        if let Err(e) = report.write(&path) {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+// Throughput and accuracy of every ed25519 validator over a million keys.
+//
+//    cargo bench --bench million_keys [-- [--keys N] [--threads N] [--json FILE] [--label TEXT]]
+//
+// The keys are synthetic, in fixed proportions out of every twenty:
+//
+//    16  valid: consecutive multiples of the basepoint
+//     2  torsioned: one of those plus a point of order 2, 4, or 8
+//     1  small-order: a point of E[8] other than the identity
+//     1  not on the curve: an encoding which fails to decompress
+//
+// so only the valid ones should be accepted, under either policy.  Each
+// validator is run over every key, split across the threads, and the report
+// gives its throughput, how many keys it got wrong each way, and how many
+// allocations it made per key and how far the heap rose while it ran, which
+// includes the few the threads themselves take.  With `--json`, the
+// throughput is also written to FILE as described in `report`, as
+// wall-clock time per key over the single run, with the allocation counts.
+
+extern crate curve25519_dalek;
+extern crate tor22006;
+
+mod alloc;
+mod report;
+
+use std::env;
+use std::process;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use tor22006::dual::DualValidator;
+use tor22006::hardened::validate_hardened;
+use tor22006::key::UncheckedKey;
+use tor22006::mult_by_cofactor_and_validate;
+use tor22006::policy::KeyPolicy;
+use tor22006::traits::PrimeOrderEncoding;
+
+use alloc::Footprint;
+use report::Measurement;
+use report::Report;
+
+static USAGE: &str = "usage: million_keys [--keys N] [--threads N] [--json FILE] [--label TEXT]";
+
+struct Args {
+    keys: usize,
+    threads: usize,
+    json: Option<String>,
+    label: Option<String>,
+}
+
+// Whether a key passes.
+type Validator = fn(&CompressedEdwardsY) -> bool;
+
+static VALIDATORS: [(&str, Validator); 6] = [
+    ("mult_by_cofactor_and_validate", cofactor_mult),
+    ("validate", validate),
+    ("validate_strict", validate_strict),
+    ("check_hardened", hardened),
+    ("dual", dual),
+    ("diagnose", diagnose),
+];
+
+fn cofactor_mult(key: &CompressedEdwardsY) -> bool {
+    mult_by_cofactor_and_validate(key).is_some()
+}
+
+fn validate(key: &CompressedEdwardsY) -> bool {
+    key.validate().is_some()
+}
+
+fn validate_strict(key: &CompressedEdwardsY) -> bool {
+    key.validate_strict().is_some()
+}
+
+fn hardened(key: &CompressedEdwardsY) -> bool {
+    validate_hardened(key, KeyPolicy::Strict).is_some()
+}
+
+// A disagreement between the two methods counts as a rejection.
+fn dual(key: &CompressedEdwardsY) -> bool {
+    match DualValidator::new(KeyPolicy::Strict).check(&UncheckedKey::new(*key)) {
+        Ok(checked) => checked.is_some(),
+        Err(_)      => false,
+    }
+}
+
+fn diagnose(key: &CompressedEdwardsY) -> bool {
+    UncheckedKey::new(*key).diagnose().is_none()
+}
+
+// The `i`th key, after `p`, which is the `i`th multiple of the basepoint, and
+// whether it should be accepted.
+fn synthetic_key(i: usize, p: &ExtendedPoint) -> (CompressedEdwardsY, bool) {
+    match i % 20 {
+        16 | 17 => ((p + &constants::EIGHT_TORSION[1 + i % 7]).compress_edwards(), false),
+        18      => (constants::EIGHT_TORSION[1 + i % 7].compress_edwards(), false),
+        19      => {
+            // About half of all y-coordinates are on the curve, so this
+            // doesn't take long.
+            let mut bytes: [u8; 32] = p.compress_edwards().to_bytes();
+            while CompressedEdwardsY(bytes).decompress().is_some() {
+                bytes[0] = bytes[0].wrapping_add(1);
+            }
+            (CompressedEdwardsY(bytes), false)
+        },
+        _       => (p.compress_edwards(), true),
+    }
+}
+
+// Each thread starts from its own multiple of the basepoint and adds B from
+// there, which is much cheaper than a scalar multiplication per key.
+fn synthetic_keys(count: usize, threads: usize) -> Vec<(CompressedEdwardsY, bool)> {
+    let chunk: usize = count.div_ceil(threads);
+
+    thread::scope(|scope| {
+        let handles: Vec<thread::ScopedJoinHandle<Vec<(CompressedEdwardsY, bool)>>> = (0..threads)
+            .map(|t| scope.spawn(move || {
+                let start: usize = (t * chunk).min(count);
+                let end: usize = (start + chunk).min(count);
+                let mut p: ExtendedPoint = &Scalar::from_u64(start as u64 + 1) * &constants::ED25519_BASEPOINT;
+                let mut keys: Vec<(CompressedEdwardsY, bool)> = Vec::with_capacity(end - start);
+
+                for i in start..end {
+                    keys.push(synthetic_key(i, &p));
+                    p = &p + &constants::ED25519_BASEPOINT;
+                }
+                keys
+            }))
+            .collect();
+
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+// Run `validator` over `keys` on `threads` threads, returning the time and
+// cycles taken, what was allocated, and the number of false accepts and false
+// rejects.
+fn run(validator: fn(&CompressedEdwardsY) -> bool, keys: &[(CompressedEdwardsY, bool)], threads: usize)
+    -> (Duration, Option<u64>, Footprint, usize, usize)
+{
+    let chunk: usize = keys.len().div_ceil(threads);
+    let start: Instant = Instant::now();
+    let start_cycles: Option<u64> = report::cycles();
+
+    let (wrong, footprint): ((usize, usize), Footprint) = alloc::measure(|| thread::scope(|scope| {
+        let handles: Vec<thread::ScopedJoinHandle<(usize, usize)>> = keys.chunks(chunk.max(1))
+            .map(|keys| scope.spawn(move || {
+                keys.iter().fold((0, 0), |(accepts, rejects), &(ref key, valid)| {
+                    match (validator(key), valid) {
+                        (true, false) => (accepts + 1, rejects),
+                        (false, true) => (accepts, rejects + 1),
+                        _             => (accepts, rejects),
+                    }
+                })
+            }))
+            .collect();
+
+        handles.into_iter().map(|handle| handle.join().unwrap())
+            .fold((0, 0), |(a, r), (accepts, rejects)| (a + accepts, r + rejects))
+    }));
+    let (false_accepts, false_rejects): (usize, usize) = wrong;
+
+    let elapsed: Duration = start.elapsed();
+    let cycles: Option<u64> = match (start_cycles, report::cycles()) {
+        (Some(start), Some(end)) => Some(end - start),
+        _                        => None,
+    };
+
+    (elapsed, cycles, footprint, false_accepts, false_rejects)
+}
+
+fn parse_args() -> Option<Args> {
+    let mut parsed: Args = Args {
+        keys: 1_000_000,
+        threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        json: None,
+        label: None,
+    };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keys"    => parsed.keys = args.next()?.parse().ok()?,
+            "--threads" => parsed.threads = args.next()?.parse().ok()?,
+            "--json"    => parsed.json = Some(args.next()?),
+            "--label"   => parsed.label = Some(args.next()?),
+            // Passed by `cargo bench` to every bench target.
+            "--bench"   => {},
+            _           => return None,
+        }
+    }
+    match parsed.keys > 0 && parsed.threads > 0 {
+        true  => Some(parsed),
+        false => None,
+    }
+}
+
+fn main() {
+    let args: Args = match parse_args() {
+        Some(args) => args,
+        None       => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        },
+    };
+    let (count, threads): (usize, usize) = (args.keys, args.threads);
+    let mut report: Report = Report::new("million_keys", args.label);
+
+    let keys: Vec<(CompressedEdwardsY, bool)> = synthetic_keys(count, threads);
+    let valid: usize = keys.iter().filter(|&&(_, valid)| valid).count();
+
+    println!("{} keys ({} valid, {} invalid), {} threads", count, valid, count - valid, threads);
+    println!();
+    println!("{:<30} {:>12} {:>14} {:>14} {:>10} {:>11} {:>11}",
+             "validator", "keys/s", "false accepts", "false rejects", "accuracy", "allocs/key", "peak bytes");
+
+    for &(name, validator) in VALIDATORS.iter() {
+        let (elapsed, cycles, footprint, false_accepts, false_rejects):
+            (Duration, Option<u64>, Footprint, usize, usize) = run(validator, &keys, threads);
+        let seconds: f64 = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+        let accuracy: f64 = 100.0 * (count - false_accepts - false_rejects) as f64 / count as f64;
+
+        let allocations: f64 = footprint.allocations as f64 / count as f64;
+
+        println!("{:<30} {:>12.0} {:>14} {:>14} {:>9.4}% {:>11.4} {:>11}",
+                 name, count as f64 / seconds, false_accepts, false_rejects, accuracy,
+                 allocations, footprint.peak_bytes);
+
+        report.push(Measurement {
+            strategy: name.to_string(),
+            batch: None,
+            ns_per_item: seconds * 1e9 / count as f64,
+            cycles_per_item: cycles.map(|cycles| cycles as f64 / count as f64),
+            allocations_per_item: Some(allocations),
+            peak_bytes: Some(footprint.peak_bytes),
+        });
+    }
+
+    if let Some(path) = args.json {
+        if let Err(e) = report.write(&path) {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
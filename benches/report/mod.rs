@@ -0,0 +1,125 @@
+// Benchmark results as JSON, for tracking performance across versions of
+// curve25519-dalek and across machines.
+//
+// A report is one object per run:
+//
+//    {"bench":"batch_sweep","label":"dalek-0.9.2","crate_version":"0.1.0",
+//     "field_backend":"u64","target":"x86_64-linux","pointer_width":64,
+//     "cpu":"...","time":1500000000,
+//     "results":[{"strategy":"validate","batch":64,"ns_per_item":121558.0,
+//                 "cycles_per_item":303895.0,"items_per_second":8226.5,
+//                 "allocations_per_item":1.0,"peak_bytes":16384}, ...]}
+//
+// `label` is whatever was passed with `--label`, and is the place to record
+// which curve25519-dalek was built against, since cargo doesn't tell a
+// binary the versions of its dependencies.  `cycles_per_item` is null on
+// targets without a cycle counter, and `allocations_per_item` and
+// `peak_bytes`, counted by `alloc`, are null if nothing was counted.
+
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// One measurement: the median cost per item of one strategy.
+pub struct Measurement {
+    pub strategy: String,
+    pub batch: Option<usize>,
+    pub ns_per_item: f64,
+    pub cycles_per_item: Option<f64>,
+    pub allocations_per_item: Option<f64>,
+    pub peak_bytes: Option<usize>,
+}
+
+pub struct Report {
+    bench: &'static str,
+    label: Option<String>,
+    results: Vec<Measurement>,
+}
+
+impl Report {
+    pub fn new(bench: &'static str, label: Option<String>) -> Report {
+        Report { bench, label, results: Vec::new() }
+    }
+
+    pub fn push(&mut self, measurement: Measurement) {
+        self.results.push(measurement);
+    }
+
+    pub fn to_json(&self) -> String {
+        let results: Vec<String> = self.results.iter().map(|m| {
+            format!("{{\"strategy\":\"{}\",\"batch\":{},\"ns_per_item\":{:.1},\"cycles_per_item\":{},\
+                     \"items_per_second\":{:.1},\"allocations_per_item\":{},\"peak_bytes\":{}}}",
+                    escape(&m.strategy),
+                    m.batch.map(|n| n.to_string()).unwrap_or("null".to_string()),
+                    m.ns_per_item,
+                    m.cycles_per_item.map(|c| format!("{:.1}", c)).unwrap_or("null".to_string()),
+                    1e9 / m.ns_per_item,
+                    m.allocations_per_item.map(|a| format!("{:.2}", a)).unwrap_or("null".to_string()),
+                    m.peak_bytes.map(|n| n.to_string()).unwrap_or("null".to_string()))
+        }).collect();
+
+        format!("{{\"bench\":\"{}\",\"label\":{},\"crate_version\":\"{}\",\"field_backend\":\"{}\",\
+                 \"target\":\"{}-{}\",\"pointer_width\":{},\"cpu\":\"{}\",\"time\":{},\"results\":[{}]}}",
+                self.bench,
+                self.label.as_ref().map(|label| format!("\"{}\"", escape(label))).unwrap_or("null".to_string()),
+                env!("CARGO_PKG_VERSION"),
+                FIELD_BACKEND,
+                ::std::env::consts::ARCH, ::std::env::consts::OS,
+                ::std::mem::size_of::<usize>() * 8,
+                escape(&cpu_model()),
+                SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+                results.join(","))
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        File::create(path)?.write_all(format!("{}\n", self.to_json()).as_bytes())
+    }
+}
+
+// The field arithmetic curve25519-dalek was built with, where u64_backend
+// wins if both are enabled.
+#[cfg(feature = "u64_backend")]
+static FIELD_BACKEND: &str = "u64";
+#[cfg(not(feature = "u64_backend"))]
+static FIELD_BACKEND: &'static str = "u32";
+
+/// The cycle counter, where there is one.
+#[cfg(target_arch = "x86_64")]
+pub fn cycles() -> Option<u64> {
+    Some(unsafe { ::std::arch::x86_64::_rdtsc() })
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub fn cycles() -> Option<u64> {
+    None
+}
+
+/// The median of `samples`, which must not be empty.
+#[allow(dead_code)] // million_keys takes one sample
+pub fn median(samples: &mut [f64]) -> f64 {
+    samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    samples[samples.len() / 2]
+}
+
+// The CPU's model name, on Linux, or the architecture otherwise.
+fn cpu_model() -> String {
+    let mut text: String = String::new();
+
+    if File::open("/proc/cpuinfo").and_then(|mut file| file.read_to_string(&mut text)).is_ok() {
+        for line in text.lines() {
+            if line.starts_with("model name") {
+                if let Some(i) = line.find(':') {
+                    return line[i + 1..].trim().to_string();
+                }
+            }
+        }
+    }
+    ::std::env::consts::ARCH.to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
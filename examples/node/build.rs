@@ -0,0 +1,11 @@
+// The Node-API functions are supplied by node when it loads the addon, which
+// ELF linkers allow for by default, and Mach-O ones have to be told about.
+
+use std::env;
+
+fn main() {
+    if env::var("CARGO_CFG_TARGET_OS").map(|os| os == "macos").unwrap_or(false) {
+        println!("cargo:rustc-cdylib-link-arg=-undefined");
+        println!("cargo:rustc-cdylib-link-arg=dynamic_lookup");
+    }
+}
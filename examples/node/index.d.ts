@@ -0,0 +1,37 @@
+// Types for the tor22006 Node-API addon.
+
+/** The policies keys are checked under. */
+export type Policy = "prime-order" | "strict";
+
+/** What is wrong with a key, as the library's `KeyProblem::as_str` names it. */
+export type KeyProblem = "malformed" | "not_on_curve" | "torsioned" | "non_canonical" | "identity";
+
+/** An encoding two validators disagree about. */
+export interface Divergence {
+  /** The 32-byte encoding, in hex. */
+  encoding: string;
+  /** How the encoding was constructed. */
+  annotation: string;
+  /** Whether the first validator accepted it.  The second said the opposite. */
+  firstAccepted: boolean;
+}
+
+/**
+ * Check a 32-byte ed25519 key under `policy`, returning null if it passes
+ * and otherwise what is wrong with it.  A key of any other length is
+ * "malformed".
+ */
+export function validateKey(key: Uint8Array, policy: Policy): KeyProblem | null;
+
+/**
+ * Check the key named by a v3 onion address, with or without its ".onion"
+ * suffix, under `policy`, as `validateKey` does.  An address which doesn't
+ * parse is "malformed".
+ */
+export function checkOnionAddress(address: string, policy: Policy): KeyProblem | null;
+
+/**
+ * The encodings two validators disagree about, each given as BACKEND:POLICY,
+ * where BACKEND is "native" or "decompress-only".
+ */
+export function divergences(first: string, second: string): Divergence[];
@@ -0,0 +1,278 @@
+// Node.js bindings, as a Node-API addon, so that onion-site monitoring
+// dashboards written in TypeScript can check keys and onion addresses, and
+// find the keys two validators disagree about, in process rather than by
+// shelling out to a reimplementation.
+//
+//    cd examples/node && npm run build
+//
+// copies the library to tor22006.node, which `require` loads; index.d.ts
+// gives its types, and `npm test` checks it against the library's own
+// answers.  On macOS, copy libtor22006_node.dylib instead.
+//
+// The Node-API functions used are declared here rather than taken from
+// napi-rs, and left undefined in the library for node to supply when it
+// loads the addon, which is all napi-rs's build script arranges for as
+// well.  Each of them is in version 1 of Node-API.
+//
+// A panic must not unwind out of a callback into node, so each callback's
+// body runs under `catch_unwind`, and a panic becomes a thrown Error.
+
+extern crate curve25519_dalek;
+extern crate tor22006;
+
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+use std::panic;
+use std::ptr;
+use std::slice;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use tor22006::backend::Backend;
+use tor22006::backend::Native;
+use tor22006::corpus::build_corpus;
+use tor22006::corpus::CorpusEntry;
+use tor22006::corpus::DecompressOnly;
+use tor22006::corpus::Validator;
+use tor22006::encoding::hex_encode;
+use tor22006::key::KeyProblem;
+use tor22006::key::UncheckedKey;
+use tor22006::onion::parse_onion_address;
+use tor22006::policy::KeyPolicy;
+
+type Env = *mut c_void;
+type Value = *mut c_void;
+type CallbackInfo = *mut c_void;
+type Callback = unsafe extern "C" fn(Env, CallbackInfo) -> Value;
+type Status = c_int;
+
+// napi_ok, and napi_uint8_array of napi_typedarray_type.
+const OK: Status = 0;
+const UINT8_ARRAY: c_int = 1;
+
+extern "C" {
+    fn napi_create_function(env: Env, name: *const c_char, length: usize, cb: Callback, data: *mut c_void, result: *mut Value) -> Status;
+    fn napi_set_named_property(env: Env, object: Value, name: *const c_char, value: Value) -> Status;
+    fn napi_get_cb_info(env: Env, info: CallbackInfo, argc: *mut usize, argv: *mut Value, this: *mut Value, data: *mut *mut c_void) -> Status;
+    fn napi_is_typedarray(env: Env, value: Value, result: *mut bool) -> Status;
+    fn napi_get_typedarray_info(env: Env, value: Value, kind: *mut c_int, length: *mut usize, data: *mut *mut c_void,
+                                arraybuffer: *mut Value, offset: *mut usize) -> Status;
+    fn napi_get_value_string_utf8(env: Env, value: Value, buf: *mut c_char, size: usize, result: *mut usize) -> Status;
+    fn napi_create_string_utf8(env: Env, string: *const c_char, length: usize, result: *mut Value) -> Status;
+    fn napi_get_boolean(env: Env, value: bool, result: *mut Value) -> Status;
+    fn napi_get_null(env: Env, result: *mut Value) -> Status;
+    fn napi_create_object(env: Env, result: *mut Value) -> Status;
+    fn napi_create_array_with_length(env: Env, length: usize, result: *mut Value) -> Status;
+    fn napi_set_element(env: Env, object: Value, index: u32, value: Value) -> Status;
+    fn napi_throw_error(env: Env, code: *const c_char, message: *const c_char) -> Status;
+    fn napi_throw_type_error(env: Env, code: *const c_char, message: *const c_char) -> Status;
+}
+
+/// Export `validateKey`, `checkOnionAddress`, and `divergences`.
+///
+/// # Safety
+///
+/// Only node should call this, when it loads the addon.
+#[no_mangle]
+pub unsafe extern "C" fn napi_register_module_v1(env: Env, exports: Value) -> Value {
+    let functions: [(&[u8], Callback); 3] = [
+        (b"validateKey\0",       validate_key),
+        (b"checkOnionAddress\0", check_onion_address),
+        (b"divergences\0",       divergences),
+    ];
+
+    for &(name, function) in functions.iter() {
+        let mut value: Value = ptr::null_mut();
+
+        if napi_create_function(env, name.as_ptr() as *const c_char, name.len() - 1, function, ptr::null_mut(), &mut value) != OK ||
+           napi_set_named_property(env, exports, name.as_ptr() as *const c_char, value) != OK {
+            return ptr::null_mut();
+        }
+    }
+    exports
+}
+
+unsafe extern "C" fn validate_key(env: Env, info: CallbackInfo) -> Value {
+    call(env, info, validate_key_body)
+}
+
+unsafe extern "C" fn check_onion_address(env: Env, info: CallbackInfo) -> Value {
+    call(env, info, check_onion_address_body)
+}
+
+unsafe extern "C" fn divergences(env: Env, info: CallbackInfo) -> Value {
+    call(env, info, divergences_body)
+}
+
+// Run `body` on the callback's first two arguments, with undefined for any
+// not passed, throwing rather than unwinding if it panics.
+unsafe fn call(env: Env, info: CallbackInfo, body: unsafe fn(Env, [Value; 2]) -> Value) -> Value {
+    let mut argc: usize = 2;
+    let mut argv: [Value; 2] = [ptr::null_mut(); 2];
+
+    if napi_get_cb_info(env, info, &mut argc, argv.as_mut_ptr(), ptr::null_mut(), ptr::null_mut()) != OK {
+        return throw_error(env, b"could not read the arguments\0");
+    }
+    match panic::catch_unwind(|| body(env, argv)) {
+        Ok(value) => value,
+        Err(_)    => throw_error(env, b"tor22006 panicked\0"),
+    }
+}
+
+// validateKey(key, policy)
+unsafe fn validate_key_body(env: Env, args: [Value; 2]) -> Value {
+    let policy: KeyPolicy = match string(env, args[1]).as_ref().and_then(|policy| key_policy(policy)) {
+        Some(policy) => policy,
+        None         => return throw(env, b"policy must be \"prime-order\" or \"strict\"\0"),
+    };
+    let key: &[u8] = match bytes(env, args[0]) {
+        Some(key) => key,
+        None      => return throw(env, b"key must be a Uint8Array\0"),
+    };
+
+    match key.len() {
+        32 => {
+            let mut encoding: [u8; 32] = [0u8; 32];
+            encoding.copy_from_slice(key);
+            verdict(env, UncheckedKey::new(CompressedEdwardsY(encoding)), policy)
+        },
+        _ => problem(env, Some(KeyProblem::Malformed)),
+    }
+}
+
+// checkOnionAddress(address, policy)
+unsafe fn check_onion_address_body(env: Env, args: [Value; 2]) -> Value {
+    let policy: KeyPolicy = match string(env, args[1]).as_ref().and_then(|policy| key_policy(policy)) {
+        Some(policy) => policy,
+        None         => return throw(env, b"policy must be \"prime-order\" or \"strict\"\0"),
+    };
+    let address: String = match string(env, args[0]) {
+        Some(address) => address,
+        None          => return throw(env, b"address must be a string\0"),
+    };
+
+    match parse_onion_address(&address) {
+        Some(key) => verdict(env, key, policy),
+        None      => problem(env, Some(KeyProblem::Malformed)),
+    }
+}
+
+// divergences(first, second)
+unsafe fn divergences_body(env: Env, args: [Value; 2]) -> Value {
+    static NATIVE: Native = Native;
+    static DECOMPRESS_ONLY: DecompressOnly = DecompressOnly;
+
+    let mut validators: Vec<Validator> = Vec::new();
+
+    for &arg in args.iter() {
+        let spec: String = string(env, arg).unwrap_or_default();
+        let mut parts = spec.splitn(2, ':');
+        let backend: &'static dyn Backend = match parts.next() {
+            Some("native")          => &NATIVE,
+            Some("decompress-only") => &DECOMPRESS_ONLY,
+            _                       => return throw(env, b"validators are BACKEND:POLICY, for a BACKEND of \"native\" or \"decompress-only\"\0"),
+        };
+        let policy: KeyPolicy = match parts.next().and_then(key_policy) {
+            Some(policy) => policy,
+            None         => return throw(env, b"policy must be \"prime-order\" or \"strict\"\0"),
+        };
+        validators.push(Validator::new(backend, policy));
+    }
+
+    let entries: Vec<CorpusEntry> = build_corpus(&validators[0], &validators[1]);
+    let mut array: Value = ptr::null_mut();
+    if napi_create_array_with_length(env, entries.len(), &mut array) != OK {
+        return ptr::null_mut();
+    }
+
+    for (index, entry) in entries.iter().enumerate() {
+        let mut object: Value = ptr::null_mut();
+        let mut first_accepted: Value = ptr::null_mut();
+
+        if napi_create_object(env, &mut object) != OK ||
+           napi_get_boolean(env, entry.first_accepted(), &mut first_accepted) != OK ||
+           napi_set_named_property(env, object, b"encoding\0".as_ptr() as *const c_char, create_string(env, &hex_encode(entry.encoding()))) != OK ||
+           napi_set_named_property(env, object, b"annotation\0".as_ptr() as *const c_char, create_string(env, entry.annotation())) != OK ||
+           napi_set_named_property(env, object, b"firstAccepted\0".as_ptr() as *const c_char, first_accepted) != OK ||
+           napi_set_element(env, array, index as u32, object) != OK {
+            return ptr::null_mut();
+        }
+    }
+    array
+}
+
+fn key_policy(policy: &str) -> Option<KeyPolicy> {
+    match policy {
+        "prime-order" => Some(KeyPolicy::PrimeOrder),
+        "strict"      => Some(KeyPolicy::Strict),
+        _             => None,
+    }
+}
+
+unsafe fn verdict(env: Env, key: UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy) -> Value {
+    problem(env, key.check_or_diagnose(policy).err())
+}
+
+// null for no problem, and otherwise the problem's name.
+unsafe fn problem(env: Env, problem: Option<KeyProblem>) -> Value {
+    match problem {
+        Some(problem) => create_string(env, problem.as_str()),
+        None          => {
+            let mut null: Value = ptr::null_mut();
+            napi_get_null(env, &mut null);
+            null
+        },
+    }
+}
+
+// The bytes of a Uint8Array, such as a Buffer, which live as long as the
+// call does.
+unsafe fn bytes<'a>(env: Env, value: Value) -> Option<&'a [u8]> {
+    let mut is_typedarray: bool = false;
+    let mut kind: c_int = 0;
+    let mut length: usize = 0;
+    let mut data: *mut c_void = ptr::null_mut();
+
+    if napi_is_typedarray(env, value, &mut is_typedarray) != OK || !is_typedarray ||
+       napi_get_typedarray_info(env, value, &mut kind, &mut length, &mut data, ptr::null_mut(), ptr::null_mut()) != OK ||
+       kind != UINT8_ARRAY {
+        return None;
+    }
+    match data.is_null() {
+        true  => Some(&[]), // an empty array may have no buffer at all
+        false => Some(slice::from_raw_parts(data as *const u8, length)),
+    }
+}
+
+unsafe fn string(env: Env, value: Value) -> Option<String> {
+    let mut length: usize = 0;
+    if napi_get_value_string_utf8(env, value, ptr::null_mut(), 0, &mut length) != OK {
+        return None; // not a string
+    }
+
+    // The buffer's size includes the NUL node writes after the string.
+    let mut buf: Vec<u8> = vec![0u8; length + 1];
+    if napi_get_value_string_utf8(env, value, buf.as_mut_ptr() as *mut c_char, buf.len(), &mut length) != OK {
+        return None;
+    }
+    buf.truncate(length);
+    String::from_utf8(buf).ok()
+}
+
+unsafe fn create_string(env: Env, string: &str) -> Value {
+    let mut value: Value = ptr::null_mut();
+
+    napi_create_string_utf8(env, string.as_ptr() as *const c_char, string.len(), &mut value);
+    value
+}
+
+unsafe fn throw(env: Env, message: &[u8]) -> Value {
+    napi_throw_type_error(env, ptr::null(), message.as_ptr() as *const c_char);
+    ptr::null_mut()
+}
+
+unsafe fn throw_error(env: Env, message: &[u8]) -> Value {
+    napi_throw_error(env, ptr::null(), message.as_ptr() as *const c_char);
+    ptr::null_mut()
+}
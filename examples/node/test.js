@@ -0,0 +1,63 @@
+// Check the addon against the library's own answers.  Build it first, with
+// `npm run build`.
+
+"use strict";
+
+const assert = require("assert");
+const crypto = require("crypto");
+const tor22006 = require("./tor22006.node");
+
+const BASEPOINT = Buffer.from("58" + "66".repeat(31), "hex");
+const IDENTITY = Buffer.from("01" + "00".repeat(31), "hex");
+
+// The v3 onion address of `key`, as rend-spec-v3 gives it.
+function onionAddress(key) {
+  const version = Buffer.from([3]);
+  const checksum = crypto.createHash("sha3-256")
+    .update(Buffer.concat([Buffer.from(".onion checksum"), key, version]))
+    .digest().subarray(0, 2);
+  const bytes = Buffer.concat([key, checksum, version]);
+  const alphabet = "abcdefghijklmnopqrstuvwxyz234567";
+  let bits = "";
+  for (const byte of bytes) {
+    bits += byte.toString(2).padStart(8, "0");
+  }
+  let address = "";
+  for (let i = 0; i < bits.length; i += 5) {
+    address += alphabet[parseInt(bits.slice(i, i + 5), 2)];
+  }
+  return address + ".onion";
+}
+
+assert.strictEqual(tor22006.validateKey(BASEPOINT, "strict"), null);
+assert.strictEqual(tor22006.validateKey(new Uint8Array(BASEPOINT), "prime-order"), null);
+assert.strictEqual(tor22006.validateKey(IDENTITY, "prime-order"), null);
+assert.strictEqual(tor22006.validateKey(IDENTITY, "strict"), "identity");
+assert.strictEqual(tor22006.validateKey(Buffer.alloc(32, 2), "prime-order"), "not_on_curve");
+assert.strictEqual(tor22006.validateKey(Buffer.alloc(31), "strict"), "malformed");
+assert.throws(() => tor22006.validateKey("not bytes", "strict"), TypeError);
+assert.throws(() => tor22006.validateKey(BASEPOINT, "lax"), TypeError);
+assert.throws(() => tor22006.validateKey(BASEPOINT), TypeError);
+
+assert.strictEqual(tor22006.checkOnionAddress(onionAddress(BASEPOINT), "strict"), null);
+assert.strictEqual(tor22006.checkOnionAddress(onionAddress(BASEPOINT).slice(0, -6), "strict"), null);
+assert.strictEqual(tor22006.checkOnionAddress(onionAddress(IDENTITY), "strict"), "identity");
+assert.strictEqual(tor22006.checkOnionAddress("torproject.onion", "strict"), "malformed");
+assert.throws(() => tor22006.checkOnionAddress(42, "strict"), TypeError);
+
+const divergences = tor22006.divergences("native:strict", "decompress-only:strict");
+assert(divergences.length > 0);
+for (const divergence of divergences) {
+  const key = Buffer.from(divergence.encoding, "hex");
+
+  assert.strictEqual(divergence.firstAccepted, false);
+  assert.notStrictEqual(tor22006.validateKey(key, "strict"), null);
+  if (divergence.annotation.startsWith("a prime-order point plus")) {
+    assert.strictEqual(tor22006.validateKey(key, "strict"), "torsioned");
+  }
+}
+assert.deepStrictEqual(tor22006.divergences("native:strict", "native:strict"), []);
+assert.throws(() => tor22006.divergences("ring:strict", "native:strict"), TypeError);
+assert.throws(() => tor22006.divergences("native", "native:strict"), TypeError);
+
+console.log("ok");
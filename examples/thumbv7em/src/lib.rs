@@ -0,0 +1,81 @@
+// Key validation for Cortex-M4 and M7 firmware, as a static library with a
+// C interface, so that a hardware Tor appliance can check the keys it is
+// handed without trusting the host to have done so.
+//
+//    rustup target add thumbv7em-none-eabihf
+//    cd examples/thumbv7em && cargo build --release
+//
+// and link target/thumbv7em-none-eabihf/release/libtor22006_thumbv7em.a into
+// the firmware.  Nothing here allocates, and the stack used is that of one
+// validation however many keys are passed, so it fits in a task's stack
+// without a heap.
+
+#![no_std]
+
+extern crate curve25519_dalek;
+extern crate tor22006;
+
+use core::panic::PanicInfo;
+use core::slice;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+
+use tor22006::key::UncheckedKey;
+use tor22006::policy::KeyPolicy;
+use tor22006::traits::validate_batch_into;
+
+// Keys are validated this many at a time.
+const CHUNK: usize = 8;
+
+/// Check the 32-byte ed25519 key at `key` under the strict policy, returning
+/// 1 if it is valid and 0 otherwise.
+#[no_mangle]
+pub extern "C" fn tor22006_validate_key(key: *const u8) -> i32 {
+    if key.is_null() {
+        return 0;
+    }
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(unsafe { slice::from_raw_parts(key, 32) });
+
+    match UncheckedKey::new(CompressedEdwardsY(bytes)).check(KeyPolicy::Strict) {
+        Some(_) => 1,
+        None    => 0,
+    }
+}
+
+/// Check each of the `count` 32-byte keys at `keys` under the prime-order
+/// policy, writing 1 or 0 for each to `results`, and return how many were
+/// valid.
+#[no_mangle]
+pub extern "C" fn tor22006_validate_keys(keys: *const u8, count: usize, results: *mut u8) -> usize {
+    if keys.is_null() || results.is_null() {
+        return 0;
+    }
+    let bytes: &[u8] = unsafe { slice::from_raw_parts(keys, 32 * count) };
+    let results: &mut [u8] = unsafe { slice::from_raw_parts_mut(results, count) };
+    let mut valid: usize = 0;
+
+    for (keys, results) in bytes.chunks(32 * CHUNK).zip(results.chunks_mut(CHUNK)) {
+        let mut encodings: [CompressedEdwardsY; CHUNK] = [CompressedEdwardsY([0u8; 32]); CHUNK];
+        let mut points: [Option<ExtendedPoint>; CHUNK] = [None; CHUNK];
+
+        for (encoding, key) in encodings.iter_mut().zip(keys.chunks(32)) {
+            encoding.0.copy_from_slice(key);
+        }
+        let n: usize = validate_batch_into(&encodings[..results.len()], &mut points);
+
+        for (result, point) in results.iter_mut().zip(points[..n].iter()) {
+            *result = point.is_some() as u8;
+            valid += *result as usize;
+        }
+    }
+    valid
+}
+
+// Firmware should never get here: nothing above panics on any input.
+#[panic_handler]
+fn panic(_: &PanicInfo) -> ! {
+    loop {}
+}
+
@@ -0,0 +1,167 @@
+// Choosing the size of `ed25519::verify_batch` batches at runtime, to meet a
+// latency target.
+//
+// A bigger batch is cheaper per signature, since the multiscalar
+// multiplication shares its doublings among every term, but takes longer
+// before anything is known, and where the trade falls depends on the machine
+// far more than on anything which could be fixed in advance.  A
+// `BatchSizer` keeps a running estimate of the cost per signature, from
+// every batch it is told about, and suggests the largest batch expected to
+// finish within the target.  It at most doubles or halves the size at each
+// step, so that one slow batch, such as one interrupted by the scheduler,
+// doesn't throw the size far off, and since the cost per signature falls as
+// batches grow, the estimate errs towards batches a little over the target,
+// which the next measurement corrects.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::verify_batch;
+use entropy::EntropySource;
+use policy::VerificationProfile;
+
+/// Suggests batch sizes to meet a latency target.
+#[derive(Clone, Debug)]
+pub struct BatchSizer {
+    target: Duration,
+    min: usize,
+    max: usize,
+    size: usize,
+    nanos_per_item: Option<u64>,
+}
+
+impl BatchSizer {
+    /// Aim for batches taking `target`, of between 1 and 4096 items.
+    pub fn new(target: Duration) -> BatchSizer {
+        BatchSizer::with_bounds(target, 1, 4096)
+    }
+
+    /// Aim for batches taking `target`, of between `min` and `max` items.
+    /// Starts at `min`.
+    ///
+    /// Panics if `min` is zero or greater than `max`.
+    pub fn with_bounds(target: Duration, min: usize, max: usize) -> BatchSizer {
+        assert!(min > 0 && min <= max);
+
+        BatchSizer { target, min, max, size: min, nanos_per_item: None }
+    }
+
+    /// The size the next batch should be.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The running estimate of the cost of each item, once there is one.
+    pub fn cost_per_item(&self) -> Option<Duration> {
+        self.nanos_per_item.map(Duration::from_nanos)
+    }
+
+    /// Note that a batch of `items` took `elapsed`, and pick the next size.
+    pub fn record(&mut self, items: usize, elapsed: Duration) {
+        if items == 0 {
+            return;
+        }
+
+        let sample: u64 = (elapsed.as_nanos() / items as u128).max(1) as u64;
+        let estimate: u64 = match self.nanos_per_item {
+            Some(estimate) => (3 * estimate + sample) / 4,
+            None           => sample,
+        };
+        let ideal: u128 = self.target.as_nanos() / estimate as u128;
+
+        self.nanos_per_item = Some(estimate);
+        self.size = (ideal.min(2 * self.size as u128).max(self.size as u128 / 2) as usize)
+            .max(self.min).min(self.max);
+    }
+}
+
+/// As `verify_batch`, but in batches of the sizes `sizer` suggests, timing
+/// each to inform the next.  Stops at the first batch which fails.
+pub fn verify_batch_adaptive<E: EntropySource>(keys: &[CompressedEdwardsY],
+                                               messages: &[&[u8]],
+                                               signatures: &[[u8; 64]],
+                                               profile: VerificationProfile,
+                                               entropy: &mut E,
+                                               sizer: &mut BatchSizer) -> bool {
+    if messages.len() != keys.len() || signatures.len() != keys.len() {
+        return false;
+    }
+
+    let mut start: usize = 0;
+    while start < keys.len() {
+        let end: usize = (start + sizer.size()).min(keys.len());
+        let started: Instant = Instant::now();
+
+        if !verify_batch(&keys[start..end], &messages[start..end], &signatures[start..end], profile, entropy) {
+            return false;
+        }
+        sizer.record(end - start, started.elapsed());
+        start = end;
+    }
+    true
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use ed25519::public_key_from_seed;
+    use ed25519::sign;
+    use entropy::OsEntropy;
+
+    #[test]
+    fn sizes_move_towards_the_target() {
+        let mut sizer: BatchSizer = BatchSizer::new(Duration::from_millis(10));
+
+        // 10µs an item wants 1000-item batches, reached by doubling.
+        for &expected in [2, 4, 8, 16, 32, 64, 128, 256, 512, 1000, 1000].iter() {
+            sizer.record(sizer.size(), Duration::from_micros(10) * sizer.size() as u32);
+            assert_eq!(sizer.size(), expected);
+        }
+        assert_eq!(sizer.cost_per_item(), Some(Duration::from_micros(10)));
+
+        // Items getting five times as expensive at once only halve it.
+        sizer.record(1000, Duration::from_millis(50));
+        assert_eq!(sizer.size(), 500);
+
+        sizer.record(0, Duration::from_secs(1));
+        assert_eq!(sizer.size(), 500);
+    }
+
+    #[test]
+    fn sizes_stay_in_bounds() {
+        let mut sizer: BatchSizer = BatchSizer::with_bounds(Duration::from_millis(1), 8, 16);
+
+        assert_eq!(sizer.size(), 8);
+        for _ in 0..4 {
+            sizer.record(8, Duration::from_nanos(8));
+        }
+        assert_eq!(sizer.size(), 16);
+        for _ in 0..8 {
+            sizer.record(16, Duration::from_secs(16));
+        }
+        assert_eq!(sizer.size(), 8);
+    }
+
+    #[test]
+    fn adaptive_batches_verify() {
+        let seeds: Vec<[u8; 32]> = (0..20u8).map(|i| [i; 32]).collect();
+        let keys: Vec<CompressedEdwardsY> = seeds.iter().map(public_key_from_seed).collect();
+        let messages: Vec<&[u8]> = seeds.iter().map(|seed| &seed[..]).collect();
+        let mut signatures: Vec<[u8; 64]> = seeds.iter().map(|seed| sign(seed, seed)).collect();
+        let mut entropy: OsEntropy = OsEntropy::new();
+        let mut sizer: BatchSizer = BatchSizer::with_bounds(Duration::from_millis(1), 1, 8);
+
+        assert!(verify_batch_adaptive(&keys, &messages, &signatures, VerificationProfile::Strict,
+                                      &mut entropy, &mut sizer));
+        assert!(sizer.cost_per_item().is_some());
+
+        signatures[19][0] ^= 1;
+        assert!(!verify_batch_adaptive(&keys, &messages, &signatures, VerificationProfile::Strict,
+                                       &mut entropy, &mut sizer));
+        assert!(!verify_batch_adaptive(&keys[1..], &messages, &signatures, VerificationProfile::Strict,
+                                       &mut entropy, &mut sizer));
+    }
+}
@@ -0,0 +1,133 @@
+// Uncompressed affine encodings of ed25519 points, as some HSMs and test
+// harnesses exchange them:
+//
+//    x  32 bytes, little-endian
+//    y  32 bytes, little-endian
+//
+// Nothing about such an encoding is implied by the other coordinate, so it
+// has more ways to be wrong than a compressed one: either coordinate may be
+// unreduced, and the pair may not satisfy -x² + y² = 1 + dx²y² at all, which
+// no compressed encoding can get wrong except by having no x.  Both
+// coordinates have to be reduced mod p, the equation has to hold, and the
+// point then goes through the same subgroup checks as any other key, by
+// way of its standard compressed encoding.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::field::FieldElement;
+
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The compressed encoding of the point in `bytes`, if both coordinates
+/// are reduced and it is on the curve.  Says nothing about its subgroup.
+pub fn compress_affine(bytes: &[u8; 64]) -> Result<CompressedEdwardsY, KeyProblem> {
+    let (x, x_canonical): (FieldElement, bool) = coordinate(&bytes[..32]);
+    let (y, y_canonical): (FieldElement, bool) = coordinate(&bytes[32..]);
+
+    if !(x_canonical && y_canonical) {
+        return Err(KeyProblem::NonCanonical);
+    }
+
+    let xx: FieldElement = x.square();
+    let yy: FieldElement = y.square();
+    let left: FieldElement = &yy - &xx;                                                // -x² + y²
+    let right: FieldElement = &FieldElement::one() + &(&constants::d * &(&xx * &yy)); // 1 + dx²y²
+
+    if left != right {
+        return Err(KeyProblem::NotOnCurve);
+    }
+
+    let mut compressed: [u8; 32] = y.to_bytes();
+    compressed[31] |= x.is_negative_ed25519() << 7;
+
+    Ok(CompressedEdwardsY(compressed))
+}
+
+/// Parse the point in `bytes` and check it under `policy`, returning the
+/// first problem found if it fails.
+pub fn check_affine(bytes: &[u8; 64], policy: KeyPolicy) -> Result<CheckedKey<CompressedEdwardsY>, KeyProblem> {
+    UncheckedKey::new(compress_affine(bytes)?).check_or_diagnose(policy)
+}
+
+/// The affine encoding of `point`.
+pub fn affine_encoding(point: &ExtendedPoint) -> [u8; 64] {
+    let z_inverse: FieldElement = point.Z.invert();
+    let mut bytes: [u8; 64] = [0u8; 64];
+
+    bytes[..32].copy_from_slice(&(&point.X * &z_inverse).to_bytes());
+    bytes[32..].copy_from_slice(&(&point.Y * &z_inverse).to_bytes());
+    bytes
+}
+
+// A coordinate, and whether it was already reduced mod p.
+fn coordinate(bytes: &[u8]) -> (FieldElement, bool) {
+    let mut encoding: [u8; 32] = [0u8; 32];
+    encoding.copy_from_slice(bytes);
+
+    let element: FieldElement = FieldElement::from_bytes(&encoding);
+    let canonical: bool = element.to_bytes() == encoding;
+
+    (element, canonical)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::scalar::Scalar;
+
+    fn point() -> ExtendedPoint {
+        &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT
+    }
+
+    #[test]
+    fn affine_encodings_compress_to_the_standard_encoding() {
+        for k in 1..16u64 {
+            let p: ExtendedPoint = &Scalar::from_u64(k) * &constants::ED25519_BASEPOINT;
+
+            assert_eq!(compress_affine(&affine_encoding(&p)), Ok(p.compress_edwards()));
+        }
+        let key: CheckedKey<CompressedEdwardsY> = check_affine(&affine_encoding(&point()), KeyPolicy::Strict).unwrap();
+        assert_eq!(key.encoding(), &point().compress_edwards());
+    }
+
+    #[test]
+    fn points_off_the_curve_are_rejected() {
+        let mut bytes: [u8; 64] = affine_encoding(&point());
+        bytes[0] ^= 1;
+
+        assert_eq!(compress_affine(&bytes), Err(KeyProblem::NotOnCurve));
+        assert_eq!(compress_affine(&[0u8; 64]), Err(KeyProblem::NotOnCurve));
+    }
+
+    #[test]
+    fn unreduced_coordinates_are_rejected() {
+        // The identity, (0, 1), with y written as p + 1.
+        let mut bytes: [u8; 64] = [0u8; 64];
+        bytes[32] = 0xee;
+        for byte in bytes[33..63].iter_mut() {
+            *byte = 0xff;
+        }
+        bytes[63] = 0x7f;
+        assert_eq!(compress_affine(&bytes), Err(KeyProblem::NonCanonical));
+
+        // x with its top bit set, which only a compressed encoding may use.
+        let mut bytes: [u8; 64] = affine_encoding(&point());
+        bytes[31] |= 0x80;
+        assert_eq!(compress_affine(&bytes), Err(KeyProblem::NonCanonical));
+    }
+
+    #[test]
+    fn subgroup_checks_still_apply() {
+        let torsioned: ExtendedPoint = &point() + &constants::EIGHT_TORSION[1];
+
+        assert!(compress_affine(&affine_encoding(&torsioned)).is_ok());
+        assert_eq!(check_affine(&affine_encoding(&torsioned), KeyPolicy::Strict).err(), Some(KeyProblem::Torsioned));
+        assert_eq!(check_affine(&affine_encoding(&constants::EIGHT_TORSION[0]), KeyPolicy::Strict).err(),
+                   Some(KeyProblem::Identity));
+    }
+}
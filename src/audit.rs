@@ -0,0 +1,290 @@
+// Counting what is wrong with a large corpus of key encodings, for the
+// empirical side of the cofactor debate: how often non-canonical and
+// torsioned keys actually turn up, which torsion orders they have, and how
+// many keys are the same key with a different torsion component.
+//
+// The torsion component of P has the same order as [l]P, since l is prime
+// to 8, and that order is found by doubling [l]P until it is the identity.
+// Two points are equal modulo torsion exactly when their multiples by 8 are
+// equal, so encodings are grouped by [8]P to find duplicates.
+//
+// `dedup_report` names the duplicates instead of counting them.  Each of its
+// three relations is coarser than the one before: byte-for-byte equal
+// encodings, encodings of the same point, and points equal modulo torsion.
+// A group is only reported under a relation if the finer one doesn't
+// already explain it, so two encodings of one point are a duplicate modulo
+// encoding only if they differ, and two points are a duplicate modulo
+// torsion only if they differ, but every group lists every index it covers.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+
+use edwards::mul_by_pow_2;
+
+/// The statistics for a corpus.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    total: u64,
+    not_on_curve: u64,
+    non_canonical: u64,
+    identity: u64,
+    torsion_orders: [u64; 4],
+    exact_duplicates: u64,
+    duplicates_mod_torsion: u64,
+}
+
+impl AuditReport {
+    /// How many encodings were scanned.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// How many weren't points at all.
+    pub fn not_on_curve(&self) -> u64 {
+        self.not_on_curve
+    }
+
+    /// How many were points, but not canonically encoded.
+    pub fn non_canonical(&self) -> u64 {
+        self.non_canonical
+    }
+
+    /// How many were the identity.
+    pub fn identity(&self) -> u64 {
+        self.identity
+    }
+
+    /// How many points had a torsion component.
+    pub fn torsioned(&self) -> u64 {
+        self.torsion_orders[1..].iter().sum()
+    }
+
+    /// How many points had a torsion component of order 1, 2, 4, and 8.
+    pub fn torsion_orders(&self) -> &[u64; 4] {
+        &self.torsion_orders
+    }
+
+    /// How many encodings were byte-for-byte repeats of an earlier one.
+    pub fn exact_duplicates(&self) -> u64 {
+        self.exact_duplicates
+    }
+
+    /// How many encodings were new, but named a point which differed from
+    /// an earlier one only by torsion.  Non-canonical encodings of an
+    /// earlier point are counted here too.
+    pub fn duplicates_mod_torsion(&self) -> u64 {
+        self.duplicates_mod_torsion
+    }
+}
+
+/// Scan `encodings`.
+pub fn audit(encodings: &[CompressedEdwardsY]) -> AuditReport {
+    let mut report: AuditReport = AuditReport::default();
+    let mut seen: HashSet<[u8; 32]> = HashSet::new();
+    let mut classes: HashMap<[u8; 32], u64> = HashMap::new();
+
+    for encoding in encodings.iter() {
+        report.total += 1;
+
+        if !seen.insert(encoding.to_bytes()) {
+            report.exact_duplicates += 1;
+            continue;
+        }
+
+        let p: ExtendedPoint = match encoding.decompress() {
+            Some(p) => p,
+            None    => { report.not_on_curve += 1; continue; },
+        };
+        if p.compress_edwards() != *encoding {
+            report.non_canonical += 1;
+        }
+        if p.is_identity() {
+            report.identity += 1;
+        }
+        report.torsion_orders[torsion_order_log2(&p)] += 1;
+
+        let class: &mut u64 = classes.entry(mul_by_pow_2(&p, 3).compress_edwards().to_bytes()).or_insert(0);
+        if *class > 0 {
+            report.duplicates_mod_torsion += 1;
+        }
+        *class += 1;
+    }
+    report
+}
+
+/// The groups of encodings in a corpus which name the same key, each as the
+/// indices of its encodings in increasing order, and the groups in the order
+/// of their first index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    exact: Vec<Vec<usize>>,
+    modulo_encoding: Vec<Vec<usize>>,
+    modulo_torsion: Vec<Vec<usize>>,
+}
+
+impl DedupReport {
+    /// Encodings repeated byte for byte, whether or not they are points.
+    pub fn exact(&self) -> &[Vec<usize>] {
+        &self.exact
+    }
+
+    /// Points with more than one distinct encoding in the corpus.
+    pub fn modulo_encoding(&self) -> &[Vec<usize>] {
+        &self.modulo_encoding
+    }
+
+    /// Points which differ only by torsion, with more than one distinct
+    /// point in the group.
+    pub fn modulo_torsion(&self) -> &[Vec<usize>] {
+        &self.modulo_torsion
+    }
+
+    /// Whether every encoding names a different key.
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.modulo_encoding.is_empty() && self.modulo_torsion.is_empty()
+    }
+}
+
+/// Find the duplicates in `encodings`.
+pub fn dedup_report(encodings: &[CompressedEdwardsY]) -> DedupReport {
+    let exact: Vec<Vec<usize>> = group_by(encodings.iter().enumerate().map(|(i, e)| (e.to_bytes(), i)));
+
+    // Each distinct encoding which is a point, grouped by the point, with
+    // the point's multiple by 8.
+    let points: Vec<Encodings> = group_by(exact.iter().filter_map(|indices| {
+        encodings[indices[0]].decompress().map(|p| {
+            (p.compress_edwards().to_bytes(), (mul_by_pow_2(&p, 3).compress_edwards().to_bytes(), &indices[..]))
+        })
+    }));
+    // Each distinct point, grouped by its multiple by 8.
+    let classes: Vec<Vec<&Encodings>> = group_by(points.iter().map(|point| (point[0].0, point)));
+
+    DedupReport {
+        exact: exact.iter().filter(|indices| indices.len() > 1).cloned().collect(),
+        modulo_encoding: points.iter().filter(|point| point.len() > 1)
+            .map(|point| sorted_indices(point.iter().map(|&(_, indices)| indices)))
+            .collect(),
+        modulo_torsion: classes.iter().filter(|class| class.len() > 1)
+            .map(|class| sorted_indices(class.iter().flat_map(|point| point.iter().map(|&(_, indices)| indices))))
+            .collect(),
+    }
+}
+
+// The distinct encodings of one point, each with the point's multiple by 8
+// and the indices it was found at.
+type Encodings<'a> = Vec<([u8; 32], &'a [usize])>;
+
+// Group `items` by key, the groups in the order their keys first appear.
+fn group_by<K, V, I>(items: I) -> Vec<Vec<V>>
+    where K: Eq + Hash, I: Iterator<Item = (K, V)>
+{
+    let mut positions: HashMap<K, usize> = HashMap::new();
+    let mut groups: Vec<Vec<V>> = Vec::new();
+
+    for (key, value) in items {
+        let next: usize = groups.len();
+        let i: usize = *positions.entry(key).or_insert(next);
+
+        if i == next {
+            groups.push(Vec::new());
+        }
+        groups[i].push(value);
+    }
+    groups
+}
+
+fn sorted_indices<'a, I: Iterator<Item = &'a [usize]>>(groups: I) -> Vec<usize> {
+    let mut indices: Vec<usize> = groups.flat_map(|indices| indices.iter().cloned()).collect();
+
+    indices.sort();
+    indices
+}
+
+// log2 of the order of P's torsion component.
+pub(crate) fn torsion_order_log2(p: &ExtendedPoint) -> usize {
+    let mut t: ExtendedPoint = p * &constants::l;
+    let mut k: usize = 0;
+
+    while !t.is_identity() {
+        t = mul_by_pow_2(&t, 1);
+        k += 1;
+    }
+    k
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn counts() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        let mut encodings: Vec<CompressedEdwardsY> = constants::EIGHT_TORSION.iter()
+            .map(|t| (&p + t).compress_edwards()).collect();
+
+        encodings.push(encodings[0]);
+        encodings.push(CompressedEdwardsY([0x02; 32]));
+        encodings.push(constants::EIGHT_TORSION[0].compress_edwards());
+
+        let report: AuditReport = audit(&encodings);
+
+        assert_eq!(report.total(), 11);
+        assert_eq!(report.exact_duplicates(), 1);
+        assert_eq!(report.not_on_curve(), 1);
+        assert_eq!(report.identity(), 1);
+        assert_eq!(report.duplicates_mod_torsion(), 7);
+        assert_eq!(report.torsioned(), 7);
+        // E[8] is cyclic: one point of order 1, one of 2, two of 4, four of 8.
+        assert_eq!(report.torsion_orders(), &[2, 1, 2, 4]);
+    }
+
+    #[test]
+    fn non_canonical_encodings() {
+        // p + 1, which encodes y = 1 again.
+        let mut bytes: [u8; 32] = [0xff; 32];
+        bytes[0] = 0xee;
+        bytes[31] = 0x7f;
+
+        let report: AuditReport = audit(&[CompressedEdwardsY(bytes), constants::EIGHT_TORSION[0].compress_edwards()]);
+
+        assert_eq!(report.non_canonical(), 1);
+        assert_eq!(report.identity(), 2);
+        assert_eq!(report.duplicates_mod_torsion(), 1);
+    }
+
+    #[test]
+    fn duplicates_are_named_by_index() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        let q: CompressedEdwardsY = (&Scalar::from_u64(2) * &constants::ED25519_BASEPOINT).compress_edwards();
+        // p + 1, which encodes y = 1, as the identity does.
+        let mut unreduced: [u8; 32] = [0xff; 32];
+        unreduced[0] = 0xee;
+        unreduced[31] = 0x7f;
+
+        let encodings: Vec<CompressedEdwardsY> = vec![
+            p.compress_edwards(),                                     // 0
+            q,                                                        // 1
+            (&p + &constants::EIGHT_TORSION[3]).compress_edwards(),   // 2
+            constants::EIGHT_TORSION[0].compress_edwards(),           // 3
+            CompressedEdwardsY([0x02; 32]),                           // 4
+            CompressedEdwardsY(unreduced),                            // 5
+            p.compress_edwards(),                                     // 6
+            CompressedEdwardsY([0x02; 32]),                           // 7
+        ];
+        let report: DedupReport = dedup_report(&encodings);
+
+        assert_eq!(report.exact(), &[vec![0, 6], vec![4, 7]]);
+        assert_eq!(report.modulo_encoding(), &[vec![3, 5]]);
+        assert_eq!(report.modulo_torsion(), &[vec![0, 2, 6]]);
+        assert!(!report.is_empty());
+        assert!(dedup_report(&encodings[..2]).is_empty());
+    }
+}
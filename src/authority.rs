@@ -0,0 +1,307 @@
+// The ed25519 keys in a directory authority's key certificate, as in
+// dir-spec §3.1:
+//
+//    dir-key-certificate-version 3
+//    fingerprint <hex RSA identity digest>
+//    dir-key-published <YYYY-MM-DD HH:MM:SS>
+//    dir-key-expires <YYYY-MM-DD HH:MM:SS>
+//    ...
+//    identity-ed25519
+//    -----BEGIN ED25519 CERT-----  ...  -----END ED25519 CERT-----
+//    master-key-ed25519 <base64 identity>
+//    ...
+//    router-sig-ed25519 <base64 signature>
+//    dir-key-certification
+//    -----BEGIN SIGNATURE-----  ...  -----END SIGNATURE-----
+//
+// The certificates the authorities serve today bind only RSA keys, and
+// their `dir-key-crosscert` and `dir-key-certification` signatures, which
+// are RSA, aren't checked here.  The ed25519 lines are the ones router
+// descriptors carry, and are read wherever an authority includes them: the
+// ED25519 CERT, of type 0x04, certifies the ed25519 signing key and is signed
+// by the identity named in its signed-with-ed25519-key extension, which the
+// `master-key-ed25519` line must repeat; `router-sig-ed25519` signs, with the
+// signing key, the SHA-256 digest of "Tor router descriptor signature v1"
+// followed by everything up to and including the space after it.  The two
+// signatures cross: a signing key which didn't sign the document, or an
+// identity which didn't certify it, is reported.
+//
+// Both keys are checked under `KeyPolicy::Strict`, and signatures are
+// verified cofactorless, as tor verifies them.  A monitor tracking key
+// rotations compares `signing_key` across an authority's certificates; only
+// one which `is_valid` says anything about that authority.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::verify;
+use encoding::base64_decode;
+use encoding::decode_key;
+use encoding::hex_decode;
+use formats::cert::parse_certificate;
+use formats::cert::Certificate;
+use formats::pem;
+use formats::FormatError;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+static SIGNATURE_PREFIX: &[u8] = b"Tor router descriptor signature v1";
+
+/// The certificate type of an ed25519 signing key certified by an identity.
+pub const CERT_TYPE_SIGNING_KEY: u8 = 0x04;
+
+/// Something wrong with an authority's ed25519 keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuthorityProblem {
+    /// The certificate carries no ed25519 keys at all.
+    NoEd25519Keys,
+    /// The ED25519 CERT is not for a signing key.
+    CertificateType,
+    /// The ED25519 CERT doesn't say which identity signed it.
+    MissingIdentity,
+    /// `master-key-ed25519` names some other identity than the ED25519 CERT.
+    IdentityMismatch,
+    /// The ed25519 identity key fails validation.
+    InvalidIdentityKey,
+    /// The ed25519 signing key fails validation.
+    InvalidSigningKey,
+    /// The ED25519 CERT's signature doesn't verify under the identity.
+    BadCertificateSignature,
+    /// `router-sig-ed25519` is missing, or doesn't verify under the signing
+    /// key.
+    BadDocumentSignature,
+}
+
+/// What was found in an authority's key certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorityKeys {
+    fingerprint: [u8; 20],
+    published: String,
+    expires: String,
+    identity: Option<CompressedEdwardsY>,
+    signing_key: Option<CompressedEdwardsY>,
+    signing_key_expiration: Option<u64>,
+    problems: Vec<AuthorityProblem>,
+}
+
+impl AuthorityKeys {
+    /// The authority's RSA identity fingerprint.
+    pub fn fingerprint(&self) -> &[u8; 20] {
+        &self.fingerprint
+    }
+
+    /// When the certificate was published, as written.
+    pub fn published(&self) -> &str {
+        &self.published
+    }
+
+    /// When the certificate expires, as written.
+    pub fn expires(&self) -> &str {
+        &self.expires
+    }
+
+    /// The ed25519 identity, if the certificate names one.
+    pub fn identity(&self) -> Option<&CompressedEdwardsY> {
+        self.identity.as_ref()
+    }
+
+    /// The ed25519 signing key, if the certificate carries one.
+    pub fn signing_key(&self) -> Option<&CompressedEdwardsY> {
+        self.signing_key.as_ref()
+    }
+
+    /// When the ed25519 signing key's certification expires, in hours since
+    /// the epoch.
+    pub fn signing_key_expiration(&self) -> Option<u64> {
+        self.signing_key_expiration
+    }
+
+    /// Everything found wrong with the ed25519 keys.
+    pub fn problems(&self) -> &[AuthorityProblem] {
+        &self.problems
+    }
+
+    /// Whether the certificate carries ed25519 keys and nothing was found
+    /// wrong with them.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Read and check the ed25519 keys in the authority key certificate `text`.
+///
+/// Returns an error if the document isn't a version 3 key certificate, and
+/// otherwise what was found, with every problem in the ed25519 keys.
+pub fn check_key_certificate(text: &str) -> Result<AuthorityKeys, FormatError> {
+    if text.lines().next() != Some("dir-key-certificate-version 3") {
+        return Err(FormatError::Malformed);
+    }
+    let mut fingerprint: [u8; 20] = [0u8; 20];
+    match keyword_value(text, "fingerprint").and_then(hex_decode) {
+        Some(ref bytes) if bytes.len() == 20 => fingerprint.copy_from_slice(bytes),
+        _                                    => return Err(FormatError::Malformed),
+    }
+    let published: String = keyword_value(text, "dir-key-published").ok_or(FormatError::Malformed)?.to_string();
+    let expires: String = keyword_value(text, "dir-key-expires").ok_or(FormatError::Malformed)?.to_string();
+
+    let mut keys: AuthorityKeys = AuthorityKeys {
+        fingerprint,
+        published,
+        expires,
+        identity: None,
+        signing_key: None,
+        signing_key_expiration: None,
+        problems: Vec::new(),
+    };
+    if !text.contains("\nidentity-ed25519\n") {
+        keys.problems.push(AuthorityProblem::NoEd25519Keys);
+        return Ok(keys);
+    }
+
+    let cert_bytes: Vec<u8> = pem::decode(text, "ED25519 CERT")?;
+    let cert: Certificate = parse_certificate(&cert_bytes)?;
+    let master_key: Option<CompressedEdwardsY> = match keyword_value(text, "master-key-ed25519") {
+        Some(value) => Some(decode_key(value).ok_or(FormatError::Malformed)?),
+        None        => None,
+    };
+
+    keys.signing_key = Some(cert.certified_key);
+    keys.signing_key_expiration = Some(cert.expiration);
+    keys.identity = cert.signing_key.or(master_key);
+
+    if cert.cert_type != CERT_TYPE_SIGNING_KEY {
+        keys.problems.push(AuthorityProblem::CertificateType);
+    }
+    match cert.signing_key {
+        None => keys.problems.push(AuthorityProblem::MissingIdentity),
+        Some(ref identity) => {
+            if master_key.is_some() && master_key != cert.signing_key {
+                keys.problems.push(AuthorityProblem::IdentityMismatch);
+            }
+            if UncheckedKey::new(*identity).check(KeyPolicy::Strict).is_none() {
+                keys.problems.push(AuthorityProblem::InvalidIdentityKey);
+            }
+            let signed: &[u8] = &cert_bytes[..cert.signed_length];
+
+            if !verify(identity, signed, &cert.signature, VerificationProfile::Cofactorless) {
+                keys.problems.push(AuthorityProblem::BadCertificateSignature);
+            }
+        },
+    }
+    if UncheckedKey::new(cert.certified_key).check(KeyPolicy::Strict).is_none() {
+        keys.problems.push(AuthorityProblem::InvalidSigningKey);
+    }
+    if !document_signature_verifies(text, &cert.certified_key) {
+        keys.problems.push(AuthorityProblem::BadDocumentSignature);
+    }
+    Ok(keys)
+}
+
+// Whether `router-sig-ed25519` verifies under `signing_key`.
+fn document_signature_verifies(text: &str, signing_key: &CompressedEdwardsY) -> bool {
+    let start: usize = match text.find("\nrouter-sig-ed25519 ") {
+        Some(i) => i + 1 + "router-sig-ed25519 ".len(),
+        None    => return false, // there is no signature
+    };
+    let encoded: &str = text[start..].lines().next().unwrap_or("");
+    let mut signature: [u8; 64] = [0u8; 64];
+    match base64_decode(encoded.trim()) {
+        Some(ref bytes) if bytes.len() == 64 => signature.copy_from_slice(bytes),
+        _                                    => return false,
+    }
+
+    verify(signing_key, &document_digest(&text[..start]), &signature, VerificationProfile::Cofactorless)
+}
+
+// The digest `router-sig-ed25519` signs, of the document up to the signature.
+fn document_digest(signed: &str) -> Vec<u8> {
+    let mut h: Sha256 = Sha256::default();
+    h.input(SIGNATURE_PREFIX);
+    h.input(signed.as_bytes());
+
+    h.result().as_slice().to_vec()
+}
+
+// The rest of the first line starting with `keyword` and a space.
+fn keyword_value<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    text.lines()
+        .find(|line| line.starts_with(keyword) && line[keyword.len()..].starts_with(' '))
+        .map(|line| line[keyword.len() + 1..].trim())
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use ed25519::public_key_from_seed;
+    use ed25519::sign;
+    use encoding::base64_encode;
+    use formats::cert::unsigned_certificate;
+
+    static IDENTITY_SEED: [u8; 32] = [1u8; 32];
+    static SIGNING_SEED: [u8; 32] = [2u8; 32];
+
+    fn header() -> String {
+        "dir-key-certificate-version 3\nfingerprint 0123456789ABCDEF0123456789ABCDEF01234567\n\
+         dir-key-published 2017-05-01 00:00:00\ndir-key-expires 2018-05-01 00:00:00\n".to_string()
+    }
+
+    // A key certificate whose signing key is certified by `certifier`, with
+    // `master` on the `master-key-ed25519` line.
+    fn key_certificate(certifier: &[u8; 32], master: &CompressedEdwardsY) -> String {
+        let signing_key: CompressedEdwardsY = public_key_from_seed(&SIGNING_SEED);
+        let mut cert: Vec<u8> = unsigned_certificate(CERT_TYPE_SIGNING_KEY, 420_000, &signing_key,
+                                                     Some(&public_key_from_seed(certifier)));
+        let signature: [u8; 64] = sign(certifier, &cert);
+        cert.extend_from_slice(&signature);
+
+        let mut text: String = format!("{}identity-ed25519\n\
+                                        -----BEGIN ED25519 CERT-----\n{}\n-----END ED25519 CERT-----\n\
+                                        master-key-ed25519 {}\nrouter-sig-ed25519 ",
+                                       header(), base64_encode(&cert, true), base64_encode(master.as_bytes(), false));
+        let signature: [u8; 64] = sign(&SIGNING_SEED, &document_digest(&text));
+        text.push_str(&base64_encode(&signature, false));
+        text.push_str("\ndir-key-certification\n-----BEGIN SIGNATURE-----\nAAAA\n-----END SIGNATURE-----\n");
+        text
+    }
+
+    #[test]
+    fn valid_certificates_pass() {
+        let identity: CompressedEdwardsY = public_key_from_seed(&IDENTITY_SEED);
+        let keys: AuthorityKeys = check_key_certificate(&key_certificate(&IDENTITY_SEED, &identity)).unwrap();
+
+        assert!(keys.is_valid(), "{:?}", keys.problems());
+        assert_eq!(keys.identity(), Some(&identity));
+        assert_eq!(keys.signing_key(), Some(&public_key_from_seed(&SIGNING_SEED)));
+        assert_eq!(keys.signing_key_expiration(), Some(420_000));
+        assert_eq!(keys.fingerprint()[..2], [0x01, 0x23]);
+        assert_eq!(keys.published(), "2017-05-01 00:00:00");
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let identity: CompressedEdwardsY = public_key_from_seed(&IDENTITY_SEED);
+        let other: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+
+        let keys: AuthorityKeys = check_key_certificate(&key_certificate(&IDENTITY_SEED, &other)).unwrap();
+        assert_eq!(keys.problems(), &[AuthorityProblem::IdentityMismatch]);
+
+        // The document signature no longer covers what follows it.
+        let tampered: String = key_certificate(&IDENTITY_SEED, &identity)
+            .replace("2018-05-01", "2019-05-01");
+        assert_eq!(check_key_certificate(&tampered).unwrap().problems(), &[AuthorityProblem::BadDocumentSignature]);
+
+        let rsa_only: AuthorityKeys = check_key_certificate(&header()).unwrap();
+        assert_eq!(rsa_only.problems(), &[AuthorityProblem::NoEd25519Keys]);
+        assert!(rsa_only.signing_key().is_none());
+
+        assert_eq!(check_key_certificate("dir-key-certificate-version 2\n"), Err(FormatError::Malformed));
+        assert_eq!(check_key_certificate(&header().replace("fingerprint 01", "fingerprint ")),
+                   Err(FormatError::Malformed));
+    }
+}
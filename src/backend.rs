@@ -0,0 +1,244 @@
+// Running the key checks on more than one curve25519 implementation, and
+// comparing their answers.
+//
+// Before a validation rule is recommended for tor, it should be known to
+// give the same answers whichever library a relay was built with; and an
+// answer which two independent implementations agree on is much less likely
+// to be an artifact of a bug in one of them.  A `Backend` is one
+// implementation's answer to "does this encoding pass under this policy?",
+// and `differential` runs two of them over a list of encodings, collecting
+// every one they disagree about.  The lists come from `adversarial_encodings`,
+// the cases implementations are known to get wrong, and `random_encodings`.
+//
+// `Native` is this crate, on curve25519-dalek 0.9; `Fiat` (with `fiat`) is
+// the fiat-crypto re-implementation in `fiat`; and `Dalek4` (with
+// `curve25519-dalek-4`) is curve25519-dalek 4, whose field and curve code
+// was rewritten since 0.9.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use entropy::EntropySource;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// One implementation of the key checks.
+pub trait Backend {
+    /// A short name for reports.
+    fn name(&self) -> &'static str;
+
+    /// Whether `encoding` passes under `policy`, as `UncheckedKey::check`
+    /// decides it.
+    fn accepts(&self, encoding: &[u8; 32], policy: KeyPolicy) -> bool;
+}
+
+/// This crate's own checks.
+#[derive(Copy, Clone, Debug)]
+pub struct Native;
+
+impl Backend for Native {
+    fn name(&self) -> &'static str {
+        "curve25519-dalek-0.9"
+    }
+
+    fn accepts(&self, encoding: &[u8; 32], policy: KeyPolicy) -> bool {
+        UncheckedKey::new(CompressedEdwardsY(*encoding)).check(policy).is_some()
+    }
+}
+
+/// The checks redone in fiat-crypto's field arithmetic.
+#[cfg(feature = "fiat")]
+#[derive(Copy, Clone, Debug)]
+pub struct Fiat;
+
+#[cfg(feature = "fiat")]
+impl Backend for Fiat {
+    fn name(&self) -> &'static str {
+        "fiat-crypto"
+    }
+
+    fn accepts(&self, encoding: &[u8; 32], policy: KeyPolicy) -> bool {
+        let p: ::fiat::FiatPoint = match ::fiat::decompress(&CompressedEdwardsY(*encoding)) {
+            Some(p) => p,
+            None    => return false, // the point was invalid
+        };
+        if !p.is_torsion_free() {
+            return false;
+        }
+
+        let mut canonical: [u8; 32] = *p.y();
+        canonical[31] |= (p.x()[0] & 1) << 7;
+        let identity: bool = *p.x() == [0u8; 32] && p.y()[0] == 1 && p.y()[1..] == [0u8; 31];
+
+        match policy {
+            KeyPolicy::PrimeOrder => true,
+            KeyPolicy::Strict     => canonical == *encoding && !identity,
+        }
+    }
+}
+
+/// curve25519-dalek 4.
+#[cfg(feature = "curve25519-dalek-4")]
+#[derive(Copy, Clone, Debug)]
+pub struct Dalek4;
+
+#[cfg(feature = "curve25519-dalek-4")]
+impl Backend for Dalek4 {
+    fn name(&self) -> &'static str {
+        "curve25519-dalek-4"
+    }
+
+    fn accepts(&self, encoding: &[u8; 32], policy: KeyPolicy) -> bool {
+        use curve25519_dalek_4::edwards::CompressedEdwardsY as CompressedEdwardsY4;
+        use curve25519_dalek_4::edwards::EdwardsPoint;
+        use curve25519_dalek_4::traits::IsIdentity;
+
+        let p: EdwardsPoint = match CompressedEdwardsY4(*encoding).decompress() {
+            Some(p) => p,
+            None    => return false, // the point was invalid
+        };
+
+        match policy {
+            KeyPolicy::PrimeOrder => p.is_torsion_free(),
+            KeyPolicy::Strict     => p.is_torsion_free() && p.compress().0 == *encoding && !p.is_identity(),
+        }
+    }
+}
+
+/// Two backends gave different answers for an encoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    encoding: [u8; 32],
+    policy: KeyPolicy,
+    first: bool,
+}
+
+impl Divergence {
+    /// The encoding they disagreed about.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// The policy it was checked under.
+    pub fn policy(&self) -> KeyPolicy {
+        self.policy
+    }
+
+    /// Whether the first backend accepted it.  The second said the opposite.
+    pub fn first_accepted(&self) -> bool {
+        self.first
+    }
+}
+
+/// Check every one of `encodings` under both policies with both backends,
+/// returning each case where they disagree.
+pub fn differential<A: Backend, B: Backend>(first: &A, second: &B, encodings: &[[u8; 32]]) -> Vec<Divergence> {
+    let mut divergences: Vec<Divergence> = Vec::new();
+
+    for encoding in encodings.iter() {
+        for &policy in [KeyPolicy::PrimeOrder, KeyPolicy::Strict].iter() {
+            let accepted: bool = first.accepts(encoding, policy);
+
+            if accepted != second.accepts(encoding, policy) {
+                divergences.push(Divergence { encoding: *encoding, policy, first: accepted });
+            }
+        }
+    }
+    divergences
+}
+
+/// The encodings implementations are known to disagree about: every point
+/// of small order, with and without its sign bit flipped; a prime-order
+/// point plus each of them; every non-canonical encoding, of y + p for
+/// y < 19, with either sign; and some which aren't points at all.
+pub fn adversarial_encodings() -> Vec<[u8; 32]> {
+    let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+    let mut encodings: Vec<[u8; 32]> = Vec::new();
+
+    for t in constants::EIGHT_TORSION.iter() {
+        let small: [u8; 32] = t.compress_edwards().to_bytes();
+        let mut flipped: [u8; 32] = small;
+        flipped[31] ^= 0x80;
+
+        encodings.push(small);
+        encodings.push(flipped);
+        encodings.push((&p + t).compress_edwards().to_bytes());
+    }
+
+    // p = 2^255 - 19 is ed ff ... ff 7f, little-endian.
+    for y in 0..19u8 {
+        let mut unreduced: [u8; 32] = [0xff; 32];
+        unreduced[0] = 0xed + y;
+        unreduced[31] = 0x7f;
+
+        encodings.push(unreduced);
+        unreduced[31] |= 0x80;
+        encodings.push(unreduced);
+    }
+
+    encodings.push([0xff; 32]);
+    encodings.push([0x02; 32]);
+    encodings
+}
+
+/// `count` uniformly random encodings, about half of which are points.
+pub fn random_encodings<E: EntropySource>(entropy: &mut E, count: usize) -> Vec<[u8; 32]> {
+    (0..count).map(|_| {
+        let mut encoding: [u8; 32] = [0u8; 32];
+
+        entropy.fill_bytes(&mut encoding);
+        encoding
+    }).collect()
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use entropy::OsEntropy;
+
+    // Accepts anything which decompresses, as a verifier which skips the
+    // torsion check would.
+    struct Decompresses;
+
+    impl Backend for Decompresses {
+        fn name(&self) -> &'static str {
+            "decompress-only"
+        }
+
+        fn accepts(&self, encoding: &[u8; 32], _: KeyPolicy) -> bool {
+            CompressedEdwardsY(*encoding).decompress().is_some()
+        }
+    }
+
+    fn encodings() -> Vec<[u8; 32]> {
+        let mut encodings: Vec<[u8; 32]> = adversarial_encodings();
+
+        encodings.extend(random_encodings(&mut OsEntropy::new(), 256));
+        encodings
+    }
+
+    #[test]
+    fn divergences_are_found() {
+        let divergences: Vec<Divergence> = differential(&Native, &Decompresses, &adversarial_encodings());
+
+        assert!(divergences.iter().all(|d| !d.first_accepted()));
+        // Each of the seven torsioned points, at least.
+        assert!(divergences.iter().filter(|d| d.policy() == KeyPolicy::PrimeOrder).count() >= 7);
+        assert_eq!(differential(&Native, &Native, &encodings()), vec![]);
+    }
+
+    #[cfg(feature = "fiat")]
+    #[test]
+    fn fiat_agrees() {
+        assert_eq!(differential(&Native, &Fiat, &encodings()), vec![]);
+    }
+
+    #[cfg(feature = "curve25519-dalek-4")]
+    #[test]
+    fn dalek4_agrees() {
+        assert_eq!(differential(&Native, &Dalek4, &encodings()), vec![]);
+    }
+}
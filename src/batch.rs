@@ -0,0 +1,103 @@
+// Validating large batches of keys which repeat themselves.
+//
+// A consensus document names each relay's keys once per appearance, and the
+// same keys turn up again in every descriptor, microdescriptor, and vote, so
+// a batch assembled from directory data is mostly duplicates.  Sorting the
+// batch first means each distinct encoding is decompressed and checked once,
+// and the answer is then copied back to every position it came from.
+
+use traits::PrimeOrderEncoding;
+
+/// Validate every key in `keys`, returning results in the same order as
+/// `traits::validate_batch` would, but validating each distinct encoding
+/// only once.
+pub fn validate_deduplicated<E>(keys: &[E]) -> Vec<Option<E::Point>>
+    where E: PrimeOrderEncoding, E::Point: Clone
+{
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    let mut results: Vec<Option<E::Point>> = vec![None; keys.len()];
+
+    order.sort_by(|&i, &j| keys[i].as_slice().cmp(keys[j].as_slice()));
+
+    let mut start: usize = 0;
+    while start < order.len() {
+        let key: &E = &keys[order[start]];
+        let result: Option<E::Point> = key.validate();
+
+        let mut end: usize = start;
+        while end < order.len() && keys[order[end]].as_slice() == key.as_slice() {
+            results[order[end]] = result.clone();
+            end += 1;
+        }
+        start = end;
+    }
+    results
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::CompressedEdwardsY;
+    use curve25519_dalek::scalar::Scalar;
+
+    use traits::validate_batch;
+
+    // Counts how many times it is asked to validate.
+    struct CountingKey<'a>(CompressedEdwardsY, &'a Cell<usize>);
+
+    impl<'a> PrimeOrderEncoding for CountingKey<'a> {
+        type Point = ();
+
+        fn as_slice(&self) -> &[u8] {
+            self.0.as_bytes()
+        }
+
+        fn validate(&self) -> Option<()> {
+            self.1.set(self.1.get() + 1);
+            self.0.validate().map(|_| ())
+        }
+
+        fn validate_strict(&self) -> Option<()> {
+            self.validate()
+        }
+    }
+
+    #[test]
+    fn results_are_scattered_back_in_order() {
+        let p: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let q: CompressedEdwardsY = (&constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1])
+            .compress_edwards();
+        let r: CompressedEdwardsY = (&Scalar::from_u64(3) * &constants::ED25519_BASEPOINT).compress_edwards();
+        let keys: [CompressedEdwardsY; 6] = [q, p, r, p, q, p];
+
+        let expected: Vec<bool> = validate_batch(&keys).iter().map(|x| x.is_some()).collect();
+        let actual: Vec<bool> = validate_deduplicated(&keys).iter().map(|x| x.is_some()).collect();
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual, vec![false, true, true, true, false, true]);
+    }
+
+    #[test]
+    fn each_distinct_key_is_validated_once() {
+        let count: Cell<usize> = Cell::new(0);
+        let p: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let q: CompressedEdwardsY = (&Scalar::from_u64(3) * &constants::ED25519_BASEPOINT).compress_edwards();
+        let keys: Vec<CountingKey> = (0..100)
+            .map(|i| CountingKey(if i % 3 == 0 { q } else { p }, &count))
+            .collect();
+
+        assert_eq!(validate_deduplicated(&keys).len(), 100);
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn empty_batches() {
+        let keys: [CompressedEdwardsY; 0] = [];
+
+        assert!(validate_deduplicated(&keys).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+// A small HTTP front end to the validators, so that components not written
+// in Rust can share one validator.  Built only with the `serve` feature.
+//
+//    serve [ADDRESS]                     (default 127.0.0.1:2206)
+//
+//    POST /v1/check?policy=strict        check the keys in the body
+//    GET  /healthz                       always {"status":"ok"}
+//
+// The body of a check is one ed25519 key per line, as hex or base64, and the
+// policy is `strict` (the default) or `prime_order`.  The response is a JSON
+// object with one verdict per line of the body, in order:
+//
+//    {"verdicts":[{"key":"...","valid":false,"problem":"torsioned"}, ...]}
+
+extern crate tiny_http;
+extern crate tor22006;
+
+use std::env;
+use std::io::Read;
+use std::process;
+
+use tiny_http::Header;
+use tiny_http::Method;
+use tiny_http::Request;
+use tiny_http::Response;
+use tiny_http::Server;
+
+use tor22006::encoding::decode_key;
+use tor22006::key::KeyProblem;
+use tor22006::key::UncheckedKey;
+use tor22006::policy::KeyPolicy;
+
+static DEFAULT_ADDRESS: &str = "127.0.0.1:2206";
+
+// Refuse bodies larger than this, which is some 15,000 hex keys.
+const MAX_BODY: u64 = 1 << 20;
+
+fn main() {
+    let address: String = env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+
+    let server: Server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(e)     => {
+            eprintln!("{}: {}", address, e);
+            process::exit(2);
+        },
+    };
+
+    for request in server.incoming_requests() {
+        respond(request);
+    }
+}
+
+fn respond(mut request: Request) {
+    let url: String = request.url().to_string();
+    let (path, query): (&str, &str) = match url.find('?') {
+        Some(i) => (&url[..i], &url[i + 1..]),
+        None    => (&url[..], ""),
+    };
+
+    let (status, body): (u16, String) = match (request.method(), path) {
+        (&Method::Get, "/healthz") => (200, "{\"status\":\"ok\"}".to_string()),
+        (&Method::Post, "/v1/check") => match parse_policy(query) {
+            Some(policy) => {
+                let mut text: String = String::new();
+
+                match request.as_reader().take(MAX_BODY + 1).read_to_string(&mut text) {
+                    Ok(n) if n as u64 <= MAX_BODY => (200, check_keys(&text, policy)),
+                    Ok(_)  => (413, error("body too large")),
+                    Err(_) => (400, error("body is not UTF-8")),
+                }
+            },
+            None => (400, error("policy must be strict or prime_order")),
+        },
+        (_, "/healthz") | (_, "/v1/check") => (405, error("method not allowed")),
+        _ => (404, error("not found")),
+    };
+
+    let content_type: Header = "Content-Type: application/json".parse().expect("a valid header");
+    let response = Response::from_string(body).with_status_code(status).with_header(content_type);
+
+    // The client may have gone away, which is its business.
+    let _ = request.respond(response);
+}
+
+fn parse_policy(query: &str) -> Option<KeyPolicy> {
+    let mut policy: KeyPolicy = KeyPolicy::Strict;
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        policy = match pair {
+            "policy=strict"      => KeyPolicy::Strict,
+            "policy=prime_order" => KeyPolicy::PrimeOrder,
+            _                    => return None,
+        };
+    }
+    Some(policy)
+}
+
+fn check_keys(text: &str, policy: KeyPolicy) -> String {
+    let verdicts: Vec<String> = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty())
+        .map(|line| {
+            let problem: Option<KeyProblem> = match decode_key(line) {
+                Some(key) => UncheckedKey::new(key).check_or_diagnose(policy).err(),
+                None      => Some(KeyProblem::Malformed),
+            };
+            let problem_json: String = match problem {
+                Some(problem) => format!("\"{}\"", problem.as_str()),
+                None          => "null".to_string(),
+            };
+
+            // Hex and base64 need no escaping, and anything else is
+            // already malformed, so it is left out rather than escaped.
+            let key_json: String = match problem {
+                Some(KeyProblem::Malformed) => "null".to_string(),
+                _                           => format!("\"{}\"", line),
+            };
+
+            format!("{{\"key\":{},\"valid\":{},\"problem\":{}}}", key_json, problem.is_none(), problem_json)
+        }).collect();
+
+    format!("{{\"verdicts\":[{}]}}", verdicts.join(","))
+}
+
+fn error(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", message)
+}
@@ -0,0 +1,353 @@
+// The core of the validation API in the shape UniFFI wants, behind the
+// `bindings` feature, so that mobile tor clients in Kotlin or Swift, and
+// anything else UniFFI generates for, share one interface rather than each
+// getting its own hand-written one.
+//
+// `tor22006.udl`, beside this file, declares the interface.  Everything it
+// names is here, with owned arguments and a `Result` for anything which can
+// fail, since UniFFI can't carry borrows or `Option`s of our own types
+// across.  The scaffolding itself is generated in the crate which builds the
+// bindings, with `uniffi::include_scaffolding!("tor22006")` after a
+// `pub use tor22006::bindings::*;`, which keeps UniFFI and its build script
+// out of this crate's dependencies.  The tests check the declarations there
+// against the signatures here instead, since nothing else does until the
+// scaffolding is built.
+
+use std::error::Error;
+use std::fmt;
+
+use rand::OsRng;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519;
+use key::CheckedKey;
+use key::UncheckedKey;
+use onion;
+
+pub use key::KeyProblem;
+pub use policy::KeyPolicy;
+pub use policy::VerificationProfile;
+
+/// Why a call failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A key was not 32 bytes long.
+    WrongLength,
+    /// An onion address was malformed, for another version, or had the wrong
+    /// checksum.
+    BadAddress,
+    /// `KeyProblem::Malformed`.
+    Malformed,
+    /// `KeyProblem::NotOnCurve`.
+    NotOnCurve,
+    /// `KeyProblem::Torsioned`.
+    Torsioned,
+    /// `KeyProblem::NonCanonical`.
+    NonCanonical,
+    /// `KeyProblem::Identity`.
+    Identity,
+}
+
+impl From<KeyProblem> for ValidationError {
+    fn from(problem: KeyProblem) -> ValidationError {
+        match problem {
+            KeyProblem::Malformed    => ValidationError::Malformed,
+            KeyProblem::NotOnCurve   => ValidationError::NotOnCurve,
+            KeyProblem::Torsioned    => ValidationError::Torsioned,
+            KeyProblem::NonCanonical => ValidationError::NonCanonical,
+            KeyProblem::Identity     => ValidationError::Identity,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            ValidationError::WrongLength  => "key is not 32 bytes",
+            ValidationError::BadAddress   => "malformed onion address",
+            ValidationError::Malformed    => "malformed key",
+            ValidationError::NotOnCurve   => "key is not a point on the curve",
+            ValidationError::Torsioned    => "key has a torsion component",
+            ValidationError::NonCanonical => "key is not canonically encoded",
+            ValidationError::Identity     => "key is the identity",
+        })
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Check the 32-byte ed25519 `key` under `policy`.
+pub fn validate_key(key: Vec<u8>, policy: KeyPolicy) -> Result<(), ValidationError> {
+    checked(unchecked(&key)?, policy).map(|_| ())
+}
+
+/// Check the key named by the v3 onion `address` under `policy`, returning
+/// its encoding.
+pub fn check_onion_address(address: String, policy: KeyPolicy) -> Result<Vec<u8>, ValidationError> {
+    let key: UncheckedKey<CompressedEdwardsY> = match onion::parse_onion_address(&address) {
+        Some(key) => key,
+        None      => return Err(ValidationError::BadAddress),
+    };
+    checked(key, policy).map(|key| key.encoding().as_bytes().to_vec())
+}
+
+/// The v3 onion address of the service whose identity `key` is, which must
+/// pass `Strict`.
+pub fn onion_address(key: Vec<u8>) -> Result<String, ValidationError> {
+    checked(unchecked(&key)?, KeyPolicy::Strict).map(|key| onion::onion_address(&key))
+}
+
+/// Check each of the 32-byte `keys` under `policy`, in order, giving `None`
+/// for each which passes and its problem for each which doesn't.  A key of
+/// the wrong length is `Malformed`.
+pub fn validate_keys(keys: Vec<Vec<u8>>, policy: KeyPolicy) -> Vec<Option<KeyProblem>> {
+    keys.iter()
+        .map(|key| match unchecked(key) {
+            Ok(key) => key.check_or_diagnose(policy).err(),
+            Err(_)  => Some(KeyProblem::Malformed),
+        })
+        .collect()
+}
+
+/// Verify the ed25519 `signature` on `message` under `key`.  A key or
+/// signature of the wrong length fails, as an invalid one does.
+pub fn verify_signature(key: Vec<u8>, message: Vec<u8>, signature: Vec<u8>, profile: VerificationProfile) -> bool {
+    match (unchecked(&key), signature_bytes(&signature)) {
+        (Ok(key), Some(signature)) => ed25519::verify(key.encoding(), &message, &signature, profile),
+        _                          => false,
+    }
+}
+
+/// Verify each `signatures[i]` on `messages[i]` under `keys[i]` as one batch,
+/// returning true only if every one of them verifies under `profile`.
+/// Fails if the lists differ in length, or if any key or signature is the
+/// wrong length.
+pub fn verify_signatures(keys: Vec<Vec<u8>>, messages: Vec<Vec<u8>>, signatures: Vec<Vec<u8>>,
+                         profile: VerificationProfile) -> bool {
+    if messages.len() != keys.len() || signatures.len() != keys.len() {
+        return false;
+    }
+    let keys: Vec<CompressedEdwardsY> = match keys.iter().map(|key| unchecked(key).ok().map(|key| *key.encoding())).collect() {
+        Some(keys) => keys,
+        None       => return false,
+    };
+    let signatures: Vec<[u8; 64]> = match signatures.iter().map(|signature| signature_bytes(signature)).collect() {
+        Some(signatures) => signatures,
+        None             => return false,
+    };
+    let messages: Vec<&[u8]> = messages.iter().map(|message| &message[..]).collect();
+
+    // Without the OS's randomness for the batch's coefficients, each is
+    // verified on its own, which needs none.
+    match OsRng::new() {
+        Ok(mut entropy) => ed25519::verify_batch(&keys, &messages, &signatures, profile, &mut entropy),
+        Err(_)          => (0..keys.len()).all(|i| ed25519::verify(&keys[i], messages[i], &signatures[i], profile)),
+    }
+}
+
+fn signature_bytes(signature: &[u8]) -> Option<[u8; 64]> {
+    let mut bytes: [u8; 64] = [0u8; 64];
+
+    match signature.len() {
+        64 => bytes.copy_from_slice(signature),
+        _  => return None,
+    }
+    Some(bytes)
+}
+
+fn unchecked(key: &[u8]) -> Result<UncheckedKey<CompressedEdwardsY>, ValidationError> {
+    let mut bytes: [u8; 32] = [0u8; 32];
+
+    match key.len() {
+        32 => bytes.copy_from_slice(key),
+        _  => return Err(ValidationError::WrongLength),
+    }
+    Ok(UncheckedKey::new(CompressedEdwardsY(bytes)))
+}
+
+fn checked(key: UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+           -> Result<CheckedKey<CompressedEdwardsY>, ValidationError> {
+    key.check_or_diagnose(policy).map_err(ValidationError::from)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use std::any::type_name;
+
+    use curve25519_dalek::constants;
+
+    use ed25519::public_key_from_seed;
+    use ed25519::sign;
+    use onion::adversarial_onion_addresses;
+
+    static UDL: &str = include_str!("tor22006.udl");
+
+    // A function pointer's type, without the paths to the types in it, as
+    // in "fn(Vec<u8>, KeyPolicy) -> Result<(), ValidationError>".
+    fn signature<F>(_: F) -> String {
+        let mut out: String = String::new();
+        let mut word: String = String::new();
+        let mut chars = type_name::<F>().chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                ':' if chars.peek() == Some(&':') => { chars.next(); word.clear(); },
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => word.push(c),
+                _ => { out.push_str(&word); word.clear(); out.push(c); },
+            }
+        }
+        out + &word
+    }
+
+    // The Rust type UniFFI maps `udl` to.
+    fn rust_type(udl: &str) -> String {
+        if let Some(inner) = udl.strip_suffix('?') {
+            return format!("Option<{}>", rust_type(inner));
+        }
+        if let Some(inner) = udl.strip_prefix("sequence<").and_then(|rest| rest.strip_suffix('>')) {
+            return format!("Vec<{}>", rust_type(inner));
+        }
+        match udl {
+            "bytes"   => "Vec<u8>".to_string(),
+            "string"  => "String".to_string(),
+            "boolean" => "bool".to_string(),
+            "void"    => "()".to_string(),
+            _         => udl.to_string(),
+        }
+    }
+
+    // Each function the UDL's namespace declares, with the signature UniFFI
+    // expects it to have.
+    fn declared_functions() -> Vec<(String, String)> {
+        let start: usize = UDL.find("namespace tor22006 {").unwrap();
+        let end: usize = start + UDL[start..].find("};").unwrap();
+        let body: String = UDL[start..end].lines().skip(1)
+            .filter(|line| !line.trim().starts_with("//"))
+            .collect::<Vec<&str>>().join(" ");
+
+        body.split(';').map(|declaration| declaration.trim()).filter(|declaration| !declaration.is_empty())
+            .map(|declaration| {
+                let (throws, declaration): (Option<&str>, &str) = match declaration.strip_prefix("[Throws=") {
+                    Some(rest) => (Some(&rest[..rest.find(']').unwrap()]), rest[rest.find(']').unwrap() + 1..].trim()),
+                    None       => (None, declaration),
+                };
+                let open: usize = declaration.find('(').unwrap();
+                let (returned, name): (&str, &str) = declaration[..open].trim().split_at(declaration[..open].trim().rfind(' ').unwrap());
+                let arguments: Vec<String> = declaration[open + 1..declaration.len() - 1].split(',')
+                    .map(|argument| argument.split_whitespace().collect::<Vec<&str>>())
+                    .map(|words| rust_type(&words[..words.len() - 1].join(" ")))
+                    .collect();
+                let returned: String = match throws {
+                    Some(error) => format!("Result<{}, {}>", rust_type(returned), error),
+                    None        => rust_type(returned),
+                };
+                let signature: String = match returned.as_str() {
+                    "()"     => format!("fn({})", arguments.join(", ")),
+                    returned => format!("fn({}) -> {}", arguments.join(", "), returned),
+                };
+                (name.trim().to_string(), signature)
+            })
+            .collect()
+    }
+
+    // The quoted variants the UDL gives the enum `name`.
+    fn declared_variants(name: &str) -> Vec<String> {
+        let start: usize = UDL.find(&format!("enum {} {{", name)).unwrap();
+        let end: usize = start + UDL[start..].find("};").unwrap();
+
+        UDL[start..end].split('"').skip(1).step_by(2).map(|variant| variant.to_string()).collect()
+    }
+
+    fn debug<T: fmt::Debug>(values: &[T]) -> Vec<String> {
+        values.iter().map(|value| format!("{:?}", value)).collect()
+    }
+
+    #[test]
+    fn udl_matches_the_rust_signatures() {
+        let functions: Vec<(String, String)> = vec![
+            ("validate_key".to_string(),        signature(validate_key as fn(_, _) -> _)),
+            ("check_onion_address".to_string(), signature(check_onion_address as fn(_, _) -> _)),
+            ("onion_address".to_string(),       signature(onion_address as fn(_) -> _)),
+            ("validate_keys".to_string(),       signature(validate_keys as fn(_, _) -> _)),
+            ("verify_signature".to_string(),    signature(verify_signature as fn(_, _, _, _) -> _)),
+            ("verify_signatures".to_string(),   signature(verify_signatures as fn(_, _, _, _) -> _)),
+        ];
+
+        assert_eq!(declared_functions(), functions);
+        assert_eq!(declared_variants("KeyPolicy"), debug(&[KeyPolicy::PrimeOrder, KeyPolicy::Strict]));
+        assert_eq!(declared_variants("VerificationProfile"),
+                   debug(&[VerificationProfile::Cofactored, VerificationProfile::Cofactorless, VerificationProfile::Strict]));
+        assert_eq!(declared_variants("KeyProblem"),
+                   debug(&[KeyProblem::Malformed, KeyProblem::NotOnCurve, KeyProblem::Torsioned,
+                           KeyProblem::NonCanonical, KeyProblem::Identity]));
+        assert_eq!(declared_variants("ValidationError"),
+                   debug(&[ValidationError::WrongLength, ValidationError::BadAddress, ValidationError::Malformed,
+                           ValidationError::NotOnCurve, ValidationError::Torsioned, ValidationError::NonCanonical,
+                           ValidationError::Identity]));
+    }
+
+    #[test]
+    fn keys_are_checked() {
+        let key: Vec<u8> = constants::ED25519_BASEPOINT.compress_edwards().as_bytes().to_vec();
+        let identity: Vec<u8> = constants::EIGHT_TORSION[0].compress_edwards().as_bytes().to_vec();
+
+        assert_eq!(validate_key(key.clone(), KeyPolicy::Strict), Ok(()));
+        assert_eq!(validate_key(identity.clone(), KeyPolicy::PrimeOrder), Ok(()));
+        assert_eq!(validate_key(identity.clone(), KeyPolicy::Strict), Err(ValidationError::Identity));
+        assert_eq!(validate_key(key[1..].to_vec(), KeyPolicy::Strict), Err(ValidationError::WrongLength));
+        assert_eq!(onion_address(identity), Err(ValidationError::Identity));
+    }
+
+    #[test]
+    fn onion_addresses_are_checked() {
+        let key: Vec<u8> = constants::ED25519_BASEPOINT.compress_edwards().as_bytes().to_vec();
+        let address: String = onion_address(key.clone()).unwrap();
+
+        assert_eq!(check_onion_address(address.clone(), KeyPolicy::Strict), Ok(key));
+        assert_eq!(check_onion_address(address[1..].to_string(), KeyPolicy::Strict), Err(ValidationError::BadAddress));
+        for adversarial in adversarial_onion_addresses().iter() {
+            assert_eq!(check_onion_address(adversarial.address().to_string(), KeyPolicy::Strict),
+                       Err(adversarial.problem().into()));
+        }
+        assert_eq!(ValidationError::BadAddress.to_string(), "malformed onion address");
+    }
+
+    #[test]
+    fn batches_of_keys_are_checked() {
+        let keys: Vec<Vec<u8>> = vec![
+            constants::ED25519_BASEPOINT.compress_edwards().as_bytes().to_vec(),
+            constants::EIGHT_TORSION[0].compress_edwards().as_bytes().to_vec(),
+            constants::EIGHT_TORSION[2].compress_edwards().as_bytes().to_vec(),
+            vec![0u8; 31],
+        ];
+
+        assert_eq!(validate_keys(keys.clone(), KeyPolicy::Strict),
+                   vec![None, Some(KeyProblem::Identity), Some(KeyProblem::Torsioned), Some(KeyProblem::Malformed)]);
+        assert_eq!(validate_keys(keys, KeyPolicy::PrimeOrder),
+                   vec![None, None, Some(KeyProblem::Torsioned), Some(KeyProblem::Malformed)]);
+        assert_eq!(validate_keys(Vec::new(), KeyPolicy::Strict), Vec::new());
+    }
+
+    #[test]
+    fn signatures_are_verified() {
+        let seeds: [[u8; 32]; 3] = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        let keys: Vec<Vec<u8>> = seeds.iter().map(|seed| public_key_from_seed(seed).as_bytes().to_vec()).collect();
+        let messages: Vec<Vec<u8>> = vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()];
+        let signatures: Vec<Vec<u8>> = seeds.iter().zip(messages.iter())
+            .map(|(seed, message)| sign(seed, message).to_vec()).collect();
+        let profile: VerificationProfile = VerificationProfile::Strict;
+
+        assert!(verify_signature(keys[0].clone(), messages[0].clone(), signatures[0].clone(), profile));
+        assert!(!verify_signature(keys[0].clone(), messages[1].clone(), signatures[0].clone(), profile));
+        assert!(!verify_signature(keys[0][1..].to_vec(), messages[0].clone(), signatures[0].clone(), profile));
+        assert!(!verify_signature(keys[0].clone(), messages[0].clone(), signatures[0][1..].to_vec(), profile));
+
+        assert!(verify_signatures(keys.clone(), messages.clone(), signatures.clone(), profile));
+        assert!(!verify_signatures(keys.clone(), messages[..2].to_vec(), signatures.clone(), profile));
+        assert!(!verify_signatures(keys.clone(), messages.iter().rev().cloned().collect(), signatures.clone(), profile));
+        assert!(!verify_signatures(vec![keys[0][1..].to_vec()], vec![messages[0].clone()], vec![signatures[0].clone()], profile));
+    }
+}
@@ -0,0 +1,349 @@
+// Onion service key blinding, as in rend-spec-v3 appendix A.2, behind the
+// `blinding` feature.
+//
+// A v3 onion service publishes descriptors under a different key for every
+// time period, derived from its identity key A as A' = [h]A for
+//
+//    h = SHA3-256("Derive temporary signing key" ‖ 0x00 ‖ A ‖ B ‖ N)
+//    N = "key-blind" ‖ INT_8(period number) ‖ INT_8(period length)
+//
+// where B is the basepoint, written out as a string, and h is clamped the way
+// an ed25519 secret scalar is.  Since the clamped h is a multiple of the
+// cofactor, A' has no torsion component even when A does, so blinding hides
+// a torsioned identity key rather than rejecting it; only checking A first
+// catches that.
+//
+// Which relays store a descriptor depends on the period's shared random
+// value as well, through each relay's position on the hash ring,
+//
+//    hs_relay_index = SHA3-256("node-idx" ‖ ID ‖ SRV ‖ INT_8(period number) ‖ INT_8(period length))
+//
+// and `relay_index` only takes an SRV which `srv` has checked.
+
+use sha2::Digest;
+use sha3::Sha3_256;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use ed25519::ExpandedPublicKey;
+use key::CheckedKey;
+use params::reduce_mod_l;
+use srv::SharedRandomValue;
+use wipe::wipe;
+use wipe::wipe_scalar;
+
+static BLIND_STRING: &[u8] = b"Derive temporary signing key\x00";
+static BASEPOINT_STRING: &[u8] =
+    b"(15112221349535400772501151409588531511454012693041857206046113283949847762202, \
+       46316835694926478169428394003475163141307993866256225615783033603165251855960)";
+
+/// The clamped blinding factor h for `key` in the given time period.
+pub fn blinding_factor(key: &CompressedEdwardsY, period_number: u64, period_length: u64) -> Scalar {
+    let mut hash: Sha3_256 = Sha3_256::default();
+    let mut h: [u8; 32] = [0u8; 32];
+
+    hash.input(BLIND_STRING);
+    hash.input(key.as_bytes());
+    hash.input(BASEPOINT_STRING);
+    hash.input(b"key-blind");
+    hash.input(&period_number.to_be_bytes());
+    hash.input(&period_length.to_be_bytes());
+    h.copy_from_slice(hash.result().as_slice());
+
+    let factor: Scalar = clamp(&h);
+
+    wipe(&mut h);
+    factor
+}
+
+// h, clamped.  This is the step tor's ed25519_exts_ref.py tests blinding
+// from, given an h which was never hashed from anything.
+fn clamp(h: &[u8; 32]) -> Scalar {
+    let mut clamped: [u8; 32] = *h;
+
+    clamped[0] &= 248;
+    clamped[31] &= 63;
+    clamped[31] |= 64;
+    let factor: Scalar = Scalar(clamped);
+
+    wipe(&mut clamped);
+    factor
+}
+
+/// Blind `key` for the given time period.
+pub fn blind_public_key(key: &CheckedKey<CompressedEdwardsY>, period_number: u64, period_length: u64)
+    -> CompressedEdwardsY
+{
+    let mut h: Scalar = blinding_factor(key.encoding(), period_number, period_length);
+    let blinded: CompressedEdwardsY = (key.point() * &h).compress_edwards();
+
+    wipe_scalar(&mut h);
+    blinded
+}
+
+/// Blind `key` for the given time period without checking it first, as
+/// tor itself does.  Returns `None` only if `key` is not a point at all.
+pub fn blind_public_key_unchecked(key: &CompressedEdwardsY, period_number: u64, period_length: u64)
+    -> Option<CompressedEdwardsY>
+{
+    let a: ExtendedPoint = key.decompress()?;
+    let mut h: Scalar = blinding_factor(key, period_number, period_length);
+    let blinded: CompressedEdwardsY = (&a * &h).compress_edwards();
+
+    wipe_scalar(&mut h);
+    Some(blinded)
+}
+
+/// Blind `key` for each of the `count` time periods starting at
+/// `first_period`, as a client fetching descriptors ahead of time, or a
+/// service publishing them, does.  Building the table costs about as much as
+/// twenty blindings, so for short windows `blind_public_key` is faster.
+pub fn blind_window(key: &ExpandedPublicKey, first_period: u64, count: u64, period_length: u64)
+    -> Vec<CompressedEdwardsY>
+{
+    (first_period..first_period + count).map(|period_number| {
+        let mut h: Scalar = blinding_factor(key.encoding(), period_number, period_length);
+
+        // The key is torsion-free, so h can be reduced mod l first, as the
+        // table needs.
+        let mut reduced: Scalar = reduce_mod_l(h.as_bytes());
+        let blinded: CompressedEdwardsY = key.mul(&reduced).compress_edwards();
+
+        wipe_scalar(&mut h);
+        wipe_scalar(&mut reduced);
+        blinded
+    }).collect()
+}
+
+/// The position on the hash ring of the relay with ed25519 identity
+/// `identity`, for the given time period.
+pub fn relay_index(identity: &CheckedKey<CompressedEdwardsY>, srv: &SharedRandomValue,
+                   period_number: u64, period_length: u64) -> [u8; 32] {
+    let mut hash: Sha3_256 = Sha3_256::default();
+    let mut index: [u8; 32] = [0u8; 32];
+
+    hash.input(b"node-idx");
+    hash.input(identity.encoding().as_bytes());
+    hash.input(srv.as_bytes());
+    hash.input(&period_number.to_be_bytes());
+    hash.input(&period_length.to_be_bytes());
+    index.copy_from_slice(hash.result().as_slice());
+    index
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use encoding::hex_decode;
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+    use traits::PrimeOrderEncoding;
+
+    // One day, in minutes, as in the consensus default.
+    const PERIOD_LENGTH: u64 = 1440;
+
+    fn bytes_from_hex(hex: &str) -> [u8; 32] {
+        let mut bytes: [u8; 32] = [0u8; 32];
+
+        bytes.copy_from_slice(&hex_decode(hex).unwrap());
+        bytes
+    }
+
+    fn identity_key() -> CheckedKey<CompressedEdwardsY> {
+        let key: CompressedEdwardsY =
+            (&constants::ED25519_BASEPOINT * &Scalar::from_u64(22006)).compress_edwards();
+
+        UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap()
+    }
+
+    #[test]
+    fn blinded_keys_are_valid_and_differ_by_period() {
+        let key: CheckedKey<CompressedEdwardsY> = identity_key();
+        let today: CompressedEdwardsY = blind_public_key(&key, 17000, PERIOD_LENGTH);
+        let tomorrow: CompressedEdwardsY = blind_public_key(&key, 17001, PERIOD_LENGTH);
+
+        assert!(today.validate_strict().is_some());
+        assert!(today != tomorrow);
+        assert_eq!(blind_public_key_unchecked(key.encoding(), 17000, PERIOD_LENGTH), Some(today));
+    }
+
+    // Keys A, unclamped factors h, and the blinded keys [h]A, as generated
+    // by tor's ed25519_exts_ref.py and used in its test_crypto.c; Arti's
+    // tor-llcrypto and onionbalance test against the same ones.
+    #[test]
+    fn blinding_matches_c_tor() {
+        let vectors: [(&str, &str, &str); 10] = [
+            ("c2247870536a192d142d056abefca68d6193158e7c1a59c1654c954eccaff894",
+             "54a513898b471d1d448a2f3c55c1de2c0ef718c447b04497eeb999ed32027823",
+             "1fc1fa4465bd9d4956fdbdc9d3acb3c7019bb8d5606b951c2e1dfe0b42eaeb41"),
+            ("1519a3b15816a1aafab0b213892026ebf5c0dc232c58b21088d88cb90e9b940d",
+             "831e9b5325b5d31b7ae6197e9c7a7baf2ec361e08248bce055908971047a2347",
+             "1cbbd4a88ce8f165447f159d9f628ada18674158c4f7c5ead44ce8eb0fa6eb7e"),
+            ("081faa81992e360ea22c06af1aba096e7a73f1c665bc8b3e4e531c46455fd1dd",
+             "ac78a1d46faf3bfbbdc5af5f053dc6dc9023ed78236bec1760dadfd0b2603760",
+             "c5419ad133ffde7e0ac882055d942f582054132b092de377d587435722deb028"),
+            ("73cfa1189a723aad7966137cbffa35140bb40d7e16eae4c40b79b5f0360dd65a",
+             "f9c84dc0ac31571507993df94da1b3d28684a12ad14e67d0a068aba5c53019fc",
+             "3e08d0dc291066272e313014bfac4d39ad84aa93c038478a58011f431648105f"),
+            ("66c1a77104d86461b6f98f73acf3cd229c80624495d2d74d6fda1e940080a96b",
+             "b1fe79d1dec9bc108df69f6612c72812755751f21ecc5af99663b30be8b9081f",
+             "59381f06acb6bf1389ba305f70874eed3e0f2ab57cdb7bc69ed59a9b8899ff4d"),
+            ("d21c294db0e64cb2d8976625786ede1d9754186ae8197a64d72f68c792eecc19",
+             "81f1512b63ab5fb5c1711a4ec83d379c420574aedffa8c3368e1c3989a3a0084",
+             "2b946a484344eb1c17c89dd8b04196a84f3b7222c876a07a4cece85f676f87d9"),
+            ("c4d58b4cf85a348ff3d410dd936fa460c4f18da962c01b1963792b9dcc8a6ea6",
+             "97f45142597c473a4b0e9a12d64561133ad9e1155fe5a9807fe6af8a93557818",
+             "c6b585129b135f8769df2eba987e76e089e80ba3a2a6729134d3b28008ac098e"),
+            ("95126f14d86494020665face03f2d42ee2b312a85bc729903eb17522954a1c4a",
+             "3f44f6a5a92cde816635dfc12ade70539871078d2ff097278be2a555c9859cd0",
+             "0eefdc795b59cabbc194c6174e34ba9451e8355108520554ec285acabebb34ac"),
+            ("95126f14d86494020665face03f2d42ee2b312a85bc729903eb17522954a1c4a",
+             "0000000000000000000000000000000000000000000000000000000000000000",
+             "312404d06a0a9de489904b18d5233e83a50b225977fa8734f2c897a73c067952"),
+            ("95126f14d86494020665face03f2d42ee2b312a85bc729903eb17522954a1c4a",
+             "1111111111111111111111111111111111111111111111111111111111111111",
+             "952a908a4a9e0e5176a2549f8f328955aca6817a9fdc59e3acec5dec50838108"),
+        ];
+
+        for &(key, h, blinded) in vectors.iter() {
+            let key: CheckedKey<CompressedEdwardsY> =
+                UncheckedKey::new(CompressedEdwardsY(bytes_from_hex(key))).check(KeyPolicy::Strict).unwrap();
+
+            assert_eq!((key.point() * &clamp(&bytes_from_hex(h))).compress_edwards(),
+                       CompressedEdwardsY(bytes_from_hex(blinded)));
+        }
+    }
+
+    // h, and [h]A, for a whole period, from a separate implementation of
+    // appendix A.2 in Python, which gives the answers above for their h.
+    #[test]
+    fn blinding_for_a_period_matches_known_answers() {
+        let key: CheckedKey<CompressedEdwardsY> = identity_key();
+
+        assert_eq!(key.encoding(), &CompressedEdwardsY(bytes_from_hex("70259b904cc2ce3916ea873d24ef3e6d6303d557c4e51753e929a69de30fcd4b")));
+        assert_eq!(blinding_factor(key.encoding(), 17000, PERIOD_LENGTH),
+                   clamp(&bytes_from_hex("f4f6e74fb677c4440cbf1b75a41ba03b0f66faf2376d4e34de6c3517c1a7bb93")));
+        assert_eq!(blind_public_key(&key, 17000, PERIOD_LENGTH),
+                   CompressedEdwardsY(bytes_from_hex("b988be1d922bcc922bd9cfa143b3fd98dcca37c0cce270dfbe1bf983aa04082c")));
+    }
+
+    #[test]
+    fn windows_match_single_blindings() {
+        let key: CheckedKey<CompressedEdwardsY> = identity_key();
+        let window: Vec<CompressedEdwardsY> =
+            blind_window(&ExpandedPublicKey::new(&key), 17000, 3, PERIOD_LENGTH);
+
+        assert_eq!(window, (17000..17003).map(|n| blind_public_key(&key, n, PERIOD_LENGTH))
+                                         .collect::<Vec<CompressedEdwardsY>>());
+    }
+
+    #[test]
+    fn blinding_hides_torsion() {
+        let key: CompressedEdwardsY = (identity_key().point() + &constants::EIGHT_TORSION[1]).compress_edwards();
+        let blinded: CompressedEdwardsY = blind_public_key_unchecked(&key, 17000, PERIOD_LENGTH).unwrap();
+
+        assert!(key.validate().is_none());
+        assert!(blinded.validate_strict().is_some());
+    }
+
+    #[test]
+    fn relay_indices_depend_on_the_srv() {
+        let key: CheckedKey<CompressedEdwardsY> = identity_key();
+        let current: SharedRandomValue =
+            SharedRandomValue::parse("shared-rand-current-value 9 AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+        let previous: SharedRandomValue =
+            SharedRandomValue::parse("shared-rand-previous-value 9 AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").unwrap();
+
+        assert!(relay_index(&key, &current, 17000, PERIOD_LENGTH) != relay_index(&key, &previous, 17000, PERIOD_LENGTH));
+        assert!(relay_index(&key, &current, 17000, PERIOD_LENGTH) != relay_index(&key, &current, 17001, PERIOD_LENGTH));
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::*;
+
+    use test::Bencher;
+
+    use curve25519_dalek::constants;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    const PERIOD_LENGTH: u64 = 1440;
+
+    fn key() -> CompressedEdwardsY {
+        (&constants::ED25519_BASEPOINT * &Scalar::from_u64(22006)).compress_edwards()
+    }
+
+    #[bench]
+    fn blind_without_validation(b: &mut Bencher) {
+        let key: CompressedEdwardsY = key();
+
+        b.iter(| | blind_public_key_unchecked(&key, 17000, PERIOD_LENGTH) )
+    }
+
+    #[bench]
+    fn blind_validating_input(b: &mut Bencher) {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(key());
+
+        b.iter(| | key.check(KeyPolicy::Strict).map(|key| blind_public_key(&key, 17000, PERIOD_LENGTH)) )
+    }
+
+    #[bench]
+    fn blind_validating_input_and_output(b: &mut Bencher) {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(key());
+
+        b.iter(| | {
+            key.check(KeyPolicy::Strict)
+                .and_then(|key| UncheckedKey::new(blind_public_key(&key, 17000, PERIOD_LENGTH))
+                                    .check(KeyPolicy::Strict))
+        })
+    }
+
+    // A window of three periods, which is what a service publishing for the
+    // current and next periods, with one spare, derives.
+    #[bench]
+    fn blind_window_without_validation(b: &mut Bencher) {
+        let key: CompressedEdwardsY = key();
+
+        b.iter(| | {
+            (17000..17003).map(|n| blind_public_key_unchecked(&key, n, PERIOD_LENGTH)).collect::<Vec<_>>()
+        })
+    }
+
+    #[bench]
+    fn blind_window_validating_input(b: &mut Bencher) {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(key());
+
+        b.iter(| | {
+            key.check(KeyPolicy::Strict)
+                .map(|key| (17000..17003).map(|n| blind_public_key(&key, n, PERIOD_LENGTH)).collect::<Vec<_>>())
+        })
+    }
+
+    // A month of periods at once, from a table.
+    #[bench]
+    fn blind_long_window_validating_input(b: &mut Bencher) {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(key());
+
+        b.iter(| | {
+            key.check(KeyPolicy::Strict)
+                .map(|key| blind_window(&ExpandedPublicKey::new(&key), 17000, 30, PERIOD_LENGTH))
+        })
+    }
+
+    #[bench]
+    fn blind_long_window_without_validation(b: &mut Bencher) {
+        let key: CompressedEdwardsY = key();
+
+        b.iter(| | {
+            (17000..17030).map(|n| blind_public_key_unchecked(&key, n, PERIOD_LENGTH)).collect::<Vec<_>>()
+        })
+    }
+}
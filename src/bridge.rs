@@ -0,0 +1,140 @@
+// Conversions between the prime-order group encodings and plain ed25519
+// points, for systems which hold both kinds of key material while migrating.
+//
+// A `DecafPoint` or `RistrettoPoint` wraps some `ExtendedPoint` in the coset
+// P + E[4], and which representative it is depends on how the point was
+// produced.  Exactly one member of each coset is torsion-free, and that is the
+// point an ed25519 implementation would agree upon, so that is what these
+// helpers hand out.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::decaf::DecafPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use edwards::mul_by_pow_2;
+use ristretto::CompressedRistretto;
+use ristretto::RistrettoPoint;
+
+/// Return the unique torsion-free `ExtendedPoint` in the coset represented by
+/// `point`.
+pub fn decaf_to_edwards(point: &DecafPoint) -> ExtendedPoint {
+    torsion_free_representative(&point.0)
+}
+
+/// Lift a torsion-free `ExtendedPoint` into the decaf group.
+///
+/// Returns `None` if `point` has a torsion component, since such a point is
+/// not what any ed25519 key in the prime-order subgroup decompresses to, and
+/// silently projecting it would hide exactly the problem this crate is
+/// about.
+pub fn edwards_to_decaf(point: &ExtendedPoint) -> Option<DecafPoint> {
+    match (point * &constants::l).is_identity() {
+        true  => Some(DecafPoint(*point)),
+        false => None, // the point has a torsion component
+    }
+}
+
+/// Translate a ristretto255 encoding into the ed25519 public key for the same
+/// group element.
+///
+/// Returns `None` if `key` doesn't decode.  Otherwise the result is the
+/// canonical Edwards encoding of the torsion-free point in the coset, which
+/// is checked to be in the prime-order subgroup again before it is handed
+/// out, so that nothing published under this bridge could fail validation by
+/// `mult_by_cofactor_and_validate` on the other side.
+pub fn ristretto_to_ed25519(key: &CompressedRistretto) -> Option<CompressedEdwardsY> {
+    let p: RistrettoPoint = key.decompress()?;
+    let q: ExtendedPoint = torsion_free_representative(&p.0);
+
+    match (&q * &constants::l).is_identity() {
+        true  => Some(q.compress_edwards()),
+        false => None,
+    }
+}
+
+/// Translate an ed25519 public key into the ristretto255 encoding of the same
+/// group element.
+///
+/// Returns `None` unless `key` is the canonical encoding of a point in the
+/// prime-order subgroup: any other key would either have no ristretto
+/// equivalent or share one with a different ed25519 key, and in both cases
+/// the translation wouldn't round-trip.
+pub fn ed25519_to_ristretto(key: &CompressedEdwardsY) -> Option<CompressedRistretto> {
+    let p: ExtendedPoint = key.decompress()?;
+
+    if p.compress_edwards() != *key {
+        return None; // non-canonical encoding
+    }
+
+    match (&p * &constants::l).is_identity() {
+        true  => Some(RistrettoPoint(p).compress()),
+        false => None, // the point has a torsion component
+    }
+}
+
+// Computes 4⁻¹·(4·P), where 4⁻¹ is taken mod l: multiplying by four kills the
+// 4-torsion component, and 4⁻¹ then undoes the multiplication on the
+// prime-order component.
+fn torsion_free_representative(point: &ExtendedPoint) -> ExtendedPoint {
+    let four_inverse: Scalar = Scalar::from_u64(4).invert();
+
+    &mul_by_pow_2(point, 2) * &four_inverse
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use rand::OsRng;
+
+    #[test]
+    fn decaf_roundtrip() {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let p: DecafPoint = DecafPoint::random(&mut csprng);
+        let q: ExtendedPoint = decaf_to_edwards(&p);
+
+        assert!((&q * &constants::l).is_identity());
+        assert_eq!(edwards_to_decaf(&q).unwrap().compress(), p.compress());
+    }
+
+    #[test]
+    fn every_coset_member_maps_to_the_same_point() {
+        let a: Scalar = Scalar::from_u64(8675309);
+        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
+
+        for i in [0, 2, 4, 6].iter() {
+            let q: DecafPoint = DecafPoint(&p + &constants::EIGHT_TORSION[*i]);
+
+            assert_eq!(decaf_to_edwards(&q).compress_edwards(), p.compress_edwards());
+        }
+    }
+
+    #[test]
+    fn ristretto_roundtrip() {
+        let a: Scalar = Scalar::from_u64(31337);
+        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
+        let key: CompressedEdwardsY = p.compress_edwards();
+
+        let translated: CompressedRistretto = ed25519_to_ristretto(&key).unwrap();
+
+        assert_eq!(translated, RistrettoPoint(p).compress());
+        assert_eq!(ristretto_to_ed25519(&translated), Some(key));
+    }
+
+    #[test]
+    fn torsioned_ed25519_keys_have_no_ristretto_equivalent() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[2];
+
+        assert!(ed25519_to_ristretto(&p.compress_edwards()).is_none());
+    }
+
+    #[test]
+    fn torsioned_points_are_not_lifted() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+
+        assert!(edwards_to_decaf(&p).is_none());
+    }
+}
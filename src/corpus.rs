@@ -0,0 +1,284 @@
+// Building a corpus of encodings which two validators disagree about, each
+// annotated with how it was made, as a concrete answer to "which keys would
+// change status if tor switched rules?".
+//
+// A `Validator` is a `Backend` run under a `KeyPolicy`, so the same library
+// under two policies can be compared as well as two libraries under one.
+// Nothing is searched for at random: the candidates are constructed to hit
+// each place validators are known to differ, which is every point of small
+// order, with either sign bit; a prime-order point plus each of them; every
+// y + p for y < 19, which is every non-canonical encoding of y, with either
+// sign bit; and an encoding which isn't a point.  `DecompressOnly` stands
+// for a validator which checks nothing beyond decompression, as tor did
+// when tor#22006 was opened.
+//
+// `write_corpus` writes one line per divergence,
+//
+//    <hex encoding> <first verdict> <second verdict> <annotation>
+//
+// under a header naming both validators, with "accept" or "reject" for each
+// verdict.
+//
+// The candidates also make a seed corpus for fuzzing, so the fuzz targets
+// start from the encodings most likely to find a bug rather than from
+// noise.  `seed_corpus` adds valid keys and the encodings either side of
+// p = 2^255 - 19 to them, and `write_seed_corpus` writes one file per
+// seed, named by its encoding in hex, into a fuzzer's corpus directory.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::scalar::Scalar;
+
+use audit::torsion_order_log2;
+use backend::Backend;
+use encoding::hex_encode;
+use policy::KeyPolicy;
+
+/// A validator which accepts anything which decompresses, whatever the
+/// policy.
+#[derive(Copy, Clone, Debug)]
+pub struct DecompressOnly;
+
+impl Backend for DecompressOnly {
+    fn name(&self) -> &'static str {
+        "decompress-only"
+    }
+
+    fn accepts(&self, encoding: &[u8; 32], _: KeyPolicy) -> bool {
+        CompressedEdwardsY(*encoding).decompress().is_some()
+    }
+}
+
+/// A backend, and the policy it checks keys under.
+#[derive(Copy, Clone)]
+pub struct Validator<'a> {
+    backend: &'a dyn Backend,
+    policy: KeyPolicy,
+}
+
+impl<'a> Validator<'a> {
+    /// Run `backend` under `policy`.
+    pub fn new(backend: &'a dyn Backend, policy: KeyPolicy) -> Validator<'a> {
+        Validator { backend, policy }
+    }
+
+    /// Whether this validator accepts `encoding`.
+    pub fn accepts(&self, encoding: &[u8; 32]) -> bool {
+        self.backend.accepts(encoding, self.policy)
+    }
+
+    /// The backend's name and the policy, for reports.
+    pub fn describe(&self) -> String {
+        format!("{} under {:?}", self.backend.name(), self.policy)
+    }
+}
+
+/// An encoding two validators disagree about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorpusEntry {
+    encoding: [u8; 32],
+    annotation: String,
+    first_accepted: bool,
+}
+
+impl CorpusEntry {
+    /// The encoding.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// How the encoding was constructed.
+    pub fn annotation(&self) -> &str {
+        &self.annotation
+    }
+
+    /// Whether the first validator accepted it.  The second said the
+    /// opposite.
+    pub fn first_accepted(&self) -> bool {
+        self.first_accepted
+    }
+}
+
+/// Every candidate encoding, with how it was constructed.
+pub fn candidates() -> Vec<([u8; 32], String)> {
+    let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+    let mut candidates: Vec<([u8; 32], String)> = Vec::new();
+
+    for t in constants::EIGHT_TORSION.iter() {
+        let small: [u8; 32] = t.compress_edwards().to_bytes();
+        let mut flipped: [u8; 32] = small;
+        flipped[31] ^= 0x80;
+
+        let name: String = match torsion_order_log2(t) {
+            0 => "the identity".to_string(),
+            k => format!("a point of order {}", 1 << k),
+        };
+        if !t.is_identity() {
+            candidates.push(((&p + t).compress_edwards().to_bytes(), format!("a prime-order point plus {}", name)));
+        }
+        candidates.push((flipped, format!("{}, with its sign bit flipped", name)));
+        candidates.push((small, name));
+    }
+
+    // p = 2^255 - 19 is ed ff ... ff 7f, little-endian.
+    for y in 0..19u8 {
+        let mut unreduced: [u8; 32] = [0xff; 32];
+        unreduced[0] = 0xed + y;
+        unreduced[31] = 0x7f;
+
+        candidates.push((unreduced, format!("y = {} + p", y)));
+        unreduced[31] |= 0x80;
+        candidates.push((unreduced, format!("y = {} + p, with the sign bit set", y)));
+    }
+
+    candidates.push(([0x02; 32], "not a point".to_string()));
+    candidates
+}
+
+/// Every candidate which `first` and `second` disagree about.
+pub fn build_corpus(first: &Validator, second: &Validator) -> Vec<CorpusEntry> {
+    candidates().into_iter().filter_map(|(encoding, annotation)| {
+        let accepted: bool = first.accepts(&encoding);
+
+        match accepted != second.accepts(&encoding) {
+            true  => Some(CorpusEntry { encoding, annotation, first_accepted: accepted }),
+            false => None,
+        }
+    }).collect()
+}
+
+/// Seeds for fuzzing anything which parses keys: every candidate, some
+/// valid keys, and the field boundaries p - 1, p, p + 1, and 2^255 - 1, each
+/// with and without the sign bit.  Every encoding appears once.
+pub fn seed_corpus() -> Vec<([u8; 32], String)> {
+    let mut seeds: Vec<([u8; 32], String)> = candidates();
+
+    for &k in [1u64, 2, 22006].iter() {
+        seeds.push(((&Scalar::from_u64(k) * &constants::ED25519_BASEPOINT).compress_edwards().to_bytes(),
+                    format!("[{}]B", k)));
+    }
+
+    // p - 1 is ec ff ... ff 7f, and 2^255 - 1 is ff ... ff 7f.
+    let mut boundaries: Vec<([u8; 32], String)> = Vec::new();
+    for &(low, name) in [(0xec, "p - 1"), (0xed, "p"), (0xee, "p + 1"), (0xff, "2^255 - 1")].iter() {
+        let mut y: [u8; 32] = [0xff; 32];
+        y[0] = low;
+        y[31] = 0x7f;
+
+        boundaries.push((y, format!("y = {}", name)));
+        y[31] |= 0x80;
+        boundaries.push((y, format!("y = {}, with the sign bit set", name)));
+    }
+    boundaries.push(([0u8; 32], "y = 0".to_string()));
+    seeds.extend(boundaries);
+
+    // Keep the first name each encoding was given.
+    let mut seen: Vec<[u8; 32]> = Vec::new();
+    seeds.retain(|&(encoding, _)| match seen.contains(&encoding) {
+        true  => false,
+        false => { seen.push(encoding); true },
+    });
+    seeds
+}
+
+/// Write every seed in `seed_corpus` into `dir`, creating it if need be,
+/// each in a file named by its encoding in hex.  Returns how many there
+/// were.
+pub fn write_seed_corpus(dir: &Path) -> io::Result<usize> {
+    let seeds: Vec<([u8; 32], String)> = seed_corpus();
+
+    fs::create_dir_all(dir)?;
+    for (encoding, _) in seeds.iter() {
+        fs::write(dir.join(hex_encode(encoding)), encoding)?;
+    }
+    Ok(seeds.len())
+}
+
+/// The corpus as text, in the format described above.
+pub fn write_corpus(first: &Validator, second: &Validator, entries: &[CorpusEntry]) -> String {
+    let verdict = |accepted: bool| if accepted { "accept" } else { "reject" };
+    let mut text: String = format!("# first: {}\n# second: {}\n", first.describe(), second.describe());
+
+    for entry in entries.iter() {
+        text.push_str(&format!("{} {} {} {}\n", hex_encode(entry.encoding()),
+                               verdict(entry.first_accepted()), verdict(!entry.first_accepted()),
+                               entry.annotation()));
+    }
+    text
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use backend::Native;
+
+    #[test]
+    fn policies_diverge_on_non_canonical_identities() {
+        let prime_order: Validator = Validator::new(&Native, KeyPolicy::PrimeOrder);
+        let strict: Validator = Validator::new(&Native, KeyPolicy::Strict);
+        let corpus: Vec<CorpusEntry> = build_corpus(&prime_order, &strict);
+
+        assert!(!corpus.is_empty());
+        assert!(corpus.iter().all(|entry| entry.first_accepted()));
+        assert!(corpus.iter().any(|entry| entry.annotation() == "y = 1 + p"));
+        assert!(corpus.iter().any(|entry| entry.annotation() == "the identity"));
+    }
+
+    #[test]
+    fn decompression_alone_accepts_torsion() {
+        let permissive: Validator = Validator::new(&DecompressOnly, KeyPolicy::Strict);
+        let strict: Validator = Validator::new(&Native, KeyPolicy::Strict);
+        let corpus: Vec<CorpusEntry> = build_corpus(&permissive, &strict);
+
+        assert_eq!(corpus.iter().filter(|entry| entry.annotation().starts_with("a prime-order point plus")).count(), 7);
+        assert!(corpus.iter().all(|entry| entry.annotation() != "not a point"));
+        assert!(build_corpus(&strict, &strict).is_empty());
+    }
+
+    #[test]
+    fn corpus_is_written_one_line_each() {
+        let permissive: Validator = Validator::new(&DecompressOnly, KeyPolicy::Strict);
+        let strict: Validator = Validator::new(&Native, KeyPolicy::Strict);
+        let corpus: Vec<CorpusEntry> = build_corpus(&permissive, &strict);
+        let text: String = write_corpus(&permissive, &strict, &corpus);
+
+        assert!(text.starts_with("# first: decompress-only under Strict\n# second: curve25519-dalek-0.9 under Strict\n"));
+        assert_eq!(text.lines().count(), corpus.len() + 2);
+        assert!(text.lines().skip(2).all(|line| line.split(' ').nth(1) == Some("accept")));
+    }
+
+    #[test]
+    fn seeds_cover_the_boundaries() {
+        let seeds: Vec<([u8; 32], String)> = seed_corpus();
+        let mut encodings: Vec<[u8; 32]> = seeds.iter().map(|seed| seed.0).collect();
+
+        encodings.sort();
+        encodings.dedup();
+        assert_eq!(encodings.len(), seeds.len());
+        for &low in [0xec, 0xed, 0xee].iter() {
+            let mut y: [u8; 32] = [0xff; 32];
+            y[0] = low;
+            y[31] = 0x7f;
+            assert!(encodings.contains(&y));
+        }
+        assert_eq!(seeds.iter().filter(|seed| Native.accepts(&seed.0, KeyPolicy::Strict)).count(), 3);
+    }
+
+    #[test]
+    fn seeds_are_written_one_file_each() {
+        let dir: ::std::path::PathBuf = ::std::env::temp_dir().join(format!("tor22006-seeds-{}", ::std::process::id()));
+        let written: usize = write_seed_corpus(&dir).unwrap();
+
+        assert_eq!(written, seed_corpus().len());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), written);
+        assert_eq!(fs::read(dir.join(hex_encode(&[0u8; 32]))).unwrap(), vec![0u8; 32]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+// decaf448, per RFC 9496 §5.
+//
+// Decoding only ever produces points in 2E, the image of doubling, which has
+// order 2·l.  Each decaf448 element is a coset P + E[2] there, encoded as a
+// single field element s which must be canonical and non-negative.  Since
+// every encoding describes the whole coset, there is nothing for a torsion
+// component to hide in, and decoding doubles as validation.
+
+use core::cmp::Ordering;
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::hash::Hasher;
+
+use subtle::arrays_equal;
+use subtle::CTEq;
+
+use curve448::edwards::edwards_d;
+use curve448::edwards::ExtendedPoint;
+use curve448::field::FieldElement;
+
+static SQRT_MINUS_D: [u8; 56] = [
+    0x36, 0x27, 0x57, 0x45, 0x0f, 0xef, 0x42, 0x96,
+    0x52, 0xce, 0x20, 0xaa, 0xf6, 0x7b, 0x33, 0x60,
+    0xd2, 0xde, 0x6e, 0xfd, 0xf4, 0x66, 0x9a, 0x83,
+    0xba, 0x14, 0x8c, 0x96, 0x80, 0xd7, 0xa2, 0x64,
+    0x4b, 0xd5, 0xb8, 0xa5, 0xb8, 0xa7, 0xf1, 0xa1,
+    0xa0, 0x6a, 0xa2, 0x2f, 0x72, 0x8d, 0xf6, 0x3b,
+    0x68, 0xf7, 0x24, 0xeb, 0xfb, 0x62, 0xd9, 0x22 ];
+
+static INVSQRT_MINUS_D: [u8; 56] = [
+    0x2c, 0x68, 0x78, 0xb8, 0x5e, 0xbb, 0xaf, 0x53,
+    0xf3, 0x94, 0x9e, 0xf1, 0x79, 0x24, 0xbb, 0xef,
+    0x15, 0xba, 0x1f, 0xc2, 0xe2, 0x7e, 0x70, 0xbe,
+    0x1a, 0x52, 0xa6, 0x28, 0xf1, 0x56, 0xba, 0xd6,
+    0xa7, 0x27, 0x5b, 0x3a, 0x0c, 0x95, 0x90, 0x5a,
+    0x07, 0xc8, 0xca, 0x0b, 0x5a, 0xe3, 0x2b, 0x90,
+    0x57, 0xc0, 0x22, 0xe2, 0x52, 0x06, 0xf4, 0x6e ];
+
+/// A decaf448 encoding.
+#[derive(Copy, Clone)]
+pub struct CompressedDecaf(pub [u8; 56]);
+
+impl Debug for CompressedDecaf {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "CompressedDecaf448: {:?}", &self.0[..])
+    }
+}
+
+impl CTEq for CompressedDecaf {
+    fn ct_eq(&self, other: &CompressedDecaf) -> u8 {
+        arrays_equal(&self.0, &other.0)
+    }
+}
+
+impl PartialEq for CompressedDecaf {
+    fn eq(&self, other: &CompressedDecaf) -> bool {
+        self.ct_eq(other) == 1u8
+    }
+}
+
+impl Eq for CompressedDecaf {}
+
+// Via slices, as for the 57-byte Edwards encoding.
+impl Hash for CompressedDecaf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0[..].hash(state)
+    }
+}
+
+impl PartialOrd for CompressedDecaf {
+    fn partial_cmp(&self, other: &CompressedDecaf) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CompressedDecaf {
+    fn cmp(&self, other: &CompressedDecaf) -> Ordering {
+        self.0[..].cmp(&other.0[..])
+    }
+}
+
+impl CompressedDecaf {
+    /// The encoding of the identity, which is all zeroes.
+    pub fn identity() -> CompressedDecaf {
+        CompressedDecaf([0u8; 56])
+    }
+
+    /// View this encoding as a byte array.
+    pub fn as_bytes(&self) -> &[u8; 56] {
+        &self.0
+    }
+
+    /// Attempt to decode to a `DecafPoint`.
+    ///
+    /// Returns `None` if the encoding is not canonical, is negative, or does
+    /// not describe a point on the curve.
+    pub fn decompress(&self) -> Option<DecafPoint> {
+        let s: FieldElement = FieldElement::from_bytes(&self.0);
+
+        let s_is_canonical: u8 = arrays_equal(&s.to_bytes(), &self.0);
+        let s_is_negative: u8 = s.is_negative();
+
+        if s_is_canonical != 1u8 || s_is_negative == 1u8 {
+            return None; // non-canonical or negative encoding
+        }
+
+        let one: FieldElement = FieldElement::one();
+        let ss: FieldElement = s.square();
+        let u1: FieldElement = &one + &ss;
+        let four_d_ss: FieldElement = &(&FieldElement::from_u32(4) * &edwards_d()) * &ss;
+        let u2: FieldElement = &u1.square() - &four_d_ss;
+        let (was_square, invsqrt) = FieldElement::sqrt_ratio(&one, &(&u2 * &u1.square()));
+
+        let sqrt_minus_d: FieldElement = FieldElement::from_bytes(&SQRT_MINUS_D);
+        let invsqrt_minus_d: FieldElement = FieldElement::from_bytes(&INVSQRT_MINUS_D);
+
+        let u3: FieldElement = abs(&(&(&(&s + &s) * &invsqrt) * &(&u1 * &sqrt_minus_d)));
+        let x: FieldElement = &(&(&u3 * &invsqrt) * &u2) * &invsqrt_minus_d;
+        let y: FieldElement = &(&(&one - &ss) * &invsqrt) * &u1;
+
+        match was_square == 1u8 {
+            true  => Some(DecafPoint(ExtendedPoint{ X: x, Y: y, Z: one, T: &x * &y })),
+            false => None, // not the encoding of any point
+        }
+    }
+}
+
+/// An element of the decaf448 group, represented by either point in its
+/// coset.
+#[derive(Copy, Clone, Debug)]
+pub struct DecafPoint(pub ExtendedPoint);
+
+impl CTEq for DecafPoint {
+    // Two points of 2E are in the same coset exactly when x₁y₂ = y₁x₂.
+    fn ct_eq(&self, other: &DecafPoint) -> u8 {
+        (&self.0.X * &other.0.Y).ct_eq(&(&self.0.Y * &other.0.X))
+    }
+}
+
+impl PartialEq for DecafPoint {
+    fn eq(&self, other: &DecafPoint) -> bool {
+        self.ct_eq(other) == 1u8
+    }
+}
+
+impl Eq for DecafPoint {}
+
+impl DecafPoint {
+    /// Encode this element.
+    pub fn compress(&self) -> CompressedDecaf {
+        let one_minus_d: FieldElement = &FieldElement::one() - &edwards_d();
+        let sqrt_minus_d: FieldElement = FieldElement::from_bytes(&SQRT_MINUS_D);
+        let invsqrt_minus_d: FieldElement = FieldElement::from_bytes(&INVSQRT_MINUS_D);
+        let p: &ExtendedPoint = &self.0;
+
+        let u1: FieldElement = &(&p.X + &p.T) * &(&p.X - &p.T);
+        let (_, invsqrt) = FieldElement::sqrt_ratio(&FieldElement::one(),
+                                                    &(&(&u1 * &one_minus_d) * &p.X.square()));
+        let ratio: FieldElement = abs(&(&(&invsqrt * &u1) * &sqrt_minus_d));
+        let u2: FieldElement = &(&(&invsqrt_minus_d * &ratio) * &p.Z) - &p.T;
+        let s: FieldElement = abs(&(&(&(&one_minus_d * &invsqrt) * &p.X) * &u2));
+
+        CompressedDecaf(s.to_bytes())
+    }
+}
+
+// CT_ABS from RFC 9496 §4.
+fn abs(x: &FieldElement) -> FieldElement {
+    let (_, r) = FieldElement::sqrt_ratio(&x.square(), &FieldElement::one());
+    r
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // From RFC 9496, Appendix C: the decaf448 generator, which is 2·B on
+    // Ed448, and twice the generator.
+    static GENERATOR: [u8; 56] = [
+        0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+        0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+        0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+        0x66, 0x66, 0x66, 0x66, 0x33, 0x33, 0x33, 0x33,
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33,
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33,
+        0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33 ];
+    static TWICE_GENERATOR: [u8; 56] = [
+        0xc8, 0x98, 0xeb, 0x4f, 0x87, 0xf9, 0x7c, 0x56,
+        0x4c, 0x6f, 0xd6, 0x1f, 0xc7, 0xe4, 0x96, 0x89,
+        0x31, 0x4a, 0x1f, 0x81, 0x8e, 0xc8, 0x5e, 0xeb,
+        0x3b, 0xd5, 0x51, 0x4a, 0xc8, 0x16, 0xd3, 0x87,
+        0x78, 0xf6, 0x9e, 0xf3, 0x47, 0xa8, 0x9f, 0xca,
+        0x81, 0x7e, 0x66, 0xde, 0xfd, 0xed, 0xce, 0x17,
+        0x8c, 0x7c, 0xc7, 0x09, 0xb2, 0x11, 0x6e, 0x75 ];
+
+    #[test]
+    fn rfc9496_generator() {
+        let g: DecafPoint = CompressedDecaf(GENERATOR).decompress().unwrap();
+
+        assert_eq!(g, DecafPoint(ExtendedPoint::basepoint().double()));
+        assert_eq!(g.compress(), CompressedDecaf(GENERATOR));
+        assert_eq!(DecafPoint(g.0.double()).compress(), CompressedDecaf(TWICE_GENERATOR));
+    }
+
+    #[test]
+    fn identity_roundtrip() {
+        let id: DecafPoint = CompressedDecaf::identity().decompress().unwrap();
+
+        assert_eq!(id, DecafPoint(ExtendedPoint::identity()));
+        assert_eq!(DecafPoint(ExtendedPoint::identity()).compress(), CompressedDecaf::identity());
+    }
+
+    #[test]
+    fn two_torsion_is_invisible() {
+        let g: ExtendedPoint = ExtendedPoint::basepoint().double();
+        let t: ExtendedPoint = ExtendedPoint{
+            X: FieldElement::zero(),
+            Y: -&FieldElement::one(),
+            Z: FieldElement::one(),
+            T: FieldElement::zero(),
+        };
+
+        assert_eq!(DecafPoint(&g + &t).compress(), CompressedDecaf(GENERATOR));
+    }
+
+    #[test]
+    fn negative_encodings_are_rejected() {
+        let mut bytes: [u8; 56] = GENERATOR;
+        bytes[0] |= 1;
+
+        assert!(CompressedDecaf(bytes).decompress().is_none());
+    }
+}
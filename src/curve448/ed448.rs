@@ -0,0 +1,162 @@
+// Ed448 signature verification under each `VerificationProfile`, for the
+// pure variant with an empty context.
+//
+// A signature is R ‖ S, with R a 57-byte point encoding and S a 57-byte
+// scalar, and the challenge is
+//
+//    k ← SHAKE256(dom4(0, "") ‖ R ‖ A ‖ M, 114) mod l
+//
+// Exactly as for Ed25519, the profiles differ only in how they treat torsion,
+// and here the cofactored equation multiplies through by 4.
+
+use digest::ExtendableOutput;
+use digest::Input;
+use digest::XofReader;
+
+use sha3::Shake256;
+
+use curve448::edwards::CompressedEdwardsY;
+use curve448::edwards::ExtendedPoint;
+use curve448::edwards::L;
+use curve448::scalar;
+
+use policy::VerificationProfile;
+
+/// Verify an Ed448 `signature` on `message` under the public `key`.
+pub fn verify(key: &CompressedEdwardsY,
+              message: &[u8],
+              signature: &[u8; 114],
+              profile: VerificationProfile) -> bool {
+    let mut r_bytes: [u8; 57] = [0u8; 57];
+    let mut s_bytes: [u8; 57] = [0u8; 57];
+
+    r_bytes.copy_from_slice(&signature[..57]);
+    s_bytes.copy_from_slice(&signature[57..]);
+
+    let s: [u8; 56] = match scalar::from_canonical_bytes(&s_bytes) {
+        Some(s) => s,
+        None    => return false, // S was not reduced
+    };
+    let r_key: CompressedEdwardsY = CompressedEdwardsY(r_bytes);
+    let a: ExtendedPoint = match key.decompress() {
+        Some(a) => a,
+        None    => return false, // the key was invalid
+    };
+    let r: ExtendedPoint = match r_key.decompress() {
+        Some(r) => r,
+        None    => return false, // R was invalid
+    };
+
+    // Decompression already insists on canonical encodings, so only the
+    // subgroup is left to check.
+    if profile == VerificationProfile::Strict &&
+        !(a.scalar_mult(&L).is_identity() && r.scalar_mult(&L).is_identity()) {
+        return false;
+    }
+
+    let mut h: Shake256 = Shake256::default();
+    let mut hash: [u8; 114] = [0u8; 114];
+    h.process(b"SigEd448\x00\x00");
+    h.process(&r_bytes);
+    h.process(key.as_bytes());
+    h.process(message);
+    h.xof_result().read(&mut hash);
+    let k: [u8; 56] = scalar::reduce(&hash);
+
+    // [S]B - [k]A - R, which is the identity for a valid signature.
+    let check: ExtendedPoint = &(&ExtendedPoint::basepoint().scalar_mult(&s) -
+                                 &a.scalar_mult(&k)) - &r;
+
+    match profile {
+        VerificationProfile::Cofactored => check.mult_by_cofactor().is_identity(),
+        _                               => check.is_identity(),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // RFC 8032 §7.4, test 1.
+    static PUBLIC_KEY: [u8; 57] = [
+        0x5f, 0xd7, 0x44, 0x9b, 0x59, 0xb4, 0x61, 0xfd,
+        0x2c, 0xe7, 0x87, 0xec, 0x61, 0x6a, 0xd4, 0x6a,
+        0x1d, 0xa1, 0x34, 0x24, 0x85, 0xa7, 0x0e, 0x1f,
+        0x8a, 0x0e, 0xa7, 0x5d, 0x80, 0xe9, 0x67, 0x78,
+        0xed, 0xf1, 0x24, 0x76, 0x9b, 0x46, 0xc7, 0x06,
+        0x1b, 0xd6, 0x78, 0x3d, 0xf1, 0xe5, 0x0f, 0x6c,
+        0xd1, 0xfa, 0x1a, 0xbe, 0xaf, 0xe8, 0x25, 0x61,
+        0x80 ];
+    static SIGNATURE: [u8; 114] = [
+        0x53, 0x3a, 0x37, 0xf6, 0xbb, 0xe4, 0x57, 0x25,
+        0x1f, 0x02, 0x3c, 0x0d, 0x88, 0xf9, 0x76, 0xae,
+        0x2d, 0xfb, 0x50, 0x4a, 0x84, 0x3e, 0x34, 0xd2,
+        0x07, 0x4f, 0xd8, 0x23, 0xd4, 0x1a, 0x59, 0x1f,
+        0x2b, 0x23, 0x3f, 0x03, 0x4f, 0x62, 0x82, 0x81,
+        0xf2, 0xfd, 0x7a, 0x22, 0xdd, 0xd4, 0x7d, 0x78,
+        0x28, 0xc5, 0x9b, 0xd0, 0xa2, 0x1b, 0xfd, 0x39,
+        0x80, 0xff, 0x0d, 0x20, 0x28, 0xd4, 0xb1, 0x8a,
+        0x9d, 0xf6, 0x3e, 0x00, 0x6c, 0x5d, 0x1c, 0x2d,
+        0x34, 0x5b, 0x92, 0x5d, 0x8d, 0xc0, 0x0b, 0x41,
+        0x04, 0x85, 0x2d, 0xb9, 0x9a, 0xc5, 0xc7, 0xcd,
+        0xda, 0x85, 0x30, 0xa1, 0x13, 0xa0, 0xf4, 0xdb,
+        0xb6, 0x11, 0x49, 0xf0, 0x5a, 0x73, 0x63, 0x26,
+        0x8c, 0x71, 0xd9, 0x58, 0x08, 0xff, 0x2e, 0x65,
+        0x26, 0x00 ];
+
+    // The test 1 key plus the point of order 2, and a signature on the
+    // message [0x01] made with the same secret scalar under the torsioned key.
+    static TORSIONED_KEY: [u8; 57] = [
+        0xa0, 0x28, 0xbb, 0x64, 0xa6, 0x4b, 0x9e, 0x02,
+        0xd3, 0x18, 0x78, 0x13, 0x9e, 0x95, 0x2b, 0x95,
+        0xe2, 0x5e, 0xcb, 0xdb, 0x7a, 0x58, 0xf1, 0xe0,
+        0x75, 0xf1, 0x58, 0xa2, 0x7e, 0x16, 0x98, 0x87,
+        0x12, 0x0e, 0xdb, 0x89, 0x64, 0xb9, 0x38, 0xf9,
+        0xe4, 0x29, 0x87, 0xc2, 0x0e, 0x1a, 0xf0, 0x93,
+        0x2e, 0x05, 0xe5, 0x41, 0x50, 0x17, 0xda, 0x9e,
+        0x00 ];
+    static TORSIONED_SIGNATURE: [u8; 114] = [
+        0xdf, 0x6f, 0x05, 0x56, 0xeb, 0x6b, 0x79, 0x28,
+        0x89, 0x87, 0x83, 0xf1, 0xf9, 0xda, 0x30, 0x38,
+        0xa6, 0xaf, 0xf0, 0x56, 0x96, 0x15, 0x2e, 0xf3,
+        0xdb, 0x56, 0x22, 0x01, 0x34, 0xf8, 0xf8, 0xe9,
+        0x08, 0x2e, 0x9b, 0x6d, 0x01, 0x56, 0xfe, 0xfe,
+        0xa1, 0xac, 0x97, 0x14, 0x91, 0xe5, 0x95, 0x1e,
+        0xb0, 0x83, 0x4a, 0xdb, 0x8e, 0x07, 0xdd, 0xb0,
+        0x80, 0x2a, 0x95, 0x65, 0xd2, 0x5a, 0x3f, 0xd1,
+        0x3c, 0x44, 0x3b, 0xe4, 0x4b, 0x8d, 0x65, 0x93,
+        0x19, 0xaf, 0x3c, 0x58, 0xf4, 0x78, 0x91, 0x80,
+        0x33, 0x0d, 0x1f, 0x31, 0xc6, 0xbb, 0x20, 0x57,
+        0xe0, 0xce, 0xd3, 0xb5, 0x53, 0x38, 0x3e, 0x59,
+        0x10, 0x42, 0xa7, 0x30, 0x74, 0x7c, 0x1d, 0xda,
+        0xf8, 0xa3, 0xc9, 0x9f, 0xce, 0xc5, 0x03, 0x82,
+        0x3f, 0x00 ];
+
+    #[test]
+    fn rfc8032_vector_passes_every_profile() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+
+        assert!(verify(&key, b"", &SIGNATURE, VerificationProfile::Cofactored));
+        assert!(verify(&key, b"", &SIGNATURE, VerificationProfile::Cofactorless));
+        assert!(verify(&key, b"", &SIGNATURE, VerificationProfile::Strict));
+        assert!(!verify(&key, b"x", &SIGNATURE, VerificationProfile::Cofactored));
+    }
+
+    #[test]
+    fn profiles_disagree_on_torsioned_keys() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(TORSIONED_KEY);
+
+        assert!(verify(&key, &[1u8], &TORSIONED_SIGNATURE, VerificationProfile::Cofactored));
+        assert!(!verify(&key, &[1u8], &TORSIONED_SIGNATURE, VerificationProfile::Cofactorless));
+        assert!(!verify(&key, &[1u8], &TORSIONED_SIGNATURE, VerificationProfile::Strict));
+    }
+
+    #[test]
+    fn nonzero_final_byte_of_s_is_rejected() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+        let mut signature: [u8; 114] = SIGNATURE;
+        signature[113] = 1;
+
+        assert!(!verify(&key, b"", &signature, VerificationProfile::Cofactored));
+    }
+}
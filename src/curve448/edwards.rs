@@ -0,0 +1,361 @@
+// Points on Ed448-Goldilocks, the untwisted Edwards curve x² + y² = 1 + dx²y²
+// with d = -39081, in extended coordinates (X:Y:Z:T) with x = X/Z, y = Y/Z and
+// xy = T/Z.
+//
+// Since d is not a square, the addition law below is complete: it has no
+// exceptional cases, including doubling and the identity.  The group has order
+// 4·l, and the 4-torsion subgroup is generated by (1, 0).
+
+use core::cmp::Ordering;
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::hash::Hasher;
+use core::ops::{Add, Sub, Neg};
+
+use subtle::arrays_equal;
+use subtle::CTAssignable;
+use subtle::CTEq;
+
+use curve448::field::FieldElement;
+
+static BASEPOINT_X: [u8; 56] = [
+    0x5e, 0xc0, 0x0c, 0xc7, 0x2b, 0xa8, 0x26, 0x26,
+    0x8e, 0x93, 0x00, 0x8b, 0xe1, 0x80, 0x3b, 0x43,
+    0x11, 0x65, 0xb6, 0x2a, 0xf7, 0x1a, 0xae, 0x12,
+    0x64, 0xa4, 0xd3, 0xa3, 0x24, 0xe3, 0x6d, 0xea,
+    0x67, 0x17, 0x0f, 0x47, 0x70, 0x65, 0x14, 0x9e,
+    0xda, 0x36, 0xbf, 0x22, 0xa6, 0x15, 0x1d, 0x22,
+    0xed, 0x0d, 0xed, 0x6b, 0xc6, 0x70, 0x19, 0x4f ];
+
+static BASEPOINT_Y: [u8; 56] = [
+    0x14, 0xfa, 0x30, 0xf2, 0x5b, 0x79, 0x08, 0x98,
+    0xad, 0xc8, 0xd7, 0x4e, 0x2c, 0x13, 0xbd, 0xfd,
+    0xc4, 0x39, 0x7c, 0xe6, 0x1c, 0xff, 0xd3, 0x3a,
+    0xd7, 0xc2, 0xa0, 0x05, 0x1e, 0x9c, 0x78, 0x87,
+    0x40, 0x98, 0xa3, 0x6c, 0x73, 0x73, 0xea, 0x4b,
+    0x62, 0xc7, 0xc9, 0x56, 0x37, 0x20, 0x76, 0x88,
+    0x24, 0xbc, 0xb6, 0x6e, 0x71, 0x46, 0x3f, 0x69 ];
+
+/// The order of the prime-order subgroup as 56 little-endian bytes, where
+///
+///    l = 2⁴⁴⁶ - 13818066809895115352007386748515426880336692474882178609894547503885
+pub static L: [u8; 56] = [
+    0xf3, 0x44, 0x58, 0xab, 0x92, 0xc2, 0x78, 0x23,
+    0x55, 0x8f, 0xc5, 0x8d, 0x72, 0xc2, 0x6c, 0x21,
+    0x90, 0x36, 0xd6, 0xae, 0x49, 0xdb, 0x4e, 0xc4,
+    0xe9, 0x23, 0xca, 0x7c, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x3f ];
+
+// The curve constant d = -39081.
+pub(crate) fn edwards_d() -> FieldElement {
+    -&FieldElement::from_u32(39081)
+}
+
+/// An Ed448 public key: the 56-byte little-endian y-coordinate, followed by a
+/// byte whose top bit is the sign of x and whose other bits must be zero, as
+/// in RFC 8032 §5.2.2.
+#[derive(Copy, Clone)]
+pub struct CompressedEdwardsY(pub [u8; 57]);
+
+impl Debug for CompressedEdwardsY {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "CompressedEdwardsY448: {:?}", &self.0[..])
+    }
+}
+
+impl PartialEq for CompressedEdwardsY {
+    fn eq(&self, other: &CompressedEdwardsY) -> bool {
+        arrays_equal(&self.0, &other.0) == 1u8
+    }
+}
+
+impl Eq for CompressedEdwardsY {}
+
+// Arrays this long don't implement Hash or Ord themselves, so these go via
+// slices, comparing byte-wise just as `eq` does.
+impl Hash for CompressedEdwardsY {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0[..].hash(state)
+    }
+}
+
+impl PartialOrd for CompressedEdwardsY {
+    fn partial_cmp(&self, other: &CompressedEdwardsY) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CompressedEdwardsY {
+    fn cmp(&self, other: &CompressedEdwardsY) -> Ordering {
+        self.0[..].cmp(&other.0[..])
+    }
+}
+
+impl CompressedEdwardsY {
+    /// View this key as a byte array.
+    pub fn as_bytes(&self) -> &[u8; 57] {
+        &self.0
+    }
+
+    /// Attempt to decompress to an `ExtendedPoint`.
+    ///
+    /// Returns `None` if the trailing bits are set, if y is not canonical, or
+    /// if there is no x with the requested sign.  Nothing here checks which
+    /// subgroup the point lies in.
+    pub fn decompress(&self) -> Option<ExtendedPoint> {
+        if self.0[56] & 0x7f != 0 {
+            return None; // stray bits in the final byte
+        }
+        let x_sign: u8 = self.0[56] >> 7;
+
+        let mut y_bytes: [u8; 56] = [0u8; 56];
+        y_bytes.copy_from_slice(&self.0[..56]);
+
+        let y: FieldElement = FieldElement::from_bytes(&y_bytes);
+
+        if arrays_equal(&y.to_bytes(), &y_bytes) != 1u8 {
+            return None; // y was not reduced mod p
+        }
+
+        // x² = (y² - 1)/(dy² - 1), and dy² - 1 is never zero since d is not
+        // a square.
+        let one: FieldElement = FieldElement::one();
+        let y2: FieldElement = y.square();
+        let u: FieldElement = &y2 - &one;
+        let v: FieldElement = &(&edwards_d() * &y2) - &one;
+        let (is_square, mut x) = FieldElement::sqrt_ratio(&u, &v);
+
+        if is_square != 1u8 {
+            return None; // not a point on the curve
+        }
+        if x.is_zero() == 1u8 && x_sign == 1u8 {
+            return None; // -0 is not a valid encoding
+        }
+
+        let minus_x: FieldElement = -&x;
+        x.conditional_assign(&minus_x, x_sign);
+
+        Some(ExtendedPoint{ X: x, Y: y, Z: one, T: &x * &y })
+    }
+}
+
+/// A point on Ed448 in extended coordinates.
+#[derive(Copy, Clone)]
+#[allow(non_snake_case)]
+pub struct ExtendedPoint {
+    pub X: FieldElement,
+    pub Y: FieldElement,
+    pub Z: FieldElement,
+    pub T: FieldElement,
+}
+
+impl Debug for ExtendedPoint {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "ExtendedPoint448: {:?}", &self.compress_edwards().0[..])
+    }
+}
+
+impl CTEq for ExtendedPoint {
+    fn ct_eq(&self, other: &ExtendedPoint) -> u8 {
+        (&self.X * &other.Z).ct_eq(&(&other.X * &self.Z)) &
+            (&self.Y * &other.Z).ct_eq(&(&other.Y * &self.Z))
+    }
+}
+
+impl PartialEq for ExtendedPoint {
+    fn eq(&self, other: &ExtendedPoint) -> bool {
+        self.ct_eq(other) == 1u8
+    }
+}
+
+impl Eq for ExtendedPoint {}
+
+impl CTAssignable for ExtendedPoint {
+    fn conditional_assign(&mut self, other: &ExtendedPoint, choice: u8) {
+        self.X.conditional_assign(&other.X, choice);
+        self.Y.conditional_assign(&other.Y, choice);
+        self.Z.conditional_assign(&other.Z, choice);
+        self.T.conditional_assign(&other.T, choice);
+    }
+}
+
+impl<'b> Add<&'b ExtendedPoint> for &ExtendedPoint {
+    type Output = ExtendedPoint;
+
+    // add-2008-hwcd-3 with a = 1.
+    fn add(self, other: &'b ExtendedPoint) -> ExtendedPoint {
+        let a: FieldElement = &self.X * &other.X;
+        let b: FieldElement = &self.Y * &other.Y;
+        let c: FieldElement = &(&self.T * &edwards_d()) * &other.T;
+        let d: FieldElement = &self.Z * &other.Z;
+        let e: FieldElement = &(&(&(&self.X + &self.Y) * &(&other.X + &other.Y)) - &a) - &b;
+        let f: FieldElement = &d - &c;
+        let g: FieldElement = &d + &c;
+        let h: FieldElement = &b - &a;
+
+        ExtendedPoint{ X: &e * &f, Y: &g * &h, Z: &f * &g, T: &e * &h }
+    }
+}
+
+impl Neg for &ExtendedPoint {
+    type Output = ExtendedPoint;
+
+    fn neg(self) -> ExtendedPoint {
+        ExtendedPoint{ X: -&self.X, Y: self.Y, Z: self.Z, T: -&self.T }
+    }
+}
+
+impl<'b> Sub<&'b ExtendedPoint> for &ExtendedPoint {
+    type Output = ExtendedPoint;
+
+    fn sub(self, other: &'b ExtendedPoint) -> ExtendedPoint {
+        self + &(-other)
+    }
+}
+
+impl ExtendedPoint {
+    /// The identity element, (0, 1).
+    pub fn identity() -> ExtendedPoint {
+        ExtendedPoint{
+            X: FieldElement::zero(),
+            Y: FieldElement::one(),
+            Z: FieldElement::one(),
+            T: FieldElement::zero(),
+        }
+    }
+
+    /// The RFC 8032 basepoint, which generates the prime-order subgroup.
+    pub fn basepoint() -> ExtendedPoint {
+        let x: FieldElement = FieldElement::from_bytes(&BASEPOINT_X);
+        let y: FieldElement = FieldElement::from_bytes(&BASEPOINT_Y);
+
+        ExtendedPoint{ X: x, Y: y, Z: FieldElement::one(), T: &x * &y }
+    }
+
+    /// Return true if this is the identity.
+    pub fn is_identity(&self) -> bool {
+        (self.X.is_zero() & self.Y.ct_eq(&self.Z)) == 1u8
+    }
+
+    /// Compress to the RFC 8032 encoding.
+    pub fn compress_edwards(&self) -> CompressedEdwardsY {
+        let z_inv: FieldElement = self.Z.invert();
+        let x: FieldElement = &self.X * &z_inv;
+        let y: FieldElement = &self.Y * &z_inv;
+        let mut bytes: [u8; 57] = [0u8; 57];
+
+        bytes[..56].copy_from_slice(&y.to_bytes());
+        bytes[56] = x.is_negative() << 7;
+
+        CompressedEdwardsY(bytes)
+    }
+
+    /// Double this point.
+    pub fn double(&self) -> ExtendedPoint {
+        let a: FieldElement = self.X.square();
+        let b: FieldElement = self.Y.square();
+        let z2: FieldElement = self.Z.square();
+        let c: FieldElement = &z2 + &z2;
+        let e: FieldElement = &(&(&self.X + &self.Y).square() - &a) - &b;
+        let g: FieldElement = &a + &b;
+        let f: FieldElement = &g - &c;
+        let h: FieldElement = &a - &b;
+
+        ExtendedPoint{ X: &e * &f, Y: &g * &h, Z: &f * &g, T: &e * &h }
+    }
+
+    /// Compute 2ᵏ·P.
+    pub fn mult_by_pow_2(&self, k: u32) -> ExtendedPoint {
+        let mut r: ExtendedPoint = *self;
+
+        for _ in 0..k {
+            r = r.double();
+        }
+        r
+    }
+
+    /// Multiply by the cofactor, 4.
+    pub fn mult_by_cofactor(&self) -> ExtendedPoint {
+        self.mult_by_pow_2(2)
+    }
+
+    /// Return true if this point has order dividing 4.
+    pub fn is_small_order(&self) -> bool {
+        self.mult_by_cofactor().is_identity()
+    }
+
+    /// Multiply by a scalar given as 56 little-endian bytes, in constant
+    /// time with respect to the scalar.
+    pub fn scalar_mult(&self, scalar: &[u8; 56]) -> ExtendedPoint {
+        let mut r: ExtendedPoint = ExtendedPoint::identity();
+
+        for i in (0..448).rev() {
+            r = r.double();
+
+            let sum: ExtendedPoint = &r + self;
+            r.conditional_assign(&sum, (scalar[i >> 3] >> (i & 7)) & 1);
+        }
+        r
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // The RFC 8032 basepoint, compressed.
+    static BASEPOINT_COMPRESSED: [u8; 57] = [
+        0x14, 0xfa, 0x30, 0xf2, 0x5b, 0x79, 0x08, 0x98,
+        0xad, 0xc8, 0xd7, 0x4e, 0x2c, 0x13, 0xbd, 0xfd,
+        0xc4, 0x39, 0x7c, 0xe6, 0x1c, 0xff, 0xd3, 0x3a,
+        0xd7, 0xc2, 0xa0, 0x05, 0x1e, 0x9c, 0x78, 0x87,
+        0x40, 0x98, 0xa3, 0x6c, 0x73, 0x73, 0xea, 0x4b,
+        0x62, 0xc7, 0xc9, 0x56, 0x37, 0x20, 0x76, 0x88,
+        0x24, 0xbc, 0xb6, 0x6e, 0x71, 0x46, 0x3f, 0x69,
+        0x00 ];
+
+    #[test]
+    fn basepoint_roundtrip() {
+        let b: ExtendedPoint = ExtendedPoint::basepoint();
+
+        assert_eq!(b.compress_edwards().0[..], BASEPOINT_COMPRESSED[..]);
+        assert_eq!(CompressedEdwardsY(BASEPOINT_COMPRESSED).decompress().unwrap(), b);
+    }
+
+    #[test]
+    fn basepoint_has_order_l() {
+        let b: ExtendedPoint = ExtendedPoint::basepoint();
+
+        assert!(b.scalar_mult(&L).is_identity());
+        assert!(!b.is_small_order());
+    }
+
+    #[test]
+    fn doubling_matches_addition() {
+        let b: ExtendedPoint = ExtendedPoint::basepoint();
+
+        assert_eq!(b.double(), &b + &b);
+        assert_eq!(&b.double() - &b, b);
+    }
+
+    #[test]
+    fn non_canonical_y_is_rejected() {
+        // y = p + 1 would otherwise decode as y = 1, the identity.
+        let mut bytes: [u8; 57] = [0u8; 57];
+        for byte in bytes[28..56].iter_mut() {
+            *byte = 0xff;
+        }
+
+        assert!(CompressedEdwardsY(bytes).decompress().is_none());
+    }
+
+    #[test]
+    fn high_bits_are_rejected() {
+        let mut bytes: [u8; 57] = BASEPOINT_COMPRESSED;
+        bytes[56] = 0x01;
+
+        assert!(CompressedEdwardsY(bytes).decompress().is_none());
+    }
+}
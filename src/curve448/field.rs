@@ -0,0 +1,341 @@
+// Arithmetic in GF(p) for p = 2⁴⁴⁸ - 2²²⁴ - 1.
+//
+// Elements are sixteen 28-bit limbs held in u32s, so that products fit in u64
+// without needing u128.  Because 2⁴⁴⁸ ≡ 2²²⁴ + 1 (mod p) and 224 = 8·28,
+// anything carried out of the top limb folds back into limbs 0 and 8, which
+// keeps the reduction to a couple of additions.
+//
+// This is written for clarity rather than speed: exponentiations are plain
+// square-and-multiply.  The exponents are public constants, so all operations
+// are still constant time with respect to the field elements themselves.
+
+use core::fmt::Debug;
+use core::ops::{Add, Sub, Mul, Neg};
+
+use subtle::arrays_equal;
+use subtle::CTAssignable;
+use subtle::CTEq;
+
+const MASK: u64 = (1 << 28) - 1;
+
+// p - 2, little-endian
+static P_MINUS_2: [u8; 56] = [
+    0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff ];
+
+// (p - 3)/4, little-endian
+static P_MINUS_3_DIV_4: [u8; 56] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xbf, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x3f ];
+
+/// An element of GF(2⁴⁴⁸ - 2²²⁴ - 1).
+#[derive(Copy, Clone)]
+pub struct FieldElement([u32; 16]);
+
+impl Debug for FieldElement {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "FieldElement448: {:?}", &self.to_bytes()[..])
+    }
+}
+
+impl CTEq for FieldElement {
+    fn ct_eq(&self, other: &FieldElement) -> u8 {
+        arrays_equal(&self.to_bytes(), &other.to_bytes())
+    }
+}
+
+impl PartialEq for FieldElement {
+    fn eq(&self, other: &FieldElement) -> bool {
+        self.ct_eq(other) == 1u8
+    }
+}
+
+impl Eq for FieldElement {}
+
+impl CTAssignable for FieldElement {
+    fn conditional_assign(&mut self, other: &FieldElement, choice: u8) {
+        let mask: u32 = (-(choice as i32)) as u32;
+
+        for i in 0..16 {
+            self.0[i] ^= mask & (self.0[i] ^ other.0[i]);
+        }
+    }
+}
+
+impl<'b> Add<&'b FieldElement> for &FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, other: &'b FieldElement) -> FieldElement {
+        let mut c: [u64; 16] = [0u64; 16];
+
+        for (c, (a, b)) in c.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+            *c = *a as u64 + *b as u64;
+        }
+        FieldElement::weak_reduce(c)
+    }
+}
+
+impl<'b> Sub<&'b FieldElement> for &FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, other: &'b FieldElement) -> FieldElement {
+        // Compute self + 2p - other, so that no limb goes negative.  2p has
+        // limbs 2²⁹ - 2, except limb 8 which is 2²⁹ - 4.
+        let mut c: [u64; 16] = [0u64; 16];
+
+        for (i, c) in c.iter_mut().enumerate() {
+            let two_p: u64 = if i == 8 { (MASK << 1) - 2 } else { MASK << 1 };
+            *c = self.0[i] as u64 + two_p - other.0[i] as u64;
+        }
+        FieldElement::weak_reduce(c)
+    }
+}
+
+impl<'b> Mul<&'b FieldElement> for &FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, other: &'b FieldElement) -> FieldElement {
+        let mut c: [u64; 33] = [0u64; 33];
+
+        // Limbs are below 2²⁹, so each of the at most sixteen products summed
+        // into a column is below 2⁵⁸ and the column fits in a u64.
+        for i in 0..16 {
+            for j in 0..16 {
+                c[i + j] += self.0[i] as u64 * other.0[j] as u64;
+            }
+        }
+        for i in 0..32 {
+            c[i + 1] += c[i] >> 28;
+            c[i] &= MASK;
+        }
+
+        // Fold 2⁴⁴⁸⁺²⁸ⁱ ≡ 2²²⁴⁺²⁸ⁱ + 2²⁸ⁱ from the top down, so that anything
+        // landing back above limb 15 is folded again before we get there.
+        for i in (16..33).rev() {
+            c[i - 16] += c[i];
+            c[i - 8] += c[i];
+            c[i] = 0;
+        }
+
+        let mut low: [u64; 16] = [0u64; 16];
+        low.copy_from_slice(&c[..16]);
+        FieldElement::weak_reduce(low)
+    }
+}
+
+impl Neg for &FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        &FieldElement::zero() - self
+    }
+}
+
+impl FieldElement {
+    /// The additive identity.
+    pub fn zero() -> FieldElement {
+        FieldElement([0u32; 16])
+    }
+
+    /// The multiplicative identity.
+    pub fn one() -> FieldElement {
+        FieldElement::from_u32(1)
+    }
+
+    /// Construct a small field element.
+    pub fn from_u32(x: u32) -> FieldElement {
+        let mut c: [u64; 16] = [0u64; 16];
+        c[0] = x as u64;
+        FieldElement::weak_reduce(c)
+    }
+
+    /// Load a little-endian integer below 2⁴⁴⁸.  The result is taken mod p,
+    /// so callers which care must check canonicity by re-encoding.
+    pub fn from_bytes(bytes: &[u8; 56]) -> FieldElement {
+        let mut limbs: [u32; 16] = [0u32; 16];
+
+        // Every 7 bytes hold exactly two limbs.
+        for i in 0..8 {
+            let mut chunk: u64 = 0;
+            for j in 0..7 {
+                chunk |= (bytes[7 * i + j] as u64) << (8 * j);
+            }
+            limbs[2 * i] = (chunk & MASK) as u32;
+            limbs[2 * i + 1] = (chunk >> 28) as u32;
+        }
+        FieldElement(limbs)
+    }
+
+    /// Encode the unique representative in [0, p) as 56 little-endian bytes.
+    pub fn to_bytes(&self) -> [u8; 56] {
+        let limbs: [u32; 16] = self.strong_reduce();
+        let mut bytes: [u8; 56] = [0u8; 56];
+
+        for i in 0..8 {
+            let chunk: u64 = limbs[2 * i] as u64 | ((limbs[2 * i + 1] as u64) << 28);
+            for j in 0..7 {
+                bytes[7 * i + j] = (chunk >> (8 * j)) as u8;
+            }
+        }
+        bytes
+    }
+
+    /// Square this field element.
+    pub fn square(&self) -> FieldElement {
+        self * self
+    }
+
+    /// Compute the inverse as self^(p-2), which gives zero for zero.
+    pub fn invert(&self) -> FieldElement {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// Return `1u8` if this element is zero and `0u8` otherwise.
+    pub fn is_zero(&self) -> u8 {
+        arrays_equal(&self.to_bytes(), &[0u8; 56])
+    }
+
+    /// Return the low bit of the canonical encoding, which is what both
+    /// RFC 8032 and RFC 9496 mean by a negative field element.
+    pub fn is_negative(&self) -> u8 {
+        self.to_bytes()[0] & 1
+    }
+
+    /// Given `u` and `v`, compute r = u·(uv)^((p-3)/4) in constant time.
+    ///
+    /// If u/v is square then r² = u/v, and otherwise r² = -u/v.  Returns
+    /// `(1u8, |r|)` in the first case and `(0u8, |r|)` in the second, as
+    /// SQRT_RATIO_M1 in RFC 9496 §5.2 does.
+    pub fn sqrt_ratio(u: &FieldElement, v: &FieldElement) -> (u8, FieldElement) {
+        let mut r: FieldElement = u * &(u * v).pow(&P_MINUS_3_DIV_4);
+        let was_square: u8 = (&r.square() * v).ct_eq(u);
+
+        let r_is_negative: u8 = r.is_negative();
+        let minus_r: FieldElement = -&r;
+        r.conditional_assign(&minus_r, r_is_negative);
+
+        (was_square, r)
+    }
+
+    // Square-and-multiply over a public, little-endian exponent.
+    fn pow(&self, exponent: &[u8; 56]) -> FieldElement {
+        let mut r: FieldElement = FieldElement::one();
+
+        for i in (0..448).rev() {
+            r = r.square();
+            if (exponent[i >> 3] >> (i & 7)) & 1 == 1 {
+                r = &r * self;
+            }
+        }
+        r
+    }
+
+    // Carry every limb into the next and fold the carry out of the top limb
+    // back in.  Two passes bring every limb below 2²⁸ + 2.
+    fn weak_reduce(mut c: [u64; 16]) -> FieldElement {
+        for _ in 0..2 {
+            let mut carry: u64 = 0;
+            for limb in c.iter_mut() {
+                *limb += carry;
+                carry = *limb >> 28;
+                *limb &= MASK;
+            }
+            c[0] += carry;
+            c[8] += carry;
+        }
+
+        let mut limbs: [u32; 16] = [0u32; 16];
+        for (limb, &c) in limbs.iter_mut().zip(c.iter()) {
+            *limb = c as u32;
+        }
+        FieldElement(limbs)
+    }
+
+    // Bring the limbs to the unique representative in [0, p), in constant
+    // time.
+    fn strong_reduce(&self) -> [u32; 16] {
+        let mut c: [i64; 16] = [0i64; 16];
+        for (c, &limb) in c.iter_mut().zip(self.0.iter()) {
+            *c = limb as i64;
+        }
+
+        // Normalise to exactly 28-bit limbs, which leaves a value below 2⁴⁴⁸
+        // and hence below 2p.
+        for _ in 0..2 {
+            let mut carry: i64 = 0;
+            for limb in c.iter_mut() {
+                *limb += carry;
+                carry = *limb >> 28;
+                *limb &= MASK as i64;
+            }
+            c[0] += carry;
+            c[8] += carry;
+        }
+
+        // Subtract p once, keeping the result unless it went negative.
+        let mut d: [i64; 16] = [0i64; 16];
+        let mut borrow: i64 = 0;
+        for i in 0..16 {
+            let p_i: i64 = if i == 8 { MASK as i64 - 1 } else { MASK as i64 };
+            d[i] = c[i] - p_i + borrow;
+            borrow = d[i] >> 28;
+            d[i] &= MASK as i64;
+        }
+
+        // borrow is -1 if c < p and 0 otherwise.
+        let keep_c: i64 = borrow;
+        let mut limbs: [u32; 16] = [0u32; 16];
+        for i in 0..16 {
+            limbs[i] = ((c[i] & keep_c) | (d[i] & !keep_c)) as u32;
+        }
+        limbs
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn p_is_zero() {
+        let mut p: [u8; 56] = [0xff; 56];
+        p[28] = 0xfe;
+
+        assert_eq!(FieldElement::from_bytes(&p).to_bytes(), [0u8; 56]);
+    }
+
+    #[test]
+    fn inverse() {
+        let a: FieldElement = FieldElement::from_bytes(&[0x5a; 56]);
+
+        assert_eq!(&a * &a.invert(), FieldElement::one());
+    }
+
+    #[test]
+    fn square_roots() {
+        let a: FieldElement = FieldElement::from_bytes(&[0x17; 56]);
+        let (was_square, r) = FieldElement::sqrt_ratio(&a.square(), &FieldElement::one());
+
+        assert_eq!(was_square, 1u8);
+        assert_eq!(r.square(), a.square());
+        assert_eq!(r.is_negative(), 0u8);
+    }
+
+    #[test]
+    fn minus_one_is_not_square() {
+        // p ≡ 3 (mod 4)
+        let minus_one: FieldElement = -&FieldElement::one();
+
+        assert_eq!(FieldElement::sqrt_ratio(&minus_one, &FieldElement::one()).0, 0u8);
+    }
+}
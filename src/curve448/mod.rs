@@ -0,0 +1,130 @@
+// The same two validation strategies as for curve25519, on Ed448-Goldilocks.
+//
+// Ed448 has cofactor 4 rather than 8, so torsioned keys are rarer but no less
+// real: an RFC 8032 public key is any point on the curve, and P + T for T in
+// E[4] verifies signatures made under P whenever the verifier clears the
+// cofactor.  Either we check that l·P is the identity, or we use decaf448,
+// whose encodings can only name prime-order group elements in the first
+// place.
+//
+// There is no Ed448 arithmetic in curve25519-dalek, so the field and curve
+// are implemented here, simply and in constant time.
+
+pub mod decaf;
+pub mod ed448;
+pub mod edwards;
+pub mod field;
+pub mod scalar;
+
+use curve448::decaf::CompressedDecaf;
+use curve448::decaf::DecafPoint;
+use curve448::edwards::CompressedEdwardsY;
+use curve448::edwards::ExtendedPoint;
+use curve448::edwards::L;
+
+use policy::IdentityPolicy;
+
+/// Decompress an Ed448 public key, returning the point only if it lies in
+/// the prime-order subgroup.
+pub fn mult_by_cofactor_and_validate(key: &CompressedEdwardsY) -> Option<ExtendedPoint> {
+    let p: ExtendedPoint = key.decompress()?;
+
+    // l·P is the identity exactly when P has no component in E[4].
+    match p.scalar_mult(&L).is_identity() {
+        true  => Some(p),
+        false => None, // the point has a torsion component
+    }
+}
+
+/// Decode a decaf448 public key, rejecting the identity.
+pub fn decaf448_decompress(key: &CompressedDecaf) -> Option<DecafPoint> {
+    decaf448_decompress_with(key, IdentityPolicy::Reject)
+}
+
+/// Decode a decaf448 public key, doing with the identity whatever
+/// `identity` says.
+pub fn decaf448_decompress_with(key: &CompressedDecaf, identity: IdentityPolicy) -> Option<DecafPoint> {
+    match identity == IdentityPolicy::Reject && *key == CompressedDecaf::identity() {
+        true  => None, // the point was the identity
+        false => key.decompress(),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve448::field::FieldElement;
+
+    // The public key from RFC 8032 §7.4, test 1.
+    static RFC8032_PUBLIC_KEY: [u8; 57] = [
+        0x5f, 0xd7, 0x44, 0x9b, 0x59, 0xb4, 0x61, 0xfd,
+        0x2c, 0xe7, 0x87, 0xec, 0x61, 0x6a, 0xd4, 0x6a,
+        0x1d, 0xa1, 0x34, 0x24, 0x85, 0xa7, 0x0e, 0x1f,
+        0x8a, 0x0e, 0xa7, 0x5d, 0x80, 0xe9, 0x67, 0x78,
+        0xed, 0xf1, 0x24, 0x76, 0x9b, 0x46, 0xc7, 0x06,
+        0x1b, 0xd6, 0x78, 0x3d, 0xf1, 0xe5, 0x0f, 0x6c,
+        0xd1, 0xfa, 0x1a, 0xbe, 0xaf, 0xe8, 0x25, 0x61,
+        0x80 ];
+
+    // The point of order 2, (0, -1).
+    fn two_torsion() -> ExtendedPoint {
+        ExtendedPoint{
+            X: FieldElement::zero(),
+            Y: -&FieldElement::one(),
+            Z: FieldElement::one(),
+            T: FieldElement::zero(),
+        }
+    }
+
+    #[test]
+    fn current_design() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(RFC8032_PUBLIC_KEY);
+
+        assert_eq!(mult_by_cofactor_and_validate(&key).unwrap().compress_edwards(), key);
+    }
+
+    #[test]
+    fn torsioned_keys_are_rejected() {
+        let p: ExtendedPoint = &CompressedEdwardsY(RFC8032_PUBLIC_KEY).decompress().unwrap() +
+            &two_torsion();
+
+        assert!(mult_by_cofactor_and_validate(&p.compress_edwards()).is_none());
+        assert!(mult_by_cofactor_and_validate(&two_torsion().compress_edwards()).is_none());
+    }
+
+    #[test]
+    fn with_decaf_instead() {
+        let p: ExtendedPoint = CompressedEdwardsY(RFC8032_PUBLIC_KEY).decompress().unwrap();
+        let key: CompressedDecaf = DecafPoint(p).compress();
+
+        assert!(decaf448_decompress(&key).is_some());
+        assert_eq!(DecafPoint(&p + &two_torsion()).compress(), key);
+    }
+
+    #[test]
+    fn decaf_identity_is_rejected() {
+        assert!(decaf448_decompress(&CompressedDecaf::identity()).is_none());
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::*;
+
+    use test::Bencher;
+
+    #[bench]
+    fn current_design(b: &mut Bencher) {
+        let key: CompressedEdwardsY = ExtendedPoint::basepoint().compress_edwards();
+
+        b.iter(| | mult_by_cofactor_and_validate(&key) )
+    }
+
+    #[bench]
+    fn with_decaf_instead(b: &mut Bencher) {
+        let key: CompressedDecaf = DecafPoint(ExtendedPoint::basepoint()).compress();
+
+        b.iter(| | decaf448_decompress(&key) )
+    }
+}
@@ -0,0 +1,93 @@
+// Integers modulo the Ed448 group order l, which are only needed here for
+// reducing signature challenges and checking that S is in range.
+//
+// Scalars are kept as 56 little-endian bytes, which is the form
+// `ExtendedPoint::scalar_mult` takes.
+
+use curve448::edwards::L;
+
+/// Reduce a little-endian integer of any length modulo l.
+pub fn reduce(bytes: &[u8]) -> [u8; 56] {
+    let l: [u32; 15] = to_limbs(&L);
+    let mut r: [u32; 15] = [0u32; 15];
+
+    // Shift the input in a bit at a time from the top, keeping r < l.
+    for i in (0..8 * bytes.len()).rev() {
+        let mut carry: u32 = (bytes[i >> 3] >> (i & 7)) as u32 & 1;
+        for limb in r.iter_mut() {
+            let next: u32 = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        conditional_subtract(&mut r, &l);
+    }
+
+    let mut out: [u8; 56] = [0u8; 56];
+    for i in 0..56 {
+        out[i] = (r[i / 4] >> (8 * (i % 4))) as u8;
+    }
+    out
+}
+
+/// Return `Some` of the 56-byte scalar if `bytes`, as used for S in an Ed448
+/// signature, is already reduced mod l.
+pub fn from_canonical_bytes(bytes: &[u8; 57]) -> Option<[u8; 56]> {
+    let s: [u8; 56] = reduce(bytes);
+
+    match bytes[56] == 0 && s[..] == bytes[..56] {
+        true  => Some(s),
+        false => None,
+    }
+}
+
+fn to_limbs(bytes: &[u8; 56]) -> [u32; 15] {
+    let mut limbs: [u32; 15] = [0u32; 15];
+
+    for i in 0..56 {
+        limbs[i / 4] |= (bytes[i] as u32) << (8 * (i % 4));
+    }
+    limbs
+}
+
+// Replace r by r - l, unless that would be negative.
+fn conditional_subtract(r: &mut [u32; 15], l: &[u32; 15]) {
+    let mut d: [u32; 15] = [0u32; 15];
+    let mut borrow: u64 = 0;
+
+    for j in 0..15 {
+        let t: u64 = (r[j] as u64).wrapping_sub(l[j] as u64).wrapping_sub(borrow);
+        d[j] = t as u32;
+        borrow = (t >> 63) & 1;
+    }
+
+    let keep_r: u32 = (-(borrow as i32)) as u32;
+    for j in 0..15 {
+        r[j] = (r[j] & keep_r) | (d[j] & !keep_r);
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn l_reduces_to_zero() {
+        assert_eq!(reduce(&L)[..], [0u8; 56][..]);
+    }
+
+    #[test]
+    fn l_is_not_canonical() {
+        let mut bytes: [u8; 57] = [0u8; 57];
+        bytes[..56].copy_from_slice(&L);
+
+        assert!(from_canonical_bytes(&bytes).is_none());
+    }
+
+    #[test]
+    fn small_scalars_are_unchanged() {
+        let mut bytes: [u8; 57] = [0u8; 57];
+        bytes[0] = 7;
+
+        assert_eq!(from_canonical_bytes(&bytes).unwrap()[0], 7);
+    }
+}
@@ -0,0 +1,171 @@
+// Validating keys encoded with curve25519-dalek's decaf encoding, whose
+// every valid encoding names an element of a prime-order group, so there
+// is no torsion to check for.
+
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::decaf::CompressedDecaf;
+use curve25519_dalek::decaf::DecafPoint;
+use curve25519_dalek::field::FieldElement;
+
+use subtle::Mask;
+use subtle::arrays_equal;
+
+use policy::IdentityPolicy;
+
+// Whether `key` is the decaf encoding of the identity, in constant time:
+// 1 if it is, and 0 if not.  Equality of decaf points is only defined for
+// their encodings, and the identity has exactly one, all zeroes.
+pub fn is_identity_encoding(key: &CompressedDecaf) -> Mask {
+    arrays_equal(key.as_bytes(), CompressedDecaf::identity().as_bytes())
+}
+
+// Decaf decompression ensures both that the point is a valid point on
+// the curve and that it is within a prime-order group.  The identity is
+// rejected, as `Strict` rejects it.
+pub fn decaf_decompress(key: &CompressedDecaf) -> Option<DecafPoint> {
+    decaf_decompress_with(key, IdentityPolicy::Reject)
+}
+
+// As `decaf_decompress`, but doing with the identity whatever `identity`
+// says.
+pub fn decaf_decompress_with(key: &CompressedDecaf, identity: IdentityPolicy) -> Option<DecafPoint> {
+    if identity == IdentityPolicy::Reject && is_identity_encoding(key) == 1 {
+        return None; // the point was the identity
+    }
+
+    key.decompress()
+}
+
+// Decompress and validate many decaf keys, in order, as
+// `traits::validate_batch` does for Edwards keys.  Unlike an inversion, the
+// inverse square root each decompression takes can't be shared between
+// encodings with Montgomery's trick, since the square root of a product
+// doesn't give the square roots of its factors; dalek already folds the one
+// inversion, of Z, into it.  So this costs the same per key as
+// `decaf_decompress`, and exists so that the two designs can be benchmarked
+// over the same batches.
+#[cfg(feature = "std")]
+pub fn decaf_decompress_batch(keys: &[CompressedDecaf]) -> Vec<Option<DecafPoint>> {
+    keys.iter().map(decaf_decompress).collect()
+}
+
+// Compress many decaf points, in order.  As with decompression, the one
+// costly step of a decaf compression is an inverse square root, of
+// (TZ)²(Z² + X²), and dalek has already merged the inversion of Z into it,
+// leaving nothing for Montgomery's trick to share: normalising every point
+// to Z = 1 with `inversion::batch_invert` first would still leave an inverse
+// square root of 1 + X² per point.  So this costs what calling `compress` on
+// each point does, and is here to pair with `decaf_decompress_batch`.
+#[cfg(feature = "std")]
+pub fn decaf_compress_batch(points: &[DecafPoint]) -> Vec<CompressedDecaf> {
+    points.iter().map(|point| point.compress()).collect()
+}
+
+// Derive a decaf point from 64 uniformly random bytes by running the
+// decaf-flavoured Elligator map on each half and adding the results, so that
+// tests and fuzzers can get well-distributed valid points from a seed.
+pub fn decaf_from_uniform_bytes(bytes: &[u8; 64]) -> DecafPoint {
+    let mut r_0: [u8; 32] = [0u8; 32];
+    let mut r_1: [u8; 32] = [0u8; 32];
+
+    r_0.copy_from_slice(&bytes[..32]);
+    r_1.copy_from_slice(&bytes[32..]);
+
+    let p: DecafPoint = DecafPoint::elligator_decaf_flavour(&FieldElement::from_bytes(&r_0));
+    let q: DecafPoint = DecafPoint::elligator_decaf_flavour(&FieldElement::from_bytes(&r_1));
+
+    &p + &q
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use rand::OsRng;
+
+    #[test]
+    fn with_decaf_instead() {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let p: DecafPoint = DecafPoint::random(&mut csprng);
+        let key: CompressedDecaf = p.compress();
+
+        let check = decaf_decompress(&key);
+        assert!(check.is_some());
+    }
+
+    #[test]
+    fn decaf_from_seed() {
+        let p: DecafPoint = decaf_from_uniform_bytes(&[0x5a; 64]);
+        let q: DecafPoint = decaf_from_uniform_bytes(&[0x5a; 64]);
+
+        assert_eq!(p.compress(), q.compress());
+        assert!(decaf_decompress(&p.compress()).is_some());
+    }
+
+    #[test]
+    fn identity_encodings() {
+        assert_eq!(is_identity_encoding(&CompressedDecaf::identity()), 1);
+        assert_eq!(is_identity_encoding(&decaf_from_uniform_bytes(&[1; 64]).compress()), 0);
+    }
+
+    #[test]
+    fn decaf_batches() {
+        let keys: Vec<CompressedDecaf> = (0..8u8)
+            .map(|i| match i % 3 {
+                0 => CompressedDecaf::identity(),
+                1 => CompressedDecaf([0x01; 32]),
+                _ => decaf_from_uniform_bytes(&[i; 64]).compress(),
+            })
+            .collect();
+        let batch: Vec<bool> = decaf_decompress_batch(&keys).iter().map(|p| p.is_some()).collect();
+
+        assert_eq!(batch, keys.iter().map(|key| decaf_decompress(key).is_some()).collect::<Vec<bool>>());
+        assert_eq!(batch, vec![false, false, true, false, false, true, false, false]);
+    }
+
+    #[test]
+    fn decaf_batches_round_trip() {
+        let points: Vec<DecafPoint> = (0..8u8).map(|i| decaf_from_uniform_bytes(&[i; 64])).collect();
+        let keys: Vec<CompressedDecaf> = decaf_compress_batch(&points);
+
+        for (point, key) in points.iter().zip(keys.iter()) {
+            assert!(point.compress() == *key);
+        }
+        for (point, key) in decaf_decompress_batch(&keys).iter().zip(keys.iter()) {
+            assert!(point.unwrap().compress() == *key);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::*;
+
+    use test::Bencher;
+    use rand::OsRng;
+
+    #[bench]
+    fn with_decaf_instead(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let p: DecafPoint = DecafPoint::random(&mut csprng);
+        let key: CompressedDecaf = p.compress();
+
+        b.iter(| | decaf_decompress(&key) )
+    }
+
+    #[bench]
+    fn with_decaf_instead_batch(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let keys: Vec<CompressedDecaf> = (0..64).map(|_| DecafPoint::random(&mut csprng).compress()).collect();
+
+        b.iter(| | decaf_decompress_batch(&keys) )
+    }
+
+    #[bench]
+    fn decaf_compression_batch(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let points: Vec<DecafPoint> = (0..64).map(|_| DecafPoint::random(&mut csprng)).collect();
+
+        b.iter(| | decaf_compress_batch(&points) )
+    }
+}
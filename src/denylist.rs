@@ -0,0 +1,91 @@
+// Rejecting keys which are valid but could never honestly be anyone's.
+//
+// The basepoint B is a perfectly good point of prime order, but it is the
+// public key for the secret scalar 1, as -B is for -1, so a relay whose
+// identity is either has either a broken key generator or a key its
+// attacker knows the secret for.  A `Denylist` holds these and any others
+// the caller supplies, and is checked against the canonical encoding of a
+// key's point after it has passed its policy, so that a non-canonical
+// encoding can't slip a denied key past it.  Every entry is compared every
+// time, so how long the check takes says nothing about which entry, if any,
+// matched.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use subtle::arrays_equal;
+
+// B and -B.
+static BUILT_IN: [[u8; 32]; 2] = [
+    [0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+     0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+     0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+     0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66 ],
+    [0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+     0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+     0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+     0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0xe6 ],
+];
+
+/// Keys to reject however valid they are.
+#[derive(Copy, Clone, Debug)]
+pub struct Denylist<'a> {
+    extra: &'a [[u8; 32]],
+}
+
+impl<'a> Denylist<'a> {
+    /// The basepoint and its negation, and the canonical encodings in
+    /// `extra`.
+    pub fn new(extra: &'a [[u8; 32]]) -> Denylist<'a> {
+        Denylist { extra }
+    }
+
+    /// Whether `encoding` is on the list, in constant time: 1 if it is, and
+    /// 0 if not.
+    pub fn contains(&self, encoding: &CompressedEdwardsY) -> u8 {
+        let mut found: u8 = 0;
+
+        for entry in BUILT_IN.iter().chain(self.extra.iter()) {
+            found |= arrays_equal(encoding.as_bytes(), entry);
+        }
+        found
+    }
+}
+
+impl Default for Denylist<'static> {
+    fn default() -> Denylist<'static> {
+        Denylist::new(&[])
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    #[test]
+    fn the_basepoint_is_denied() {
+        let b: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let minus_b: CompressedEdwardsY = (-&constants::ED25519_BASEPOINT).compress_edwards();
+
+        assert_eq!(Denylist::default().contains(&b), 1);
+        assert_eq!(Denylist::default().contains(&minus_b), 1);
+        assert!(UncheckedKey::new(b).check_with_denylist(KeyPolicy::Strict, &Denylist::default()).is_none());
+        assert!(UncheckedKey::new(b).check(KeyPolicy::Strict).is_some());
+    }
+
+    #[test]
+    fn caller_supplied_keys_are_denied() {
+        let p: CompressedEdwardsY = (&Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT).compress_edwards();
+        let q: CompressedEdwardsY = (&Scalar::from_u64(2015) * &constants::ED25519_BASEPOINT).compress_edwards();
+        let extra: [[u8; 32]; 1] = [p.to_bytes()];
+        let denylist: Denylist = Denylist::new(&extra);
+
+        assert!(UncheckedKey::new(p).check_with_denylist(KeyPolicy::PrimeOrder, &denylist).is_none());
+        assert!(UncheckedKey::new(q).check_with_denylist(KeyPolicy::PrimeOrder, &denylist).is_some());
+    }
+}
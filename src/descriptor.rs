@@ -0,0 +1,376 @@
+// Offline checking of the outer layer of a v3 onion service descriptor, as
+// in rend-spec-v3 §2.4, behind the `blinding` feature:
+//
+//    hs-descriptor 3
+//    descriptor-lifetime <minutes>
+//    descriptor-signing-key-cert
+//    -----BEGIN ED25519 CERT-----  ...  -----END ED25519 CERT-----
+//    revision-counter <n>
+//    superencrypted
+//    -----BEGIN MESSAGE-----  ...  -----END MESSAGE-----
+//    signature <base64>
+//
+// The certificate, in the format of cert-spec §2.1, is of type 0x08 and
+// certifies the descriptor signing key; it must carry the blinded key in a
+// signed-with-ed25519-key extension, and be signed by it.  The document is
+// signed by the descriptor signing key, over "Tor onion service descriptor
+// sig v3" followed by everything up to the `signature` line.
+//
+// The blinded key must be the onion address's identity key blinded for some
+// time period.  A descriptor doesn't say which, so the periods which the
+// certificate could have been issued in, going back from its expiration by
+// the 54 hours Tor certifies descriptor signing keys for, are all tried.
+// Nothing here is judged against the current time, so that descriptors from
+// old crawls can still be checked, and the superencrypted layer is left
+// alone, since it can't be read without the subcredential anyway.
+//
+// Signatures are verified cofactorless, as Tor verifies them, and each key
+// is checked separately under `KeyPolicy::Strict`.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use blinding::blind_public_key;
+use ed25519::verify;
+use encoding::base64_decode;
+use formats::cert::parse_certificate;
+use formats::cert::Certificate;
+use formats::pem;
+use formats::FormatError;
+use key::CheckedKey;
+use key::UncheckedKey;
+use onion::parse_onion_address;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+static SIGNATURE_PREFIX: &[u8] = b"Tor onion service descriptor sig v3";
+
+/// The length of a time period, in minutes, on the live network.
+pub const PERIOD_LENGTH: u64 = 1440;
+
+// How long Tor certifies a descriptor signing key for, in hours.
+const CERT_LIFETIME: u64 = 54;
+// Time periods start 12 hours after the epoch's midnight.
+const PERIOD_OFFSET: u64 = 12 * 60;
+
+const CERT_TYPE_DESCRIPTOR_SIGNING: u8 = 0x08;
+
+/// Something wrong with a descriptor which parsed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorProblem {
+    /// The certificate is not for a descriptor signing key.
+    CertificateType,
+    /// The certificate doesn't say which blinded key signed it.
+    MissingBlindedKey,
+    /// The blinded key fails validation.
+    InvalidBlindedKey,
+    /// The descriptor signing key fails validation.
+    InvalidSigningKey,
+    /// The blinded key is not the address's key blinded for any time period
+    /// the certificate could have been issued in.
+    WrongBlindedKey,
+    /// The certificate's signature doesn't verify under the blinded key.
+    BadCertificateSignature,
+    /// The descriptor's signature doesn't verify under the signing key.
+    BadDescriptorSignature,
+}
+
+/// What checking a descriptor found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DescriptorReport {
+    lifetime: u64,
+    revision_counter: u64,
+    expiration: u64,
+    blinded_key: Option<CompressedEdwardsY>,
+    signing_key: CompressedEdwardsY,
+    time_period: Option<u64>,
+    problems: Vec<DescriptorProblem>,
+}
+
+impl DescriptorReport {
+    /// How long the descriptor is valid for, in minutes.
+    pub fn lifetime(&self) -> u64 {
+        self.lifetime
+    }
+
+    /// The revision counter.
+    pub fn revision_counter(&self) -> u64 {
+        self.revision_counter
+    }
+
+    /// When the signing key certificate expires, in hours since the epoch.
+    pub fn expiration(&self) -> u64 {
+        self.expiration
+    }
+
+    /// The blinded key the certificate names, if it names one.
+    pub fn blinded_key(&self) -> Option<&CompressedEdwardsY> {
+        self.blinded_key.as_ref()
+    }
+
+    /// The descriptor signing key.
+    pub fn signing_key(&self) -> &CompressedEdwardsY {
+        &self.signing_key
+    }
+
+    /// The time period the blinded key is for, if it was found.
+    pub fn time_period(&self) -> Option<u64> {
+        self.time_period
+    }
+
+    /// Everything found wrong with the descriptor.
+    pub fn problems(&self) -> &[DescriptorProblem] {
+        &self.problems
+    }
+
+    /// Whether nothing was found wrong.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check the descriptor in `text` against the v3 onion `address` it was
+/// fetched for.
+///
+/// Returns an error if the descriptor can't be parsed, if the address is
+/// malformed, or if the identity key it names fails validation, and
+/// otherwise a report of everything else which was wrong.
+pub fn check_descriptor(text: &str, address: &str) -> Result<DescriptorReport, FormatError> {
+    let identity: CheckedKey<CompressedEdwardsY> = match parse_onion_address(address) {
+        Some(key) => match key.check(KeyPolicy::Strict) {
+            Some(key) => key,
+            None      => return Err(FormatError::InvalidKey),
+        },
+        None => return Err(FormatError::Malformed),
+    };
+
+    match text.lines().next() {
+        Some("hs-descriptor 3") => (),
+        Some(line) if line.starts_with("hs-descriptor ") => return Err(FormatError::UnsupportedAlgorithm),
+        _ => return Err(FormatError::Malformed),
+    }
+    let lifetime: u64 = keyword_number(text, "descriptor-lifetime")?;
+    let revision_counter: u64 = keyword_number(text, "revision-counter")?;
+
+    let cert_bytes: Vec<u8> = pem::decode(text, "ED25519 CERT")?;
+    let cert: Certificate = parse_certificate(&cert_bytes)?;
+
+    let signature_start: usize = match text.rfind("\nsignature ") {
+        Some(i) => i + 1,
+        None    => return Err(FormatError::Malformed),
+    };
+    let signature: [u8; 64] = match base64_decode(text[signature_start + 10..].trim()) {
+        Some(ref bytes) if bytes.len() == 64 => {
+            let mut signature: [u8; 64] = [0u8; 64];
+            signature.copy_from_slice(bytes);
+            signature
+        },
+        _ => return Err(FormatError::Malformed),
+    };
+
+    let mut problems: Vec<DescriptorProblem> = Vec::new();
+    let mut time_period: Option<u64> = None;
+
+    if cert.cert_type != CERT_TYPE_DESCRIPTOR_SIGNING {
+        problems.push(DescriptorProblem::CertificateType);
+    }
+
+    match cert.signing_key {
+        None => problems.push(DescriptorProblem::MissingBlindedKey),
+        Some(ref blinded) => {
+            if UncheckedKey::new(*blinded).check(KeyPolicy::Strict).is_none() {
+                problems.push(DescriptorProblem::InvalidBlindedKey);
+            }
+
+            time_period = find_time_period(&identity, blinded, cert.expiration);
+            if time_period.is_none() {
+                problems.push(DescriptorProblem::WrongBlindedKey);
+            }
+
+            if !verify(blinded, &cert_bytes[..cert.signed_length], &cert.signature, VerificationProfile::Cofactorless) {
+                problems.push(DescriptorProblem::BadCertificateSignature);
+            }
+        },
+    }
+
+    if UncheckedKey::new(cert.certified_key).check(KeyPolicy::Strict).is_none() {
+        problems.push(DescriptorProblem::InvalidSigningKey);
+    }
+
+    let mut signed: Vec<u8> = SIGNATURE_PREFIX.to_vec();
+    signed.extend_from_slice(&text.as_bytes()[..signature_start]);
+    if !verify(&cert.certified_key, &signed, &signature, VerificationProfile::Cofactorless) {
+        problems.push(DescriptorProblem::BadDescriptorSignature);
+    }
+
+    Ok(DescriptorReport {
+        lifetime,
+        revision_counter,
+        expiration: cert.expiration,
+        blinded_key: cert.signing_key,
+        signing_key: cert.certified_key,
+        time_period,
+        problems,
+    })
+}
+
+// The number on the first line starting with `keyword`.
+fn keyword_number(text: &str, keyword: &str) -> Result<u64, FormatError> {
+    for line in text.lines() {
+        let mut words = line.split(' ');
+
+        if words.next() == Some(keyword) {
+            return match (words.next().map(|word| word.parse::<u64>()), words.next()) {
+                (Some(Ok(n)), None) => Ok(n),
+                _                   => Err(FormatError::Malformed),
+            };
+        }
+    }
+    Err(FormatError::Malformed) // the keyword is missing
+}
+
+// The time period, from those a certificate expiring at `expiration` could
+// have been issued in, for which `identity` blinds to `blinded`.
+fn find_time_period(identity: &CheckedKey<CompressedEdwardsY>, blinded: &CompressedEdwardsY, expiration: u64)
+    -> Option<u64>
+{
+    let period = |hours: u64| (hours * 60).saturating_sub(PERIOD_OFFSET) / PERIOD_LENGTH;
+    let last: u64 = period(expiration);
+    let first: u64 = period(expiration.saturating_sub(CERT_LIFETIME));
+
+    (first..last + 1).find(|&period_number| &blind_public_key(identity, period_number, PERIOD_LENGTH) == blinded)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use sha2::Digest;
+    use sha2::Sha512;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use blinding::blinding_factor;
+    use encoding::base64_encode;
+    use encoding::hex_decode;
+    use formats::cert::EXTENSION_SIGNED_WITH_KEY;
+    use onion::onion_address;
+
+    // 2017-06-01, in hours since the epoch.
+    const EXPIRATION: u64 = 415_632;
+    const PERIOD: u64 = 17_317;
+
+    // Sign with a bare scalar, as a blinded key has no seed.
+    fn sign(a: &Scalar, message: &[u8]) -> [u8; 64] {
+        let key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * a).compress_edwards();
+        let mut h: Sha512 = Sha512::default();
+        h.input(a.as_bytes());
+        h.input(message);
+        let r: Scalar = Scalar::from_hash(h);
+        let r_key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * &r).compress_edwards();
+
+        let mut h: Sha512 = Sha512::default();
+        h.input(r_key.as_bytes());
+        h.input(key.as_bytes());
+        h.input(message);
+        let s: Scalar = Scalar::multiply_add(&Scalar::from_hash(h), a, &r);
+
+        let mut signature: [u8; 64] = [0u8; 64];
+        signature[..32].copy_from_slice(r_key.as_bytes());
+        signature[32..].copy_from_slice(s.as_bytes());
+        signature
+    }
+
+    fn key(a: &Scalar) -> CompressedEdwardsY {
+        (&constants::ED25519_BASEPOINT * a).compress_edwards()
+    }
+
+    // An address, and a descriptor for it signed for `period`.
+    fn descriptor(period: u64) -> (String, String) {
+        let identity_scalar: Scalar = Scalar::from_u64(22006);
+        let identity: CheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(key(&identity_scalar)).check(KeyPolicy::Strict).unwrap();
+        let blinded_scalar: Scalar = &Scalar::reduce(&{
+            let mut wide: [u8; 64] = [0u8; 64];
+            wide[..32].copy_from_slice(blinding_factor(identity.encoding(), period, PERIOD_LENGTH).as_bytes());
+            wide
+        }) * &identity_scalar;
+        let signing_scalar: Scalar = Scalar::from_u64(2015);
+
+        let mut cert: Vec<u8> = vec![1, CERT_TYPE_DESCRIPTOR_SIGNING];
+        cert.extend_from_slice(&[(EXPIRATION >> 24) as u8, (EXPIRATION >> 16) as u8,
+                                 (EXPIRATION >> 8) as u8, EXPIRATION as u8]);
+        cert.push(1);
+        cert.extend_from_slice(key(&signing_scalar).as_bytes());
+        cert.extend_from_slice(&[1, 0, 32, EXTENSION_SIGNED_WITH_KEY, 0]);
+        cert.extend_from_slice(key(&blinded_scalar).as_bytes());
+        let cert_signature: [u8; 64] = sign(&blinded_scalar, &cert);
+        cert.extend_from_slice(&cert_signature);
+
+        let mut text: String = format!("hs-descriptor 3\ndescriptor-lifetime 180\ndescriptor-signing-key-cert\n\
+                                        -----BEGIN ED25519 CERT-----\n{}\n-----END ED25519 CERT-----\n\
+                                        revision-counter 42\nsuperencrypted\n\
+                                        -----BEGIN MESSAGE-----\nAAAA\n-----END MESSAGE-----\n",
+                                       base64_encode(&cert, true));
+        let mut signed: Vec<u8> = SIGNATURE_PREFIX.to_vec();
+        signed.extend_from_slice(text.as_bytes());
+        text.push_str(&format!("signature {}\n", base64_encode(&sign(&signing_scalar, &signed), false)));
+
+        (onion_address(&identity), text)
+    }
+
+    #[test]
+    fn valid_descriptors_pass() {
+        let (address, text): (String, String) = descriptor(PERIOD);
+        let report: DescriptorReport = check_descriptor(&text, &address).unwrap();
+
+        assert!(report.is_valid(), "{:?}", report.problems());
+        assert_eq!(report.time_period(), Some(PERIOD));
+        assert_eq!(report.revision_counter(), 42);
+        assert_eq!(report.lifetime(), 180);
+        assert_eq!(report.expiration(), EXPIRATION);
+    }
+
+    // The blinded keys of `descriptor`'s identity key for the last two
+    // periods its certificate could have been issued in, from a separate
+    // implementation of rend-spec-v3 appendix A.2 in Python.
+    #[test]
+    fn blinded_keys_match_known_answers() {
+        let blinded = |hex: &str| {
+            let mut bytes: [u8; 32] = [0u8; 32];
+            bytes.copy_from_slice(&hex_decode(hex).unwrap());
+            CompressedEdwardsY(bytes)
+        };
+        let identity: CheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(key(&Scalar::from_u64(22006))).check(KeyPolicy::Strict).unwrap();
+        let current: CompressedEdwardsY = blinded("5d3e9952f5c1403e0635e5c37db74f8c469249315be9645b9609e8515085ebd3");
+        let previous: CompressedEdwardsY = blinded("420b4c97bf5e7ee3637fd131b2bdcdb499d11aa3da67bf2cf2b3a134a072da7f");
+        let (address, text): (String, String) = descriptor(PERIOD);
+
+        assert_eq!(check_descriptor(&text, &address).unwrap().blinded_key(), Some(&current));
+        assert_eq!(find_time_period(&identity, &current, EXPIRATION), Some(PERIOD));
+        assert_eq!(find_time_period(&identity, &previous, EXPIRATION), Some(PERIOD - 1));
+        assert_eq!(find_time_period(&identity, &previous, EXPIRATION + 48), None);
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let (address, text): (String, String) = descriptor(PERIOD - 5);
+        assert_eq!(check_descriptor(&text, &address).unwrap().problems(), &[DescriptorProblem::WrongBlindedKey]);
+
+        let (address, text): (String, String) = descriptor(PERIOD);
+        let tampered: String = text.replace("revision-counter 42", "revision-counter 43");
+        assert_eq!(check_descriptor(&tampered, &address).unwrap().problems(),
+                   &[DescriptorProblem::BadDescriptorSignature]);
+    }
+
+    #[test]
+    fn malformed_descriptors_are_errors() {
+        let (address, text): (String, String) = descriptor(PERIOD);
+
+        assert_eq!(check_descriptor(&text.replace("hs-descriptor 3", "hs-descriptor 2"), &address),
+                   Err(FormatError::UnsupportedAlgorithm));
+        assert_eq!(check_descriptor(&text, "not an address"), Err(FormatError::Malformed));
+        assert_eq!(check_descriptor(&text.replace("signature ", "signed "), &address), Err(FormatError::Malformed));
+    }
+}
@@ -0,0 +1,138 @@
+// Checking every key twice, by two unrelated methods, and refusing to answer
+// if they disagree.
+//
+// The first method is `UncheckedKey::check`, which multiplies by l and
+// compares against the identity.  The second never multiplies by l at all:
+// it computes 8⁻¹·(8·P), with 8⁻¹ taken mod l, which kills any torsion
+// component and leaves the prime-order component alone, so P is torsion-free
+// exactly when that gives back P.  A bug or miscompilation in one backend
+// routine is very unlikely to break both in the same way, so a disagreement
+// is reported rather than resolved.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::scalar::Scalar;
+
+use edwards::mul_by_pow_2;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The two methods gave different answers for a key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Disagreement {
+    encoding: CompressedEdwardsY,
+    policy: KeyPolicy,
+    cofactor_mult: bool,
+}
+
+impl Disagreement {
+    /// The key the methods disagreed about.
+    pub fn encoding(&self) -> &CompressedEdwardsY {
+        &self.encoding
+    }
+
+    /// The policy the key was being checked under.
+    pub fn policy(&self) -> KeyPolicy {
+        self.policy
+    }
+
+    /// Whether multiplying by l accepted the key.  The other method said
+    /// the opposite.
+    pub fn cofactor_mult_accepted(&self) -> bool {
+        self.cofactor_mult
+    }
+}
+
+/// A validator which checks each key by both methods.
+#[derive(Copy, Clone)]
+pub struct DualValidator {
+    policy: KeyPolicy,
+    second: fn(&CompressedEdwardsY, KeyPolicy) -> bool,
+}
+
+impl DualValidator {
+    /// A validator checking keys under `policy`.
+    pub fn new(policy: KeyPolicy) -> DualValidator {
+        DualValidator { policy, second: check_by_projection }
+    }
+
+    /// Check `key` by both methods, returning the checked key if both accept
+    /// it, `None` if both reject it, and an error otherwise.
+    pub fn check(&self, key: &UncheckedKey<CompressedEdwardsY>)
+        -> Result<Option<CheckedKey<CompressedEdwardsY>>, Disagreement>
+    {
+        let checked: Option<CheckedKey<CompressedEdwardsY>> = key.check(self.policy);
+        let projected: bool = (self.second)(key.encoding(), self.policy);
+
+        match checked.is_some() == projected {
+            true  => Ok(checked),
+            false => Err(Disagreement {
+                encoding: *key.encoding(),
+                policy: self.policy,
+                cofactor_mult: checked.is_some(),
+            }),
+        }
+    }
+}
+
+fn check_by_projection(key: &CompressedEdwardsY, policy: KeyPolicy) -> bool {
+    let p: ExtendedPoint = match key.decompress() {
+        Some(p) => p,
+        None    => return false, // the point was invalid
+    };
+    let eight_inverse: Scalar = Scalar::from_u64(8).invert();
+    let projected: CompressedEdwardsY = (&mul_by_pow_2(&p, 3) * &eight_inverse).compress_edwards();
+
+    // Both sides are compressed, so the comparison is of affine points.
+    if projected != p.compress_edwards() {
+        return false; // the point has a torsion component
+    }
+
+    match policy {
+        KeyPolicy::PrimeOrder => true,
+        KeyPolicy::Strict     => projected == *key && projected != ExtendedPoint::identity().compress_edwards(),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    fn keys() -> Vec<UncheckedKey<CompressedEdwardsY>> {
+        let mut keys: Vec<UncheckedKey<CompressedEdwardsY>> = vec![CompressedEdwardsY([0xff; 32]).into()];
+
+        // T[0] is the identity, so this includes both it and the basepoint.
+        for t in constants::EIGHT_TORSION.iter() {
+            keys.push(t.compress_edwards().into());
+            keys.push((&constants::ED25519_BASEPOINT + t).compress_edwards().into());
+        }
+        keys
+    }
+
+    #[test]
+    fn methods_agree() {
+        for &policy in [KeyPolicy::PrimeOrder, KeyPolicy::Strict].iter() {
+            let validator: DualValidator = DualValidator::new(policy);
+            let accepted: usize = keys().iter().filter(|key| validator.check(key).unwrap().is_some()).count();
+
+            // The basepoint, plus the identity if it is allowed.
+            assert_eq!(accepted, match policy { KeyPolicy::PrimeOrder => 2, KeyPolicy::Strict => 1 });
+        }
+    }
+
+    #[test]
+    fn disagreements_are_reported() {
+        let validator: DualValidator = DualValidator { policy: KeyPolicy::Strict, second: |_, _| true };
+        let torsioned: UncheckedKey<CompressedEdwardsY> = constants::EIGHT_TORSION[1].compress_edwards().into();
+
+        let disagreement: Disagreement = validator.check(&torsioned).unwrap_err();
+
+        assert_eq!(disagreement.encoding(), torsioned.encoding());
+        assert!(!disagreement.cofactor_mult_accepted());
+        assert!(validator.check(&constants::ED25519_BASEPOINT.compress_edwards().into()).is_ok());
+    }
+}
@@ -0,0 +1,609 @@
+// Ed25519 signature verification under each `VerificationProfile`, cold,
+// with a precomputed table for the key, or in batches, checking the points
+// of many signatures at once, and public key derivation and signing from an
+// RFC 8032 secret seed.
+//
+// A signature is R ‖ S for a compressed point R and a scalar S, and the
+// challenge is k = SHA-512(R ‖ A ‖ M) mod l.  Every profile requires S to be
+// reduced mod l, since otherwise signatures are trivially malleable; they
+// differ only in what they do about torsion.
+
+use std::collections::HashMap;
+
+use sha2::Digest;
+use sha2::Sha512;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::EdwardsBasepointTable;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::curve::vartime;
+use curve25519_dalek::scalar::Scalar;
+
+use edwards::mul_by_pow_2;
+use entropy::EntropySource;
+use key::CheckedKey;
+use key::UncheckedKey;
+use params::hash_to_scalar;
+use params::is_canonical_scalar;
+use params::reduce_mod_l;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+use traits::PrimeOrderEncoding;
+use wipe::wipe;
+use wipe::wipe_scalar;
+
+/// Verify an Ed25519 `signature` on `message` under the public `key`.
+pub fn verify(key: &CompressedEdwardsY,
+              message: &[u8],
+              signature: &[u8; 64],
+              profile: VerificationProfile) -> bool {
+    let (r_key, r, s): (CompressedEdwardsY, ExtendedPoint, Scalar) = match split_signature(signature) {
+        Some(parts) => parts,
+        None        => return false, // S was not reduced, or R was invalid
+    };
+    let a: ExtendedPoint = match key.decompress() {
+        Some(a) => a,
+        None    => return false, // the key was invalid
+    };
+
+    if profile == VerificationProfile::Strict &&
+        !(is_canonical_and_torsion_free(&a, key) && is_canonical_and_torsion_free(&r, &r_key)) {
+        return false;
+    }
+
+    let k: Scalar = challenge(&r_key, key, message);
+
+    // [S]B - [k]A - R, which is the identity for a valid signature.
+    let check: ExtendedPoint = &vartime::k_fold_scalar_mult(&[s, k],
+                                                            &[constants::ED25519_BASEPOINT, -&a]) - &r;
+
+    is_accepted(&check, profile)
+}
+
+/// Verify each `signatures[i]` on `messages[i]` under `keys[i]`, returning
+/// true only if every one of them verifies under `profile`.
+///
+/// The equations are checked at once as one random linear combination, with
+/// 128-bit coefficients drawn from `entropy`, so a false batch passes with
+/// probability about 2^-128.  A batch can't reproduce the cofactorless
+/// equation, whose answer for a torsioned key depends on the message, so
+/// under `Cofactorless` each signature is verified on its own.  Returns false
+/// if the slices differ in length.
+pub fn verify_batch<E: EntropySource>(keys: &[CompressedEdwardsY],
+                                      messages: &[&[u8]],
+                                      signatures: &[[u8; 64]],
+                                      profile: VerificationProfile,
+                                      entropy: &mut E) -> bool {
+    if messages.len() != keys.len() || signatures.len() != keys.len() {
+        return false;
+    }
+    if profile == VerificationProfile::Cofactorless {
+        return (0..keys.len()).all(|i| verify(&keys[i], messages[i], &signatures[i], profile));
+    }
+
+    let mut scalars: Vec<Scalar> = Vec::with_capacity(2 * keys.len() + 1);
+    let mut points: Vec<ExtendedPoint> = Vec::with_capacity(2 * keys.len() + 1);
+    let mut b_coefficient: Scalar = Scalar::zero();
+
+    for i in 0..keys.len() {
+        let (r_key, r, s): (CompressedEdwardsY, ExtendedPoint, Scalar) = match split_signature(&signatures[i]) {
+            Some(parts) => parts,
+            None        => return false, // S was not reduced, or R was invalid
+        };
+        let a: ExtendedPoint = match keys[i].decompress() {
+            Some(a) => a,
+            None    => return false, // the key was invalid
+        };
+
+        if profile == VerificationProfile::Strict &&
+            !(is_canonical_and_torsion_free(&a, &keys[i]) && is_canonical_and_torsion_free(&r, &r_key)) {
+            return false;
+        }
+
+        let k: Scalar = challenge(&r_key, &keys[i], messages[i]);
+        let mut z: Scalar = random_coefficient(entropy);
+
+        // z([S]B - [k]A - R), with the [zS]B terms collected into one.
+        b_coefficient += &(&z * &s);
+        scalars.push(&z * &k);
+        points.push(-&a);
+        scalars.push(z);
+        points.push(-&r);
+        wipe_scalar(&mut z);
+    }
+    scalars.push(b_coefficient);
+    points.push(constants::ED25519_BASEPOINT);
+
+    let accepted: bool = is_accepted(&vartime::k_fold_scalar_mult(&scalars, &points), profile);
+
+    for scalar in scalars.iter_mut() {
+        wipe_scalar(scalar);
+    }
+    wipe_scalar(&mut b_coefficient);
+    accepted
+}
+
+/// Check the key A and the commitment R of every signature in `pairs`
+/// under `policy`, returning whether both passed for each.
+///
+/// A log of signatures names each signing key many times over, so every
+/// distinct A is checked only once, while each R, being a fresh nonce, is
+/// checked where it appears, and only if its A passed.  The torsion checks
+/// can't be folded into one check of a random linear combination, as
+/// `verify_batch` folds the equations: the torsion of Σ zᵢPᵢ depends only on
+/// each zᵢ mod 8, so a single torsioned point of order 2 escapes it whenever
+/// its coefficient is even, which is half the time.
+pub fn validate_signature_points(pairs: &[(CompressedEdwardsY, [u8; 64])], policy: KeyPolicy) -> Vec<bool> {
+    let mut keys: HashMap<[u8; 32], bool> = HashMap::new();
+    let mut results: Vec<bool> = Vec::with_capacity(pairs.len());
+
+    for (key, signature) in pairs.iter() {
+        let a_passed: bool = *keys.entry(key.to_bytes()).or_insert_with(|| passes(key, policy));
+        let mut r_key: CompressedEdwardsY = CompressedEdwardsY([0u8; 32]);
+        r_key.0.copy_from_slice(&signature[..32]);
+
+        results.push(a_passed && passes(&r_key, policy));
+    }
+    results
+}
+
+/// A checked public key, together with a table of multiples of it, for
+/// verifying many signatures under the same key.
+///
+/// The key is decompressed and checked once, rather than once per
+/// signature, and [k]A comes from the table instead of a variable-base
+/// multiplication, which makes a strict verification a little over twice
+/// as fast.  Building the table costs about as much as half a dozen
+/// verifications, and it takes 30KiB, so this is only worth having for keys
+/// which sign a lot, such as a relay's signing key over the lifetime of a
+/// consensus.
+pub struct ExpandedPublicKey {
+    encoding: CompressedEdwardsY,
+    a: Box<EdwardsBasepointTable>,
+    canonical: bool,
+}
+
+impl ExpandedPublicKey {
+    /// Build the table for `key`.
+    pub fn new(key: &CheckedKey<CompressedEdwardsY>) -> ExpandedPublicKey {
+        ExpandedPublicKey {
+            encoding: *key.encoding(),
+            a: Box::new(EdwardsBasepointTable::create(key.point())),
+            canonical: key.point().compress_edwards() == *key.encoding(),
+        }
+    }
+
+    /// The key this table was built for.
+    pub fn encoding(&self) -> &CompressedEdwardsY {
+        &self.encoding
+    }
+
+    /// Compute [h]A, for a reduced scalar `h`, as a key blinding scheme
+    /// deriving many keys from this one would.
+    pub fn mul(&self, h: &Scalar) -> ExtendedPoint {
+        &*self.a * h
+    }
+
+    /// Verify `signature` on `message`, giving the same answer as `verify`
+    /// would for this key.
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64], profile: VerificationProfile) -> bool {
+        let (r_key, r, s): (CompressedEdwardsY, ExtendedPoint, Scalar) = match split_signature(signature) {
+            Some(parts) => parts,
+            None        => return false, // S was not reduced, or R was invalid
+        };
+
+        // A is already known to be torsion-free.
+        if profile == VerificationProfile::Strict &&
+            !(self.canonical && is_canonical_and_torsion_free(&r, &r_key)) {
+            return false;
+        }
+
+        let k: Scalar = challenge(&r_key, &self.encoding, message);
+        let check: ExtendedPoint = &(&(&constants::ED25519_BASEPOINT_TABLE * &s) - &self.mul(&k)) - &r;
+
+        is_accepted(&check, profile)
+    }
+}
+
+/// An RFC 8032 secret key after hashing and clamping: the secret scalar a,
+/// and the prefix that signing nonces are derived from.
+#[derive(Clone)]
+pub struct ExpandedSecretKey {
+    a: [u8; 32],
+    prefix: [u8; 32],
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ExpandedSecretKey {
+    fn drop(&mut self) {
+        wipe(&mut self.a);
+        wipe(&mut self.prefix);
+    }
+}
+
+impl ExpandedSecretKey {
+    /// Expand the 32-byte secret `seed`, as RFC 8032 §5.1.5 does: hash the
+    /// seed, and clamp the low half of the hash to get a.
+    pub fn from_seed(seed: &[u8; 32]) -> ExpandedSecretKey {
+        let mut h: Sha512 = Sha512::default();
+        let mut expanded: [u8; 64] = [0u8; 64];
+
+        h.input(seed);
+        expanded.copy_from_slice(h.result().as_slice());
+        expanded[0] &= 248;
+        expanded[31] &= 127;
+        expanded[31] |= 64;
+
+        let key: Option<ExpandedSecretKey> = ExpandedSecretKey::from_bytes(&expanded);
+
+        wipe(&mut expanded);
+        key.unwrap() // it was just clamped
+    }
+
+    /// Use an expanded key, a ‖ prefix, stored elsewhere.  Returns `None`
+    /// unless a is clamped, since no seed expands to anything else.
+    pub fn from_bytes(expanded: &[u8; 64]) -> Option<ExpandedSecretKey> {
+        if expanded[0] & 7 != 0 || expanded[31] & 0xc0 != 0x40 {
+            return None; // a is not clamped
+        }
+
+        let mut key: ExpandedSecretKey = ExpandedSecretKey { a: [0u8; 32], prefix: [0u8; 32] };
+        key.a.copy_from_slice(&expanded[..32]);
+        key.prefix.copy_from_slice(&expanded[32..]);
+        Some(key)
+    }
+
+    /// a ‖ prefix, which the caller should wipe once written out.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut expanded: [u8; 64] = [0u8; 64];
+        expanded[..32].copy_from_slice(&self.a);
+        expanded[32..].copy_from_slice(&self.prefix);
+        expanded
+    }
+
+    /// The clamped scalar a, unreduced.
+    pub fn scalar(&self) -> &[u8; 32] {
+        &self.a
+    }
+
+    /// The prefix.
+    pub fn prefix(&self) -> &[u8; 32] {
+        &self.prefix
+    }
+
+    /// The public key [a]B.
+    pub fn public_key(&self) -> CompressedEdwardsY {
+        let mut a: Scalar = Scalar(self.a);
+        let key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * &a).compress_edwards();
+
+        wipe_scalar(&mut a);
+        key
+    }
+
+    /// The public key [a]B, checked under `policy`.  A key derived this way
+    /// can only fail if a is a multiple of l, but a key which is about to be
+    /// trusted is checked all the same.
+    pub fn check_public_key(&self, policy: KeyPolicy) -> Option<CheckedKey<CompressedEdwardsY>> {
+        UncheckedKey::new(self.public_key()).check(policy)
+    }
+}
+
+/// Derive the public key for the 32-byte secret `seed`, as RFC 8032 §5.1.5
+/// does.
+pub fn public_key_from_seed(seed: &[u8; 32]) -> CompressedEdwardsY {
+    ExpandedSecretKey::from_seed(seed).public_key()
+}
+
+/// Sign `message` with the 32-byte secret `seed`, as RFC 8032 §5.1.6 does.
+/// This exists for the statements this crate issues about keys, such as
+/// revocations, and is not constant-time.
+pub fn sign(seed: &[u8; 32], message: &[u8]) -> [u8; 64] {
+    let expanded: ExpandedSecretKey = ExpandedSecretKey::from_seed(seed);
+    let key: CompressedEdwardsY = expanded.public_key();
+
+    let mut a: Scalar = reduce_mod_l(expanded.scalar());
+    let mut r: Scalar = hash_to_scalar(&[expanded.prefix(), message]);
+    let r_key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * &r).compress_edwards();
+
+    let s: Scalar = Scalar::multiply_add(&challenge(&r_key, &key, message), &a, &r);
+    let mut signature: [u8; 64] = [0u8; 64];
+
+    wipe_scalar(&mut a);
+    wipe_scalar(&mut r);
+
+    signature[..32].copy_from_slice(r_key.as_bytes());
+    signature[32..].copy_from_slice(s.as_bytes());
+    signature
+}
+
+// Split a signature into R, both as encoded and decompressed, and S.
+fn split_signature(signature: &[u8; 64]) -> Option<(CompressedEdwardsY, ExtendedPoint, Scalar)> {
+    let mut r_bytes: [u8; 32] = [0u8; 32];
+    let mut s_bytes: [u8; 32] = [0u8; 32];
+
+    r_bytes.copy_from_slice(&signature[..32]);
+    s_bytes.copy_from_slice(&signature[32..]);
+
+    let s: Scalar = scalar_from_canonical_bytes(&s_bytes)?;
+    let r_key: CompressedEdwardsY = CompressedEdwardsY(r_bytes);
+
+    r_key.decompress().map(|r| (r_key, r, s))
+}
+
+// k = SHA-512(R ‖ A ‖ M) mod l.
+fn challenge(r_key: &CompressedEdwardsY, key: &CompressedEdwardsY, message: &[u8]) -> Scalar {
+    hash_to_scalar(&[r_key.as_bytes(), key.as_bytes(), message])
+}
+
+// Whether `check`, which is [S]B - [k]A - R, passes under `profile`.
+fn is_accepted(check: &ExtendedPoint, profile: VerificationProfile) -> bool {
+    match profile {
+        VerificationProfile::Cofactored => mul_by_pow_2(check, 3).is_identity(),
+        _                               => check.is_identity(),
+    }
+}
+
+// A uniformly random scalar below 2^128.
+fn random_coefficient<E: EntropySource>(entropy: &mut E) -> Scalar {
+    let mut z: [u8; 32] = [0u8; 32];
+
+    entropy.fill_bytes(&mut z[..16]);
+    let coefficient: Scalar = Scalar(z);
+
+    wipe(&mut z);
+    coefficient
+}
+
+// Return `Some(s)` only if `bytes` is already reduced mod l.
+pub(crate) fn scalar_from_canonical_bytes(bytes: &[u8; 32]) -> Option<Scalar> {
+    match is_canonical_scalar(bytes) {
+        true  => Some(Scalar(*bytes)),
+        false => None,
+    }
+}
+
+fn passes(encoding: &CompressedEdwardsY, policy: KeyPolicy) -> bool {
+    match policy {
+        KeyPolicy::PrimeOrder => encoding.validate().is_some(),
+        KeyPolicy::Strict     => encoding.validate_strict().is_some(),
+    }
+}
+
+fn is_canonical_and_torsion_free(p: &ExtendedPoint, encoding: &CompressedEdwardsY) -> bool {
+    p.compress_edwards() == *encoding && (p * &constants::l).is_identity()
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // RFC 8032 §7.1, test 1.
+    static PUBLIC_KEY: [u8; 32] = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7,
+        0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+        0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+        0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a ];
+    static SIGNATURE: [u8; 64] = [
+        0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+        0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+        0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+        0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+        0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+        0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+        0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+        0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b ];
+
+    // The test 1 key plus a point of order 8, and a signature on the message
+    // [0x00] made with the same secret scalar under the torsioned key.
+    static TORSIONED_KEY: [u8; 32] = [
+        0x91, 0x58, 0x31, 0x2a, 0x9a, 0x8d, 0x6e, 0x3b,
+        0x34, 0xc8, 0x91, 0xd6, 0xd6, 0x14, 0x44, 0xf8,
+        0xb8, 0x21, 0x1c, 0x51, 0x17, 0xeb, 0xad, 0x15,
+        0xbd, 0xb0, 0xbd, 0x68, 0xb0, 0x7e, 0x02, 0x45 ];
+    static TORSIONED_SIGNATURE: [u8; 64] = [
+        0xfb, 0x66, 0x65, 0xa8, 0xe2, 0x78, 0xa0, 0xd6,
+        0xa8, 0x04, 0x50, 0xb9, 0x5d, 0x4c, 0x4e, 0xf7,
+        0xe4, 0xbc, 0x78, 0x69, 0x4d, 0xb7, 0x66, 0xe1,
+        0x6c, 0x8b, 0x75, 0x4f, 0x85, 0x89, 0xe1, 0xf3,
+        0x95, 0x3a, 0x49, 0xb1, 0xec, 0xd1, 0x3c, 0xff,
+        0x0f, 0xf7, 0xe7, 0x9e, 0xac, 0x18, 0x91, 0xac,
+        0xb8, 0xf2, 0x86, 0xfa, 0x72, 0xf5, 0xd2, 0x24,
+        0x6a, 0x0c, 0xf7, 0x5c, 0x58, 0x35, 0xc8, 0x02 ];
+
+    // The secret seed for test 1.
+    static SEED: [u8; 32] = [
+        0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60,
+        0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+        0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19,
+        0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60 ];
+
+    #[test]
+    fn public_key_derivation() {
+        assert_eq!(public_key_from_seed(&SEED), CompressedEdwardsY(PUBLIC_KEY));
+    }
+
+    #[test]
+    fn seeds_are_expanded() {
+        let key: ExpandedSecretKey = ExpandedSecretKey::from_seed(&SEED);
+        let mut unclamped: [u8; 64] = key.to_bytes();
+
+        assert_eq!(key.scalar()[0] & 7, 0);
+        assert_eq!(key.scalar()[31] & 0xc0, 0x40);
+        assert_eq!(key.check_public_key(KeyPolicy::Strict).unwrap().encoding(), &CompressedEdwardsY(PUBLIC_KEY));
+        assert_eq!(ExpandedSecretKey::from_bytes(&unclamped).unwrap().prefix(), key.prefix());
+
+        unclamped[0] |= 1;
+        assert!(ExpandedSecretKey::from_bytes(&unclamped).is_none());
+        unclamped[0] &= 248;
+        unclamped[31] |= 0x80;
+        assert!(ExpandedSecretKey::from_bytes(&unclamped).is_none());
+    }
+
+    #[test]
+    fn signing() {
+        assert_eq!(&sign(&SEED, b"")[..], &SIGNATURE[..]);
+        assert!(verify(&CompressedEdwardsY(PUBLIC_KEY), b"x", &sign(&SEED, b"x"), VerificationProfile::Strict));
+    }
+
+    #[test]
+    fn rfc8032_vector_passes_every_profile() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+
+        assert!(verify(&key, b"", &SIGNATURE, VerificationProfile::Cofactored));
+        assert!(verify(&key, b"", &SIGNATURE, VerificationProfile::Cofactorless));
+        assert!(verify(&key, b"", &SIGNATURE, VerificationProfile::Strict));
+        assert!(!verify(&key, b"x", &SIGNATURE, VerificationProfile::Cofactored));
+    }
+
+    #[test]
+    fn profiles_disagree_on_torsioned_keys() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(TORSIONED_KEY);
+
+        assert!(verify(&key, &[0u8], &TORSIONED_SIGNATURE, VerificationProfile::Cofactored));
+        assert!(!verify(&key, &[0u8], &TORSIONED_SIGNATURE, VerificationProfile::Cofactorless));
+        assert!(!verify(&key, &[0u8], &TORSIONED_SIGNATURE, VerificationProfile::Strict));
+    }
+
+    #[test]
+    fn expanded_keys_agree_with_verify() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+        let checked: CheckedKey<CompressedEdwardsY> = UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap();
+        let expanded: ExpandedPublicKey = ExpandedPublicKey::new(&checked);
+        let profiles: [VerificationProfile; 3] = [
+            VerificationProfile::Cofactored,
+            VerificationProfile::Cofactorless,
+            VerificationProfile::Strict,
+        ];
+
+        for &profile in profiles.iter() {
+            for message in [&b""[..], &b"x"[..]].iter() {
+                assert_eq!(expanded.verify(message, &SIGNATURE, profile),
+                           verify(&key, message, &SIGNATURE, profile));
+            }
+        }
+        assert!(expanded.verify(b"", &SIGNATURE, VerificationProfile::Strict));
+        assert_eq!(expanded.mul(&Scalar::from_u64(7)).compress_edwards(),
+                   (&key.decompress().unwrap() * &Scalar::from_u64(7)).compress_edwards());
+    }
+
+    // Not remotely random, but a batch only needs its coefficients to be
+    // unpredictable to whoever made the signatures, and these tests made
+    // them all.
+    struct Counter(u8);
+
+    impl EntropySource for Counter {
+        fn fill_bytes(&mut self, bytes: &mut [u8]) {
+            for byte in bytes.iter_mut() {
+                *byte = self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+        }
+    }
+
+    #[test]
+    fn batches_agree_with_verify() {
+        let keys: [CompressedEdwardsY; 2] = [CompressedEdwardsY(PUBLIC_KEY), CompressedEdwardsY(TORSIONED_KEY)];
+        let messages: [&[u8]; 2] = [b"", &[0u8]];
+        let signatures: [[u8; 64]; 2] = [SIGNATURE, TORSIONED_SIGNATURE];
+        let profiles: [VerificationProfile; 3] = [
+            VerificationProfile::Cofactored,
+            VerificationProfile::Cofactorless,
+            VerificationProfile::Strict,
+        ];
+
+        for &profile in profiles.iter() {
+            assert!(verify_batch(&keys[..1], &messages[..1], &signatures[..1], profile, &mut Counter(0)));
+            assert_eq!(verify_batch(&keys, &messages, &signatures, profile, &mut Counter(0)),
+                       profile == VerificationProfile::Cofactored);
+        }
+        assert!(!verify_batch(&keys[..1], &[&b"x"[..]], &signatures[..1],
+                              VerificationProfile::Cofactored, &mut Counter(0)));
+        assert!(!verify_batch(&keys, &messages[..1], &signatures, VerificationProfile::Cofactored, &mut Counter(0)));
+        assert!(verify_batch(&[], &[], &[], VerificationProfile::Strict, &mut Counter(0)));
+    }
+
+    #[test]
+    fn signature_points() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+        let torsioned: CompressedEdwardsY = CompressedEdwardsY(TORSIONED_KEY);
+        let mut torsioned_r: [u8; 64] = SIGNATURE;
+        torsioned_r[..32].copy_from_slice(&TORSIONED_KEY);
+
+        let pairs: [(CompressedEdwardsY, [u8; 64]); 4] = [
+            (key, SIGNATURE),
+            (torsioned, TORSIONED_SIGNATURE),
+            (key, torsioned_r),
+            (key, SIGNATURE),
+        ];
+
+        assert_eq!(validate_signature_points(&pairs, KeyPolicy::Strict), vec![true, false, false, true]);
+        assert!(validate_signature_points(&[], KeyPolicy::PrimeOrder).is_empty());
+    }
+
+    #[test]
+    fn unreduced_s_is_rejected() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+        let mut signature: [u8; 64] = SIGNATURE;
+
+        // Replace S by S + l, which is congruent but not reduced.
+        let mut carry: u16 = 0;
+        for i in 0..32 {
+            let sum: u16 = SIGNATURE[32 + i] as u16 + constants::l.as_bytes()[i] as u16 + carry;
+            signature[32 + i] = sum as u8;
+            carry = sum >> 8;
+        }
+
+        assert!(!verify(&key, b"", &signature, VerificationProfile::Cofactored));
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::*;
+
+    use test::Bencher;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    // RFC 8032 §7.1, test 1, again.
+    static PUBLIC_KEY: [u8; 32] = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7,
+        0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+        0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+        0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a ];
+    static SIGNATURE: [u8; 64] = [
+        0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+        0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+        0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+        0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+        0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+        0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+        0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+        0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b ];
+
+    #[bench]
+    fn verify_cold(b: &mut Bencher) {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+
+        b.iter(| | verify(&key, b"", &SIGNATURE, VerificationProfile::Strict) )
+    }
+
+    #[bench]
+    fn verify_expanded(b: &mut Bencher) {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(CompressedEdwardsY(PUBLIC_KEY));
+        let expanded: ExpandedPublicKey = ExpandedPublicKey::new(&key.check(KeyPolicy::Strict).unwrap());
+
+        b.iter(| | expanded.verify(b"", &SIGNATURE, VerificationProfile::Strict) )
+    }
+
+    #[bench]
+    fn expand(b: &mut Bencher) {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(CompressedEdwardsY(PUBLIC_KEY));
+        let checked: CheckedKey<CompressedEdwardsY> = key.check(KeyPolicy::Strict).unwrap();
+
+        b.iter(| | ExpandedPublicKey::new(&checked) )
+    }
+}
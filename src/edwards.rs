@@ -0,0 +1,203 @@
+// Validating ed25519 public keys as Edwards points, and the cofactor
+// arithmetic the rest of the crate builds on.
+//
+// The public key for an ed25519 scheme is a compressed edwards point (the
+// Y-coordinate and the sign of X).
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::scalar::Scalar;
+
+use subtle::Mask;
+use subtle::arrays_equal;
+
+use policy::PointOutput;
+use torsion;
+
+/// Decompress an ed25519 public key, returning the point only if it lies in
+/// the prime-order subgroup.
+pub fn mult_by_cofactor_and_validate(key: &CompressedEdwardsY) -> Option<ExtendedPoint> {
+    validate_and_decompress(key, PointOutput::Decompressed)
+}
+
+/// Decompress an ed25519 public key and check that it lies in the
+/// prime-order subgroup, returning P or [8]P as `output` says.
+pub fn validate_and_decompress(key: &CompressedEdwardsY, output: PointOutput) -> Option<ExtendedPoint> {
+    // decompression of Y in any sane curve25519 library should check validation
+    // by computing:
+    //
+    //    Z ← fe(1)
+    //    u ← Y² - Z
+    //    v ← dY² + Z
+    //    check ← sqrt(u/v)
+    //
+    // If `v` is nonzero and `check` is okay (meaning that `u/v` is square),
+    // then the point is valid.
+    let p: ExtendedPoint = key.decompress()?;
+
+    // We need to check that p*l is the identity (the identity point
+    // is X:Y:Z:T == 0:1:1:0 since this shows there is no torsion
+    // component).  It always is for a valid key, so it's p, not p*l, that
+    // is handed back.
+    match (&p * &constants::l).is_identity() {
+        true  => (),
+        false => return None, // point * l was not the identity
+    }
+
+    match output {
+        PointOutput::Decompressed    => Some(p),
+        PointOutput::CofactorCleared => Some(mul_by_pow_2(&p, 3)),
+    }
+}
+
+// Compute [2^k]P by k doublings, as clearing the cofactor of ed25519 (k = 3)
+// or of a 4-torsion coset (k = 2) does.  curve25519-dalek's own
+// `mult_by_pow_2` underflows for k = 0, so that case is handled here.
+pub fn mul_by_pow_2(point: &ExtendedPoint, k: u32) -> ExtendedPoint {
+    match k {
+        0 => *point,
+        _ => point.mult_by_pow_2(k),
+    }
+}
+
+// Undo a multiplication by the cofactor: return the torsion-free Q with
+// [8]Q = P.  Such a Q exists only if P is itself torsion-free, since [8]Q
+// always is, and then it is [8⁻¹]P with 8⁻¹ taken mod l.  Every Q + T for T
+// in E[8] is also an answer, but none of those is torsion-free.
+pub fn div_by_cofactor(point: &ExtendedPoint) -> Option<ExtendedPoint> {
+    match torsion::is_torsion_free(point) {
+        true  => Some(point * &Scalar::from_u64(8).invert()),
+        false => None, // no point times 8 gives this one
+    }
+}
+
+// Whether `key` is the canonical Edwards encoding of the identity, y = 1
+// with the sign bit clear, in constant time: 1 if it is, and 0 if not.  The
+// identity has a second, non-canonical, encoding of y = p + 1, which this
+// doesn't match; under `Strict` that is rejected as non-canonical anyway.
+pub fn is_identity_encoding_edwards(key: &CompressedEdwardsY) -> Mask {
+    arrays_equal(key.as_bytes(), CompressedEdwardsY::identity().as_bytes())
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use rand::OsRng;
+
+    use encoding;
+
+    #[test]
+    fn current_design() {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let a: Scalar = Scalar::random(&mut csprng);
+        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
+        let key: CompressedEdwardsY = p.compress_edwards();
+
+        let check = mult_by_cofactor_and_validate(&key);
+        assert_eq!(check.map(|q| q.compress_edwards()), Some(key));
+        assert_eq!(validate_and_decompress(&key, PointOutput::CofactorCleared).map(|q| q.compress_edwards()),
+                   Some(mul_by_pow_2(&p, 3).compress_edwards()));
+    }
+
+    #[test]
+    fn powers_of_two() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        let mut q: ExtendedPoint = p;
+
+        for k in 0..5 {
+            assert_eq!(mul_by_pow_2(&p, k).compress_edwards(), q.compress_edwards());
+            q = &q + &q;
+        }
+        assert!(mul_by_pow_2(&constants::EIGHT_TORSION[1], 3).is_identity());
+    }
+
+    #[test]
+    fn dividing_by_the_cofactor() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+
+        assert_eq!(div_by_cofactor(&mul_by_pow_2(&p, 3)).map(|q| q.compress_edwards()), Some(p.compress_edwards()));
+        assert_eq!(mul_by_pow_2(&div_by_cofactor(&p).unwrap(), 3).compress_edwards(), p.compress_edwards());
+        assert!(div_by_cofactor(&(&p + &constants::EIGHT_TORSION[4])).is_none());
+    }
+
+    // The same answers from either field backend, on any target.
+    #[test]
+    fn backend_known_answers() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        // Adding the point of order 2, (0, -1), negates both coordinates.
+        let q: ExtendedPoint = &p + &constants::EIGHT_TORSION[4];
+
+        assert_eq!(encoding::hex_encode(p.compress_edwards().as_bytes()),
+                   "70259b904cc2ce3916ea873d24ef3e6d6303d557c4e51753e929a69de30fcd4b");
+        assert_eq!(encoding::hex_encode(q.compress_edwards().as_bytes()),
+                   "7dda646fb33d31c6e91578c2db10c1929cfc2aa83b1ae8ac16d659621cf032b4");
+        assert!(mult_by_cofactor_and_validate(&p.compress_edwards()).is_some());
+        assert!(mult_by_cofactor_and_validate(&q.compress_edwards()).is_none());
+    }
+
+    #[test]
+    fn identity_encodings() {
+        let mut p_plus_one: [u8; 32] = [0xff; 32];
+        p_plus_one[0] = 0xee;
+        p_plus_one[31] = 0x7f;
+
+        assert_eq!(is_identity_encoding_edwards(&ExtendedPoint::identity().compress_edwards()), 1);
+        assert_eq!(is_identity_encoding_edwards(&constants::EIGHT_TORSION[4].compress_edwards()), 0);
+        assert_eq!(is_identity_encoding_edwards(&CompressedEdwardsY(p_plus_one)), 0);
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod bench {
+    use super::*;
+
+    use test::Bencher;
+    use rand::OsRng;
+
+    use traits;
+    use traits::PrimeOrderEncoding;
+
+    #[bench]
+    fn current_design(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let a: Scalar = Scalar::random(&mut csprng);
+        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
+        let key: CompressedEdwardsY = p.compress_edwards();
+
+        b.iter(| | mult_by_cofactor_and_validate(&key) )
+    }
+
+    #[bench]
+    fn current_design_batch(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let keys: Vec<CompressedEdwardsY> = (0..64)
+            .map(|_| (&Scalar::random(&mut csprng) * &constants::ED25519_BASEPOINT).compress_edwards())
+            .collect();
+
+        b.iter(| | traits::validate_batch(&keys) )
+    }
+
+    #[bench]
+    fn current_design_strict(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let keys: Vec<CompressedEdwardsY> = (0..64)
+            .map(|_| (&Scalar::random(&mut csprng) * &constants::ED25519_BASEPOINT).compress_edwards())
+            .collect();
+
+        b.iter(| | keys.iter().map(|key| key.validate_strict()).collect::<Vec<_>>() )
+    }
+
+    #[bench]
+    fn current_design_strict_batch(b: &mut Bencher) {
+        let mut csprng: OsRng = OsRng::new().unwrap();
+        let keys: Vec<CompressedEdwardsY> = (0..64)
+            .map(|_| (&Scalar::random(&mut csprng) * &constants::ED25519_BASEPOINT).compress_edwards())
+            .collect();
+
+        b.iter(| | traits::validate_strict_batch(&keys) )
+    }
+}
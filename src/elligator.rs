@@ -0,0 +1,66 @@
+// The Elligator2 map from a field element to the Montgomery u-coordinate of
+// a point on curve25519, v² = u³ + Au² + u, with non-square parameter 2.
+//
+// This is the "direct" map used by agl's extra25519 (and hence obfs4):
+//
+//    v ← -A / (1 + 2r²)
+//    ε ← χ(v³ + Av² + v)
+//    u ← v           if ε ≠ -1
+//    u ← -v - A      otherwise
+//
+// Every output lies on curve25519 (not its twist), but nothing about the map
+// keeps it inside the prime-order subgroup: roughly seven out of every eight
+// inputs land on a point with a torsion component.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::field::FieldElement;
+
+use subtle::CTAssignable;
+use subtle::CTEq;
+
+/// Map a field element `r` to the Montgomery u-coordinate of a curve25519
+/// point, in constant time.
+pub fn elligator2(r: &FieldElement) -> FieldElement {
+    elligator2_branch(r).0
+}
+
+// As above, but also return 1 if the first candidate v was kept and 0 if it
+// was replaced by -v - A, which RFC 9380 uses to pick the sign of the
+// v-coordinate.
+pub(crate) fn elligator2_branch(r: &FieldElement) -> (FieldElement, u8) {
+    let one: FieldElement = FieldElement::one();
+
+    // 1 + 2r² is never zero, since -1/2 is not a square mod p.
+    let w: FieldElement = &one + &r.square2();
+    let v: FieldElement = -&(&constants::A * &w.invert());
+
+    let v2: FieldElement = v.square();
+    let eps: FieldElement = &(&(&v2 * &v) + &(&constants::A * &v2)) + &v;
+    let eps_is_nonsquare: u8 = eps.chi().ct_eq(&FieldElement::minus_one());
+
+    let mut u: FieldElement = v;
+    u.conditional_assign(&(&(-&v) - &constants::A), eps_is_nonsquare);
+    (u, 1u8 ^ eps_is_nonsquare)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zero_maps_to_the_two_torsion_point() {
+        // v = -A, and -A is a non-square, so u = A - A = 0.
+        let u: FieldElement = elligator2(&FieldElement::zero());
+
+        assert_eq!(u.to_bytes(), [0u8; 32]);
+    }
+
+    #[test]
+    fn output_is_on_the_curve() {
+        let r: FieldElement = FieldElement::from_bytes(&[0x2a; 32]);
+        let u: FieldElement = elligator2(&r);
+        let v2: FieldElement = &(&(&u.square() * &u) + &(&constants::A * &u.square())) + &u;
+
+        assert!(v2.chi() != FieldElement::minus_one());
+    }
+}
@@ -0,0 +1,352 @@
+// Base64, as used by Tor's directory documents and most of the key formats
+// the parsers in this crate read, lowercase unpadded base32, as in onion
+// addresses, and lowercase hex for logs.
+//
+// Only the standard alphabets are supported.  Decoding skips nothing: callers
+// strip whitespace and line breaks themselves, so that a stray character in
+// a key file is an error rather than silently ignored.
+//
+// The decoders above find each character's value by searching or indexing
+// an alphabet, so which cache lines they touch depends on the characters,
+// and text naming a secret, such as a blinding factor or a client
+// authorization key, leaks through cache timing.  With the `ct_decoding`
+// feature, `ct_hex_decode`, `ct_base32_decode` and `ct_base64_decode`
+// compute each value from range masks instead, and look at every character
+// whatever they find, so that how long they take depends only on the length
+// of the text, where any padding is, and whether it was valid.  They accept
+// exactly what the others do, and wipe what they decoded if they fail.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+#[cfg(feature = "ct_decoding")]
+use wipe::wipe;
+
+static ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+static BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+/// Encode `bytes` as base64, with `=` padding if `pad` is true.
+pub fn base64_encode(bytes: &[u8], pad: bool) -> String {
+    let mut out: String = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b: [u8; 3] = [chunk[0],
+                          if chunk.len() > 1 { chunk[1] } else { 0 },
+                          if chunk.len() > 2 { chunk[2] } else { 0 }];
+        let n: u32 = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else if pad {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Encode `bytes` as lowercase hex.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode hex in either case, returning `None` on an odd number of digits
+/// or anything which isn't a hex digit.
+pub fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    let digits: Option<Vec<u8>> = text.chars().map(|c| c.to_digit(16).map(|d| d as u8)).collect();
+
+    match digits {
+        Some(ref digits) if digits.len() % 2 == 0 => {
+            Some(digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect())
+        },
+        _ => None,
+    }
+}
+
+/// Decode base64, with or without `=` padding.
+///
+/// Returns `None` on any character outside the alphabet, on misplaced
+/// padding, or if the unused low bits of the final character are set, so
+/// that every byte string has exactly one accepted unpadded encoding.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text: &[u8] = text.as_bytes();
+    let unpadded: &[u8] = match text.iter().position(|&c| c == b'=') {
+        Some(i) => {
+            if !text.len().is_multiple_of(4) || text.len() - i > 2 || text[i..].iter().any(|&c| c != b'=') {
+                return None; // misplaced padding
+            }
+            &text[..i]
+        },
+        None    => text,
+    };
+
+    if unpadded.len() % 4 == 1 {
+        return None; // a single character can't encode a whole byte
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(unpadded.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+
+    for &c in unpadded {
+        let v: u32 = {
+            let v = ALPHABET.iter().position(|&a| a == c)?;
+            v as u32
+        };
+        acc = (acc << 6) | v;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    match acc {
+        0 => Some(out),
+        _ => None, // non-zero trailing bits
+    }
+}
+
+/// Encode `bytes` as lowercase base32, without padding.
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut out: String = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+
+    for &b in bytes {
+        acc = (acc << 8) | b as u32;
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[(acc >> bits & 0x1f) as usize] as char);
+        }
+        acc &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[(acc << (5 - bits) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Decode unpadded base32 in either case, returning `None` on anything
+/// outside the alphabet, or if the unused low bits of the final character
+/// are set.
+pub fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::with_capacity(text.len() * 5 / 8);
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+
+    for c in text.bytes() {
+        let v: u32 = {
+            let v = BASE32_ALPHABET.iter().position(|&a| a == c.to_ascii_lowercase())?;
+            v as u32
+        };
+        acc = (acc << 5) | v;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    match acc == 0 && bits < 5 {
+        true  => Some(out),
+        false => None, // a stray character, or non-zero trailing bits
+    }
+}
+
+/// Decode a 32-byte key given as 64 hex digits or as base64, as people
+/// paste keys into the command line and the service, and as directory
+/// documents give them.
+pub fn decode_key(text: &str) -> Option<CompressedEdwardsY> {
+    let bytes: Vec<u8> = match text.len() {
+        64 => hex_decode(text)?,
+        _  => base64_decode(text)?,
+    };
+    if bytes.len() != 32 {
+        return None;
+    }
+
+    let mut key: [u8; 32] = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    Some(CompressedEdwardsY(key))
+}
+
+/// As `hex_decode`, in time independent of the digits.
+#[cfg(feature = "ct_decoding")]
+pub fn ct_hex_decode(text: &str) -> Option<Vec<u8>> {
+    ct_decode(text.as_bytes(), 4, &[(b'0', b'9', 0), (b'a', b'f', 10), (b'A', b'F', 10)])
+}
+
+/// As `base32_decode`, in time independent of the characters.
+#[cfg(feature = "ct_decoding")]
+pub fn ct_base32_decode(text: &str) -> Option<Vec<u8>> {
+    ct_decode(text.as_bytes(), 5, &[(b'a', b'z', 0), (b'A', b'Z', 0), (b'2', b'7', 26)])
+}
+
+/// As `base64_decode`, in time independent of the characters.
+#[cfg(feature = "ct_decoding")]
+pub fn ct_base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text: &[u8] = text.as_bytes();
+    // Where the padding starts is a matter of the length, and not secret.
+    let unpadded: &[u8] = match text.iter().position(|&c| c == b'=') {
+        Some(i) => {
+            if !text.len().is_multiple_of(4) || text.len() - i > 2 || text[i..].iter().any(|&c| c != b'=') {
+                return None; // misplaced padding
+            }
+            &text[..i]
+        },
+        None    => text,
+    };
+
+    ct_decode(unpadded, 6, &[(b'A', b'Z', 0), (b'a', b'z', 26), (b'0', b'9', 52), (b'+', b'+', 62), (b'/', b'/', 63)])
+}
+
+// Decode `text`, `width` bits to a character, where the characters from lo
+// to hi in each of `ranges` have the values from `offset` up.  Every
+// character is looked at, and every range checked, before anything is
+// rejected.
+#[cfg(feature = "ct_decoding")]
+fn ct_decode(text: &[u8], width: u32, ranges: &[(u8, u8, u32)]) -> Option<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::with_capacity(text.len() * width as usize / 8);
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut invalid: u32 = 0;
+
+    for &c in text {
+        let mut v: u32 = 0;
+        let mut valid: u32 = 0;
+
+        for &(lo, hi, offset) in ranges {
+            let mask: u32 = ct_in_range(c, lo, hi);
+
+            v |= mask & (c as u32).wrapping_sub(lo as u32).wrapping_add(offset);
+            valid |= mask;
+        }
+        invalid |= !valid;
+        acc = (acc << width) | v;
+        bits += width;
+
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    // A stray character, non-zero trailing bits, or a final character
+    // which doesn't complete a byte.
+    let rejected: bool = (invalid | acc) != 0 || bits >= width;
+    match rejected {
+        true  => { wipe(&mut out); None },
+        false => Some(out),
+    }
+}
+
+// All ones if lo <= c <= hi, and zero otherwise, without branching on c.
+#[cfg(feature = "ct_decoding")]
+fn ct_in_range(c: u8, lo: u8, hi: u8) -> u32 {
+    let c: i32 = c as i32;
+
+    // Either difference is negative, setting the sign bit, exactly when c
+    // is out of range.
+    !(((c - lo as i32) | (hi as i32 - c)) >> 31) as u32
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rfc4648_vectors() {
+        let vectors: [(&str, &str); 7] = [
+            ("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="), ("fooba", "Zm9vYmE="), ("foobar", "Zm9vYmFy"),
+        ];
+
+        for &(plain, encoded) in vectors.iter() {
+            assert_eq!(base64_encode(plain.as_bytes(), true), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+
+            let unpadded: &str = &encoded[..encoded.find('=').unwrap_or(encoded.len())];
+            assert_eq!(base64_decode(unpadded).unwrap(), plain.as_bytes());
+        }
+    }
+
+    #[test]
+    fn base32() {
+        let vectors: [(&str, &str); 6] = [
+            ("", ""), ("f", "my"), ("fo", "mzxq"), ("foo", "mzxw6"),
+            ("foob", "mzxw6yq"), ("foobar", "mzxw6ytboi"),
+        ];
+
+        for &(plain, encoded) in vectors.iter() {
+            assert_eq!(base32_encode(plain.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), plain.as_bytes());
+            assert_eq!(base32_decode(&encoded.to_uppercase()).unwrap(), plain.as_bytes());
+        }
+        assert!(base32_decode("mz").is_none()); // trailing bits set
+        assert!(base32_decode("my1").is_none());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(hex_encode(&[0x00, 0x0f, 0xa5, 0xff]), "000fa5ff");
+        assert_eq!(hex_decode("000FA5ff").unwrap(), [0x00, 0x0f, 0xa5, 0xff]);
+        assert!(hex_decode("abc").is_none());
+        assert!(hex_decode("0g").is_none());
+    }
+
+    #[test]
+    fn keys_decode_from_hex_or_base64() {
+        let key: [u8; 32] = [0xa5u8; 32];
+
+        assert_eq!(decode_key(&hex_encode(&key)), Some(CompressedEdwardsY(key)));
+        assert_eq!(decode_key(&base64_encode(&key, true)), Some(CompressedEdwardsY(key)));
+        assert_eq!(decode_key(&base64_encode(&key, false)), Some(CompressedEdwardsY(key)));
+        assert!(decode_key(&hex_encode(&key[..31])).is_none());
+        assert!(decode_key(&base64_encode(&key[..31], false)).is_none());
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(base64_decode("Zg=").is_none());
+        assert!(base64_decode("Z===").is_none());
+        assert!(base64_decode("Zm9v!").is_none());
+        assert!(base64_decode("Zh").is_none()); // trailing bits set
+    }
+
+    #[cfg(feature = "ct_decoding")]
+    #[test]
+    fn constant_time_decoders_agree() {
+        let mut texts: Vec<String> = vec!["".to_string(), "Zg=".to_string(), "Z===".to_string(), "Zh".to_string(),
+                                          "my1".to_string(), "abc".to_string(), "0g".to_string(), "é".to_string()];
+        for length in 0..12usize {
+            let bytes: Vec<u8> = (0..length).map(|i| (i * 37 + length * 11) as u8).collect();
+
+            texts.push(hex_encode(&bytes));
+            texts.push(hex_encode(&bytes).to_uppercase());
+            texts.push(base32_encode(&bytes));
+            texts.push(base32_encode(&bytes).to_uppercase());
+            texts.push(base64_encode(&bytes, true));
+            texts.push(base64_encode(&bytes, false));
+        }
+        // Every single character, alone and after a valid prefix.
+        for c in 0..128u8 {
+            texts.push((c as char).to_string());
+            texts.push(format!("Zm{}", c as char));
+            texts.push(format!("mzx{}", c as char));
+            texts.push(format!("a{}", c as char));
+        }
+
+        for text in texts.iter() {
+            assert_eq!(ct_hex_decode(text), hex_decode(text), "{:?}", text);
+            assert_eq!(ct_base32_decode(text), base32_decode(text), "{:?}", text);
+            assert_eq!(ct_base64_decode(text), base64_decode(text), "{:?}", text);
+        }
+    }
+}
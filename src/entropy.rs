@@ -0,0 +1,77 @@
+// Where the crate's randomness comes from.
+//
+// Deciding whether a key is valid never needs randomness.  Batch signature
+// verification does: each signature's equation is weighted by a random
+// coefficient before they are summed, so that a forger who can choose
+// several signatures can't make their errors cancel.  Inside an SGX enclave
+// or in HSM firmware there is no `OsRng` to call, so anything needing random
+// bytes takes an `EntropySource` instead, which the caller implements over
+// whatever the platform has (RDRAND, a TRNG peripheral, sgx_read_rand).
+// With the `rand` feature, every `rand::Rng` is one.
+
+/// A source of uniformly random bytes, which must be unpredictable to
+/// whoever supplies the signatures being checked.
+pub trait EntropySource {
+    /// Fill `bytes` with random bytes.
+    fn fill_bytes(&mut self, bytes: &mut [u8]);
+}
+
+#[cfg(feature = "rand")]
+impl<R: ::rand::Rng> EntropySource for R {
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        ::rand::Rng::fill_bytes(self, bytes)
+    }
+}
+
+/// `OsRng`, for tests, which need it as an `EntropySource` with or without
+/// the `rand` feature.
+#[cfg(all(test, not(feature = "bench")))]
+pub(crate) struct OsEntropy(::rand::OsRng);
+
+#[cfg(all(test, not(feature = "bench")))]
+impl OsEntropy {
+    pub(crate) fn new() -> OsEntropy {
+        OsEntropy(::rand::OsRng::new().unwrap())
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+impl EntropySource for OsEntropy {
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        // Named in full, since with `rand` this is an `EntropySource` method
+        // as well.
+        ::rand::Rng::fill_bytes(&mut self.0, bytes)
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // Counts up from zero, which is only fit for tests.
+    struct Counter(u8);
+
+    impl EntropySource for Counter {
+        fn fill_bytes(&mut self, bytes: &mut [u8]) {
+            for byte in bytes.iter_mut() {
+                *byte = self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+        }
+    }
+
+    fn fill<E: EntropySource>(entropy: &mut E) -> [u8; 4] {
+        let mut bytes: [u8; 4] = [0u8; 4];
+
+        entropy.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn sources_keep_their_state() {
+        let mut counter: Counter = Counter(0);
+
+        assert_eq!(fill(&mut counter), [0, 1, 2, 3]);
+        assert_eq!(fill(&mut counter), [4, 5, 6, 7]);
+    }
+}
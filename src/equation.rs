@@ -0,0 +1,202 @@
+// The curve equation check inside decompression, step by step, for whoever
+// has to confirm why an encoding was rejected.  Behind the `audit` feature.
+//
+// A point (x, y) is on ed25519 when -x² + y² = 1 + dx²y², that is, when
+//
+//    x² = u/v,   u = y² - 1,   v = dy² + 1.
+//
+// v is never zero, since -1/d is not a square, so an encoding of y names a
+// point exactly when u/v is a square.  Decompression computes a candidate
+//
+//    r = (uv³)(uv⁷)^((p-5)/8)
+//
+// and checks it: if vr² = u then x = ±r; if vr² = -u then x = ±r·sqrt(-1);
+// and otherwise u/v is not a square, and the encoding is rejected.  The sign
+// bit then picks which of ±x, and nothing rejects an encoding whose x is
+// zero and whose sign bit says it is negative.  `check_curve_equation` does
+// exactly this with dalek's own field arithmetic, as `decompress` does, and
+// hands back every intermediate value as reduced little-endian bytes.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::field::FieldElement;
+
+/// Which condition the candidate root r met.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RootCheck {
+    /// vr² = u, so x = ±r.
+    Square,
+    /// vr² = -u, so x = ±r·sqrt(-1).
+    SquareTimesSqrtM1,
+    /// Neither: u/v is not a square, and there is no such point.
+    NonSquare,
+}
+
+/// Every value computed in checking one encoding against the curve equation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CurveEquation {
+    y: [u8; 32],
+    y_canonical: bool,
+    sign: u8,
+    u: [u8; 32],
+    v: [u8; 32],
+    r: [u8; 32],
+    vr2: [u8; 32],
+    root: RootCheck,
+    x: Option<[u8; 32]>,
+}
+
+impl CurveEquation {
+    /// y, reduced mod p.
+    pub fn y(&self) -> &[u8; 32] {
+        &self.y
+    }
+
+    /// Whether the encoded y was already reduced mod p.
+    pub fn y_was_canonical(&self) -> bool {
+        self.y_canonical
+    }
+
+    /// The sign bit, which is 1 when x should be negative, that is, odd.
+    pub fn sign_bit(&self) -> u8 {
+        self.sign
+    }
+
+    /// u = y² - 1.
+    pub fn u(&self) -> &[u8; 32] {
+        &self.u
+    }
+
+    /// v = dy² + 1.
+    pub fn v(&self) -> &[u8; 32] {
+        &self.v
+    }
+
+    /// The candidate root r = (uv³)(uv⁷)^((p-5)/8).
+    pub fn r(&self) -> &[u8; 32] {
+        &self.r
+    }
+
+    /// vr², which is compared against ±u.
+    pub fn vr2(&self) -> &[u8; 32] {
+        &self.vr2
+    }
+
+    /// Which of the conditions held.
+    pub fn root_check(&self) -> RootCheck {
+        self.root
+    }
+
+    /// x, with the sign the encoding asked for, if there is a point.
+    pub fn x(&self) -> Option<&[u8; 32]> {
+        self.x.as_ref()
+    }
+
+    /// Whether the encoding names a point at all.
+    pub fn is_on_curve(&self) -> bool {
+        self.root != RootCheck::NonSquare
+    }
+}
+
+/// Check `key` against the curve equation as `decompress` does, keeping
+/// every intermediate value.
+pub fn check_curve_equation(key: &CompressedEdwardsY) -> CurveEquation {
+    let mut y_bytes: [u8; 32] = key.to_bytes();
+    let sign: u8 = y_bytes[31] >> 7;
+    y_bytes[31] &= 0x7f;
+
+    let y: FieldElement = FieldElement::from_bytes(&y_bytes);
+    let one: FieldElement = FieldElement::one();
+    let yy: FieldElement = y.square();
+    let u: FieldElement = &yy - &one;                    // u = y² - 1
+    let v: FieldElement = &(&yy * &constants::d) + &one; // v = dy² + 1
+
+    let v3: FieldElement = &v.square() * &v;
+    let v7: FieldElement = &v3.square() * &v;
+    let r: FieldElement = &(&u * &v3) * &(&u * &v7).pow_p58();
+    let vr2: FieldElement = &v * &r.square();
+
+    let root: RootCheck = match (vr2 == u, vr2 == -&u) {
+        (true, _)      => RootCheck::Square,
+        (_, true)      => RootCheck::SquareTimesSqrtM1,
+        (false, false) => RootCheck::NonSquare, // u/v was not square
+    };
+    let x: Option<[u8; 32]> = match root {
+        RootCheck::Square            => Some(r),
+        RootCheck::SquareTimesSqrtM1 => Some(&r * &constants::SQRT_M1),
+        RootCheck::NonSquare         => None,
+    }.map(|mut x: FieldElement| {
+        if x.is_negative_ed25519() != sign {
+            x.negate();
+        }
+        x.to_bytes()
+    });
+
+    CurveEquation {
+        y: y.to_bytes(),
+        y_canonical: y.to_bytes() == y_bytes,
+        sign,
+        u: u.to_bytes(),
+        v: v.to_bytes(),
+        r: r.to_bytes(),
+        vr2: vr2.to_bytes(),
+        root,
+        x,
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::curve::ExtendedPoint;
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn agrees_with_decompress() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        let mut keys: [CompressedEdwardsY; 12] = [CompressedEdwardsY([0u8; 32]); 12];
+
+        for (i, t) in constants::EIGHT_TORSION.iter().enumerate() {
+            keys[i] = (&p + t).compress_edwards();
+        }
+        keys[8] = CompressedEdwardsY([0xff; 32]);
+        keys[9] = CompressedEdwardsY([0x02; 32]);
+        keys[10] = constants::EIGHT_TORSION[2].compress_edwards();
+        keys[11] = CompressedEdwardsY([0x03; 32]);
+
+        for key in keys.iter() {
+            let check: CurveEquation = check_curve_equation(key);
+
+            assert_eq!(check.x().cloned(), key.decompress().map(|p| p.X.to_bytes()));
+            assert_eq!(check.is_on_curve(), key.decompress().is_some());
+        }
+    }
+
+    #[test]
+    fn y_of_one_has_x_of_zero() {
+        // The identity, with its sign bit set, is still accepted.
+        let mut bytes: [u8; 32] = [0u8; 32];
+        bytes[0] = 1;
+        bytes[31] = 0x80;
+        let check: CurveEquation = check_curve_equation(&CompressedEdwardsY(bytes));
+
+        assert_eq!(check.u(), &[0u8; 32]);
+        assert_eq!(check.root_check(), RootCheck::Square);
+        assert_eq!(check.x(), Some(&[0u8; 32]));
+        assert_eq!(check.sign_bit(), 1);
+    }
+
+    #[test]
+    fn unreduced_y_is_reduced() {
+        // p + 1, which encodes y = 1 again.
+        let mut bytes: [u8; 32] = [0xff; 32];
+        bytes[0] = 0xee;
+        bytes[31] = 0x7f;
+        let check: CurveEquation = check_curve_equation(&CompressedEdwardsY(bytes));
+
+        assert!(!check.y_was_canonical());
+        assert_eq!(check.y()[0], 1);
+        assert!(check.is_on_curve());
+    }
+}
@@ -0,0 +1,232 @@
+// An account of every check a key goes through, for working out why this
+// crate and some other implementation disagree about it.
+//
+// `explain` runs each check of the pipeline whether or not an earlier one
+// failed, wherever the check still means something, and says for each
+// whether it passed, whether the policy requires it, and the values it was
+// decided on.  The checks, in pipeline order, are
+//
+//    y          the encoded y is reduced mod p
+//    curve      x² = (y² - 1)/(dy² + 1) has a solution
+//    sign       the sign bit isn't set when x = 0
+//    torsion    the point has no torsion component
+//    identity   the point is not the identity
+//
+// of which `PrimeOrder` requires curve and torsion, and `Strict` all five;
+// y and sign together are what `Strict` means by canonical.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::field::FieldElement;
+
+use audit::torsion_order_log2;
+use encoding::hex_encode;
+use policy::KeyPolicy;
+
+/// How one check went.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The check passed.
+    Passed,
+    /// The check failed.
+    Failed,
+    /// The check couldn't be run, because the encoding isn't a point.
+    NotApplicable,
+}
+
+/// One check, and what came of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainedCheck {
+    name: &'static str,
+    outcome: Outcome,
+    required: bool,
+    detail: String,
+}
+
+impl ExplainedCheck {
+    /// The check's short name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// How it went.
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Whether the policy needs it to pass.
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// What it was decided on.
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+}
+
+/// Every check made on one encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    encoding: CompressedEdwardsY,
+    policy: KeyPolicy,
+    checks: Vec<ExplainedCheck>,
+}
+
+impl Explanation {
+    /// The checks, in pipeline order.
+    pub fn checks(&self) -> &[ExplainedCheck] {
+        &self.checks
+    }
+
+    /// Whether the key passes the policy, which is whether every required
+    /// check passed.  This is always what `UncheckedKey::check` says.
+    pub fn accepted(&self) -> bool {
+        self.checks.iter().all(|check| !check.required || check.outcome == Outcome::Passed)
+    }
+
+    /// The first required check which failed, if any.
+    pub fn first_failure(&self) -> Option<&ExplainedCheck> {
+        self.checks.iter().find(|check| check.required && check.outcome != Outcome::Passed)
+    }
+
+    /// A line for each check, and a verdict.
+    pub fn narrative(&self) -> String {
+        let mut text: String = format!("key {} under {:?}:\n", hex_encode(self.encoding.as_bytes()), self.policy);
+
+        for check in self.checks.iter() {
+            let outcome: &str = match check.outcome {
+                Outcome::Passed        => "passed",
+                Outcome::Failed        => "FAILED",
+                Outcome::NotApplicable => "not run",
+            };
+            let required: &str = match check.required {
+                true  => "",
+                false => " (not required)",
+            };
+            text.push_str(&format!("  {:<8} {}{}: {}\n", check.name, outcome, required, check.detail));
+        }
+        match self.first_failure() {
+            Some(check) => text.push_str(&format!("rejected: the {} check failed\n", check.name)),
+            None        => text.push_str("accepted\n"),
+        }
+        text
+    }
+}
+
+/// Run every check on `key` under `policy`.
+pub fn explain(key: &CompressedEdwardsY, policy: KeyPolicy) -> Explanation {
+    let strict: bool = policy == KeyPolicy::Strict;
+    let mut checks: Vec<ExplainedCheck> = Vec::with_capacity(5);
+
+    let mut y_bytes: [u8; 32] = key.to_bytes();
+    let sign: u8 = y_bytes[31] >> 7;
+    y_bytes[31] &= 0x7f;
+    let y: [u8; 32] = FieldElement::from_bytes(&y_bytes).to_bytes();
+
+    checks.push(ExplainedCheck {
+        name: "y",
+        outcome: passed(y == y_bytes),
+        required: strict,
+        detail: format!("y as encoded {}, reduced {}", hex_encode(&y_bytes), hex_encode(&y)),
+    });
+
+    let p: Option<ExtendedPoint> = key.decompress();
+    checks.push(ExplainedCheck {
+        name: "curve",
+        outcome: passed(p.is_some()),
+        required: true,
+        detail: match p {
+            Some(_) => "(y² - 1)/(dy² + 1) is a square".to_string(),
+            None    => "(y² - 1)/(dy² + 1) is not a square, so no x exists".to_string(),
+        },
+    });
+
+    let p: ExtendedPoint = match p {
+        Some(p) => p,
+        None    => {
+            for &(name, required) in [("sign", strict), ("torsion", true), ("identity", strict)].iter() {
+                checks.push(ExplainedCheck { name, outcome: Outcome::NotApplicable, required,
+                                             detail: "there is no point".to_string() });
+            }
+            return Explanation { encoding: *key, policy, checks };
+        },
+    };
+
+    let x: [u8; 32] = p.X.to_bytes();
+    checks.push(ExplainedCheck {
+        name: "sign",
+        outcome: passed(!(x == [0u8; 32] && sign == 1)),
+        required: strict,
+        detail: format!("sign bit {}, x {}", sign, hex_encode(&x)),
+    });
+
+    let order_log2: usize = torsion_order_log2(&p);
+    checks.push(ExplainedCheck {
+        name: "torsion",
+        outcome: passed(order_log2 == 0),
+        required: true,
+        detail: format!("the torsion component has order {}", 1 << order_log2),
+    });
+
+    checks.push(ExplainedCheck {
+        name: "identity",
+        outcome: passed(!p.is_identity()),
+        required: strict,
+        detail: format!("the point is {}the identity", if p.is_identity() { "" } else { "not " }),
+    });
+
+    Explanation { encoding: *key, policy, checks }
+}
+
+fn passed(ok: bool) -> Outcome {
+    match ok {
+        true  => Outcome::Passed,
+        false => Outcome::Failed,
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use backend::adversarial_encodings;
+    use key::UncheckedKey;
+
+    #[test]
+    fn agrees_with_check() {
+        for encoding in adversarial_encodings().iter() {
+            for &policy in [KeyPolicy::PrimeOrder, KeyPolicy::Strict].iter() {
+                let key: CompressedEdwardsY = CompressedEdwardsY(*encoding);
+
+                assert_eq!(explain(&key, policy).accepted(), UncheckedKey::new(key).check(policy).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn torsion_is_reported_with_its_order() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        let key: CompressedEdwardsY = (&p + &constants::EIGHT_TORSION[2]).compress_edwards();
+        let explanation: Explanation = explain(&key, KeyPolicy::Strict);
+
+        assert_eq!(explanation.first_failure().unwrap().name(), "torsion");
+        assert!(explanation.first_failure().unwrap().detail().contains("order 4"));
+        // Every other check still ran, and passed.
+        assert_eq!(explanation.checks().iter().filter(|check| check.outcome() == Outcome::Passed).count(), 4);
+        assert!(explanation.narrative().ends_with("rejected: the torsion check failed\n"));
+    }
+
+    #[test]
+    fn non_points_skip_the_point_checks() {
+        let explanation: Explanation = explain(&CompressedEdwardsY([0x02; 32]), KeyPolicy::PrimeOrder);
+
+        assert_eq!(explanation.checks()[1].outcome(), Outcome::Failed);
+        assert_eq!(explanation.checks()[3].outcome(), Outcome::NotApplicable);
+        assert!(!explanation.checks()[0].is_required());
+    }
+}
@@ -0,0 +1,259 @@
+// Relay family certificates, as in proposal 321 ("happy families").
+//
+// A relay which belongs to a family publishes, in its router descriptor,
+//
+//    master-key-ed25519 <base64 identity>
+//    family-cert
+//    -----BEGIN FAMILY CERT-----  ...  -----END FAMILY CERT-----
+//
+// with one `family-cert` for every family it is in.  Each is a cert-spec
+// certificate of type 0x0C, certifying the relay's ed25519 identity, which
+// must carry the family's ed25519 key in a signed-with-ed25519-key extension
+// and be signed by it.  A family is then named by its key, as
+//
+//    ed25519:<unpadded base64 family key>
+//
+// on the `family-ids` lines of microdescriptors, and two relays are in the
+// same family exactly when each has a valid certificate from the same key.
+//
+// Every key involved, the identity and each family key, is checked under
+// `KeyPolicy::Strict`, since a family key with a torsion component could
+// have more than one signature accepted for what is meant to be one
+// certificate.  Signatures are verified cofactorless, as tor verifies them,
+// and, as with onion service descriptors, nothing is judged against the
+// current time.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::verify;
+use encoding::base64_decode;
+use encoding::base64_encode;
+use formats::cert::parse_certificate;
+use formats::cert::Certificate;
+use formats::pem;
+use formats::FormatError;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+/// The certificate type of a family certificate.
+pub const CERT_TYPE_FAMILY: u8 = 0x0c;
+
+/// Something wrong with a family certificate which parsed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FamilyProblem {
+    /// The certificate is not a family certificate.
+    CertificateType,
+    /// The certificate doesn't say which family key signed it.
+    MissingFamilyKey,
+    /// The family key fails validation.
+    InvalidFamilyKey,
+    /// The certificate is for some other relay's identity.
+    WrongIdentity,
+    /// The certificate's signature doesn't verify under the family key.
+    BadSignature,
+}
+
+/// What checking a family certificate found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FamilyReport {
+    family_key: Option<CompressedEdwardsY>,
+    expiration: u64,
+    problems: Vec<FamilyProblem>,
+}
+
+impl FamilyReport {
+    /// The family key the certificate names, if it names one.
+    pub fn family_key(&self) -> Option<&CompressedEdwardsY> {
+        self.family_key.as_ref()
+    }
+
+    /// The family's name, as on `family-ids` lines, if the certificate is
+    /// valid.
+    pub fn family_id(&self) -> Option<String> {
+        match (self.is_valid(), self.family_key) {
+            (true, Some(key)) => Some(family_id_bytes(key.as_bytes())),
+            _                 => None, // an invalid certificate is in no family
+        }
+    }
+
+    /// When the certificate expires, in hours since the epoch.
+    pub fn expiration(&self) -> u64 {
+        self.expiration
+    }
+
+    /// Everything found wrong with the certificate.
+    pub fn problems(&self) -> &[FamilyProblem] {
+        &self.problems
+    }
+
+    /// Whether nothing was found wrong.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// A family's name, `ed25519:` and its key in unpadded base64.
+pub fn family_id(key: &CheckedKey<CompressedEdwardsY>) -> String {
+    family_id_bytes(key.encoding().as_bytes())
+}
+
+fn family_id_bytes(encoding: &[u8; 32]) -> String {
+    format!("ed25519:{}", base64_encode(encoding, false))
+}
+
+/// Check the family certificate in `bytes` as one for the relay whose
+/// identity is `identity`.
+///
+/// Returns an error if the certificate can't be parsed, and otherwise a
+/// report of everything wrong with it.
+pub fn check_family_certificate(bytes: &[u8], identity: &CheckedKey<CompressedEdwardsY>)
+    -> Result<FamilyReport, FormatError>
+{
+    let cert: Certificate = parse_certificate(bytes)?;
+    let mut problems: Vec<FamilyProblem> = Vec::new();
+
+    if cert.cert_type != CERT_TYPE_FAMILY {
+        problems.push(FamilyProblem::CertificateType);
+    }
+    if cert.certified_key != *identity.encoding() {
+        problems.push(FamilyProblem::WrongIdentity);
+    }
+
+    match cert.signing_key {
+        None => problems.push(FamilyProblem::MissingFamilyKey),
+        Some(ref family) => {
+            if UncheckedKey::new(*family).check(KeyPolicy::Strict).is_none() {
+                problems.push(FamilyProblem::InvalidFamilyKey);
+            }
+            if !verify(family, &bytes[..cert.signed_length], &cert.signature, VerificationProfile::Cofactorless) {
+                problems.push(FamilyProblem::BadSignature);
+            }
+        },
+    }
+
+    Ok(FamilyReport { family_key: cert.signing_key, expiration: cert.expiration, problems })
+}
+
+/// Check every family certificate in the router descriptor `text` against
+/// the identity on its `master-key-ed25519` line.
+///
+/// Returns an error if the identity is missing or fails validation, or if
+/// any certificate can't be parsed, and otherwise a report for each
+/// certificate, in order.
+pub fn check_router_families(text: &str) -> Result<Vec<FamilyReport>, FormatError> {
+    let identity: CheckedKey<CompressedEdwardsY> = master_key(text)?;
+
+    pem::decode_all(text, "FAMILY CERT")?.iter()
+        .map(|bytes| check_family_certificate(bytes, &identity))
+        .collect()
+}
+
+// The identity on the `master-key-ed25519` line, checked.
+fn master_key(text: &str) -> Result<CheckedKey<CompressedEdwardsY>, FormatError> {
+    let line: &str = match text.lines().find(|line| line.starts_with("master-key-ed25519 ")) {
+        Some(line) => line,
+        None       => return Err(FormatError::Malformed), // no identity
+    };
+    let mut encoding: [u8; 32] = [0u8; 32];
+    match base64_decode(line[19..].trim()) {
+        Some(ref bytes) if bytes.len() == 32 => encoding.copy_from_slice(bytes),
+        _                                    => return Err(FormatError::Malformed),
+    }
+
+    match UncheckedKey::new(CompressedEdwardsY(encoding)).check(KeyPolicy::Strict) {
+        Some(key) => Ok(key),
+        None      => Err(FormatError::InvalidKey),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use ed25519::public_key_from_seed;
+    use ed25519::sign;
+    use formats::cert::unsigned_certificate;
+
+    fn identity() -> CheckedKey<CompressedEdwardsY> {
+        let key: CompressedEdwardsY = (&Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT).compress_edwards();
+
+        UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap()
+    }
+
+    // A certificate for `identity`, signed by the family key with seed `family`.
+    fn family_certificate(family: &[u8; 32], identity: &CompressedEdwardsY, cert_type: u8) -> Vec<u8> {
+        let mut cert: Vec<u8> = unsigned_certificate(cert_type, 500_000, identity, Some(&public_key_from_seed(family)));
+        let signature: [u8; 64] = sign(family, &cert);
+
+        cert.extend_from_slice(&signature);
+        cert
+    }
+
+    #[test]
+    fn valid_certificates_name_their_family() {
+        let family: [u8; 32] = [7u8; 32];
+        let cert: Vec<u8> = family_certificate(&family, identity().encoding(), CERT_TYPE_FAMILY);
+        let report: FamilyReport = check_family_certificate(&cert, &identity()).unwrap();
+
+        assert!(report.is_valid(), "{:?}", report.problems());
+        assert_eq!(report.family_key(), Some(&public_key_from_seed(&family)));
+        assert_eq!(report.expiration(), 500_000);
+        assert_eq!(report.family_id(),
+                   Some(format!("ed25519:{}", base64_encode(public_key_from_seed(&family).as_bytes(), false))));
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let family: [u8; 32] = [7u8; 32];
+        let other: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+
+        let cert: Vec<u8> = family_certificate(&family, &other, 0x04);
+        let report: FamilyReport = check_family_certificate(&cert, &identity()).unwrap();
+        assert_eq!(report.problems(), &[FamilyProblem::CertificateType, FamilyProblem::WrongIdentity]);
+        assert_eq!(report.family_id(), None);
+
+        let mut cert: Vec<u8> = family_certificate(&family, identity().encoding(), CERT_TYPE_FAMILY);
+        let last: usize = cert.len() - 1;
+        cert[last] ^= 1;
+        assert_eq!(check_family_certificate(&cert, &identity()).unwrap().problems(), &[FamilyProblem::BadSignature]);
+
+        // A family key carrying torsion is refused even though it signed.
+        let mut cert: Vec<u8> = unsigned_certificate(CERT_TYPE_FAMILY, 0, identity().encoding(),
+                                                     Some(&constants::EIGHT_TORSION[1].compress_edwards()));
+        cert.extend_from_slice(&[0u8; 64]);
+        assert!(check_family_certificate(&cert, &identity()).unwrap().problems()
+                .contains(&FamilyProblem::InvalidFamilyKey));
+
+        let cert: Vec<u8> = unsigned_certificate(CERT_TYPE_FAMILY, 0, identity().encoding(), None);
+        assert_eq!(check_family_certificate(&[&cert[..], &[0u8; 64]].concat(), &identity()).unwrap().problems(),
+                   &[FamilyProblem::MissingFamilyKey]);
+    }
+
+    #[test]
+    fn every_family_in_a_descriptor_is_checked() {
+        let families: [[u8; 32]; 2] = [[1u8; 32], [2u8; 32]];
+        let mut text: String = format!("router test 192.0.2.1 9001 0 0\nmaster-key-ed25519 {}\n",
+                                       base64_encode(identity().encoding().as_bytes(), false));
+        for family in families.iter() {
+            let cert: Vec<u8> = family_certificate(family, identity().encoding(), CERT_TYPE_FAMILY);
+
+            text.push_str(&format!("family-cert\n-----BEGIN FAMILY CERT-----\n{}\n-----END FAMILY CERT-----\n",
+                                   base64_encode(&cert, true)));
+        }
+
+        let reports: Vec<FamilyReport> = check_router_families(&text).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|report| report.is_valid()));
+        assert_eq!(reports[1].family_key(), Some(&public_key_from_seed(&families[1])));
+
+        assert_eq!(check_router_families("router test\n"), Err(FormatError::Malformed));
+        let torsion: CompressedEdwardsY = constants::EIGHT_TORSION[1].compress_edwards();
+        let torsioned: String = format!("master-key-ed25519 {}\n", base64_encode(torsion.as_bytes(), false));
+        assert_eq!(check_router_families(&torsioned), Err(FormatError::InvalidKey));
+    }
+}
@@ -0,0 +1,246 @@
+// A C interface to key validation, behind the `ffi` feature, with numeric
+// error codes which are fixed once given out, so that C callers can branch
+// on why a key was refused without parsing strings.
+//
+// The codes are
+//
+//    0            the key passed
+//    1 ..= 5      a `KeyProblem`, in the order the enum declares them
+//    6 ..= 63     reserved for problems found by checks added later
+//    64 ..= 66    a `FormatError`, for parsers exposed here later
+//    67           a malformed v3 onion address
+//    68 ..= 127   reserved for other container formats
+//    -1, -2       the call itself was wrong: a NULL pointer, or an unknown
+//                 policy
+//
+// and a code, once assigned, keeps its meaning even if what it names is
+// removed from the Rust API.  `tor22006_strerror` gives a fixed English
+// description of each, including the reserved ones.  Build the crate as a
+// staticlib or cdylib, for instance with
+// `cargo rustc --features ffi --crate-type staticlib`, to link it from C.
+//
+// The same cdylib serves other languages with a C foreign function
+// interface.  Node has an addon of its own, in examples/node.
+
+use core::ffi::c_char;
+use core::ffi::c_int;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+#[cfg(feature = "blinding")]
+use core::ffi::CStr;
+
+use formats::FormatError;
+use key::KeyProblem;
+use key::UncheckedKey;
+#[cfg(feature = "blinding")]
+use onion::parse_onion_address;
+use policy::KeyPolicy;
+
+/// The key passed.
+pub const TOR22006_OK: c_int = 0;
+/// `KeyProblem::Malformed`.
+pub const TOR22006_E_MALFORMED: c_int = 1;
+/// `KeyProblem::NotOnCurve`.
+pub const TOR22006_E_NOT_ON_CURVE: c_int = 2;
+/// `KeyProblem::Torsioned`.
+pub const TOR22006_E_TORSIONED: c_int = 3;
+/// `KeyProblem::NonCanonical`.
+pub const TOR22006_E_NON_CANONICAL: c_int = 4;
+/// `KeyProblem::Identity`.
+pub const TOR22006_E_IDENTITY: c_int = 5;
+/// `FormatError::Malformed`.
+pub const TOR22006_E_FORMAT_MALFORMED: c_int = 64;
+/// `FormatError::UnsupportedAlgorithm`.
+pub const TOR22006_E_FORMAT_UNSUPPORTED: c_int = 65;
+/// `FormatError::InvalidKey`.
+pub const TOR22006_E_FORMAT_INVALID_KEY: c_int = 66;
+/// An onion address which `parse_onion_address` refuses.
+pub const TOR22006_E_BAD_ADDRESS: c_int = 67;
+/// A pointer argument was NULL.
+pub const TOR22006_E_NULL_ARGUMENT: c_int = -1;
+/// The policy argument was neither of the `TOR22006_POLICY_` values.
+pub const TOR22006_E_UNKNOWN_POLICY: c_int = -2;
+
+/// `KeyPolicy::PrimeOrder`.
+pub const TOR22006_POLICY_PRIME_ORDER: c_int = 0;
+/// `KeyPolicy::Strict`.
+pub const TOR22006_POLICY_STRICT: c_int = 1;
+
+/// The code for `problem`.
+pub fn problem_code(problem: KeyProblem) -> c_int {
+    match problem {
+        KeyProblem::Malformed    => TOR22006_E_MALFORMED,
+        KeyProblem::NotOnCurve   => TOR22006_E_NOT_ON_CURVE,
+        KeyProblem::Torsioned    => TOR22006_E_TORSIONED,
+        KeyProblem::NonCanonical => TOR22006_E_NON_CANONICAL,
+        KeyProblem::Identity     => TOR22006_E_IDENTITY,
+    }
+}
+
+/// The code for `error`.
+pub fn format_error_code(error: FormatError) -> c_int {
+    match error {
+        FormatError::Malformed            => TOR22006_E_FORMAT_MALFORMED,
+        FormatError::UnsupportedAlgorithm => TOR22006_E_FORMAT_UNSUPPORTED,
+        FormatError::InvalidKey           => TOR22006_E_FORMAT_INVALID_KEY,
+    }
+}
+
+/// Check the 32-byte ed25519 key at `key` under `policy`, one of the
+/// `TOR22006_POLICY_` values, returning `TOR22006_OK` or why it failed.
+///
+/// # Safety
+///
+/// `key` must be NULL or point to 32 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tor22006_validate_key(key: *const u8, policy: c_int) -> c_int {
+    let policy: KeyPolicy = match key_policy(policy) {
+        Some(policy) => policy,
+        None         => return TOR22006_E_UNKNOWN_POLICY,
+    };
+    if key.is_null() {
+        return TOR22006_E_NULL_ARGUMENT;
+    }
+
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(::core::slice::from_raw_parts(key, 32));
+    check(UncheckedKey::new(CompressedEdwardsY(bytes)), policy)
+}
+
+/// Check the key named by the v3 onion address `address`, a NUL-terminated
+/// string with or without its ".onion" suffix, under `policy`, returning
+/// `TOR22006_OK` or why it failed.
+///
+/// # Safety
+///
+/// `address` must be NULL or point to a NUL-terminated string.
+#[cfg(feature = "blinding")]
+#[no_mangle]
+pub unsafe extern "C" fn tor22006_check_onion_address(address: *const c_char, policy: c_int) -> c_int {
+    let policy: KeyPolicy = match key_policy(policy) {
+        Some(policy) => policy,
+        None         => return TOR22006_E_UNKNOWN_POLICY,
+    };
+    if address.is_null() {
+        return TOR22006_E_NULL_ARGUMENT;
+    }
+
+    let key: Option<UncheckedKey<CompressedEdwardsY>> =
+        CStr::from_ptr(address).to_str().ok().and_then(parse_onion_address);
+    match key {
+        Some(key) => check(key, policy),
+        None      => TOR22006_E_BAD_ADDRESS,
+    }
+}
+
+fn key_policy(policy: c_int) -> Option<KeyPolicy> {
+    match policy {
+        TOR22006_POLICY_PRIME_ORDER => Some(KeyPolicy::PrimeOrder),
+        TOR22006_POLICY_STRICT      => Some(KeyPolicy::Strict),
+        _                           => None,
+    }
+}
+
+fn check(key: UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy) -> c_int {
+    match key.check_or_diagnose(policy) {
+        Ok(_)        => TOR22006_OK,
+        Err(problem) => problem_code(problem),
+    }
+}
+
+/// A description of `code`, as a NUL-terminated string which lives for as
+/// long as the program.
+#[no_mangle]
+pub extern "C" fn tor22006_strerror(code: c_int) -> *const c_char {
+    let description: &'static [u8] = match code {
+        TOR22006_OK                   => b"success\0",
+        TOR22006_E_MALFORMED          => b"malformed key\0",
+        TOR22006_E_NOT_ON_CURVE       => b"key is not a point on the curve\0",
+        TOR22006_E_TORSIONED          => b"key has a torsion component\0",
+        TOR22006_E_NON_CANONICAL      => b"key is not canonically encoded\0",
+        TOR22006_E_IDENTITY           => b"key is the identity\0",
+        TOR22006_E_FORMAT_MALFORMED   => b"container is malformed\0",
+        TOR22006_E_FORMAT_UNSUPPORTED => b"container holds a key for another algorithm\0",
+        TOR22006_E_FORMAT_INVALID_KEY => b"container holds a key which fails validation\0",
+        TOR22006_E_BAD_ADDRESS        => b"malformed onion address\0",
+        TOR22006_E_NULL_ARGUMENT      => b"NULL argument\0",
+        TOR22006_E_UNKNOWN_POLICY     => b"unknown policy\0",
+        6 ..= 127                     => b"reserved error code\0",
+        _                             => b"unknown error code\0",
+    };
+    description.as_ptr() as *const c_char
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use std::ffi::CStr;
+
+    use curve25519_dalek::constants;
+
+    fn validate(key: &CompressedEdwardsY, policy: c_int) -> c_int {
+        unsafe { tor22006_validate_key(key.as_bytes().as_ptr(), policy) }
+    }
+
+    fn strerror(code: c_int) -> &'static str {
+        unsafe { CStr::from_ptr(tor22006_strerror(code)) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn keys_get_codes() {
+        let torsioned: CompressedEdwardsY = (&constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1]).compress_edwards();
+        let identity: CompressedEdwardsY = constants::EIGHT_TORSION[0].compress_edwards();
+
+        assert_eq!(validate(&constants::ED25519_BASEPOINT.compress_edwards(), TOR22006_POLICY_STRICT), TOR22006_OK);
+        assert_eq!(validate(&torsioned, TOR22006_POLICY_PRIME_ORDER), TOR22006_E_TORSIONED);
+        assert_eq!(validate(&identity, TOR22006_POLICY_PRIME_ORDER), TOR22006_OK);
+        assert_eq!(validate(&identity, TOR22006_POLICY_STRICT), TOR22006_E_IDENTITY);
+        assert_eq!(validate(&CompressedEdwardsY([0x02; 32]), TOR22006_POLICY_STRICT), TOR22006_E_NOT_ON_CURVE);
+        assert_eq!(validate(&identity, 2), TOR22006_E_UNKNOWN_POLICY);
+        assert_eq!(unsafe { tor22006_validate_key(::core::ptr::null(), TOR22006_POLICY_STRICT) }, TOR22006_E_NULL_ARGUMENT);
+    }
+
+    #[test]
+    fn every_code_is_described() {
+        let codes: [c_int; 12] = [
+            TOR22006_OK, TOR22006_E_MALFORMED, TOR22006_E_NOT_ON_CURVE, TOR22006_E_TORSIONED,
+            TOR22006_E_NON_CANONICAL, TOR22006_E_IDENTITY, TOR22006_E_FORMAT_MALFORMED,
+            TOR22006_E_FORMAT_UNSUPPORTED, TOR22006_E_FORMAT_INVALID_KEY, TOR22006_E_BAD_ADDRESS,
+            TOR22006_E_NULL_ARGUMENT, TOR22006_E_UNKNOWN_POLICY ];
+
+        for (i, &code) in codes.iter().enumerate() {
+            assert!(!strerror(code).contains("error code"), "{} is undescribed", code);
+            assert!(codes[..i].iter().all(|&other| strerror(other) != strerror(code)));
+        }
+        assert_eq!(strerror(6), "reserved error code");
+        assert_eq!(strerror(127), "reserved error code");
+        assert_eq!(strerror(128), "unknown error code");
+        assert_eq!(format_error_code(FormatError::InvalidKey), 66);
+    }
+
+    #[cfg(feature = "blinding")]
+    #[test]
+    fn onion_addresses_get_codes() {
+        use std::ffi::CString;
+
+        use key::CheckedKey;
+        use onion::adversarial_onion_addresses;
+        use onion::onion_address;
+
+        let check = |address: &str| {
+            let address: CString = CString::new(address).unwrap();
+            unsafe { tor22006_check_onion_address(address.as_ptr(), TOR22006_POLICY_STRICT) }
+        };
+        let key: CheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards()).check(KeyPolicy::Strict).unwrap();
+        let address: String = onion_address(&key);
+
+        assert_eq!(check(&address), TOR22006_OK);
+        assert_eq!(check(&address[1..]), TOR22006_E_BAD_ADDRESS);
+        assert!(adversarial_onion_addresses().iter().all(|adversarial| check(adversarial.address()) == problem_code(adversarial.problem())));
+        assert_eq!(unsafe { tor22006_check_onion_address(::core::ptr::null(), TOR22006_POLICY_STRICT) },
+                   TOR22006_E_NULL_ARGUMENT);
+    }
+}
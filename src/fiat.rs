@@ -0,0 +1,347 @@
+// Decompression and validation redone in fiat-crypto's field arithmetic, as
+// a cross-check on curve25519-dalek's, behind the `fiat` feature.
+//
+// fiat-crypto's field operations are generated from a machine-checked proof
+// that they compute what they should mod 2^255 - 19, so where the two
+// implementations differ on some encoding, dalek is the one to suspect.  The
+// curve arithmetic on top is written here to be obviously correct rather
+// than fast: the square root exponentiation is plain square-and-multiply,
+// and [l]P is plain double-and-add with the unified addition law, which is
+// complete on ed25519.  Only the field operations are taken from fiat, and
+// only the constants below are written out; nothing is taken from dalek.
+//
+// The 64-bit fiat code is used on every target, since it only needs
+// 128-bit multiplication to be correct, not fast.
+
+use fiat_crypto::curve25519_64::fiat_25519_add;
+use fiat_crypto::curve25519_64::fiat_25519_carry;
+use fiat_crypto::curve25519_64::fiat_25519_carry_mul;
+use fiat_crypto::curve25519_64::fiat_25519_carry_square;
+use fiat_crypto::curve25519_64::fiat_25519_from_bytes;
+use fiat_crypto::curve25519_64::fiat_25519_loose_field_element;
+use fiat_crypto::curve25519_64::fiat_25519_opp;
+use fiat_crypto::curve25519_64::fiat_25519_relax;
+use fiat_crypto::curve25519_64::fiat_25519_sub;
+use fiat_crypto::curve25519_64::fiat_25519_tight_field_element;
+use fiat_crypto::curve25519_64::fiat_25519_to_bytes;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+
+use traits::PrimeOrderEncoding;
+
+// d = -121665/121666.
+static D: [u8; 32] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75,
+    0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c,
+    0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52 ];
+
+// sqrt(-1) = 2^((p-1)/4).
+static SQRT_M1: [u8; 32] = [
+    0xb0, 0xa0, 0x0e, 0x4a, 0x27, 0x1b, 0xee, 0xc4,
+    0x78, 0xe4, 0x2f, 0xad, 0x06, 0x18, 0x43, 0x2f,
+    0xa7, 0xd7, 0xfb, 0x3d, 0x99, 0x00, 0x4d, 0x2b,
+    0x0b, 0xdf, 0xc1, 0x4f, 0x80, 0x24, 0x83, 0x2b ];
+
+// l = 2^252 + 27742317777372353535851937790883648493.
+static L: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+    0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10 ];
+
+/// What fiat's arithmetic made of an encoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FiatPoint {
+    x: [u8; 32],
+    y: [u8; 32],
+    torsion_free: bool,
+}
+
+impl FiatPoint {
+    /// The affine x-coordinate, reduced.
+    pub fn x(&self) -> &[u8; 32] {
+        &self.x
+    }
+
+    /// The affine y-coordinate, reduced.
+    pub fn y(&self) -> &[u8; 32] {
+        &self.y
+    }
+
+    /// Whether [l]P is the identity.
+    pub fn is_torsion_free(&self) -> bool {
+        self.torsion_free
+    }
+}
+
+/// fiat and dalek gave different answers for an encoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    encoding: CompressedEdwardsY,
+    fiat: Option<FiatPoint>,
+    dalek: Option<FiatPoint>,
+}
+
+impl Mismatch {
+    /// The encoding they disagreed about.
+    pub fn encoding(&self) -> &CompressedEdwardsY {
+        &self.encoding
+    }
+
+    /// What fiat made of it, or `None` if it didn't decompress.
+    pub fn fiat(&self) -> Option<&FiatPoint> {
+        self.fiat.as_ref()
+    }
+
+    /// What dalek made of it, in the same terms.
+    pub fn dalek(&self) -> Option<&FiatPoint> {
+        self.dalek.as_ref()
+    }
+}
+
+/// Decompress `key` and test it for torsion using only fiat's field
+/// arithmetic, accepting exactly the encodings dalek's `decompress` does.
+pub fn decompress(key: &CompressedEdwardsY) -> Option<FiatPoint> {
+    let mut y_bytes: [u8; 32] = key.to_bytes();
+    let sign: u8 = y_bytes[31] >> 7;
+    y_bytes[31] &= 0x7f;
+
+    let one: Fe = Fe::one();
+    let y: Fe = Fe::from_bytes(&y_bytes);
+    let yy: Fe = y.square();
+    let u: Fe = yy.sub(&one);                            // u = y² - 1
+    let v: Fe = yy.mul(&Fe::from_bytes(&D)).add(&one);   // v = dy² + 1
+
+    // r = (uv³)(uv⁷)^((p-5)/8), which is ±sqrt(u/v) when u/v is square.
+    let v3: Fe = v.square().mul(&v);
+    let v7: Fe = v3.square().mul(&v);
+    let mut x: Fe = u.mul(&v3).mul(&u.mul(&v7).pow_p58());
+    let check: Fe = v.mul(&x.square());
+
+    if check == u.neg() {
+        x = x.mul(&Fe::from_bytes(&SQRT_M1));
+    } else if check != u {
+        return None; // u/v was not square
+    }
+    if x.is_negative() != sign {
+        x = x.neg();
+    }
+
+    let p: Point = Point { x, y, z: one, t: x.mul(&y) };
+
+    Some(FiatPoint {
+        x: x.to_bytes(),
+        y: y.to_bytes(),
+        torsion_free: p.mul(&L).is_identity(),
+    })
+}
+
+/// Validate `key` with dalek, as `PrimeOrderEncoding::validate` does, and
+/// redo the same work with fiat, returning dalek's answer if the two agree
+/// on the decompressed point and on whether it is torsion-free.  The
+/// mismatch is boxed, being some 160 bytes.
+pub fn cross_check(key: &CompressedEdwardsY) -> Result<Option<ExtendedPoint>, Box<Mismatch>> {
+    let fiat: Option<FiatPoint> = decompress(key);
+    let dalek: Option<FiatPoint> = key.decompress().map(|p| FiatPoint {
+        // Z is 1 straight out of decompression.
+        x: p.X.to_bytes(),
+        y: p.Y.to_bytes(),
+        torsion_free: key.validate().is_some(),
+    });
+
+    match fiat == dalek {
+        true  => Ok(key.validate()),
+        false => Err(Box::new(Mismatch { encoding: *key, fiat, dalek })),
+    }
+}
+
+// A field element, always kept with tight bounds between operations.
+#[derive(Copy, Clone)]
+struct Fe(fiat_25519_tight_field_element);
+
+impl Fe {
+    fn from_bytes(bytes: &[u8; 32]) -> Fe {
+        let mut out: fiat_25519_tight_field_element = fiat_25519_tight_field_element([0; 5]);
+
+        fiat_25519_from_bytes(&mut out, bytes);
+        Fe(out)
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        let mut bytes: [u8; 32] = [0u8; 32];
+
+        fiat_25519_to_bytes(&mut bytes, &self.0);
+        bytes
+    }
+
+    fn one() -> Fe {
+        let mut one: [u8; 32] = [0u8; 32];
+        one[0] = 1;
+
+        Fe::from_bytes(&one)
+    }
+
+    fn relax(&self) -> fiat_25519_loose_field_element {
+        let mut out: fiat_25519_loose_field_element = fiat_25519_loose_field_element([0; 5]);
+
+        fiat_25519_relax(&mut out, &self.0);
+        out
+    }
+
+    fn carry(loose: &fiat_25519_loose_field_element) -> Fe {
+        let mut out: fiat_25519_tight_field_element = fiat_25519_tight_field_element([0; 5]);
+
+        fiat_25519_carry(&mut out, loose);
+        Fe(out)
+    }
+
+    fn add(&self, other: &Fe) -> Fe {
+        let mut out: fiat_25519_loose_field_element = fiat_25519_loose_field_element([0; 5]);
+
+        fiat_25519_add(&mut out, &self.0, &other.0);
+        Fe::carry(&out)
+    }
+
+    fn sub(&self, other: &Fe) -> Fe {
+        let mut out: fiat_25519_loose_field_element = fiat_25519_loose_field_element([0; 5]);
+
+        fiat_25519_sub(&mut out, &self.0, &other.0);
+        Fe::carry(&out)
+    }
+
+    fn neg(&self) -> Fe {
+        let mut out: fiat_25519_loose_field_element = fiat_25519_loose_field_element([0; 5]);
+
+        fiat_25519_opp(&mut out, &self.0);
+        Fe::carry(&out)
+    }
+
+    fn mul(&self, other: &Fe) -> Fe {
+        let mut out: fiat_25519_tight_field_element = fiat_25519_tight_field_element([0; 5]);
+
+        fiat_25519_carry_mul(&mut out, &self.relax(), &other.relax());
+        Fe(out)
+    }
+
+    fn square(&self) -> Fe {
+        let mut out: fiat_25519_tight_field_element = fiat_25519_tight_field_element([0; 5]);
+
+        fiat_25519_carry_square(&mut out, &self.relax());
+        Fe(out)
+    }
+
+    // self^((p-5)/8) = self^(2^252 - 3) = (self^(2^250 - 1))^4 · self.
+    fn pow_p58(&self) -> Fe {
+        let mut t: Fe = *self;
+
+        // After i rounds, t = self^(2^(i+1) - 1).
+        for _ in 0..249 {
+            t = t.square().mul(self);
+        }
+        t.square().square().mul(self)
+    }
+
+    // Whether the reduced value is odd, which is what the sign bit records.
+    fn is_negative(&self) -> u8 {
+        self.to_bytes()[0] & 1
+    }
+
+    fn is_zero(&self) -> bool {
+        self.to_bytes() == [0u8; 32]
+    }
+}
+
+// Limbs can differ for the same value, so compare reduced bytes.
+impl PartialEq for Fe {
+    fn eq(&self, other: &Fe) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+// A point in extended coordinates, x = X/Z, y = Y/Z, xy = T/Z.
+#[derive(Copy, Clone)]
+struct Point {
+    x: Fe,
+    y: Fe,
+    z: Fe,
+    t: Fe,
+}
+
+impl Point {
+    fn identity() -> Point {
+        let zero: Fe = Fe::from_bytes(&[0u8; 32]);
+
+        Point { x: zero, y: Fe::one(), z: Fe::one(), t: zero }
+    }
+
+    // The unified addition law for a = -1 (add-2008-hwcd-3), which also
+    // doubles.
+    fn add(&self, other: &Point) -> Point {
+        let d2: Fe = Fe::from_bytes(&D).add(&Fe::from_bytes(&D));
+        let a: Fe = self.y.sub(&self.x).mul(&other.y.sub(&other.x));
+        let b: Fe = self.y.add(&self.x).mul(&other.y.add(&other.x));
+        let c: Fe = self.t.mul(&d2).mul(&other.t);
+        let d: Fe = self.z.add(&self.z).mul(&other.z);
+        let (e, f, g, h): (Fe, Fe, Fe, Fe) = (b.sub(&a), d.sub(&c), d.add(&c), b.add(&a));
+
+        Point { x: e.mul(&f), y: g.mul(&h), z: f.mul(&g), t: e.mul(&h) }
+    }
+
+    // [scalar]P, by double-and-add from the top bit down.
+    fn mul(&self, scalar: &[u8; 32]) -> Point {
+        let mut q: Point = Point::identity();
+
+        for i in (0..256).rev() {
+            q = q.add(&q);
+            if (scalar[i / 8] >> (i % 8)) & 1 == 1 {
+                q = q.add(self);
+            }
+        }
+        q
+    }
+
+    fn is_identity(&self) -> bool {
+        self.x.is_zero() && self.y == self.z
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn constants_match_dalek() {
+        assert_eq!(Fe::from_bytes(&D).to_bytes(), constants::d.to_bytes());
+        assert!(Fe::from_bytes(&SQRT_M1).square() == Fe::one().neg());
+        assert_eq!(&L, constants::l.as_bytes());
+    }
+
+    #[test]
+    fn agrees_with_dalek() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+        let mut keys: Vec<CompressedEdwardsY> = vec![
+            CompressedEdwardsY([0xff; 32]),
+            CompressedEdwardsY([0x80; 32]),
+            // The identity with the sign bit set, which dalek accepts.
+            CompressedEdwardsY({ let mut b: [u8; 32] = [0u8; 32]; b[0] = 1; b[31] = 0x80; b }),
+        ];
+        for t in constants::EIGHT_TORSION.iter() {
+            keys.push(t.compress_edwards());
+            keys.push((&p + t).compress_edwards());
+        }
+        for i in 0..64u8 {
+            keys.push(CompressedEdwardsY([i; 32]));
+        }
+
+        for key in keys.iter() {
+            assert_eq!(cross_check(key).map(|p| p.is_some()), Ok(key.validate().is_some()));
+        }
+        assert!(decompress(&p.compress_edwards()).unwrap().is_torsion_free());
+        assert!(!decompress(&(&p + &constants::EIGHT_TORSION[2]).compress_edwards()).unwrap().is_torsion_free());
+    }
+}
@@ -0,0 +1,200 @@
+// A record of every rejected key, bytes and all, for researchers who want to
+// know what is actually out there rather than just how much of it.
+//
+// This keeps raw key material, so it is only built with the `forensics`
+// feature.  Rejections go either to a file, which is only ever appended to,
+// one JSON object per line, or to a callback.
+
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use encoding::hex_encode;
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// One rejected encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    source: String,
+    encoding: Vec<u8>,
+    problem: KeyProblem,
+    time: SystemTime,
+}
+
+impl Rejection {
+    /// Describe a rejection which happened just now.
+    pub fn new(source: &str, encoding: &[u8], problem: KeyProblem) -> Rejection {
+        Rejection {
+            source: source.to_string(),
+            encoding: encoding.to_vec(),
+            problem,
+            time: SystemTime::now(),
+        }
+    }
+
+    /// The label of the source the encoding came from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The encoding, exactly as received.
+    pub fn encoding(&self) -> &[u8] {
+        &self.encoding
+    }
+
+    /// Why it was rejected.
+    pub fn problem(&self) -> KeyProblem {
+        self.problem
+    }
+
+    /// When it was rejected.
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// This rejection as a single line of JSON, without the newline.
+    pub fn to_json(&self) -> String {
+        let since_epoch: Duration = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        format!("{{\"time\":{}.{:09},\"source\":\"{}\",\"problem\":\"{:?}\",\"encoding\":\"{}\"}}",
+                since_epoch.as_secs(), since_epoch.subsec_nanos(),
+                json_escape(&self.source), self.problem, hex_encode(&self.encoding))
+    }
+}
+
+enum Sink {
+    File(File),
+    Callback(Box<dyn Fn(&Rejection) + Send>),
+}
+
+/// Somewhere to send rejections.
+pub struct RejectionLog {
+    sink: Mutex<Sink>,
+}
+
+impl RejectionLog {
+    /// Append rejections to the file at `path`, creating it if need be.
+    pub fn append_to_file<P: AsRef<Path>>(path: P) -> io::Result<RejectionLog> {
+        let file: File = OpenOptions::new().append(true).create(true).open(path)?;
+
+        Ok(RejectionLog { sink: Mutex::new(Sink::File(file)) })
+    }
+
+    /// Hand each rejection to `callback`.
+    pub fn with_callback<F>(callback: F) -> RejectionLog
+        where F: Fn(&Rejection) + Send + 'static
+    {
+        RejectionLog { sink: Mutex::new(Sink::Callback(Box::new(callback))) }
+    }
+
+    /// Record one rejection.
+    pub fn record(&self, rejection: &Rejection) -> io::Result<()> {
+        let mut sink = self.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match *sink {
+            // One write per line, so that lines from concurrent writers
+            // to the same file don't interleave.
+            Sink::File(ref mut file) => file.write_all(format!("{}\n", rejection.to_json()).as_bytes()),
+            Sink::Callback(ref callback) => {
+                callback(rejection);
+                Ok(())
+            },
+        }
+    }
+
+    /// Check `key` under `policy`, and record it under `source` if it is
+    /// rejected.
+    pub fn check(&self, source: &str, key: &UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+        -> io::Result<Option<CheckedKey<CompressedEdwardsY>>>
+    {
+        match key.check_or_diagnose(policy) {
+            Ok(checked)  => Ok(Some(checked)),
+            Err(problem) => {
+                self.record(&Rejection::new(source, key.encoding().as_bytes(), problem))?;
+                Ok(None)
+            },
+        }
+    }
+}
+
+fn json_escape(text: &str) -> String {
+    let mut out: String = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use curve25519_dalek::constants;
+
+    fn torsioned() -> UncheckedKey<CompressedEdwardsY> {
+        (&constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[2]).compress_edwards().into()
+    }
+
+    #[test]
+    fn callback_sees_each_rejection() {
+        let seen: Arc<Mutex<Vec<Rejection>>> = Arc::new(Mutex::new(Vec::new()));
+        let log: RejectionLog = {
+            let seen: Arc<Mutex<Vec<Rejection>>> = seen.clone();
+            RejectionLog::with_callback(move |rejection| seen.lock().unwrap().push(rejection.clone()))
+        };
+        let good: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+
+        assert!(log.check("consensus", &good, KeyPolicy::Strict).unwrap().is_some());
+        assert!(log.check("consensus", &torsioned(), KeyPolicy::Strict).unwrap().is_none());
+
+        let seen: Vec<Rejection> = seen.lock().unwrap().clone();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].encoding(), torsioned().encoding().as_bytes());
+        assert_eq!(seen[0].problem(), KeyProblem::Torsioned);
+    }
+
+    #[test]
+    fn file_is_appended_to() {
+        let path: PathBuf = env::temp_dir().join(format!("tor22006-forensics-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        for _ in 0..2 {
+            let log: RejectionLog = RejectionLog::append_to_file(&path).unwrap();
+            log.check("a \"quoted\" source", &torsioned(), KeyPolicy::PrimeOrder).unwrap();
+        }
+
+        let mut text: String = String::new();
+        File::open(&path).unwrap().read_to_string(&mut text).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.lines().all(|line| {
+            line.contains("\"source\":\"a \\\"quoted\\\" source\"") &&
+            line.contains("\"problem\":\"Torsioned\"") &&
+            line.contains(&hex_encode(torsioned().encoding().as_bytes()))
+        }));
+    }
+}
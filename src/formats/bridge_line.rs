@@ -0,0 +1,227 @@
+// Tor bridge lines, as given to users and written in a torrc:
+//
+//    [Bridge] [<transport>] <address>:<port> [<ids>...] [<key>=<value>...]
+//
+// where the ids are a relay's RSA fingerprint, 40 hex digits, perhaps after
+// a `$`, and its ed25519 identity, as unpadded base64, perhaps after
+// `ed25519:`, the way Arti accepts them.  The pluggable transport's own
+// arguments, such as obfs4's `cert=` and `iat-mode=`, come last and are
+// kept as they were written.
+//
+// A bridge distributor which hands out a line whose ed25519 identity isn't a
+// valid key hands its users a bridge their client will refuse, or worse, one
+// whose identity some other key could also be taken for.  `check_bridge_line`
+// rejects such lines before they go out.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use encoding::base64_decode;
+use encoding::hex_decode;
+use formats::FormatError;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The parts of a bridge line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BridgeLine {
+    transport: Option<String>,
+    address: String,
+    fingerprint: Option<[u8; 20]>,
+    ed25519: Option<UncheckedKey<CompressedEdwardsY>>,
+    arguments: Vec<(String, String)>,
+}
+
+impl BridgeLine {
+    /// The pluggable transport, if there is one.
+    pub fn transport(&self) -> Option<&str> {
+        self.transport.as_deref()
+    }
+
+    /// The address and port, as written.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The RSA identity fingerprint, if given.
+    pub fn fingerprint(&self) -> Option<&[u8; 20]> {
+        self.fingerprint.as_ref()
+    }
+
+    /// The ed25519 identity, if given.
+    pub fn ed25519(&self) -> Option<&UncheckedKey<CompressedEdwardsY>> {
+        self.ed25519.as_ref()
+    }
+
+    /// The transport's arguments, in order.
+    pub fn arguments(&self) -> &[(String, String)] {
+        &self.arguments
+    }
+}
+
+/// Split a bridge line into its parts, without checking the ed25519
+/// identity.
+pub fn parse_bridge_line(line: &str) -> Result<BridgeLine, FormatError> {
+    let mut words = line.split_whitespace().peekable();
+
+    if words.peek() == Some(&"Bridge") {
+        words.next();
+    }
+    let transport: Option<String> = match words.peek() {
+        Some(word) if !is_address(word) => match is_transport(word) {
+            true  => Some(word.to_string()),
+            false => return Err(FormatError::Malformed),
+        },
+        _ => None,
+    };
+    if transport.is_some() {
+        words.next();
+    }
+    let address: String = match words.next() {
+        Some(word) if is_address(word) => word.to_string(),
+        _                              => return Err(FormatError::Malformed), // no address
+    };
+
+    let mut line: BridgeLine = BridgeLine {
+        transport,
+        address,
+        fingerprint: None,
+        ed25519: None,
+        arguments: Vec::new(),
+    };
+    for word in words {
+        if let Some(i) = word.find('=') {
+            line.arguments.push((word[..i].to_string(), word[i + 1..].to_string()));
+        } else if !line.arguments.is_empty() {
+            return Err(FormatError::Malformed); // an id after the arguments
+        } else if let Some(fingerprint) = parse_fingerprint(word) {
+            if line.fingerprint.replace(fingerprint).is_some() {
+                return Err(FormatError::Malformed); // two RSA identities
+            }
+        } else if let Some(key) = parse_ed25519(word) {
+            if line.ed25519.replace(UncheckedKey::new(key)).is_some() {
+                return Err(FormatError::Malformed); // two ed25519 identities
+            }
+        } else {
+            return Err(FormatError::Malformed);
+        }
+    }
+    Ok(line)
+}
+
+/// Parse a bridge line, and check its ed25519 identity, if it has one,
+/// under `policy`, returning `FormatError::InvalidKey` if it fails.
+pub fn check_bridge_line(line: &str, policy: KeyPolicy) -> Result<BridgeLine, FormatError> {
+    let parsed: BridgeLine = parse_bridge_line(line)?;
+
+    match parsed.ed25519.as_ref().map(|key| key.check(policy)) {
+        Some(None) => Err(FormatError::InvalidKey),
+        _          => Ok(parsed),
+    }
+}
+
+/// The checked ed25519 identity of a bridge line, if it has one which
+/// passes under `policy`.
+pub fn bridge_line_identity(line: &str, policy: KeyPolicy) -> Option<CheckedKey<CompressedEdwardsY>> {
+    parse_bridge_line(line).ok().and_then(|parsed| parsed.ed25519).and_then(|key| key.check(policy))
+}
+
+// An IPv4 address or a bracketed IPv6 one, and a port.
+fn is_address(word: &str) -> bool {
+    let (host, port): (&str, &str) = match word.rfind(':') {
+        Some(i) => (&word[..i], &word[i + 1..]),
+        None    => return false,
+    };
+    let host_ok: bool = match host.starts_with('[') {
+        true  => host.ends_with(']') && host.len() > 2 &&
+                 host[1..host.len() - 1].chars().all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.'),
+        false => host.split('.').count() == 4 && host.split('.').all(|octet| octet.parse::<u8>().is_ok()),
+    };
+
+    host_ok && port.parse::<u16>().map(|port| port != 0).unwrap_or(false)
+}
+
+fn is_transport(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+        word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_fingerprint(word: &str) -> Option<[u8; 20]> {
+    let hex: &str = word.trim_start_matches('$');
+    let mut fingerprint: [u8; 20] = [0u8; 20];
+
+    match hex_decode(hex) {
+        Some(ref bytes) if bytes.len() == 20 => fingerprint.copy_from_slice(bytes),
+        _                                    => return None,
+    }
+    Some(fingerprint)
+}
+
+fn parse_ed25519(word: &str) -> Option<CompressedEdwardsY> {
+    let text: &str = match word.starts_with("ed25519:") {
+        true  => &word[8..],
+        false => word,
+    };
+    let mut key: [u8; 32] = [0u8; 32];
+
+    match base64_decode(text) {
+        Some(ref bytes) if bytes.len() == 32 && text.len() == 43 => key.copy_from_slice(bytes),
+        _                                                        => return None,
+    }
+    Some(CompressedEdwardsY(key))
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use encoding::base64_encode;
+
+    static FINGERPRINT: &str = "0123456789ABCDEF0123456789ABCDEF01234567";
+
+    fn identity() -> String {
+        base64_encode(constants::ED25519_BASEPOINT.compress_edwards().as_bytes(), false)
+    }
+
+    #[test]
+    fn lines_are_split() {
+        let text: String = format!("Bridge obfs4 192.0.2.1:443 {} ed25519:{} cert=AAAA iat-mode=0",
+                                   FINGERPRINT, identity());
+        let line: BridgeLine = check_bridge_line(&text, KeyPolicy::Strict).unwrap();
+
+        assert_eq!(line.transport(), Some("obfs4"));
+        assert_eq!(line.address(), "192.0.2.1:443");
+        assert_eq!(line.fingerprint().unwrap()[0], 0x01);
+        assert_eq!(line.ed25519().unwrap().encoding(), &constants::ED25519_BASEPOINT.compress_edwards());
+        assert_eq!(line.arguments(), &[("cert".to_string(), "AAAA".to_string()),
+                                       ("iat-mode".to_string(), "0".to_string())]);
+
+        let bare: BridgeLine = parse_bridge_line(&format!("[2001:db8::1]:9001 ${}", FINGERPRINT)).unwrap();
+        assert_eq!(bare.transport(), None);
+        assert!(bare.ed25519().is_none());
+        assert!(bridge_line_identity(&text, KeyPolicy::Strict).is_some());
+    }
+
+    #[test]
+    fn bad_identities_are_rejected() {
+        let torsion: String = base64_encode(constants::EIGHT_TORSION[1].compress_edwards().as_bytes(), false);
+        let text: String = format!("snowflake 192.0.2.3:80 {} {} url=https://example.org/", FINGERPRINT, torsion);
+
+        assert!(parse_bridge_line(&text).is_ok());
+        assert_eq!(check_bridge_line(&text, KeyPolicy::Strict), Err(FormatError::InvalidKey));
+        assert_eq!(check_bridge_line(&format!("192.0.2.1:443 {}", FINGERPRINT), KeyPolicy::Strict)
+                   .map(|line| line.ed25519().is_none()), Ok(true));
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        for text in ["", "Bridge", "obfs4", "192.0.2.1", "192.0.2.1:0", "300.0.2.1:443", "obfs-4 192.0.2.1:443",
+                     "192.0.2.1:443 AAAA", "192.0.2.1:443 cert=AAAA 0123456789ABCDEF0123456789ABCDEF01234567"].iter() {
+            assert_eq!(parse_bridge_line(text), Err(FormatError::Malformed), "{}", text);
+        }
+        let twice: String = format!("192.0.2.1:443 {} {}", identity(), identity());
+        assert_eq!(parse_bridge_line(&twice), Err(FormatError::Malformed));
+    }
+}
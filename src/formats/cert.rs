@@ -0,0 +1,147 @@
+// Tor's own ed25519 certificates, in the format of cert-spec §2.1:
+//
+//    VERSION          1 byte, 1
+//    CERT_TYPE        1 byte
+//    EXPIRATION_DATE  4 bytes, hours since the epoch
+//    CERT_KEY_TYPE    1 byte, 1 for an ed25519 key
+//    CERTIFIED_KEY    32 bytes
+//    N_EXTENSIONS     1 byte
+//    N_EXTENSIONS times: ExtLength (2), ExtType, ExtFlags, ExtLength bytes
+//    SIGNATURE        64 bytes, over everything before it
+//
+// Only the one extension anything here needs is read, signed-with-ed25519-key,
+// which names the key the certificate is signed with.  Any other extension
+// which affects validation makes the certificate unparseable, as cert-spec
+// requires.  Nothing is checked beyond the framing: the keys come out as
+// they were found, for the caller to validate.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use formats::FormatError;
+
+pub const EXTENSION_SIGNED_WITH_KEY: u8 = 0x04;
+const EXTENSION_AFFECTS_VALIDATION: u8 = 0x01;
+
+// The parts of a certificate its users need.
+pub struct Certificate {
+    pub cert_type: u8,
+    pub expiration: u64,
+    pub certified_key: CompressedEdwardsY,
+    pub signing_key: Option<CompressedEdwardsY>,
+    pub signed_length: usize,
+    pub signature: [u8; 64],
+}
+
+pub fn parse_certificate(bytes: &[u8]) -> Result<Certificate, FormatError> {
+    // VERSION, CERT_TYPE, EXPIRATION_DATE, CERT_KEY_TYPE, CERTIFIED_KEY, N_EXTENSIONS
+    if bytes.len() < 40 + 64 || bytes[0] != 1 || bytes[6] != 1 {
+        return Err(FormatError::Malformed);
+    }
+
+    let expiration: u64 = bytes[2..6].iter().fold(0u64, |n, &byte| n << 8 | byte as u64);
+    let mut certified_key: [u8; 32] = [0u8; 32];
+    certified_key.copy_from_slice(&bytes[7..39]);
+
+    let mut signing_key: Option<CompressedEdwardsY> = None;
+    let mut offset: usize = 40;
+
+    for _ in 0..bytes[39] {
+        // ExtLength, ExtType, ExtFlags, then ExtLength bytes of data.
+        if offset + 4 > bytes.len() {
+            return Err(FormatError::Malformed);
+        }
+        let length: usize = (bytes[offset] as usize) << 8 | bytes[offset + 1] as usize;
+        let (ext_type, flags): (u8, u8) = (bytes[offset + 2], bytes[offset + 3]);
+        let data: &[u8] = match bytes.get(offset + 4..offset + 4 + length) {
+            Some(data) => data,
+            None       => return Err(FormatError::Malformed),
+        };
+
+        match ext_type {
+            EXTENSION_SIGNED_WITH_KEY if length == 32 => {
+                let mut key: [u8; 32] = [0u8; 32];
+                key.copy_from_slice(data);
+                signing_key = Some(CompressedEdwardsY(key));
+            },
+            EXTENSION_SIGNED_WITH_KEY => return Err(FormatError::Malformed),
+            _ if flags & EXTENSION_AFFECTS_VALIDATION != 0 => return Err(FormatError::Malformed),
+            _ => (), // an extension we may ignore
+        }
+        offset += 4 + length;
+    }
+
+    if bytes.len() != offset + 64 {
+        return Err(FormatError::Malformed);
+    }
+    let mut signature: [u8; 64] = [0u8; 64];
+    signature.copy_from_slice(&bytes[offset..]);
+
+    Ok(Certificate {
+        cert_type: bytes[1],
+        expiration,
+        certified_key: CompressedEdwardsY(certified_key),
+        signing_key,
+        signed_length: offset,
+        signature,
+    })
+}
+
+// The body of a certificate, up to its signature, for tests to sign.
+#[cfg(test)]
+pub fn unsigned_certificate(cert_type: u8, expiration: u64, certified_key: &CompressedEdwardsY,
+                            signing_key: Option<&CompressedEdwardsY>) -> Vec<u8> {
+    let mut cert: Vec<u8> = vec![1, cert_type];
+
+    cert.extend_from_slice(&[(expiration >> 24) as u8, (expiration >> 16) as u8,
+                             (expiration >> 8) as u8, expiration as u8]);
+    cert.push(1);
+    cert.extend_from_slice(certified_key.as_bytes());
+    match signing_key {
+        Some(key) => {
+            cert.extend_from_slice(&[1, 0, 32, EXTENSION_SIGNED_WITH_KEY, 0]);
+            cert.extend_from_slice(key.as_bytes());
+        },
+        None => cert.push(0),
+    }
+    cert
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    #[test]
+    fn certificates_round_trip() {
+        let key: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let mut bytes: Vec<u8> = unsigned_certificate(0x0c, 415_632, &key, Some(&key));
+        bytes.extend_from_slice(&[0x5a; 64]);
+
+        let cert: Certificate = parse_certificate(&bytes).unwrap();
+        assert_eq!(cert.cert_type, 0x0c);
+        assert_eq!(cert.expiration, 415_632);
+        assert_eq!(cert.certified_key, key);
+        assert_eq!(cert.signing_key, Some(key));
+        assert_eq!(cert.signed_length, bytes.len() - 64);
+        assert_eq!(&cert.signature[..], &[0x5a; 64][..]);
+
+        assert!(parse_certificate(&bytes[1..]).is_err());
+        bytes.push(0);
+        assert!(parse_certificate(&bytes).is_err());
+    }
+
+    #[test]
+    fn unknown_extensions_affecting_validation_are_refused() {
+        let key: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let mut bytes: Vec<u8> = unsigned_certificate(0x0c, 0, &key, None);
+
+        bytes[39] = 1;
+        bytes.extend_from_slice(&[0, 0, 0x7f, EXTENSION_AFFECTS_VALIDATION]);
+        bytes.extend_from_slice(&[0x5a; 64]);
+        assert!(parse_certificate(&bytes).is_err());
+
+        bytes[43] = 0;
+        assert!(parse_certificate(&bytes).unwrap().signing_key.is_none());
+    }
+}
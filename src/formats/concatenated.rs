@@ -0,0 +1,431 @@
+// Blobs of raw 32-byte keys, one after another with nothing between, as
+// archives of keys scraped from directory documents are usually kept.
+//
+// A `KeyBlob` borrows the bytes rather than copying them, so it can sit over
+// a memory-mapped file however large, and checking it allocates nothing per
+// key: each key is validated from where it lies, and only the indices of the
+// ones which fail are handed back, to a closure or collected into a vector.
+//
+// A `KeyStreamReader` is for the same format arriving through a `Read`, such
+// as a file too large to map or a pipe: it reads into a window of fixed
+// size, refilling it as the keys in it are used up, and checks each key as
+// it goes, so memory use doesn't grow with the input.
+//
+// `validate_in_place` is for a pipeline which owns its buffer and only
+// wants the valid keys passed on: it overwrites every invalid key with
+// `INVALID_SENTINEL`, which is itself invalid under every policy, so the
+// buffer then records which keys failed, and the `InvalidMask` it returns
+// reads them back from it, as a bitmask or as indices, allocating nothing.
+
+use core::convert::TryFrom;
+
+use std::io;
+use std::io::Read;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use formats::FormatError;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// Concatenated 32-byte keys.
+#[derive(Copy, Clone, Debug)]
+pub struct KeyBlob<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> KeyBlob<'a> {
+    /// View `bytes` as keys.  Fails unless its length is a multiple of 32.
+    pub fn new(bytes: &'a [u8]) -> Result<KeyBlob<'a>, FormatError> {
+        match bytes.len() % 32 {
+            0 => Ok(KeyBlob { bytes }),
+            _ => Err(FormatError::Malformed), // a truncated key at the end
+        }
+    }
+
+    /// How many keys there are.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 32
+    }
+
+    /// Whether there are no keys at all.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The key at `index`, if there is one.
+    pub fn get(&self, index: usize) -> Option<&'a [u8; 32]> {
+        match index < self.len() {
+            true  => Some(key_at(self.bytes, index)),
+            false => None,
+        }
+    }
+
+    /// Every key, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8; 32]> {
+        let bytes: &'a [u8] = self.bytes;
+
+        (0..self.len()).map(move |index| key_at(bytes, index))
+    }
+}
+
+/// Call `invalid` with the index of every key in `blob` which fails under
+/// `policy`, in order, and return how many there were.
+pub fn for_each_invalid<F: FnMut(usize)>(blob: &KeyBlob, policy: KeyPolicy, mut invalid: F) -> usize {
+    let mut count: usize = 0;
+
+    for (index, key) in blob.iter().enumerate() {
+        if UncheckedKey::new(CompressedEdwardsY(*key)).check(policy).is_none() {
+            invalid(index);
+            count += 1;
+        }
+    }
+    count
+}
+
+/// The indices of every key in `blob` which fails under `policy`, in order.
+pub fn invalid_entries(blob: &KeyBlob, policy: KeyPolicy) -> Vec<usize> {
+    let mut indices: Vec<usize> = Vec::new();
+
+    for_each_invalid(blob, policy, |index| indices.push(index));
+    indices
+}
+
+/// What `validate_in_place` overwrites invalid keys with.  It encodes y = 0,
+/// a point of order 4, so no policy accepts it.
+pub const INVALID_SENTINEL: [u8; 32] = [0u8; 32];
+
+/// Which keys of a buffer `validate_in_place` marked as invalid, one bit per
+/// key, read from the buffer itself.
+#[derive(Copy, Clone, Debug)]
+pub struct InvalidMask<'a> {
+    bytes: &'a [u8],
+    count: usize,
+}
+
+impl<'a> InvalidMask<'a> {
+    /// How many keys there are, valid or not.
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 32
+    }
+
+    /// Whether there are no keys at all.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// How many keys were invalid.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Whether the key at `index` was invalid.  Panics if there is no such
+    /// key.
+    pub fn is_invalid(&self, index: usize) -> bool {
+        *key_at(self.bytes, index) == INVALID_SENTINEL
+    }
+
+    /// Bits 64i to 64i + 63 of the mask, bit j set if key 64i + j was
+    /// invalid.  Bits past the last key are clear.
+    pub fn word(&self, i: usize) -> u64 {
+        (64 * i..self.len().min(64 * (i + 1)))
+            .fold(0u64, |word, index| word | (self.is_invalid(index) as u64) << (index % 64))
+    }
+
+    /// The whole mask, a word at a time.
+    pub fn words(&self) -> impl Iterator<Item = u64> + 'a {
+        let mask: InvalidMask<'a> = *self;
+
+        (0..self.len().div_ceil(64)).map(move |i| mask.word(i))
+    }
+
+    /// The index of every invalid key, in order.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + 'a {
+        let mask: InvalidMask<'a> = *self;
+
+        (0..self.len()).filter(move |&index| mask.is_invalid(index))
+    }
+}
+
+/// Check every key in `bytes` under `policy`, overwriting each which fails
+/// with `INVALID_SENTINEL`.  Fails, leaving `bytes` alone, unless its length
+/// is a multiple of 32.
+pub fn validate_in_place(bytes: &mut [u8], policy: KeyPolicy) -> Result<InvalidMask<'_>, FormatError> {
+    match bytes.len() % 32 {
+        0 => (),
+        _ => return Err(FormatError::Malformed), // a truncated key at the end
+    }
+
+    let mut count: usize = 0;
+    for key in bytes.chunks_mut(32) {
+        let encoding: CompressedEdwardsY = CompressedEdwardsY(*key_at(key, 0));
+
+        if UncheckedKey::new(encoding).check(policy).is_none() {
+            key.copy_from_slice(&INVALID_SENTINEL);
+            count += 1;
+        }
+    }
+    Ok(InvalidMask { bytes, count })
+}
+
+/// One key read by a `KeyStreamReader`, and the verdict on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamedKey {
+    index: u64,
+    key: UncheckedKey<CompressedEdwardsY>,
+    checked: Option<CheckedKey<CompressedEdwardsY>>,
+}
+
+impl StreamedKey {
+    /// Where the key was in the stream, counting keys from zero.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The key as it was read.
+    pub fn key(&self) -> &UncheckedKey<CompressedEdwardsY> {
+        &self.key
+    }
+
+    /// The key, if it passed.
+    pub fn checked(&self) -> Option<&CheckedKey<CompressedEdwardsY>> {
+        self.checked.as_ref()
+    }
+
+    /// Whether it passed.
+    pub fn is_valid(&self) -> bool {
+        self.checked.is_some()
+    }
+}
+
+/// Reads concatenated keys from `R` through a fixed-size window, checking
+/// each under a `KeyPolicy`.  Yields an error, and then nothing more, if
+/// reading fails or the stream ends partway through a key.
+pub struct KeyStreamReader<R: Read> {
+    reader: R,
+    policy: KeyPolicy,
+    window: Vec<u8>,
+    start: usize,
+    end: usize,
+    index: u64,
+    eof: bool,
+}
+
+impl<R: Read> KeyStreamReader<R> {
+    /// Read keys from `reader`, 64 KiB at a time.
+    pub fn new(reader: R, policy: KeyPolicy) -> KeyStreamReader<R> {
+        KeyStreamReader::with_window(reader, policy, 65536)
+    }
+
+    /// Read keys from `reader` through a window of `window` bytes, rounded
+    /// down to whole keys.
+    ///
+    /// Panics if `window` is smaller than one key.
+    pub fn with_window(reader: R, policy: KeyPolicy, window: usize) -> KeyStreamReader<R> {
+        assert!(window >= 32);
+
+        KeyStreamReader { reader, policy, window: vec![0u8; window - window % 32],
+                          start: 0, end: 0, index: 0, eof: false }
+    }
+
+    // Move what's left of the window to its start, and read until it is
+    // full or the stream ends.
+    fn fill(&mut self) -> io::Result<()> {
+        self.window.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+
+        while self.end < self.window.len() {
+            match self.reader.read(&mut self.window[self.end..]) {
+                Ok(0)  => { self.eof = true; break; },
+                Ok(n)  => self.end += n,
+                Err(e) => match e.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    _                          => return Err(e),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for KeyStreamReader<R> {
+    type Item = io::Result<StreamedKey>;
+
+    fn next(&mut self) -> Option<io::Result<StreamedKey>> {
+        if self.end - self.start < 32 && !self.eof {
+            if let Err(e) = self.fill() {
+                self.eof = true;
+                self.start = self.end;
+                return Some(Err(e));
+            }
+        }
+        if self.end - self.start < 32 {
+            let leftover: usize = self.end - self.start;
+            self.start = self.end;
+
+            return match leftover {
+                0 => None,
+                _ => Some(Err(io::Error::new(io::ErrorKind::UnexpectedEof, "a truncated key at the end"))),
+            };
+        }
+
+        let key: UncheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(CompressedEdwardsY(*key_at(&self.window[self.start..], 0)));
+        let streamed: StreamedKey = StreamedKey { index: self.index, key, checked: key.check(self.policy) };
+
+        self.start += 32;
+        self.index += 1;
+        Some(Ok(streamed))
+    }
+}
+
+fn key_at(bytes: &[u8], index: usize) -> &[u8; 32] {
+    <&[u8; 32]>::try_from(&bytes[32 * index..32 * (index + 1)]).unwrap() // exactly 32 bytes
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn blob() -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        for i in 0..10u64 {
+            let p: ExtendedPoint = &Scalar::from_u64(i + 1) * &constants::ED25519_BASEPOINT;
+            let key: ExtendedPoint = match i {
+                3 => &p + &constants::EIGHT_TORSION[2],
+                7 => constants::EIGHT_TORSION[0],
+                _ => p,
+            };
+            bytes.extend_from_slice(key.compress_edwards().as_bytes());
+        }
+        bytes.extend_from_slice(&[0x02; 32]);
+        bytes
+    }
+
+    #[test]
+    fn invalid_keys_are_found_in_place() {
+        let bytes: Vec<u8> = blob();
+        let blob: KeyBlob = KeyBlob::new(&bytes).unwrap();
+        let mut seen: usize = 0;
+
+        assert_eq!(blob.len(), 11);
+        assert_eq!(blob.get(10), Some(&[0x02; 32]));
+        assert_eq!(blob.get(11), None);
+        assert_eq!(invalid_entries(&blob, KeyPolicy::Strict), vec![3, 7, 10]);
+        assert_eq!(invalid_entries(&blob, KeyPolicy::PrimeOrder), vec![3, 10]);
+        assert_eq!(for_each_invalid(&blob, KeyPolicy::Strict, |_| seen += 1), 3);
+        assert_eq!(seen, 3);
+    }
+
+    #[test]
+    fn blobs_must_hold_whole_keys() {
+        let bytes: Vec<u8> = blob();
+
+        assert_eq!(KeyBlob::new(&bytes[..33]).err(), Some(FormatError::Malformed));
+        assert!(KeyBlob::new(&[]).unwrap().is_empty());
+        assert_eq!(KeyBlob::new(&bytes[..64]).unwrap().iter().count(), 2);
+    }
+
+    #[test]
+    fn invalid_keys_are_overwritten_in_place() {
+        let mut bytes: Vec<u8> = blob();
+        let original: Vec<u8> = bytes.clone();
+        let mask: InvalidMask = validate_in_place(&mut bytes, KeyPolicy::Strict).unwrap();
+
+        assert_eq!(mask.len(), 11);
+        assert_eq!(mask.count(), 3);
+        assert_eq!(mask.indices().collect::<Vec<usize>>(), vec![3, 7, 10]);
+        assert_eq!(mask.words().collect::<Vec<u64>>(), vec![1 << 3 | 1 << 7 | 1 << 10]);
+        assert!(mask.is_invalid(7) && !mask.is_invalid(8));
+
+        let invalid: Vec<bool> = (0..11).map(|index| mask.is_invalid(index)).collect();
+        for (index, &invalid) in invalid.iter().enumerate() {
+            match invalid {
+                true  => assert_eq!(key_at(&bytes, index), &INVALID_SENTINEL),
+                false => assert_eq!(key_at(&bytes, index), key_at(&original, index)),
+            }
+        }
+
+        // Marking is idempotent, since the sentinel is itself invalid.
+        let marked: Vec<u8> = bytes.clone();
+        assert_eq!(validate_in_place(&mut bytes, KeyPolicy::PrimeOrder).unwrap().count(), 3);
+        assert_eq!(bytes, marked);
+
+        let mut truncated: Vec<u8> = original[..33].to_vec();
+        assert_eq!(validate_in_place(&mut truncated, KeyPolicy::Strict).err(), Some(FormatError::Malformed));
+        assert_eq!(&truncated[..], &original[..33]);
+    }
+
+    #[test]
+    fn masks_span_many_words() {
+        let mut bytes: Vec<u8> = Vec::new();
+        for i in 0..130u64 {
+            let key: ExtendedPoint = match i % 64 {
+                0 => constants::EIGHT_TORSION[1],
+                _ => &Scalar::from_u64(i + 1) * &constants::ED25519_BASEPOINT,
+            };
+            bytes.extend_from_slice(key.compress_edwards().as_bytes());
+        }
+        let mask: InvalidMask = validate_in_place(&mut bytes, KeyPolicy::Strict).unwrap();
+
+        assert_eq!(mask.words().collect::<Vec<u64>>(), vec![1, 1, 1]);
+        assert_eq!(mask.word(3), 0);
+        assert!(validate_in_place(&mut [], KeyPolicy::Strict).unwrap().is_empty());
+    }
+
+    // Hands out at most `step` bytes a read, and is interrupted once.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        step: usize,
+        interrupted: bool,
+    }
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.interrupted {
+                self.interrupted = true;
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+            }
+
+            let n: usize = self.step.min(buf.len()).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn streams_are_read_a_window_at_a_time() {
+        let bytes: Vec<u8> = blob();
+        let reader: Trickle = Trickle { bytes: &bytes, step: 5, interrupted: false };
+        let results: Vec<StreamedKey> = KeyStreamReader::with_window(reader, KeyPolicy::Strict, 70)
+            .map(|result| result.unwrap())
+            .collect();
+
+        assert_eq!(results.len(), 11);
+        assert!(results.iter().enumerate().all(|(i, result)| result.index() == i as u64));
+        assert_eq!(results.iter().filter(|result| !result.is_valid()).map(|result| result.index()).collect::<Vec<u64>>(),
+                   vec![3, 7, 10]);
+        assert_eq!(results[10].key(), &UncheckedKey::new(CompressedEdwardsY([0x02; 32])));
+        assert_eq!(KeyStreamReader::new(&bytes[..], KeyPolicy::PrimeOrder).filter(|r| !r.as_ref().unwrap().is_valid()).count(), 2);
+    }
+
+    #[test]
+    fn truncated_streams_end_in_an_error() {
+        let bytes: Vec<u8> = blob();
+        let mut reader: KeyStreamReader<&[u8]> = KeyStreamReader::with_window(&bytes[..80], KeyPolicy::Strict, 32);
+
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert_eq!(reader.next().unwrap().err().map(|e| e.kind()), Some(io::ErrorKind::UnexpectedEof));
+        assert!(reader.next().is_none());
+        assert!(KeyStreamReader::new(&[][..], KeyPolicy::Strict).next().is_none());
+    }
+}
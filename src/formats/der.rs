@@ -0,0 +1,115 @@
+// Just enough DER to walk the structures which carry Ed25519 keys.
+//
+// Only definite lengths in their minimal encoding are accepted, since DER
+// allows nothing else, and anything unexpected is reported as `Malformed`.
+
+use formats::FormatError;
+
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+
+/// The DER content octets of the id-Ed25519 OID, 1.3.101.112.
+pub static ID_ED25519: [u8; 3] = [0x2b, 0x65, 0x70];
+
+/// A cursor over a run of DER elements.
+pub struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub fn new(input: &'a [u8]) -> Reader<'a> {
+        Reader(input)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The tag of the next element, if there is one.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.0.first().cloned()
+    }
+
+    /// Read the next element, which must have tag `tag`, and return its
+    /// contents.
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8], FormatError> {
+        let (found, contents) = self.read_any()?;
+
+        match found == tag {
+            true  => Ok(contents),
+            false => Err(FormatError::Malformed),
+        }
+    }
+
+    /// Read the next element, whatever its tag.
+    pub fn read_any(&mut self) -> Result<(u8, &'a [u8]), FormatError> {
+        let input: &'a [u8] = self.0;
+
+        if input.len() < 2 {
+            return Err(FormatError::Malformed);
+        }
+
+        let (len, header): (usize, usize) = match input[1] {
+            n if n < 0x80 => (n as usize, 2),
+            0x81 if input.len() >= 3 && input[2] >= 0x80 => (input[2] as usize, 3),
+            0x82 if input.len() >= 4 && input[2] != 0 => {
+                ((input[2] as usize) << 8 | input[3] as usize, 4)
+            },
+            _ => return Err(FormatError::Malformed), // indefinite, overlong, or huge
+        };
+
+        if input.len() - header < len {
+            return Err(FormatError::Malformed);
+        }
+
+        self.0 = &input[header + len..];
+        Ok((input[0], &input[header..header + len]))
+    }
+
+    /// Read the next element, which must have tag `tag`, and return all of
+    /// its encoding rather than just its contents.
+    pub fn read_raw(&mut self, tag: u8) -> Result<&'a [u8], FormatError> {
+        let input: &'a [u8] = self.0;
+        self.read(tag)?;
+
+        Ok(&input[..input.len() - self.0.len()])
+    }
+
+    /// Read a SEQUENCE and return a reader over its elements.
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>, FormatError> {
+        Ok(Reader(self.read(SEQUENCE)?))
+    }
+
+    /// Require that nothing is left.
+    pub fn finish(&self) -> Result<(), FormatError> {
+        match self.is_empty() {
+            true  => Ok(()),
+            false => Err(FormatError::Malformed),
+        }
+    }
+}
+
+/// Read an RFC 8410 AlgorithmIdentifier, and check that it names Ed25519
+/// with the parameters absent.
+pub fn read_ed25519_algorithm(reader: &mut Reader) -> Result<(), FormatError> {
+    let mut algorithm: Reader = reader.read_sequence()?;
+    let oid: &[u8] = algorithm.read(OBJECT_IDENTIFIER)?;
+
+    if oid != &ID_ED25519[..] {
+        return Err(FormatError::UnsupportedAlgorithm);
+    }
+    algorithm.finish()
+}
+
+/// Take a 32-byte key out of the contents of a BIT STRING with no unused
+/// bits.
+pub fn key_from_bit_string(contents: &[u8]) -> Result<[u8; 32], FormatError> {
+    if contents.len() != 33 || contents[0] != 0 {
+        return Err(FormatError::Malformed);
+    }
+
+    let mut key: [u8; 32] = [0u8; 32];
+    key.copy_from_slice(&contents[1..]);
+    Ok(key)
+}
@@ -0,0 +1,110 @@
+// Bulk scanning of OpenSSH `known_hosts` files.
+//
+// Each line is an optional `@cert-authority` or `@revoked` marker, a list of
+// host patterns (possibly hashed, as `|1|salt|hash`), the key type, and the
+// base64 wire blob, which may be followed by a comment.  Only `ssh-ed25519`
+// entries are looked at; everything else is skipped.
+
+use encoding::base64_decode;
+use formats::openssh::parse_wire_blob;
+use key::KeyProblem;
+
+/// One `ssh-ed25519` entry from a `known_hosts` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostKeyReport {
+    line: usize,
+    hosts: String,
+    problem: Option<KeyProblem>,
+}
+
+impl HostKeyReport {
+    /// The line the entry was found on, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The host patterns, exactly as written in the file.
+    pub fn hosts(&self) -> &str {
+        &self.hosts
+    }
+
+    /// What is wrong with the entry's key, if anything.
+    pub fn problem(&self) -> Option<KeyProblem> {
+        self.problem
+    }
+}
+
+/// Report on every `ssh-ed25519` entry in the contents of a `known_hosts`
+/// file.
+pub fn scan_known_hosts(text: &str) -> Vec<HostKeyReport> {
+    let mut reports: Vec<HostKeyReport> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let mut fields = line.split_whitespace().skip_while(|field| field.starts_with('@'));
+
+        let hosts: &str = match fields.next() {
+            Some(hosts) if !hosts.starts_with('#') => hosts,
+            _ => continue, // a blank line or a comment
+        };
+        if fields.next() != Some("ssh-ed25519") {
+            continue;
+        }
+
+        let problem: Option<KeyProblem> = match fields.next().and_then(base64_decode) {
+            Some(blob) => match parse_wire_blob(&blob) {
+                Ok(key) => key.diagnose(),
+                Err(_)  => Some(KeyProblem::Malformed),
+            },
+            None => Some(KeyProblem::Malformed),
+        };
+
+        reports.push(HostKeyReport { line: number + 1, hosts: hosts.to_string(), problem });
+    }
+    reports
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    use encoding::base64_encode;
+
+    fn entry(hosts: &str, key: &[u8; 32]) -> String {
+        let mut blob: Vec<u8> = vec![0, 0, 0, 11];
+        blob.extend_from_slice(b"ssh-ed25519");
+        blob.extend_from_slice(&[0, 0, 0, 32]);
+        blob.extend_from_slice(key);
+
+        format!("{} ssh-ed25519 {}\n", hosts, base64_encode(&blob, true))
+    }
+
+    #[test]
+    fn problems_are_reported_per_host() {
+        let good: ExtendedPoint = constants::ED25519_BASEPOINT;
+        let torsioned: ExtendedPoint = &good + &constants::EIGHT_TORSION[1];
+
+        // y = p + 1, a non-canonical encoding of the identity.
+        let mut non_canonical: [u8; 32] = [0xff; 32];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+
+        let mut text: String = String::from("# a comment\n\n");
+        text.push_str(&entry("good.example,192.0.2.1", good.compress_edwards().as_bytes()));
+        text.push_str("rsa.example ssh-rsa AAAAB3NzaC1yc2E=\n");
+        text.push_str(&entry("@cert-authority *.example", torsioned.compress_edwards().as_bytes()));
+        text.push_str(&entry("|1|c2FsdA==|aGFzaA==", &non_canonical));
+        text.push_str("broken.example ssh-ed25519 !!!\n");
+
+        let reports: Vec<HostKeyReport> = scan_known_hosts(&text);
+        let found: Vec<(usize, &str, Option<KeyProblem>)> =
+            reports.iter().map(|r| (r.line(), r.hosts(), r.problem())).collect();
+
+        assert_eq!(found, vec![(3, "good.example,192.0.2.1", None),
+                               (5, "*.example", Some(KeyProblem::Torsioned)),
+                               (6, "|1|c2FsdA==|aGFzaA==", Some(KeyProblem::NonCanonical)),
+                               (7, "broken.example", Some(KeyProblem::Malformed))]);
+    }
+}
@@ -0,0 +1,139 @@
+// minisign and signify public keys, which share a format:
+//
+//    untrusted comment: minisign public key E7620F1842B4E81F
+//    RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3
+//
+// The base64 line is the algorithm, "Ed", then an 8-byte key ID, then the
+// 32-byte key.  minisign shows the key ID as the hex of that little-endian
+// number, which is how it appears in the comment above.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use encoding::base64_decode;
+use formats::FormatError;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+static ALGORITHM: &[u8] = b"Ed";
+static COMMENT: &str = "untrusted comment:";
+
+/// A public key from a minisign or signify key file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PublicKeyFile {
+    key_id: u64,
+    key: UncheckedKey<CompressedEdwardsY>,
+}
+
+impl PublicKeyFile {
+    /// The key ID, which signatures name to say which key made them.
+    pub fn key_id(&self) -> u64 {
+        self.key_id
+    }
+
+    /// The key itself.
+    pub fn key(&self) -> &UncheckedKey<CompressedEdwardsY> {
+        &self.key
+    }
+}
+
+/// Extract the key from a public key file, or from its base64 line alone as
+/// given to `minisign -P`.
+pub fn parse_public_key(text: &str) -> Result<PublicKeyFile, FormatError> {
+    let mut lines = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty());
+
+    let encoded: &str = match lines.next() {
+        Some(line) if line.starts_with(COMMENT) => match lines.next() {
+            Some(line) => line,
+            None       => return Err(FormatError::Malformed),
+        },
+        Some(line) => line,
+        None       => return Err(FormatError::Malformed),
+    };
+    if lines.next().is_some() {
+        return Err(FormatError::Malformed);
+    }
+
+    let blob: Vec<u8> = match base64_decode(encoded) {
+        Some(blob) => blob,
+        None       => return Err(FormatError::Malformed),
+    };
+    if blob.len() != 42 {
+        return Err(FormatError::Malformed);
+    }
+    if &blob[..2] != ALGORITHM {
+        return Err(FormatError::UnsupportedAlgorithm);
+    }
+
+    let key_id: u64 = blob[2..10].iter().rev().fold(0u64, |id, &byte| id << 8 | byte as u64);
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(&blob[10..]);
+
+    Ok(PublicKeyFile { key_id, key: UncheckedKey::new(CompressedEdwardsY(bytes)) })
+}
+
+/// Parse a public key file as for `parse_public_key`, and check the key
+/// under `policy`.
+pub fn check_public_key(text: &str, policy: KeyPolicy)
+    -> Result<CheckedKey<CompressedEdwardsY>, FormatError>
+{
+    match parse_public_key(text)?.key().check(policy) {
+        Some(key) => Ok(key),
+        None      => Err(FormatError::InvalidKey),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    use encoding::base64_encode;
+
+    // The example key from the minisign documentation.
+    static PUBLIC_KEY_FILE: &str = "\
+untrusted comment: minisign public key E7620F1842B4E81F
+RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3
+";
+
+    #[test]
+    fn documented_key_is_accepted() {
+        let file: PublicKeyFile = parse_public_key(PUBLIC_KEY_FILE).unwrap();
+
+        assert_eq!(format!("{:016X}", file.key_id()), "E7620F1842B4E81F");
+        assert_eq!(parse_public_key(PUBLIC_KEY_FILE.lines().nth(1).unwrap()).unwrap(), file);
+        assert!(check_public_key(PUBLIC_KEY_FILE, KeyPolicy::Strict).is_ok());
+    }
+
+    #[test]
+    fn torsioned_keys_are_found() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[5];
+        let mut blob: Vec<u8> = b"Ed".to_vec();
+        blob.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        blob.extend_from_slice(p.compress_edwards().as_bytes());
+
+        let text: String = format!("untrusted comment: signify public key\n{}\n",
+                                   base64_encode(&blob, true));
+
+        assert_eq!(parse_public_key(&text).unwrap().key_id(), 0x0807060504030201);
+        assert_eq!(check_public_key(&text, KeyPolicy::PrimeOrder).unwrap_err(),
+                   FormatError::InvalidKey);
+    }
+
+    #[test]
+    fn framing_errors() {
+        let mut blob: Vec<u8> = b"XX".to_vec();
+        blob.extend_from_slice(&[0u8; 40]);
+
+        assert_eq!(parse_public_key(&base64_encode(&blob, true)).unwrap_err(),
+                   FormatError::UnsupportedAlgorithm);
+        assert_eq!(parse_public_key(&base64_encode(&blob[..41], true)).unwrap_err(),
+                   FormatError::Malformed);
+        assert_eq!(parse_public_key("untrusted comment: nothing else\n").unwrap_err(),
+                   FormatError::Malformed);
+        assert_eq!(parse_public_key(&format!("{}\ntrailing", PUBLIC_KEY_FILE)).unwrap_err(),
+                   FormatError::Malformed);
+    }
+}
@@ -0,0 +1,30 @@
+// Parsers for the file and wire formats ed25519 public keys are distributed
+// in, each of which hands back a key ready for the validators.
+//
+// The parsers only undo the framing.  What comes out is an `UncheckedKey`, so
+// that audit tools can still look at exactly what was found, and each module
+// also has a helper which checks the key under a `KeyPolicy` in the same step.
+
+pub mod bridge_line;
+pub(crate) mod cert;
+pub mod concatenated;
+pub(crate) mod der;
+pub mod known_hosts;
+pub mod minisign;
+pub mod openpgp;
+pub mod openssh;
+pub(crate) mod pem;
+pub mod pkcs8;
+pub mod tor_keys;
+pub mod x509;
+
+/// Why a key couldn't be taken from its container.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The input was not in the expected format.
+    Malformed,
+    /// The input was well-formed, but held a key for some other algorithm.
+    UnsupportedAlgorithm,
+    /// The key was found, but failed validation.
+    InvalidKey,
+}
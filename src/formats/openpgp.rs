@@ -0,0 +1,205 @@
+// OpenPGP v4 EdDSA public keys, as found in keyring files and keyserver dumps.
+//
+// A public key (tag 6) or public subkey (tag 14) packet body is
+//
+//    version (4) || creation time (4 bytes) || algorithm || key material
+//
+// For algorithm 22 (EdDSALegacy, RFC 9580 §5.5.5.5) the key material is the
+// curve OID, prefixed by its length, and then an MPI whose value is 0x40
+// followed by the 32-byte key.  For algorithm 27 (Ed25519, RFC 9580
+// §5.5.5.9) it is simply the 32-byte key.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use formats::FormatError;
+use key::UncheckedKey;
+
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+
+const ALGORITHM_EDDSA_LEGACY: u8 = 22;
+const ALGORITHM_ED25519: u8 = 27;
+
+/// The OID 1.3.6.1.4.1.11591.15.1, which names Ed25519 for EdDSALegacy.
+static OID_ED25519: [u8; 9] = [0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+
+/// Extract the key from a v4 public key or subkey packet body.
+pub fn parse_key_packet_body(body: &[u8]) -> Result<UncheckedKey<CompressedEdwardsY>, FormatError> {
+    if body.len() < 6 || body[0] != 4 {
+        return Err(FormatError::Malformed);
+    }
+
+    let key: &[u8] = match body[5] {
+        ALGORITHM_EDDSA_LEGACY => {
+            let material: &[u8] = &body[6..];
+
+            if material.is_empty() || material.len() < 1 + material[0] as usize {
+                return Err(FormatError::Malformed);
+            }
+            let (oid, mpi) = material[1..].split_at(material[0] as usize);
+
+            if oid != &OID_ED25519[..] {
+                return Err(FormatError::UnsupportedAlgorithm); // some other curve
+            }
+            // An MPI of 263 bits: the 0x40 prefix has its top set bit at bit 6.
+            if mpi.len() != 35 || mpi[0] != 0x01 || mpi[1] != 0x07 || mpi[2] != 0x40 {
+                return Err(FormatError::Malformed);
+            }
+            &mpi[3..]
+        },
+        ALGORITHM_ED25519 => &body[6..],
+        _ => return Err(FormatError::UnsupportedAlgorithm),
+    };
+
+    if key.len() != 32 {
+        return Err(FormatError::Malformed);
+    }
+
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(key);
+
+    Ok(UncheckedKey::new(CompressedEdwardsY(bytes)))
+}
+
+/// Walk a stream of binary OpenPGP packets and extract every Ed25519 public
+/// key and subkey in it.
+///
+/// Key packets for other algorithms, and packets which aren't keys at all,
+/// are skipped.  If the packet framing itself is broken, the last entry is
+/// `Err(FormatError::Malformed)` and nothing after that point is read.
+pub fn scan_keys(mut stream: &[u8]) -> Vec<Result<UncheckedKey<CompressedEdwardsY>, FormatError>> {
+    let mut keys: Vec<Result<UncheckedKey<CompressedEdwardsY>, FormatError>> = Vec::new();
+
+    while !stream.is_empty() {
+        let (tag, body, rest) = match read_packet(stream) {
+            Ok(packet) => packet,
+            Err(e)     => { keys.push(Err(e)); break; },
+        };
+        stream = rest;
+
+        if tag != TAG_PUBLIC_KEY && tag != TAG_PUBLIC_SUBKEY {
+            continue;
+        }
+        match parse_key_packet_body(body) {
+            Err(FormatError::UnsupportedAlgorithm) => continue,
+            result => keys.push(result),
+        }
+    }
+    keys
+}
+
+// Split one packet off the front of `input`, returning its tag, its body, and
+// whatever follows it.
+fn read_packet(input: &[u8]) -> Result<(u8, &[u8], &[u8]), FormatError> {
+    if input.is_empty() || input[0] & 0x80 == 0 {
+        return Err(FormatError::Malformed);
+    }
+
+    let (tag, len, header): (u8, usize, usize) = match input[0] & 0x40 != 0 {
+        true => {
+            // A new-format header.  Partial body lengths never appear on key
+            // packets, so they're rejected along with truncated headers.
+            let tag: u8 = input[0] & 0x3f;
+
+            match input.get(1).cloned() {
+                Some(n) if n < 192 => (tag, n as usize, 2),
+                Some(n) if n < 224 && input.len() >= 3 => {
+                    (tag, ((n as usize - 192) << 8) + input[2] as usize + 192, 3)
+                },
+                Some(255) if input.len() >= 6 => (tag, read_u32(&input[2..6]), 6),
+                _ => return Err(FormatError::Malformed),
+            }
+        },
+        false => {
+            // An old-format header, with the length's size in the low bits.
+            let tag: u8 = (input[0] >> 2) & 0x0f;
+
+            match input[0] & 0x03 {
+                0 if input.len() >= 2 => (tag, input[1] as usize, 2),
+                1 if input.len() >= 3 => (tag, (input[1] as usize) << 8 | input[2] as usize, 3),
+                2 if input.len() >= 5 => (tag, read_u32(&input[1..5]), 5),
+                _ => return Err(FormatError::Malformed), // truncated or indeterminate
+            }
+        },
+    };
+
+    match input.len() - header >= len {
+        true  => Ok((tag, &input[header..header + len], &input[header + len..])),
+        false => Err(FormatError::Malformed),
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> usize {
+    (bytes[0] as usize) << 24 | (bytes[1] as usize) << 16 | (bytes[2] as usize) << 8 | bytes[3] as usize
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    use policy::KeyPolicy;
+
+    fn legacy_body_for(key: &[u8; 32]) -> Vec<u8> {
+        let mut body: Vec<u8> = vec![4, 0x5b, 0x00, 0x00, 0x00, ALGORITHM_EDDSA_LEGACY, 9];
+        body.extend_from_slice(&OID_ED25519);
+        body.extend_from_slice(&[0x01, 0x07, 0x40]);
+        body.extend_from_slice(key);
+        body
+    }
+
+    // An old-format packet with a one-byte length.
+    fn packet(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut packet: Vec<u8> = vec![0x80 | tag << 2, body.len() as u8];
+        packet.extend_from_slice(body);
+        packet
+    }
+
+    #[test]
+    fn both_encodings_are_parsed() {
+        let key: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let mut native: Vec<u8> = vec![4, 0x5b, 0x00, 0x00, 0x00, ALGORITHM_ED25519];
+        native.extend_from_slice(key.as_bytes());
+
+        assert_eq!(parse_key_packet_body(&legacy_body_for(key.as_bytes())).unwrap(),
+                   UncheckedKey::new(key));
+        assert_eq!(parse_key_packet_body(&native).unwrap(), UncheckedKey::new(key));
+    }
+
+    #[test]
+    fn scan_finds_torsioned_subkeys() {
+        let good: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[3];
+        let bad: CompressedEdwardsY = p.compress_edwards();
+
+        let mut rsa: Vec<u8> = vec![4, 0, 0, 0, 0, 1];
+        rsa.extend_from_slice(&[0u8; 20]);
+
+        // A new-format user ID packet, to be skipped.
+        let mut stream: Vec<u8> = packet(TAG_PUBLIC_KEY, &legacy_body_for(good.as_bytes()));
+        stream.extend_from_slice(&[0xcd, 3, b'b', b'o', b'b']);
+        stream.extend_from_slice(&packet(TAG_PUBLIC_SUBKEY, &rsa));
+        stream.extend_from_slice(&packet(TAG_PUBLIC_SUBKEY, &legacy_body_for(bad.as_bytes())));
+
+        let keys: Vec<Result<UncheckedKey<CompressedEdwardsY>, FormatError>> = scan_keys(&stream);
+
+        assert_eq!(keys.len(), 2);
+        assert!(keys[0].unwrap().check(KeyPolicy::PrimeOrder).is_some());
+        assert!(keys[1].unwrap().check(KeyPolicy::PrimeOrder).is_none());
+    }
+
+    #[test]
+    fn framing_errors() {
+        let body: Vec<u8> = legacy_body_for(&[0u8; 32]);
+        let stream: Vec<u8> = packet(TAG_PUBLIC_KEY, &body);
+
+        assert_eq!(scan_keys(&stream[..40]), vec![Err(FormatError::Malformed)]);
+        assert_eq!(parse_key_packet_body(&body[..40]).unwrap_err(), FormatError::Malformed);
+
+        let mut nist: Vec<u8> = body.clone();
+        nist[7] = 0x2a; // the start of a NIST curve OID
+        assert_eq!(parse_key_packet_body(&nist).unwrap_err(), FormatError::UnsupportedAlgorithm);
+    }
+}
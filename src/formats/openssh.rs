@@ -0,0 +1,189 @@
+// OpenSSH ed25519 public keys, as found in `authorized_keys` and `.pub` files:
+//
+//    [options] ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI... [comment]
+//
+// The base64 part is the wire blob from RFC 8709 §4, which is the key type
+// and then the 32-byte key, each as an SSH string (a big-endian u32 length
+// followed by that many bytes).
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use encoding::base64_decode;
+use formats::FormatError;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+static KEY_TYPE: &str = "ssh-ed25519";
+
+/// Extract the key from an OpenSSH wire blob.
+pub fn parse_wire_blob(blob: &[u8]) -> Result<UncheckedKey<CompressedEdwardsY>, FormatError> {
+    let (key_type, rest) = read_string(blob)?;
+    let (key, rest) = read_string(rest)?;
+
+    if key_type != KEY_TYPE.as_bytes() {
+        return Err(FormatError::UnsupportedAlgorithm);
+    }
+    if key.len() != 32 || !rest.is_empty() {
+        return Err(FormatError::Malformed);
+    }
+
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(key);
+
+    Ok(UncheckedKey::new(CompressedEdwardsY(bytes)))
+}
+
+/// Extract the key from one line of an `authorized_keys` or `.pub` file.
+///
+/// Any options before the key type and any comment after the key are
+/// skipped.  Lines for other key types give `UnsupportedAlgorithm`.
+pub fn parse_public_key_line(line: &str) -> Result<UncheckedKey<CompressedEdwardsY>, FormatError> {
+    let line: &str = line.trim_start();
+
+    // As in OpenSSH, a line whose first field isn't a key type starts with
+    // options, and the key type has to come straight after them.
+    let rest: &str = match line.split_whitespace().next() {
+        Some(field) if is_key_type(field) => line,
+        _                                 => skip_options(line)?,
+    };
+    let mut fields = rest.split_whitespace();
+
+    match fields.next() {
+        Some(field) if field == KEY_TYPE  => (),
+        Some(field) if is_key_type(field) => return Err(FormatError::UnsupportedAlgorithm),
+        _                                 => return Err(FormatError::Malformed),
+    }
+
+    let blob: Vec<u8> = match fields.next().and_then(base64_decode) {
+        Some(blob) => blob,
+        None       => return Err(FormatError::Malformed),
+    };
+
+    parse_wire_blob(&blob)
+}
+
+/// Parse one line as for `parse_public_key_line`, and check the key under
+/// `policy`.
+pub fn check_public_key_line(line: &str, policy: KeyPolicy)
+    -> Result<CheckedKey<CompressedEdwardsY>, FormatError>
+{
+    match parse_public_key_line(line)?.check(policy) {
+        Some(key) => Ok(key),
+        None      => Err(FormatError::InvalidKey),
+    }
+}
+
+fn is_key_type(field: &str) -> bool {
+    field.starts_with("ssh-") || field.starts_with("ecdsa-")
+}
+
+// What follows the options at the start of `line`, which run to the first
+// whitespace outside double quotes, where a quote after a backslash doesn't
+// count, as OpenSSH's `authorized_keys` parser reads them.
+fn skip_options(line: &str) -> Result<&str, FormatError> {
+    let mut quoted: bool = false;
+    let mut escaped: bool = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped                      => escaped = false,
+            '\\' if quoted                    => escaped = true,
+            '"'                               => quoted = !quoted,
+            _ if c.is_whitespace() && !quoted => return Ok(&line[i..]),
+            _                                 => (),
+        }
+    }
+    Err(FormatError::Malformed) // nothing after the options, or an unterminated quote
+}
+
+// Split an SSH string off the front of `input`.
+fn read_string(input: &[u8]) -> Result<(&[u8], &[u8]), FormatError> {
+    if input.len() < 4 {
+        return Err(FormatError::Malformed);
+    }
+
+    let len: usize = (input[0] as usize) << 24 | (input[1] as usize) << 16 |
+                     (input[2] as usize) << 8 | input[3] as usize;
+
+    match input.len() - 4 >= len {
+        true  => Ok((&input[4..4 + len], &input[4 + len..])),
+        false => Err(FormatError::Malformed),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    use encoding::base64_encode;
+
+    fn blob_for(key: &[u8; 32]) -> Vec<u8> {
+        let mut blob: Vec<u8> = vec![0, 0, 0, 11];
+        blob.extend_from_slice(KEY_TYPE.as_bytes());
+        blob.extend_from_slice(&[0, 0, 0, 32]);
+        blob.extend_from_slice(key);
+        blob
+    }
+
+    fn line_for(key: &[u8; 32]) -> String {
+        format!("ssh-ed25519 {} user@host", base64_encode(&blob_for(key), true))
+    }
+
+    #[test]
+    fn blob_prefix_is_the_familiar_one() {
+        assert!(line_for(&[0u8; 32]).starts_with("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI"));
+    }
+
+    #[test]
+    fn valid_keys_are_accepted() {
+        let key: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let line: String = line_for(key.as_bytes());
+
+        assert_eq!(check_public_key_line(&line, KeyPolicy::Strict).unwrap().encoding(), &key);
+        assert!(check_public_key_line(&format!("no-pty,command=\"a b\" {}", line),
+                                      KeyPolicy::Strict).is_ok());
+    }
+
+    #[test]
+    fn torsioned_keys_are_found() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+        let line: String = line_for(p.compress_edwards().as_bytes());
+
+        assert!(parse_public_key_line(&line).is_ok());
+        assert_eq!(check_public_key_line(&line, KeyPolicy::PrimeOrder).unwrap_err(),
+                   FormatError::InvalidKey);
+    }
+
+    #[test]
+    fn framing_errors() {
+        let mut blob: Vec<u8> = blob_for(&[0u8; 32]);
+        blob.push(0);
+
+        assert_eq!(parse_wire_blob(&blob).unwrap_err(), FormatError::Malformed);
+        assert_eq!(parse_wire_blob(&blob[..20]).unwrap_err(), FormatError::Malformed);
+        assert_eq!(parse_public_key_line("ssh-rsa AAAAB3NzaC1yc2E= x").unwrap_err(),
+                   FormatError::UnsupportedAlgorithm);
+        assert_eq!(parse_public_key_line("ssh-ed25519 !!!").unwrap_err(), FormatError::Malformed);
+        assert_eq!(parse_public_key_line("").unwrap_err(), FormatError::Malformed);
+    }
+
+    #[test]
+    fn key_types_inside_options_and_comments_are_ignored() {
+        let key: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let line: String = line_for(key.as_bytes());
+
+        for options in ["command=\"x ssh-ed25519 y\"", "command=\"echo \\\"ssh-ed25519 z\\\"\",no-pty"].iter() {
+            let with_options: String = format!("{} {}", options, line);
+
+            assert_eq!(parse_public_key_line(&with_options).unwrap().encoding(), &key, "{}", with_options);
+        }
+        assert_eq!(parse_public_key_line(&format!("ssh-rsa AAAAB3NzaC1yc2E= {}", line)).unwrap_err(),
+                   FormatError::UnsupportedAlgorithm);
+        assert_eq!(parse_public_key_line(&format!("command=\"x {}", line)).unwrap_err(), FormatError::Malformed);
+        assert_eq!(parse_public_key_line(&format!("no-pty comment {}", line)).unwrap_err(), FormatError::Malformed);
+    }
+}
@@ -0,0 +1,40 @@
+// PEM armour, per RFC 7468: a `-----BEGIN label-----` line, base64 over any
+// number of lines, and a matching `-----END label-----` line.
+
+use encoding::base64_decode;
+use formats::FormatError;
+
+/// Find the first block labelled `label` in `text` and decode its body.
+pub fn decode(text: &str, label: &str) -> Result<Vec<u8>, FormatError> {
+    let begin: String = format!("-----BEGIN {}-----", label);
+    let end: String = format!("-----END {}-----", label);
+    let mut body: String = String::new();
+    let mut inside: bool = false;
+
+    for line in text.lines().map(|line| line.trim()) {
+        if !inside {
+            inside = line == begin;
+        } else if line == end {
+            return match base64_decode(&body) {
+                Some(der) => Ok(der),
+                None      => Err(FormatError::Malformed),
+            };
+        } else {
+            body.push_str(line);
+        }
+    }
+    Err(FormatError::Malformed) // no complete block
+}
+
+/// Decode the body of every block labelled `label` in `text`, in order.
+pub fn decode_all(text: &str, label: &str) -> Result<Vec<Vec<u8>>, FormatError> {
+    let begin: String = format!("-----BEGIN {}-----", label);
+    let mut blocks: Vec<Vec<u8>> = Vec::new();
+    let mut rest: &str = text;
+
+    while let Some(i) = rest.find(&begin) {
+        blocks.push(decode(&rest[i..], label)?);
+        rest = &rest[i + begin.len()..];
+    }
+    Ok(blocks)
+}
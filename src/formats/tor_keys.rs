@@ -0,0 +1,252 @@
+// The tagged key files Tor keeps in a relay's or onion service's `keys/`
+// directory, such as `ed25519_signing_secret_key`:
+//
+//    "== ed25519v1-secret: type0 =="   padded with NULs to 32 bytes
+//    a ‖ prefix                        the 64-byte expanded secret key
+//
+// and the matching `_public_key` files, whose header is
+// "== ed25519v1-public: type0 ==" and whose body is the 32-byte key.  Tor
+// stores the expanded key rather than a seed, so the first half is the
+// already clamped scalar a, and the public key is [a]B.  A file whose a isn't
+// clamped is one Tor never wrote, and is rejected as malformed.
+//
+// `check_keypair_consistency` is for auditing a secret and public key file
+// found side by side: it says which of the two is at fault when they don't
+// go together.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::ExpandedSecretKey;
+use formats::FormatError;
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+#[cfg(feature = "zeroize")]
+use wipe::wipe;
+
+static SECRET_TYPE: &[u8] = b"ed25519v1-secret";
+static PUBLIC_TYPE: &[u8] = b"ed25519v1-public";
+static TAG: &[u8] = b"type0";
+
+/// An expanded ed25519 secret key, as Tor stores it.
+#[derive(Clone)]
+pub struct TorSecretKey {
+    expanded: [u8; 64],
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for TorSecretKey {
+    fn drop(&mut self) {
+        wipe(&mut self.expanded);
+    }
+}
+
+impl TorSecretKey {
+    /// Expand a 32-byte RFC 8032 seed the way Tor does when it generates a
+    /// key, so that the result can be written out for Tor to use.
+    pub fn from_seed(seed: &[u8; 32]) -> TorSecretKey {
+        TorSecretKey { expanded: ExpandedSecretKey::from_seed(seed).to_bytes() }
+    }
+
+    /// The expanded key, a ‖ prefix.
+    pub fn expanded(&self) -> &[u8; 64] {
+        &self.expanded
+    }
+
+    fn key(&self) -> ExpandedSecretKey {
+        ExpandedSecretKey::from_bytes(&self.expanded).unwrap() // only clamped keys are kept
+    }
+
+    /// The public key [a]B which goes with this secret key.
+    pub fn public_key(&self) -> UncheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(self.key().public_key())
+    }
+}
+
+/// Why a secret and public key don't make a usable pair.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConsistencyError {
+    /// The secret key isn't clamped, or its public key fails validation.
+    CorruptSecret,
+    /// The secret key is sound, but the public key fails validation, for
+    /// the reason given.
+    InvalidPublic(KeyProblem),
+    /// Both keys are sound, but the public key isn't the secret key's.
+    MismatchedPair,
+}
+
+/// Check that `public` is the key derived from the expanded `secret`, and
+/// that both are sound, under `KeyPolicy::Strict` as Tor requires of its own
+/// keys.  The secret key is looked at first, since a corrupt one is the
+/// likelier explanation for a pair which doesn't match.
+pub fn check_keypair_consistency(secret: &[u8; 64], public: &UncheckedKey<CompressedEdwardsY>)
+    -> Result<(), ConsistencyError>
+{
+    let key: ExpandedSecretKey = match ExpandedSecretKey::from_bytes(secret) {
+        Some(key) => key,
+        None      => return Err(ConsistencyError::CorruptSecret), // a is not clamped
+    };
+    let derived: CheckedKey<CompressedEdwardsY> = match key.check_public_key(KeyPolicy::Strict) {
+        Some(key) => key,
+        None      => return Err(ConsistencyError::CorruptSecret), // a is a multiple of l
+    };
+    let claimed: CheckedKey<CompressedEdwardsY> = match public.check_or_diagnose(KeyPolicy::Strict) {
+        Ok(key)      => key,
+        Err(problem) => return Err(ConsistencyError::InvalidPublic(problem)),
+    };
+
+    match derived == claimed {
+        true  => Ok(()),
+        false => Err(ConsistencyError::MismatchedPair),
+    }
+}
+
+/// Parse the contents of an `ed25519_*_secret_key` file.
+pub fn parse_secret_key(contents: &[u8]) -> Result<TorSecretKey, FormatError> {
+    let body: &[u8] = read_tagged(contents, SECRET_TYPE, 64)?;
+
+    let mut expanded: [u8; 64] = [0u8; 64];
+    expanded.copy_from_slice(body);
+
+    match ExpandedSecretKey::from_bytes(&expanded) {
+        Some(_) => Ok(TorSecretKey { expanded }),
+        None    => Err(FormatError::Malformed), // Tor never writes an unclamped key
+    }
+}
+
+/// Parse the contents of an `ed25519_*_public_key` file.
+pub fn parse_public_key(contents: &[u8]) -> Result<UncheckedKey<CompressedEdwardsY>, FormatError> {
+    let body: &[u8] = read_tagged(contents, PUBLIC_TYPE, 32)?;
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(body);
+
+    Ok(UncheckedKey::new(CompressedEdwardsY(bytes)))
+}
+
+/// Parse a secret key file, and check the public key it derives under
+/// `policy`.
+pub fn check_secret_key(contents: &[u8], policy: KeyPolicy)
+    -> Result<CheckedKey<CompressedEdwardsY>, FormatError>
+{
+    match parse_secret_key(contents)?.public_key().check(policy) {
+        Some(key) => Ok(key),
+        None      => Err(FormatError::InvalidKey),
+    }
+}
+
+/// Parse a public key file, and check the key under `policy`.
+pub fn check_public_key(contents: &[u8], policy: KeyPolicy)
+    -> Result<CheckedKey<CompressedEdwardsY>, FormatError>
+{
+    match parse_public_key(contents)?.check(policy) {
+        Some(key) => Ok(key),
+        None      => Err(FormatError::InvalidKey),
+    }
+}
+
+/// The contents of a secret key file holding `key`, as Tor writes it.
+pub fn write_secret_key(key: &TorSecretKey) -> Vec<u8> {
+    write_tagged(SECRET_TYPE, key.expanded())
+}
+
+/// The contents of a public key file holding `key`, as Tor writes it.  Only
+/// checked keys are written, so that nothing this writes could be refused
+/// by the relay which reads it.
+pub fn write_public_key(key: &CheckedKey<CompressedEdwardsY>) -> Vec<u8> {
+    write_tagged(PUBLIC_TYPE, key.encoding().as_bytes())
+}
+
+// The 32-byte header Tor puts in front of the body of a key file.
+fn header(key_type: &[u8]) -> [u8; 32] {
+    let mut header: [u8; 32] = [0u8; 32];
+    let mut length: usize = 0;
+
+    for part in [&b"== "[..], key_type, b": ", TAG, b" =="].iter() {
+        header[length..length + part.len()].copy_from_slice(part);
+        length += part.len();
+    }
+    header
+}
+
+fn read_tagged<'a>(contents: &'a [u8], key_type: &[u8], length: usize) -> Result<&'a [u8], FormatError> {
+    if contents.len() != 32 + length {
+        return Err(FormatError::Malformed);
+    }
+    if contents[..32] == header(key_type) {
+        return Ok(&contents[32..]);
+    }
+
+    // Tell a header for some other type or tag apart from one which isn't
+    // a header at all.
+    match contents.starts_with(b"== ") && contents[..32].ends_with(&[0u8]) {
+        true  => Err(FormatError::UnsupportedAlgorithm),
+        false => Err(FormatError::Malformed),
+    }
+}
+
+fn write_tagged(key_type: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut contents: Vec<u8> = header(key_type).to_vec();
+    contents.extend_from_slice(body);
+    contents
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use ed25519::public_key_from_seed;
+
+    static SEED: [u8; 32] = [0x16; 32];
+
+    #[test]
+    fn headers_are_as_tor_writes_them() {
+        assert_eq!(&header(SECRET_TYPE)[..], &b"== ed25519v1-secret: type0 ==\0\0\0"[..]);
+        assert_eq!(&header(PUBLIC_TYPE)[..], &b"== ed25519v1-public: type0 ==\0\0\0"[..]);
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let secret: TorSecretKey = TorSecretKey::from_seed(&SEED);
+        let contents: Vec<u8> = write_secret_key(&secret);
+        let public: CheckedKey<CompressedEdwardsY> = check_secret_key(&contents, KeyPolicy::Strict).unwrap();
+
+        assert_eq!(contents.len(), 96);
+        assert_eq!(parse_secret_key(&contents).unwrap().expanded()[..], secret.expanded()[..]);
+        assert_eq!(public.encoding(), &public_key_from_seed(&SEED));
+        assert_eq!(check_public_key(&write_public_key(&public), KeyPolicy::Strict).unwrap(), public);
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let mut contents: Vec<u8> = write_secret_key(&TorSecretKey::from_seed(&SEED));
+
+        assert_eq!(parse_public_key(&contents).unwrap_err(), FormatError::Malformed);
+        assert_eq!(parse_secret_key(&contents[..95]).err(), Some(FormatError::Malformed));
+
+        contents[32] |= 1;
+        assert_eq!(parse_secret_key(&contents).err(), Some(FormatError::Malformed));
+
+        contents[32] &= 248;
+        contents[25] = b'1'; // type1
+        assert_eq!(parse_secret_key(&contents).err(), Some(FormatError::UnsupportedAlgorithm));
+    }
+
+    #[test]
+    fn pairs_are_checked() {
+        let secret: TorSecretKey = TorSecretKey::from_seed(&SEED);
+        let public: UncheckedKey<CompressedEdwardsY> = secret.public_key();
+        let other: UncheckedKey<CompressedEdwardsY> = TorSecretKey::from_seed(&[0x17; 32]).public_key();
+        let torsioned: UncheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new((public.check(KeyPolicy::Strict).unwrap().point() + &constants::EIGHT_TORSION[1]).compress_edwards());
+        let mut unclamped: [u8; 64] = *secret.expanded();
+        unclamped[31] |= 0x80;
+
+        assert_eq!(check_keypair_consistency(secret.expanded(), &public), Ok(()));
+        assert_eq!(check_keypair_consistency(secret.expanded(), &other), Err(ConsistencyError::MismatchedPair));
+        assert_eq!(check_keypair_consistency(secret.expanded(), &torsioned), Err(ConsistencyError::InvalidPublic(KeyProblem::Torsioned)));
+        assert_eq!(check_keypair_consistency(&unclamped, &public), Err(ConsistencyError::CorruptSecret));
+    }
+}
@@ -0,0 +1,166 @@
+// Ed25519 subject keys in X.509 certificates (RFC 5280, with the algorithm
+// identifiers of RFC 8410):
+//
+//    Certificate ::= SEQUENCE {
+//        tbsCertificate      TBSCertificate,
+//        signatureAlgorithm  AlgorithmIdentifier,
+//        signatureValue      BIT STRING }
+//
+// Only as much of the TBSCertificate is understood as is needed to reach the
+// SubjectPublicKeyInfo; names, validity and extensions are stepped over
+// without being interpreted, so this is no substitute for path validation.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519;
+use formats::der;
+use formats::der::Reader;
+use formats::pem;
+use formats::pkcs8;
+use formats::FormatError;
+use key::CheckedKey;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+/// The validated subject key of a certificate, together with the outcome of
+/// checking the certificate's own signature.
+pub struct CertificateKey {
+    key: CheckedKey<CompressedEdwardsY>,
+    signature_valid: Option<bool>,
+}
+
+impl CertificateKey {
+    /// The certificate's subject key.
+    pub fn key(&self) -> &CheckedKey<CompressedEdwardsY> {
+        &self.key
+    }
+
+    /// Whether the certificate's signature verified, or `None` if it was
+    /// made with something other than Ed25519.
+    pub fn signature_valid(&self) -> Option<bool> {
+        self.signature_valid
+    }
+}
+
+/// Take the subject key out of a DER certificate and check it under
+/// `policy`, then verify the certificate's signature under `profile`.
+///
+/// The signature is checked against `issuer`, or against the subject key
+/// itself if `issuer` is `None`, as for a self-signed certificate.
+pub fn check_certificate_der(input: &[u8],
+                             issuer: Option<&CompressedEdwardsY>,
+                             policy: KeyPolicy,
+                             profile: VerificationProfile)
+    -> Result<CertificateKey, FormatError>
+{
+    let mut outer: Reader = Reader::new(input);
+    let mut certificate: Reader = outer.read_sequence()?;
+    outer.finish()?;
+
+    let tbs_bytes: &[u8] = certificate.read_raw(der::SEQUENCE)?;
+    let algorithm: &[u8] = certificate.read_raw(der::SEQUENCE)?;
+    let signature: &[u8] = certificate.read(der::BIT_STRING)?;
+    certificate.finish()?;
+
+    let mut tbs: Reader = Reader::new(tbs_bytes).read_sequence()?;
+
+    if tbs.peek_tag() == Some(0xa0) {
+        tbs.read(0xa0)?; // version
+    }
+    tbs.read(der::INTEGER)?; // serialNumber
+
+    // RFC 5280 §4.1.1.2 requires the two copies of the algorithm to agree.
+    if tbs.read_raw(der::SEQUENCE)? != algorithm {
+        return Err(FormatError::Malformed);
+    }
+    tbs.read(der::SEQUENCE)?; // issuer
+    tbs.read(der::SEQUENCE)?; // validity
+    tbs.read(der::SEQUENCE)?; // subject
+
+    let key: CheckedKey<CompressedEdwardsY> =
+        match pkcs8::parse_spki_der(tbs.read_raw(der::SEQUENCE)?)?.check(policy) {
+            Some(key) => key,
+            None      => return Err(FormatError::InvalidKey),
+        };
+
+    let signature_valid: Option<bool> = match der::read_ed25519_algorithm(&mut Reader::new(algorithm)) {
+        Ok(()) => {
+            if signature.len() != 65 || signature[0] != 0 {
+                return Err(FormatError::Malformed);
+            }
+            let mut bytes: [u8; 64] = [0u8; 64];
+            bytes.copy_from_slice(&signature[1..]);
+
+            let signer: &CompressedEdwardsY = issuer.unwrap_or(key.encoding());
+            Some(ed25519::verify(signer, tbs_bytes, &bytes, profile))
+        },
+        Err(FormatError::UnsupportedAlgorithm) => None,
+        Err(e) => return Err(e),
+    };
+
+    Ok(CertificateKey { key, signature_valid })
+}
+
+/// As for `check_certificate_der`, but for a PEM `CERTIFICATE` block.
+pub fn check_certificate_pem(text: &str,
+                             issuer: Option<&CompressedEdwardsY>,
+                             policy: KeyPolicy,
+                             profile: VerificationProfile)
+    -> Result<CertificateKey, FormatError>
+{
+    check_certificate_der(&pem::decode(text, "CERTIFICATE")?, issuer, policy, profile)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    // A self-signed certificate for the RFC 8410 §10.3 key, with CN=relay.
+    static CERTIFICATE_PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBITCB1KADAgECAgEBMAUGAytlcDAQMQ4wDAYDVQQDDAVyZWxheTAeFw0yNjEw
+MTQxMDE2MTBaFw0zNjEwMTExMDE2MTBaMBAxDjAMBgNVBAMMBXJlbGF5MCowBQYD
+K2VwAyEAGb9ECWmEzf6FQbrBZ9w7lshQhqowtrbLDFw4rXAxZuGjUzBRMB0GA1Ud
+DgQWBBSijMH4bllg0+A651yWLJeo1EgpPDAfBgNVHSMEGDAWgBSijMH4bllg0+A6
+51yWLJeo1EgpPDAPBgNVHRMBAf8EBTADAQH/MAUGAytlcANBAGnYeQfj/KgMen3C
+ZStwsxt4gvh9X2MXBTUZz4VgU7xOEh/6LrB5WoOMKcx1F+2CJKbcBHJ9QndNnqR0
+3Ij8cQc=
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn self_signed_certificate() {
+        let cert: CertificateKey = check_certificate_pem(CERTIFICATE_PEM, None, KeyPolicy::Strict,
+                                                         VerificationProfile::Strict).unwrap();
+
+        assert_eq!(cert.key().encoding().as_bytes()[..4], [0x19, 0xbf, 0x44, 0x09]);
+        assert_eq!(cert.signature_valid(), Some(true));
+    }
+
+    #[test]
+    fn wrong_issuer_or_altered_contents_fail_verification() {
+        let other: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let mut der: Vec<u8> = pem::decode(CERTIFICATE_PEM, "CERTIFICATE").unwrap();
+
+        assert_eq!(check_certificate_der(&der, Some(&other), KeyPolicy::Strict,
+                                         VerificationProfile::Cofactored).unwrap()
+                   .signature_valid(), Some(false));
+
+        // Change the issuer's CN from "relay" to "relaz".
+        der[39] ^= 0x03;
+        assert_eq!(check_certificate_der(&der, None, KeyPolicy::Strict,
+                                         VerificationProfile::Cofactored).unwrap()
+                   .signature_valid(), Some(false));
+    }
+
+    #[test]
+    fn truncated_certificates_are_malformed() {
+        let der: Vec<u8> = pem::decode(CERTIFICATE_PEM, "CERTIFICATE").unwrap();
+
+        assert_eq!(check_certificate_der(&der[..200], None, KeyPolicy::Strict,
+                                         VerificationProfile::Strict).err(),
+                   Some(FormatError::Malformed));
+    }
+}
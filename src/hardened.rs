@@ -0,0 +1,103 @@
+// Key validation for hardware which may be glitched.
+//
+// A fault which skips one conditional branch is enough to turn the usual
+// `match q.is_identity()` into an accept, so `validate_hardened` never lets a
+// single comparison decide.  The point is decompressed twice, the torsion
+// test is done once on the extended coordinates and once on the compressed
+// bytes, and each intermediate result is checked against what it implies
+// about the others: the two decompressions must recompress to the same
+// bytes, and a canonical encoding must recompress to itself.  The outcomes
+// are then combined and checked a second time before anything is returned.
+
+use core::hint::black_box;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::curve::IsIdentity;
+
+use subtle::arrays_equal;
+
+use policy::KeyPolicy;
+
+/// Decode `key` and check it under `policy`, as `PrimeOrderEncoding`'s
+/// `validate` and `validate_strict` do, but with every check made twice.
+pub fn validate_hardened(key: &CompressedEdwardsY, policy: KeyPolicy) -> Option<ExtendedPoint> {
+    let p: ExtendedPoint = key.decompress()?;
+    let p_again: ExtendedPoint = black_box(key).decompress()?;
+    let identity: CompressedEdwardsY = ExtendedPoint::identity().compress_edwards();
+    let recompressed: CompressedEdwardsY = p.compress_edwards();
+
+    // Each of these is 1 for a good key, and 0 otherwise.
+    let mut checks: [u8; 4] = [
+        // The two decompressions agree.
+        arrays_equal(recompressed.as_bytes(), p_again.compress_edwards().as_bytes()),
+        // [l]P is the identity, by its extended coordinates...
+        (&p * &constants::l).is_identity() as u8,
+        // ...and by its encoding.
+        arrays_equal((&p_again * &constants::l).compress_edwards().as_bytes(), identity.as_bytes()),
+        1u8,
+    ];
+    if policy == KeyPolicy::Strict {
+        checks[3] = arrays_equal(recompressed.as_bytes(), key.as_bytes()) &
+                    (1u8 ^ arrays_equal(recompressed.as_bytes(), identity.as_bytes())) &
+                    (!p_again.is_identity()) as u8;
+    }
+    let checks: [u8; 4] = black_box(checks);
+
+    let all: u8 = checks.iter().fold(1u8, |all, &check| all & check);
+    if black_box(all) != 1u8 {
+        return None;
+    }
+    // Checked again a different way, in case the comparison above was
+    // skipped.
+    match black_box(checks).iter().map(|&check| check as usize).sum::<usize>() {
+        4 => Some(p),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::scalar::Scalar;
+
+    use traits::PrimeOrderEncoding;
+
+    fn keys() -> Vec<CompressedEdwardsY> {
+        let p: ExtendedPoint = &Scalar::from_u64(2206) * &constants::ED25519_BASEPOINT;
+        let mut keys: Vec<CompressedEdwardsY> = vec![
+            CompressedEdwardsY([0xff; 32]),
+            // The identity with the sign bit set, which is non-canonical.
+            CompressedEdwardsY({ let mut b: [u8; 32] = identity_bytes(); b[31] |= 0x80; b }),
+        ];
+        for t in constants::EIGHT_TORSION.iter() {
+            keys.push(t.compress_edwards());
+            keys.push((&p + t).compress_edwards());
+        }
+        keys
+    }
+
+    fn identity_bytes() -> [u8; 32] {
+        ExtendedPoint::identity().compress_edwards().to_bytes()
+    }
+
+    #[test]
+    fn agrees_with_the_usual_checks() {
+        for key in keys().iter() {
+            assert_eq!(validate_hardened(key, KeyPolicy::PrimeOrder).is_some(), key.validate().is_some());
+            assert_eq!(validate_hardened(key, KeyPolicy::Strict).is_some(), key.validate_strict().is_some());
+        }
+    }
+
+    #[test]
+    fn only_prime_order_keys_pass() {
+        let accepted: Vec<CompressedEdwardsY> = keys().into_iter()
+            .filter(|key| validate_hardened(key, KeyPolicy::Strict).is_some())
+            .collect();
+
+        assert_eq!(accepted, vec![(&Scalar::from_u64(2206) * &constants::ED25519_BASEPOINT).compress_edwards()]);
+    }
+}
@@ -0,0 +1,235 @@
+// Hashing to edwards25519 per RFC 9380, with the
+// `edwards25519_XMD:SHA-512_ELL2_RO_` suite:
+//
+//    u₀, u₁ ← hash_to_field(msg, 2)     via expand_message_xmd with SHA-512
+//    Q₀     ← map_to_curve(u₀)          Elligator2, then the rational map
+//    Q₁     ← map_to_curve(u₁)          from curve25519 to edwards25519
+//    P      ← 8·(Q₀ + Q₁)
+//
+// Clearing the cofactor at the end means every output is in the prime-order
+// subgroup, so these points are safe to feed to any of the validators.
+// `encode_to_curve` is the nonuniform `_NU_` variant, which maps a single
+// field element and is what ECVRF's ELL2 suite uses.
+//
+// `derive_point_from_label` is for protocols which need a generator per
+// identity: it is `hash_to_curve` under this crate's own tag, LABEL_DST, and
+// then `KeyPolicy::Strict`, so the point it hands out has been through the
+// same validation as any key.
+
+use sha2::Digest;
+use sha2::Sha512;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::field::FieldElement;
+
+use subtle::CTAssignable;
+use subtle::CTNegatable;
+
+use edwards::mul_by_pow_2;
+use elligator::elligator2_branch;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The domain separation tag `derive_point_from_label` hashes under.
+pub static LABEL_DST: &[u8] = b"tor22006-V01-CS02-with-edwards25519_XMD:SHA-512_ELL2_RO_";
+
+/// Hash `msg` under the domain separation tag `dst` to a point in the
+/// prime-order subgroup of edwards25519.
+pub fn hash_to_curve(msg: &[u8], dst: &[u8]) -> ExtendedPoint {
+    let mut uniform_bytes: [u8; 96] = [0u8; 96];
+
+    expand_message_xmd(msg, dst, &mut uniform_bytes);
+
+    let u0: FieldElement = field_element_from_be_bytes(&uniform_bytes[..48]);
+    let u1: FieldElement = field_element_from_be_bytes(&uniform_bytes[48..]);
+
+    let q: ExtendedPoint = &map_to_curve(&u0) + &map_to_curve(&u1);
+
+    mul_by_pow_2(&q, 3)
+}
+
+/// As `hash_to_curve`, but mapping one field element rather than two, so the
+/// output is not uniformly distributed.
+pub fn encode_to_curve(msg: &[u8], dst: &[u8]) -> ExtendedPoint {
+    let mut uniform_bytes: [u8; 48] = [0u8; 48];
+
+    expand_message_xmd(msg, dst, &mut uniform_bytes);
+
+    mul_by_pow_2(&map_to_curve(&field_element_from_be_bytes(&uniform_bytes)), 3)
+}
+
+/// A point in the prime-order subgroup for `label`, such as an identity,
+/// which nobody knows the discrete log of.  The identity, the only point
+/// `hash_to_curve` can give which `Strict` rejects, comes out with
+/// negligible probability, and panics here rather than being handed out.
+pub fn derive_point_from_label(label: &[u8]) -> CheckedKey<CompressedEdwardsY> {
+    let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(hash_to_curve(label, LABEL_DST).compress_edwards());
+
+    key.check(KeyPolicy::Strict).expect("hash_to_curve gave the identity")
+}
+
+// expand_message_xmd from RFC 9380 §5.3.1, instantiated with SHA-512.
+fn expand_message_xmd(msg: &[u8], dst: &[u8], out: &mut [u8]) {
+    debug_assert!(out.len() <= 255 * 64);
+
+    // Tags longer than 255 bytes are replaced by their hash (§5.3.3).
+    let mut long_dst: [u8; 64] = [0u8; 64];
+    let dst: &[u8] = if dst.len() > 255 {
+        let mut h: Sha512 = Sha512::default();
+        h.input(b"H2C-OVERSIZE-DST-");
+        h.input(dst);
+        long_dst.copy_from_slice(h.result().as_slice());
+        &long_dst
+    } else {
+        dst
+    };
+    let dst_len: [u8; 1] = [dst.len() as u8];
+    let len_in_bytes: [u8; 2] = [(out.len() >> 8) as u8, out.len() as u8];
+
+    let mut h: Sha512 = Sha512::default();
+    h.input(&[0u8; 128]);
+    h.input(msg);
+    h.input(&len_in_bytes);
+    h.input(&[0u8]);
+    h.input(dst);
+    h.input(&dst_len);
+    let b_0 = h.result();
+
+    // Starting from zero, the first xor with b_0 gives b_1 = H(b_0 ‖ 1 ‖ DST′).
+    let mut b_i: [u8; 64] = [0u8; 64];
+    for (i, chunk) in out.chunks_mut(64).enumerate() {
+        let mut h: Sha512 = Sha512::default();
+        for j in 0..64 {
+            b_i[j] ^= b_0[j];
+        }
+        h.input(&b_i);
+        h.input(&[(i + 1) as u8]);
+        h.input(dst);
+        h.input(&dst_len);
+        b_i.copy_from_slice(h.result().as_slice());
+
+        let n: usize = chunk.len();
+        chunk.copy_from_slice(&b_i[..n]);
+    }
+}
+
+// Reduce a 48-byte big-endian integer modulo p, by splitting it into two
+// 192-bit halves (each of which is already canonical) and recombining them as
+// lo + hi·2¹⁹².
+fn field_element_from_be_bytes(bytes: &[u8]) -> FieldElement {
+    debug_assert_eq!(bytes.len(), 48);
+
+    let mut lo: [u8; 32] = [0u8; 32];
+    let mut hi: [u8; 32] = [0u8; 32];
+    let mut two_192: [u8; 32] = [0u8; 32];
+
+    for i in 0..24 {
+        lo[i] = bytes[47 - i];
+        hi[i] = bytes[23 - i];
+    }
+    two_192[24] = 1;
+
+    &FieldElement::from_bytes(&lo) +
+        &(&FieldElement::from_bytes(&hi) * &FieldElement::from_bytes(&two_192))
+}
+
+// map_to_curve_elligator2_edwards25519 from RFC 9380 §6.8.2 (in the generic
+// form of §6.7.1 followed by the rational map of Appendix D.1).
+fn map_to_curve(u: &FieldElement) -> ExtendedPoint {
+    let one: FieldElement = FieldElement::one();
+
+    // Elligator2 picks the x-coordinate s of a curve25519 point; RFC 9380 then
+    // fixes the sign of the y-coordinate t according to which branch was taken.
+    let (s, kept_v): (FieldElement, u8) = elligator2_branch(u);
+    let gs: FieldElement = &(&(&s.square() * &s) + &(&constants::A * &s.square())) + &s;
+    let (_, mut t) = FieldElement::sqrt_ratio(&gs, &one);
+
+    let t_sign: u8 = t.is_negative_ed25519();
+    t.conditional_negate(t_sign ^ kept_v);
+
+    // c₁ = sqrt(-486664), chosen with sgn0(c₁) = 0.
+    let mut c1: FieldElement = constants::SQRT_MINUS_APLUS2;
+    let c1_sign: u8 = c1.is_negative_ed25519();
+    c1.conditional_negate(c1_sign);
+
+    // (x, y) = (c₁·s/t, (s - 1)/(s + 1)), kept projective to avoid inverting.
+    let s_plus_one: FieldElement = &s + &one;
+    let s_minus_one: FieldElement = &s - &one;
+    let c1_s: FieldElement = &c1 * &s;
+    let mut p: ExtendedPoint = ExtendedPoint{
+        X: &c1_s * &s_plus_one,
+        Y: &t * &s_minus_one,
+        Z: &t * &s_plus_one,
+        T: &c1_s * &s_minus_one,
+    };
+
+    // The exceptional cases t = 0 and s = -1 both map to the identity.
+    let is_exceptional: u8 = p.Z.is_zero();
+    p.conditional_assign(&ExtendedPoint::identity(), is_exceptional);
+    p
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::decaf::DecafPoint;
+
+    use decaf_decompress;
+    use edwards::mult_by_cofactor_and_validate;
+
+    static DST: &[u8] = b"QUUX-V01-CS02-with-edwards25519_XMD:SHA-512_ELL2_RO_";
+
+    // From RFC 9380, Appendix J.5.1, as compressed Edwards y-coordinates.
+    static EMPTY_MSG: [u8; 32] = [
+        0x21, 0xdc, 0x15, 0xe1, 0x02, 0x53, 0x79, 0x6d,
+        0xf2, 0x3a, 0x76, 0x99, 0xc8, 0xa3, 0x83, 0xea,
+        0x62, 0x4c, 0xce, 0x88, 0xc5, 0x24, 0x31, 0xf6,
+        0xbe, 0x22, 0x0b, 0x1a, 0x56, 0xc8, 0xa6, 0x09 ];
+    static ABC_MSG: [u8; 32] = [
+        0x31, 0x55, 0x8a, 0x26, 0x88, 0x7f, 0x23, 0xfb,
+        0x82, 0x18, 0xf1, 0x43, 0xe6, 0x9d, 0x5f, 0x0a,
+        0xf2, 0xe7, 0x83, 0x11, 0x30, 0xbd, 0x5b, 0x43,
+        0x2e, 0xf2, 0x38, 0x83, 0xb8, 0x95, 0x83, 0x9a ];
+
+    // derive_point_from_label(b"relay"), as a regression vector; the
+    // construction itself is checked by the RFC 9380 vectors above.
+    static RELAY_LABEL: [u8; 32] = [
+        0x14, 0x93, 0x41, 0x23, 0xff, 0x71, 0x51, 0xc6,
+        0x45, 0xff, 0x84, 0x8d, 0x4e, 0xd8, 0x48, 0x93,
+        0x1f, 0xf8, 0x24, 0xa4, 0x53, 0x1d, 0xf5, 0x57,
+        0xf1, 0x22, 0x9a, 0x5a, 0xc5, 0x5a, 0x13, 0x76 ];
+
+    #[test]
+    fn rfc9380_vectors() {
+        assert_eq!(hash_to_curve(b"", DST).compress_edwards().to_bytes(), EMPTY_MSG);
+        assert_eq!(hash_to_curve(b"abc", DST).compress_edwards().to_bytes(), ABC_MSG);
+    }
+
+    #[test]
+    fn outputs_pass_every_validator() {
+        for i in 0..16u8 {
+            let p: ExtendedPoint = hash_to_curve(&[i; 7], DST);
+            let key: CompressedEdwardsY = p.compress_edwards();
+
+            assert!(mult_by_cofactor_and_validate(&key).is_some());
+            assert!(decaf_decompress(&DecafPoint(p).compress()).is_some());
+            assert!(mult_by_cofactor_and_validate(&encode_to_curve(&[i; 7], DST).compress_edwards()).is_some());
+        }
+    }
+
+    #[test]
+    fn labels_give_prime_order_points() {
+        let relay: CheckedKey<CompressedEdwardsY> = derive_point_from_label(b"relay");
+
+        assert_eq!(relay.encoding().to_bytes(), RELAY_LABEL);
+        assert_eq!(relay.point().compress_edwards(), hash_to_curve(b"relay", LABEL_DST).compress_edwards());
+        assert!(decaf_decompress(&DecafPoint(*relay.point()).compress()).is_some());
+        assert!(derive_point_from_label(b"") != relay);
+        assert!(derive_point_from_label(b"relay") == relay);
+    }
+}
@@ -0,0 +1,148 @@
+// Letting an embedder see, and stop, each stage of Edwards key validation
+// without keeping its own copy of the pipeline.
+//
+// The stages are the ones `instrument` reports, in order: "decompress",
+// "torsion", and, under `Strict`, "canonicity".  `StageHooks::before` is
+// called as each is about to run and `StageHooks::after` with whether it
+// passed, and either can return `Control::Abort` to have the key rejected
+// there, whatever the remaining stages would have said.  A failing stage
+// rejects the key whatever `after` returns.
+//
+// `validate` and `validate_strict` for `CompressedEdwardsY` are this
+// pipeline with the hooks `()`, which do nothing and compile away.
+// `roundtrip::RoundTrip` is hooks which add a check of their own.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+
+use instrument;
+use policy::IdentityPolicy;
+use policy::KeyPolicy;
+use traits::CofactorGroup;
+
+/// Whether validation should go on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// Run the next stage, if there is one.
+    Continue,
+    /// Reject the key now.
+    Abort,
+}
+
+/// Callbacks around each validation stage.  Both default to carrying on.
+pub trait StageHooks {
+    /// Called before `stage` runs on `encoding`.
+    fn before(&mut self, _stage: &'static str, _encoding: &[u8]) -> Control {
+        Control::Continue
+    }
+
+    /// Called after `stage` has run on `encoding`, with whether it passed.
+    fn after(&mut self, _stage: &'static str, _encoding: &[u8], _passed: bool) -> Control {
+        Control::Continue
+    }
+}
+
+/// No hooks at all.
+impl StageHooks for () {}
+
+/// Validate `encoding` under `policy`, calling `hooks` around each stage.
+pub fn validate_with_hooks<H: StageHooks>(encoding: &CompressedEdwardsY,
+                                          policy: KeyPolicy,
+                                          hooks: &mut H) -> Option<ExtendedPoint> {
+    let p: ExtendedPoint = run(hooks, "decompress", encoding, || encoding.decompress())?;
+
+    let p: ExtendedPoint = run(hooks, "torsion", encoding, || match p.is_torsion_free() {
+        true  => Some(p),
+        false => None, // the point has a torsion component
+    })?;
+
+    if policy == KeyPolicy::PrimeOrder {
+        return Some(p);
+    }
+
+    let identity_allowed: bool = policy.identity_policy() == IdentityPolicy::Accept;
+
+    run(hooks, "canonicity", encoding, || match p.compress_edwards() == *encoding && (identity_allowed || !p.is_identity()) {
+        true  => Some(p),
+        false => None, // non-canonical encoding, or the identity
+    })
+}
+
+// Run one stage between its hooks, reporting it to `instrument` as well.
+fn run<H, F>(hooks: &mut H, stage: &'static str, encoding: &CompressedEdwardsY, body: F) -> Option<ExtendedPoint>
+    where H: StageHooks, F: FnOnce() -> Option<ExtendedPoint>
+{
+    if hooks.before(stage, encoding.as_bytes()) == Control::Abort {
+        return None;
+    }
+
+    let started: instrument::StageStart = instrument::stage_start();
+    let p: Option<ExtendedPoint> = body();
+    instrument::stage(stage, p.is_some(), encoding.as_bytes(), started);
+
+    match hooks.after(stage, encoding.as_bytes(), p.is_some()) {
+        Control::Continue => p,
+        Control::Abort    => None,
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use key::UncheckedKey;
+
+    // Remembers every stage it sees, and aborts at `abort_at`.
+    struct Recorder {
+        seen: [Option<(&'static str, bool)>; 3],
+        count: usize,
+        abort_at: &'static str,
+    }
+
+    impl StageHooks for Recorder {
+        fn after(&mut self, stage: &'static str, _encoding: &[u8], passed: bool) -> Control {
+            self.seen[self.count] = Some((stage, passed));
+            self.count += 1;
+
+            match stage == self.abort_at {
+                true  => Control::Abort,
+                false => Control::Continue,
+            }
+        }
+    }
+
+    fn recorder(abort_at: &'static str) -> Recorder {
+        Recorder { seen: [None; 3], count: 0, abort_at }
+    }
+
+    #[test]
+    fn every_stage_is_seen() {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards());
+        let mut hooks: Recorder = recorder("");
+
+        assert!(key.check_with_hooks(KeyPolicy::Strict, &mut hooks).is_some());
+        assert_eq!(hooks.seen, [Some(("decompress", true)), Some(("torsion", true)), Some(("canonicity", true))]);
+    }
+
+    #[test]
+    fn hooks_can_abort() {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards());
+        let mut hooks: Recorder = recorder("torsion");
+
+        assert!(key.check_with_hooks(KeyPolicy::Strict, &mut hooks).is_none());
+        assert_eq!(hooks.count, 2);
+    }
+
+    #[test]
+    fn failures_stop_the_pipeline() {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(constants::EIGHT_TORSION[1].compress_edwards());
+        let mut hooks: Recorder = recorder("");
+
+        assert!(key.check_with_hooks(KeyPolicy::PrimeOrder, &mut hooks).is_none());
+        assert_eq!(hooks.seen, [Some(("decompress", true)), Some(("torsion", false)), None]);
+        assert!(validate_with_hooks(&CompressedEdwardsY([0x02; 32]), KeyPolicy::Strict, &mut ()).is_none());
+    }
+}
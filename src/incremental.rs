@@ -0,0 +1,226 @@
+// Validating an append-only feed of keys a little at a time, as a monitor
+// following hourly consensus diffs does, without going back over what it
+// has already checked.
+//
+// An `IncrementalValidator` remembers how many records it has consumed and
+// a hash chain over them,
+//
+//    c_0 = 0^32,   c_i = SHA-256(c_{i-1} ‖ record_i)
+//
+// and each call to `advance` validates only the records past that cursor.
+// The chain is recomputed over the prefix first, which costs one hash per
+// record rather than a decompression and a scalar multiplication, so that a
+// feed which was rewritten rather than appended to is noticed instead of
+// being trusted.  The cursor and chain together are a `Checkpoint`, which is
+// 40 bytes and can be written out and resumed from.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// Why a feed could not be advanced over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FeedError {
+    /// The feed is shorter than what was already consumed.
+    Truncated,
+    /// The records already consumed have changed.
+    Rewritten,
+}
+
+/// How far through a feed a validator has got.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    cursor: u64,
+    chain: [u8; 32],
+}
+
+impl Checkpoint {
+    /// The start of a feed.
+    pub fn start() -> Checkpoint {
+        Checkpoint { cursor: 0, chain: [0u8; 32] }
+    }
+
+    /// How many records have been consumed.
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// The cursor, big-endian, and the chain.
+    pub fn to_bytes(&self) -> [u8; 40] {
+        let mut bytes: [u8; 40] = [0u8; 40];
+
+        bytes[..8].copy_from_slice(&self.cursor.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.chain);
+        bytes
+    }
+
+    /// Read back a checkpoint written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8; 40]) -> Checkpoint {
+        let mut cursor: [u8; 8] = [0u8; 8];
+        let mut chain: [u8; 32] = [0u8; 32];
+
+        cursor.copy_from_slice(&bytes[..8]);
+        chain.copy_from_slice(&bytes[8..]);
+
+        Checkpoint { cursor: u64::from_be_bytes(cursor), chain }
+    }
+}
+
+/// The outcome of validating the new records in a feed.
+#[derive(Clone, Debug)]
+pub struct Advance {
+    first: u64,
+    accepted: Vec<(u64, CheckedKey<CompressedEdwardsY>)>,
+    rejected: Vec<(u64, KeyProblem)>,
+}
+
+impl Advance {
+    /// The index of the first record validated this time.
+    pub fn first(&self) -> u64 {
+        self.first
+    }
+
+    /// The new records which passed, with their indices.
+    pub fn accepted(&self) -> &[(u64, CheckedKey<CompressedEdwardsY>)] {
+        &self.accepted
+    }
+
+    /// The new records which failed, with their indices and why.
+    pub fn rejected(&self) -> &[(u64, KeyProblem)] {
+        &self.rejected
+    }
+}
+
+/// A validator which picks up a feed where it left off.
+#[derive(Copy, Clone, Debug)]
+pub struct IncrementalValidator {
+    policy: KeyPolicy,
+    checkpoint: Checkpoint,
+}
+
+impl IncrementalValidator {
+    /// Start from the beginning of a feed.
+    pub fn new(policy: KeyPolicy) -> IncrementalValidator {
+        IncrementalValidator::resume(policy, Checkpoint::start())
+    }
+
+    /// Carry on from `checkpoint`.
+    pub fn resume(policy: KeyPolicy, checkpoint: Checkpoint) -> IncrementalValidator {
+        IncrementalValidator { policy, checkpoint }
+    }
+
+    /// Where the validator has got to, for persisting.
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.checkpoint
+    }
+
+    /// Validate the records of `feed`, which is the whole feed so far, past
+    /// the cursor, and move the cursor to its end.  If the feed is not an
+    /// extension of what was consumed before, nothing is validated and the
+    /// cursor stays where it was.
+    pub fn advance(&mut self, feed: &[CompressedEdwardsY]) -> Result<Advance, FeedError> {
+        let cursor: usize = self.checkpoint.cursor as usize;
+
+        if feed.len() < cursor {
+            return Err(FeedError::Truncated);
+        }
+        if chain(&[0u8; 32], &feed[..cursor]) != self.checkpoint.chain {
+            return Err(FeedError::Rewritten);
+        }
+
+        let mut accepted: Vec<(u64, CheckedKey<CompressedEdwardsY>)> = Vec::new();
+        let mut rejected: Vec<(u64, KeyProblem)> = Vec::new();
+
+        for (i, encoding) in feed.iter().enumerate().skip(cursor) {
+            let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(*encoding);
+
+            match key.check_or_diagnose(self.policy) {
+                Ok(checked)  => accepted.push((i as u64, checked)),
+                Err(problem) => rejected.push((i as u64, problem)),
+            }
+        }
+
+        self.checkpoint = Checkpoint {
+            cursor: feed.len() as u64,
+            chain: chain(&self.checkpoint.chain, &feed[cursor..]),
+        };
+
+        Ok(Advance { first: cursor as u64, accepted, rejected })
+    }
+}
+
+// Extend the hash chain from `start` over `records`.
+fn chain(start: &[u8; 32], records: &[CompressedEdwardsY]) -> [u8; 32] {
+    let mut c: [u8; 32] = *start;
+
+    for record in records.iter() {
+        let mut h: Sha256 = Sha256::default();
+
+        h.input(&c);
+        h.input(record.as_bytes());
+        c.copy_from_slice(h.result().as_slice());
+    }
+    c
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn feed(count: u64) -> Vec<CompressedEdwardsY> {
+        (1..count + 1).map(|n| (&constants::ED25519_BASEPOINT * &Scalar::from_u64(n)).compress_edwards()).collect()
+    }
+
+    #[test]
+    fn only_new_records_are_validated() {
+        let mut feed: Vec<CompressedEdwardsY> = feed(4);
+        let mut validator: IncrementalValidator = IncrementalValidator::new(KeyPolicy::Strict);
+
+        assert_eq!(validator.advance(&feed).unwrap().accepted().len(), 4);
+
+        feed.push(constants::EIGHT_TORSION[1].compress_edwards());
+        feed.push(constants::ED25519_BASEPOINT.compress_edwards());
+        let advance: Advance = validator.advance(&feed).unwrap();
+
+        assert_eq!(advance.first(), 4);
+        assert_eq!(advance.accepted().len(), 1);
+        assert_eq!(advance.rejected(), &[(4, KeyProblem::Torsioned)]);
+        assert_eq!(validator.advance(&feed).unwrap().accepted().len(), 0);
+    }
+
+    #[test]
+    fn checkpoints_resume() {
+        let feed: Vec<CompressedEdwardsY> = feed(6);
+        let mut validator: IncrementalValidator = IncrementalValidator::new(KeyPolicy::Strict);
+        validator.advance(&feed[..3]).unwrap();
+
+        let saved: [u8; 40] = validator.checkpoint().to_bytes();
+        let mut resumed: IncrementalValidator =
+            IncrementalValidator::resume(KeyPolicy::Strict, Checkpoint::from_bytes(&saved));
+
+        assert_eq!(resumed.advance(&feed).unwrap().first(), 3);
+        assert_eq!(resumed.checkpoint().cursor(), 6);
+    }
+
+    #[test]
+    fn rewritten_feeds_are_refused() {
+        let mut feed: Vec<CompressedEdwardsY> = feed(5);
+        let mut validator: IncrementalValidator = IncrementalValidator::new(KeyPolicy::Strict);
+        validator.advance(&feed).unwrap();
+
+        assert_eq!(validator.advance(&feed[..4]).unwrap_err(), FeedError::Truncated);
+
+        feed[1] = constants::EIGHT_TORSION[1].compress_edwards();
+        assert_eq!(validator.advance(&feed).unwrap_err(), FeedError::Rewritten);
+        assert_eq!(validator.checkpoint().cursor(), 5);
+    }
+}
@@ -0,0 +1,156 @@
+// Field arithmetic in GF(2^255 - 19), behind the `inspect` feature, for
+// following ed25519 decompression by hand on a candidate encoding.
+//
+// This is no more than decompression needs.  Given the encoding with its
+// sign bit cleared as y,
+//
+//    u = y² - 1
+//    v = dy² + 1
+//    (square, x) = sqrt_ratio(u, v)      x = √(u/v), when square
+//
+// and then x is negated if its sign doesn't match the encoding's sign bit.
+// `FieldElement` wraps curve25519-dalek's, so that nothing outside this
+// module depends on which version of dalek, or which of its backends, is
+// underneath.  It is meant for study, not for building protocols on.
+
+use core::ops::{Add, Sub, Mul, Neg};
+
+use curve25519_dalek::constants;
+use curve25519_dalek::field;
+
+/// An element of GF(2^255 - 19).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FieldElement(field::FieldElement);
+
+impl FieldElement {
+    /// Zero.
+    pub fn zero() -> FieldElement {
+        FieldElement(field::FieldElement::zero())
+    }
+
+    /// One.
+    pub fn one() -> FieldElement {
+        FieldElement(field::FieldElement::one())
+    }
+
+    /// The curve constant d = -121665/121666.
+    pub fn d() -> FieldElement {
+        FieldElement(constants::d)
+    }
+
+    /// Read 32 little-endian bytes, ignoring the top bit, as an Edwards
+    /// y-coordinate is read.  Values of p and above are reduced.
+    pub fn from_bytes(bytes: &[u8; 32]) -> FieldElement {
+        FieldElement(field::FieldElement::from_bytes(bytes))
+    }
+
+    /// The canonical little-endian encoding.
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// The square of this element.
+    pub fn square(&self) -> FieldElement {
+        FieldElement(self.0.square())
+    }
+
+    /// Whether this element is negative, in ed25519's sense: whether the
+    /// low bit of its encoding is set.
+    pub fn is_negative(&self) -> bool {
+        self.0.is_negative_ed25519() == 1
+    }
+
+    /// Whether this element is zero.
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero() == 1
+    }
+
+    /// Whether u/v is a nonzero square, and √(u/v), or something meaningless
+    /// if it isn't.
+    pub fn sqrt_ratio(u: &FieldElement, v: &FieldElement) -> (bool, FieldElement) {
+        let (square, root) = field::FieldElement::sqrt_ratio(&u.0, &v.0);
+
+        (square == 1, FieldElement(root))
+    }
+}
+
+impl<'b> Add<&'b FieldElement> for &FieldElement {
+    type Output = FieldElement;
+
+    fn add(self, other: &'b FieldElement) -> FieldElement {
+        FieldElement(&self.0 + &other.0)
+    }
+}
+
+impl<'b> Sub<&'b FieldElement> for &FieldElement {
+    type Output = FieldElement;
+
+    fn sub(self, other: &'b FieldElement) -> FieldElement {
+        FieldElement(&self.0 - &other.0)
+    }
+}
+
+impl<'b> Mul<&'b FieldElement> for &FieldElement {
+    type Output = FieldElement;
+
+    fn mul(self, other: &'b FieldElement) -> FieldElement {
+        FieldElement(&self.0 * &other.0)
+    }
+}
+
+impl Neg for &FieldElement {
+    type Output = FieldElement;
+
+    fn neg(self) -> FieldElement {
+        FieldElement(-&self.0)
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::curve::CompressedEdwardsY;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    use backend::adversarial_encodings;
+
+    // Decompression as described above, giving (x, y).
+    fn decompress_by_hand(encoding: &[u8; 32]) -> Option<([u8; 32], [u8; 32])> {
+        let y: FieldElement = FieldElement::from_bytes(encoding);
+        let u: FieldElement = &y.square() - &FieldElement::one();
+        let v: FieldElement = &(&FieldElement::d() * &y.square()) + &FieldElement::one();
+        let (square, mut x): (bool, FieldElement) = FieldElement::sqrt_ratio(&u, &v);
+
+        if !square {
+            return None;
+        }
+        if x.is_negative() != (encoding[31] >> 7 == 1) {
+            x = -&x;
+        }
+        Some((x.to_bytes(), y.to_bytes()))
+    }
+
+    #[test]
+    fn decompression_can_be_followed() {
+        for encoding in adversarial_encodings().iter() {
+            let p: Option<ExtendedPoint> = CompressedEdwardsY(*encoding).decompress();
+
+            assert_eq!(decompress_by_hand(encoding), p.map(|p| (p.X.to_bytes(), p.Y.to_bytes())));
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let two: FieldElement = &FieldElement::one() + &FieldElement::one();
+        let mut p_minus_one: [u8; 32] = [0xff; 32];
+        p_minus_one[0] = 0xec;
+        p_minus_one[31] = 0x7f;
+
+        assert_eq!((&two * &two).to_bytes(), (&two.square() - &FieldElement::zero()).to_bytes());
+        assert_eq!((-&FieldElement::one()).to_bytes(), p_minus_one);
+        assert!((&two - &two).is_zero());
+        assert!(!FieldElement::from_bytes(&p_minus_one).is_negative());
+        assert!(FieldElement::sqrt_ratio(&two.square(), &FieldElement::one()).0);
+    }
+}
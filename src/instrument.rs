@@ -0,0 +1,64 @@
+// Hooks for reporting validation to `tracing`, and timing it with `timing`,
+// when the features of those names are enabled, and which compile to
+// nothing when neither is.
+//
+// Every key is named by `tor::fingerprint_bytes` rather than by its
+// encoding, so that logs can be shared without leaking the keys themselves,
+// and so that a relay's key can be matched against its published fingerprint.
+
+use policy::KeyPolicy;
+
+#[cfg(feature = "tracing")]
+use tor::fingerprint_bytes;
+
+/// Held for as long as a key is being checked.
+#[cfg(feature = "tracing")]
+pub type CheckGuard = ::tracing::span::EnteredSpan;
+#[cfg(not(feature = "tracing"))]
+pub struct CheckGuard;
+
+/// Enter a span covering the check of `encoding` under `policy`.
+#[cfg(feature = "tracing")]
+pub fn check_span(encoding: &[u8], policy: KeyPolicy) -> CheckGuard {
+    debug_span!("check", key = %fingerprint_bytes(encoding), policy = ?policy).entered()
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub fn check_span(_encoding: &[u8], _policy: KeyPolicy) -> CheckGuard {
+    CheckGuard
+}
+
+/// When a stage started, if it is being timed.
+#[cfg(feature = "timing")]
+pub type StageStart = Option<::std::time::Instant>;
+#[cfg(not(feature = "timing"))]
+#[derive(Copy, Clone)]
+pub struct StageStart;
+
+/// Note the start of a stage, or of a whole check.
+#[cfg(feature = "timing")]
+pub fn stage_start() -> StageStart {
+    ::timing::start()
+}
+
+#[cfg(not(feature = "timing"))]
+#[inline(always)]
+pub fn stage_start() -> StageStart {
+    StageStart
+}
+
+/// Record the outcome of one validation stage, such as "decompress", which
+/// began at `started`.
+#[inline(always)]
+pub fn stage(stage: &'static str, passed: bool, encoding: &[u8], started: StageStart) {
+    #[cfg(feature = "tracing")]
+    match passed {
+        true  => trace!(stage, key = %fingerprint_bytes(encoding), "passed"),
+        false => debug!(stage, key = %fingerprint_bytes(encoding), "rejected"),
+    }
+    #[cfg(feature = "timing")]
+    ::timing::record(stage, started);
+
+    let _ = (stage, passed, encoding, started);
+}
@@ -0,0 +1,116 @@
+// Conversions to and from ed25519-dalek's public key types: `VerifyingKey`
+// in 2.x, behind the `ed25519-dalek` feature, and `PublicKey` in 1.x, behind
+// `ed25519-dalek-1`.
+//
+// ed25519-dalek builds on a newer curve25519-dalek than this crate does, so
+// the conversions go through the 32-byte encoding rather than sharing points.
+
+use core::convert::TryFrom;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+#[cfg(feature = "ed25519-dalek")]
+use ed25519_dalek::VerifyingKey;
+
+#[cfg(feature = "ed25519-dalek-1")]
+use ed25519_dalek_1::PublicKey;
+
+#[cfg(feature = "ed25519-dalek")]
+impl<'a> From<&'a CheckedKey<CompressedEdwardsY>> for VerifyingKey {
+    fn from(key: &'a CheckedKey<CompressedEdwardsY>) -> VerifyingKey {
+        // Anything which passed `check` lies on the curve, which is all
+        // ed25519-dalek requires.
+        VerifyingKey::from_bytes(key.encoding().as_bytes()).expect("a checked key is a valid point")
+    }
+}
+
+#[cfg(feature = "ed25519-dalek")]
+impl<'a> From<&'a VerifyingKey> for UncheckedKey<CompressedEdwardsY> {
+    fn from(key: &'a VerifyingKey) -> UncheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(CompressedEdwardsY(key.to_bytes()))
+    }
+}
+
+#[cfg(feature = "ed25519-dalek")]
+impl<'a> TryFrom<&'a VerifyingKey> for CheckedKey<CompressedEdwardsY> {
+    type Error = UncheckedKey<CompressedEdwardsY>;
+
+    fn try_from(key: &'a VerifyingKey) -> Result<CheckedKey<CompressedEdwardsY>, Self::Error> {
+        let unchecked: UncheckedKey<CompressedEdwardsY> = UncheckedKey::from(key);
+
+        match unchecked.check(KeyPolicy::Strict) {
+            Some(checked) => Ok(checked),
+            None          => Err(unchecked),
+        }
+    }
+}
+
+#[cfg(feature = "ed25519-dalek-1")]
+impl<'a> From<&'a CheckedKey<CompressedEdwardsY>> for PublicKey {
+    fn from(key: &'a CheckedKey<CompressedEdwardsY>) -> PublicKey {
+        PublicKey::from_bytes(key.encoding().as_bytes()).expect("a checked key is a valid point")
+    }
+}
+
+#[cfg(feature = "ed25519-dalek-1")]
+impl<'a> From<&'a PublicKey> for UncheckedKey<CompressedEdwardsY> {
+    fn from(key: &'a PublicKey) -> UncheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(CompressedEdwardsY(key.to_bytes()))
+    }
+}
+
+#[cfg(feature = "ed25519-dalek-1")]
+impl<'a> TryFrom<&'a PublicKey> for CheckedKey<CompressedEdwardsY> {
+    type Error = UncheckedKey<CompressedEdwardsY>;
+
+    fn try_from(key: &'a PublicKey) -> Result<CheckedKey<CompressedEdwardsY>, Self::Error> {
+        let unchecked: UncheckedKey<CompressedEdwardsY> = UncheckedKey::from(key);
+
+        match unchecked.check(KeyPolicy::Strict) {
+            Some(checked) => Ok(checked),
+            None          => Err(unchecked),
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    #[cfg(feature = "ed25519-dalek")]
+    #[test]
+    fn verifying_keys_round_trip() {
+        let key: CheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards())
+                .check(KeyPolicy::Strict).unwrap();
+        let theirs: VerifyingKey = VerifyingKey::from(&key);
+
+        assert_eq!(CheckedKey::try_from(&theirs).unwrap(), key);
+    }
+
+    #[cfg(feature = "ed25519-dalek")]
+    #[test]
+    fn torsioned_verifying_keys_are_refused() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+        let theirs: VerifyingKey = VerifyingKey::from_bytes(p.compress_edwards().as_bytes()).unwrap();
+
+        assert_eq!(CheckedKey::try_from(&theirs).unwrap_err(),
+                   UncheckedKey::new(p.compress_edwards()));
+    }
+
+    #[cfg(feature = "ed25519-dalek-1")]
+    #[test]
+    fn torsioned_public_keys_are_refused() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+        let theirs: PublicKey = PublicKey::from_bytes(p.compress_edwards().as_bytes()).unwrap();
+
+        assert!(CheckedKey::try_from(&theirs).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+// Conversions to and from the key types of arti's `tor-llcrypto`.
+//
+// For ed25519, both `PublicKey` and the undecoded `Ed25519Identity` which
+// arti keeps in relay descriptors and channel state are covered.  Its
+// curve25519 keys are Montgomery u-coordinates, which no one type here
+// validates, so for those there are just functions to move the bytes across.
+
+use core::convert::TryFrom;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::CompressedMontgomeryU;
+
+use tor_llcrypto::pk::curve25519;
+use tor_llcrypto::pk::ed25519::Ed25519Identity;
+use tor_llcrypto::pk::ed25519::PublicKey;
+
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+impl<'a> From<&'a CheckedKey<CompressedEdwardsY>> for PublicKey {
+    fn from(key: &'a CheckedKey<CompressedEdwardsY>) -> PublicKey {
+        PublicKey::from_bytes(key.encoding().as_bytes()).expect("a checked key is a valid point")
+    }
+}
+
+impl<'a> From<&'a CheckedKey<CompressedEdwardsY>> for Ed25519Identity {
+    fn from(key: &'a CheckedKey<CompressedEdwardsY>) -> Ed25519Identity {
+        Ed25519Identity::new(key.encoding().to_bytes())
+    }
+}
+
+impl<'a> From<&'a PublicKey> for UncheckedKey<CompressedEdwardsY> {
+    fn from(key: &'a PublicKey) -> UncheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(CompressedEdwardsY(key.to_bytes()))
+    }
+}
+
+impl<'a> From<&'a Ed25519Identity> for UncheckedKey<CompressedEdwardsY> {
+    fn from(id: &'a Ed25519Identity) -> UncheckedKey<CompressedEdwardsY> {
+        let mut bytes: [u8; 32] = [0u8; 32];
+        bytes.copy_from_slice(id.as_bytes());
+
+        UncheckedKey::new(CompressedEdwardsY(bytes))
+    }
+}
+
+impl<'a> TryFrom<&'a PublicKey> for CheckedKey<CompressedEdwardsY> {
+    type Error = UncheckedKey<CompressedEdwardsY>;
+
+    fn try_from(key: &'a PublicKey) -> Result<CheckedKey<CompressedEdwardsY>, Self::Error> {
+        let unchecked: UncheckedKey<CompressedEdwardsY> = UncheckedKey::from(key);
+
+        match unchecked.check(KeyPolicy::Strict) {
+            Some(checked) => Ok(checked),
+            None          => Err(unchecked),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a Ed25519Identity> for CheckedKey<CompressedEdwardsY> {
+    type Error = UncheckedKey<CompressedEdwardsY>;
+
+    fn try_from(id: &'a Ed25519Identity) -> Result<CheckedKey<CompressedEdwardsY>, Self::Error> {
+        let unchecked: UncheckedKey<CompressedEdwardsY> = UncheckedKey::from(id);
+
+        match unchecked.check(KeyPolicy::Strict) {
+            Some(checked) => Ok(checked),
+            None          => Err(unchecked),
+        }
+    }
+}
+
+/// Copy a curve25519 key out of arti.
+pub fn curve25519_from_llcrypto(key: &curve25519::PublicKey) -> CompressedMontgomeryU {
+    CompressedMontgomeryU(key.to_bytes())
+}
+
+/// Copy a curve25519 key into arti.
+pub fn curve25519_to_llcrypto(key: &CompressedMontgomeryU) -> curve25519::PublicKey {
+    curve25519::PublicKey::from(key.0)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+
+    #[test]
+    fn identities_round_trip() {
+        let key: CheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards())
+                .check(KeyPolicy::Strict).unwrap();
+
+        assert_eq!(CheckedKey::try_from(&Ed25519Identity::from(&key)).unwrap(), key);
+        assert_eq!(CheckedKey::try_from(&PublicKey::from(&key)).unwrap(), key);
+    }
+
+    #[test]
+    fn torsioned_identities_are_refused() {
+        // arti keeps identities undecoded, so nothing stops one from naming
+        // a torsioned point.
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+        let id: Ed25519Identity = Ed25519Identity::new(p.compress_edwards().to_bytes());
+
+        assert_eq!(CheckedKey::try_from(&id).unwrap_err(), UncheckedKey::new(p.compress_edwards()));
+    }
+
+    #[test]
+    fn curve25519_keys_round_trip() {
+        let u: CompressedMontgomeryU = CompressedMontgomeryU([9u8; 32]);
+
+        assert_eq!(curve25519_from_llcrypto(&curve25519_to_llcrypto(&u)), u);
+    }
+}
@@ -0,0 +1,14 @@
+// Conversions between this crate's key types and those of other libraries,
+// each behind a feature named after the library it converts to.
+//
+// Going out, a `CheckedKey` converts infallibly, since it has already passed
+// validation.  Coming in, a foreign key becomes an `UncheckedKey`, or, via
+// `TryFrom`, a `CheckedKey` checked under `KeyPolicy::Strict`; a key which
+// fails is handed back as an `UncheckedKey` so that it can still be looked at.
+
+#[cfg(any(feature = "ed25519-dalek", feature = "ed25519-dalek-1"))]
+pub mod dalek;
+#[cfg(feature = "tor-llcrypto")]
+pub mod llcrypto;
+#[cfg(feature = "ring")]
+pub mod ring;
@@ -0,0 +1,91 @@
+// An adapter for ring's `UnparsedPublicKey` workflow.
+//
+// ring verifies Ed25519 signatures over raw key bytes and does no subgroup
+// checking of its own, so services standardised on it can put this crate's
+// validation in front: check the key here, then let ring do the signature.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ring::signature::UnparsedPublicKey;
+use ring::signature::ED25519;
+
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// Why `validate_and_verify` failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The key was the wrong length or failed validation.
+    InvalidKey,
+    /// The key was valid, but ring rejected the signature.
+    BadSignature,
+}
+
+/// Hand a validated key to ring.
+pub fn unparsed_public_key(key: &CheckedKey<CompressedEdwardsY>) -> UnparsedPublicKey<[u8; 32]> {
+    UnparsedPublicKey::new(&ED25519, key.encoding().to_bytes())
+}
+
+/// Validate `key` under `policy`, then verify `signature` on `message` with
+/// ring.
+pub fn validate_and_verify(key: &[u8], message: &[u8], signature: &[u8], policy: KeyPolicy)
+    -> Result<(), VerifyError>
+{
+    if key.len() != 32 {
+        return Err(VerifyError::InvalidKey);
+    }
+
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(key);
+
+    let checked: CheckedKey<CompressedEdwardsY> = match UncheckedKey::new(CompressedEdwardsY(bytes)).check(policy) {
+        Some(checked) => checked,
+        None          => return Err(VerifyError::InvalidKey),
+    };
+
+    match unparsed_public_key(&checked).verify(message, signature) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(VerifyError::BadSignature),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // RFC 8032 §7.1, test 1.
+    static PUBLIC_KEY: [u8; 32] = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7,
+        0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+        0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+        0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a ];
+
+    static SIGNATURE: [u8; 64] = [
+        0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+        0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+        0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+        0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+        0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+        0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+        0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+        0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b ];
+
+    #[test]
+    fn rfc8032_vector() {
+        assert_eq!(validate_and_verify(&PUBLIC_KEY, b"", &SIGNATURE, KeyPolicy::Strict), Ok(()));
+        assert_eq!(validate_and_verify(&PUBLIC_KEY, b"x", &SIGNATURE, KeyPolicy::Strict),
+                   Err(VerifyError::BadSignature));
+    }
+
+    #[test]
+    fn invalid_keys_never_reach_ring() {
+        let mut identity: [u8; 32] = [0u8; 32];
+        identity[0] = 1;
+
+        assert_eq!(validate_and_verify(&identity, b"", &SIGNATURE, KeyPolicy::Strict),
+                   Err(VerifyError::InvalidKey));
+        assert_eq!(validate_and_verify(&PUBLIC_KEY[..31], b"", &SIGNATURE, KeyPolicy::Strict),
+                   Err(VerifyError::InvalidKey));
+    }
+}
@@ -0,0 +1,144 @@
+// Batched field inversion by Montgomery's trick, and the batch conversions
+// which are built on it.
+//
+// Inverting a₁, …, aₙ takes one inversion of the product a₁⋯aₙ and three
+// multiplications per element, where inverting each would take an
+// exponentiation apiece.  Compressing an Edwards point needs 1/Z, and
+// converting one to Montgomery form needs 1/(Z - Y), so those batch well,
+// and `traits::validate_strict_batch` uses the first for its canonicity
+// checks.
+// Decompression and decaf compression don't: their one expensive step is an
+// inverse square root, which this can't share, as `traits::validate_batch`
+// and `decaf_compress_batch` explain.
+//
+// Zero has no inverse.  `batch_invert` leaves zeroes as they are, as
+// `FieldElement::invert` does, without their spoiling the rest of the
+// batch, and it does so in constant time, so a batch which might hold
+// secrets can go through it as well as one of public keys.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::CompressedMontgomeryU;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::field::FieldElement;
+
+use subtle::CTAssignable;
+
+/// Replace every nonzero element of `elements` by its inverse, with a
+/// single inversion for the whole slice.  Zeroes stay zero.
+pub fn batch_invert(elements: &mut [FieldElement]) {
+    let one: FieldElement = FieldElement::one();
+    let mut partials: Vec<FieldElement> = Vec::with_capacity(elements.len());
+    let mut product: FieldElement = one;
+
+    // partials[i] is the product of every nonzero element before i.
+    for element in elements.iter() {
+        let mut factor: FieldElement = *element;
+        factor.conditional_assign(&one, element.is_zero());
+
+        partials.push(product);
+        product = &product * &factor;
+    }
+
+    // Walking back, `inverse` is always the inverse of partials[i] times
+    // element i.
+    let mut inverse: FieldElement = product.invert();
+    for (element, partial) in elements.iter_mut().zip(partials.iter()).rev() {
+        let zero: u8 = element.is_zero();
+        let mut factor: FieldElement = *element;
+        factor.conditional_assign(&one, zero);
+
+        let mut inverted: FieldElement = &inverse * partial;
+        inverse = &inverse * &factor;
+        inverted.conditional_assign(&FieldElement::zero(), zero);
+        *element = inverted;
+    }
+}
+
+/// Compress every point in `points`, in order, as `compress_edwards` would.
+pub fn compress_edwards_batch(points: &[ExtendedPoint]) -> Vec<CompressedEdwardsY> {
+    let mut recips: Vec<FieldElement> = points.iter().map(|p| p.Z).collect();
+    batch_invert(&mut recips);
+
+    points.iter().zip(recips.iter()).map(|(p, recip)| {
+        let x: FieldElement = &p.X * recip;
+        let mut s: [u8; 32] = (&p.Y * recip).to_bytes();
+
+        s[31] ^= x.is_negative_ed25519() << 7;
+        CompressedEdwardsY(s)
+    }).collect()
+}
+
+/// Convert every point in `points` to its Montgomery u-coordinate, in order,
+/// as `compress_montgomery` would, giving `None` for the identity.
+pub fn compress_montgomery_batch(points: &[ExtendedPoint]) -> Vec<Option<CompressedMontgomeryU>> {
+    let mut recips: Vec<FieldElement> = points.iter().map(|p| &p.Z - &p.Y).collect();
+    batch_invert(&mut recips);
+
+    points.iter().zip(recips.iter()).map(|(p, recip)| {
+        match recip.is_zero() {
+            0 => Some(CompressedMontgomeryU((&(&p.Z + &p.Y) * recip).to_bytes())),
+            _ => None, // y = 1, which only the identity has
+        }
+    }).collect()
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::Identity;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn points() -> Vec<ExtendedPoint> {
+        let mut points: Vec<ExtendedPoint> = (1..20u64)
+            .map(|i| &Scalar::from_u64(i * 22006) * &constants::ED25519_BASEPOINT)
+            .collect();
+
+        points.push(ExtendedPoint::identity());
+        points.extend(constants::EIGHT_TORSION.iter().cloned());
+        points
+    }
+
+    #[test]
+    fn batches_invert_as_one_at_a_time() {
+        let elements: Vec<FieldElement> = (1..10u8).map(|i| FieldElement::from_bytes(&[i; 32])).collect();
+        let mut inverted: Vec<FieldElement> = elements.clone();
+        batch_invert(&mut inverted);
+
+        for (element, inverse) in elements.iter().zip(inverted.iter()) {
+            assert_eq!(*inverse, element.invert());
+            assert_eq!(element * inverse, FieldElement::one());
+        }
+    }
+
+    #[test]
+    fn zeroes_are_left_alone() {
+        let two: FieldElement = &FieldElement::one() + &FieldElement::one();
+        let mut elements: [FieldElement; 5] = [FieldElement::zero(), two, FieldElement::zero(), two.square(), FieldElement::zero()];
+        batch_invert(&mut elements);
+
+        assert_eq!(elements[0], FieldElement::zero());
+        assert_eq!(elements[1], two.invert());
+        assert_eq!(elements[2], FieldElement::zero());
+        assert_eq!(elements[3], two.square().invert());
+        assert_eq!(elements[4], FieldElement::zero());
+
+        let mut zeroes: [FieldElement; 2] = [FieldElement::zero(); 2];
+        let mut nothing: [FieldElement; 0] = [];
+        batch_invert(&mut zeroes);
+        batch_invert(&mut nothing);
+        assert_eq!(zeroes, [FieldElement::zero(); 2]);
+    }
+
+    #[test]
+    fn conversions_agree_with_dalek() {
+        let points: Vec<ExtendedPoint> = points();
+        let edwards: Vec<CompressedEdwardsY> = points.iter().map(|p| p.compress_edwards()).collect();
+        let montgomery: Vec<Option<CompressedMontgomeryU>> = points.iter().map(|p| p.compress_montgomery()).collect();
+
+        assert_eq!(compress_edwards_batch(&points), edwards);
+        assert_eq!(compress_montgomery_batch(&points), montgomery);
+        assert_eq!(montgomery.iter().filter(|u| u.is_none()).count(), 2);
+    }
+}
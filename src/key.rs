@@ -0,0 +1,420 @@
+// Type-state wrappers separating key material which has been checked from
+// key material which hasn't.
+//
+// Everything arriving off the wire starts life as an `UncheckedKey`, whose
+// only useful method is `check`.  A `CheckedKey` can't be built any other
+// way, so a function which takes one can rely on the key having passed the
+// policy it was checked under, without rechecking it and without trusting
+// its callers to have remembered to.
+//
+// Some legacy protocols have to accept whatever point a peer sends, torsion
+// and all.  For those there is `DangerousTorsionedKey`, which is deliberately
+// a different type from `CheckedKey` and is only handed out by
+// `accept_with_torsion_dangerously`, so that grepping for that name finds
+// every place the invariant is waived.
+//
+// Both wrappers compare, hash, and sort by their exact encoding bytes, so
+// they can be deduplicated and used as map keys.  That is deliberately not
+// the same relation as `torsion::eq_mod_torsion`: two encodings which differ
+// by a torsion component are different keys here, since only one of them
+// can pass `check`.
+
+use core::cmp::Ordering;
+use core::hash::Hash;
+use core::hash::Hasher;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+
+use denylist::Denylist;
+use hardened;
+use hooks;
+use hooks::StageHooks;
+use instrument;
+use policy::KeyPolicy;
+use traits::CofactorGroup;
+use traits::PrimeOrderEncoding;
+
+#[cfg(feature = "curve448")]
+use curve448;
+
+/// A public key encoding of unknown validity.
+#[derive(Copy, Clone, Debug)]
+pub struct UncheckedKey<T>(T);
+
+impl<T: PrimeOrderEncoding + Clone> UncheckedKey<T> {
+    /// Wrap a key encoding received from an untrusted source.
+    pub fn new(encoding: T) -> UncheckedKey<T> {
+        UncheckedKey(encoding)
+    }
+
+    /// The encoding, exactly as received.
+    pub fn encoding(&self) -> &T {
+        &self.0
+    }
+
+    /// Check this key under `policy`, returning `None` if it fails.
+    pub fn check(&self, policy: KeyPolicy) -> Option<CheckedKey<T>> {
+        let _span: instrument::CheckGuard = instrument::check_span(self.0.as_slice(), policy);
+        let started: instrument::StageStart = instrument::stage_start();
+        let point: Option<T::Point> = match policy {
+            KeyPolicy::PrimeOrder => self.0.validate(),
+            KeyPolicy::Strict     => self.0.validate_strict(),
+        };
+        instrument::stage("check", point.is_some(), self.0.as_slice(), started);
+
+        match point {
+            Some(point) => Some(CheckedKey{ encoding: self.0.clone(), point, policy }),
+            None        => None,
+        }
+    }
+}
+
+impl UncheckedKey<CompressedEdwardsY> {
+    /// Decompress this key without checking that it lies in the prime-order
+    /// subgroup.
+    ///
+    /// Only use this where a protocol already in deployment requires
+    /// torsioned keys to be accepted.  Returns `None` only if the encoding
+    /// is not a point on the curve at all.
+    pub fn accept_with_torsion_dangerously(&self) -> Option<DangerousTorsionedKey<CompressedEdwardsY>> {
+        self.0.decompress().map(|point| DangerousTorsionedKey{ encoding: self.0, point })
+    }
+
+    /// As `check`, but using `hardened::validate_hardened`, so that a fault
+    /// skipping any one comparison can't get this key accepted.
+    pub fn check_hardened(&self, policy: KeyPolicy) -> Option<CheckedKey<CompressedEdwardsY>> {
+        hardened::validate_hardened(&self.0, policy).map(|point| CheckedKey{ encoding: self.0, point, policy })
+    }
+
+    /// As `check`, but also rejecting the key if its point is on `denylist`,
+    /// whatever `policy` says of it.
+    pub fn check_with_denylist(&self, policy: KeyPolicy, denylist: &Denylist) -> Option<CheckedKey<CompressedEdwardsY>> {
+        let checked: CheckedKey<CompressedEdwardsY> = self.check(policy)?;
+
+        match denylist.contains(&checked.point.compress_edwards()) {
+            0 => Some(checked),
+            _ => None, // the key is denied
+        }
+    }
+
+    /// As `check`, but calling `hooks` before and after each stage, any of
+    /// which may have the key rejected.
+    pub fn check_with_hooks<H: StageHooks>(&self, policy: KeyPolicy, hooks: &mut H) -> Option<CheckedKey<CompressedEdwardsY>> {
+        let _span: instrument::CheckGuard = instrument::check_span(self.0.as_slice(), policy);
+        let started: instrument::StageStart = instrument::stage_start();
+        let point: Option<ExtendedPoint> = hooks::validate_with_hooks(&self.0, policy, hooks);
+        instrument::stage("check", point.is_some(), self.0.as_slice(), started);
+
+        point.map(|point| CheckedKey{ encoding: self.0, point, policy })
+    }
+
+    /// Find out what, if anything, is wrong with this key.  A key with no
+    /// problem passes `check` under every policy.
+    pub fn diagnose(&self) -> Option<KeyProblem> {
+        let torsioned: DangerousTorsionedKey<CompressedEdwardsY> = match self.accept_with_torsion_dangerously() {
+            Some(key) => key,
+            None      => return Some(KeyProblem::NotOnCurve),
+        };
+
+        if !torsioned.is_torsion_free() {
+            Some(KeyProblem::Torsioned)
+        } else if torsioned.point.compress_edwards() != self.0 {
+            Some(KeyProblem::NonCanonical)
+        } else if torsioned.point.is_identity() {
+            Some(KeyProblem::Identity)
+        } else {
+            None
+        }
+    }
+
+    /// As `check`, but returning why the key failed if it does: the first
+    /// problem `diagnose` finds is always the one `policy` rejected it for.
+    pub fn check_or_diagnose(&self, policy: KeyPolicy) -> Result<CheckedKey<CompressedEdwardsY>, KeyProblem> {
+        match self.check(policy) {
+            Some(key) => Ok(key),
+            None      => Err(self.diagnose().unwrap_or(KeyProblem::Malformed)),
+        }
+    }
+}
+
+#[cfg(feature = "curve448")]
+impl UncheckedKey<curve448::edwards::CompressedEdwardsY> {
+    /// Decompress this Ed448 key without checking that it lies in the
+    /// prime-order subgroup.  The same caveats apply as for Ed25519.
+    pub fn accept_with_torsion_dangerously(&self)
+        -> Option<DangerousTorsionedKey<curve448::edwards::CompressedEdwardsY>>
+    {
+        self.0.decompress().map(|point| DangerousTorsionedKey{ encoding: self.0, point })
+    }
+}
+
+impl<T> From<T> for UncheckedKey<T> {
+    fn from(encoding: T) -> UncheckedKey<T> {
+        UncheckedKey(encoding)
+    }
+}
+
+impl<T: PrimeOrderEncoding> PartialEq for UncheckedKey<T> {
+    fn eq(&self, other: &UncheckedKey<T>) -> bool {
+        self.0.as_slice() == other.0.as_slice()
+    }
+}
+
+impl<T: PrimeOrderEncoding> Eq for UncheckedKey<T> {}
+
+impl<T: PrimeOrderEncoding> Hash for UncheckedKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_slice().hash(state)
+    }
+}
+
+impl<T: PrimeOrderEncoding> PartialOrd for UncheckedKey<T> {
+    fn partial_cmp(&self, other: &UncheckedKey<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: PrimeOrderEncoding> Ord for UncheckedKey<T> {
+    fn cmp(&self, other: &UncheckedKey<T>) -> Ordering {
+        self.0.as_slice().cmp(other.0.as_slice())
+    }
+}
+
+/// What is wrong with a public key, as found by `UncheckedKey::diagnose`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyProblem {
+    /// The key could not be parsed out of its container at all.
+    Malformed,
+    /// The key is not a point on the curve.
+    NotOnCurve,
+    /// The key has a torsion component.
+    Torsioned,
+    /// The key is in the prime-order subgroup, but not canonically encoded.
+    NonCanonical,
+    /// The key is the identity.
+    Identity,
+}
+
+impl KeyProblem {
+    /// A short snake-case name for this problem, for logs and metrics.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            KeyProblem::Malformed    => "malformed",
+            KeyProblem::NotOnCurve   => "not_on_curve",
+            KeyProblem::Torsioned    => "torsioned",
+            KeyProblem::NonCanonical => "non_canonical",
+            KeyProblem::Identity     => "identity",
+        }
+    }
+}
+
+/// A public key which has passed `UncheckedKey::check`, together with the
+/// point it decoded to.
+#[derive(Debug)]
+pub struct CheckedKey<T: PrimeOrderEncoding> {
+    encoding: T,
+    point: T::Point,
+    policy: KeyPolicy,
+}
+
+impl<T> Clone for CheckedKey<T>
+    where T: PrimeOrderEncoding + Clone, T::Point: Clone
+{
+    fn clone(&self) -> CheckedKey<T> {
+        CheckedKey{ encoding: self.encoding.clone(), point: self.point.clone(), policy: self.policy }
+    }
+}
+
+// The policy is left out of comparisons: a key which passed `Strict` is the
+// same key as when it passed `PrimeOrder`.
+impl<T: PrimeOrderEncoding> PartialEq for CheckedKey<T> {
+    fn eq(&self, other: &CheckedKey<T>) -> bool {
+        self.encoding.as_slice() == other.encoding.as_slice()
+    }
+}
+
+impl<T: PrimeOrderEncoding> Eq for CheckedKey<T> {}
+
+impl<T: PrimeOrderEncoding> Hash for CheckedKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.encoding.as_slice().hash(state)
+    }
+}
+
+impl<T: PrimeOrderEncoding> PartialOrd for CheckedKey<T> {
+    fn partial_cmp(&self, other: &CheckedKey<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: PrimeOrderEncoding> Ord for CheckedKey<T> {
+    fn cmp(&self, other: &CheckedKey<T>) -> Ordering {
+        self.encoding.as_slice().cmp(other.encoding.as_slice())
+    }
+}
+
+impl<T: PrimeOrderEncoding> CheckedKey<T> {
+    /// The encoding which was checked.
+    pub fn encoding(&self) -> &T {
+        &self.encoding
+    }
+
+    /// The point the encoding decoded to.
+    pub fn point(&self) -> &T::Point {
+        &self.point
+    }
+
+    /// The policy this key was checked under.
+    pub fn policy(&self) -> KeyPolicy {
+        self.policy
+    }
+}
+
+/// A public key which decodes to a point on the curve, but which may have a
+/// torsion component.
+///
+/// This has none of the guarantees of a `CheckedKey`, and can only be
+/// obtained from `UncheckedKey::accept_with_torsion_dangerously`.
+#[derive(Debug)]
+pub struct DangerousTorsionedKey<T: PrimeOrderEncoding> {
+    encoding: T,
+    point: T::Point,
+}
+
+impl<T: PrimeOrderEncoding> DangerousTorsionedKey<T> {
+    /// The encoding which was decoded.
+    pub fn encoding(&self) -> &T {
+        &self.encoding
+    }
+
+    /// The point the encoding decoded to, torsion included.
+    pub fn point(&self) -> &T::Point {
+        &self.point
+    }
+}
+
+impl<T> DangerousTorsionedKey<T>
+    where T: PrimeOrderEncoding, T::Point: CofactorGroup
+{
+    /// Return true if this key turned out to have no torsion component
+    /// after all, in which case it would also have passed `check`.
+    pub fn is_torsion_free(&self) -> bool {
+        self.point.is_torsion_free()
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::Identity;
+
+    use std::collections::BTreeSet;
+    use std::collections::HashSet;
+
+    use ristretto::CompressedRistretto;
+
+    // Stands in for protocol code, which should only ever see checked keys.
+    fn key_bytes(key: &CheckedKey<CompressedEdwardsY>) -> [u8; 32] {
+        key.encoding().to_bytes()
+    }
+
+    #[test]
+    fn valid_keys_are_checked() {
+        let key: CompressedEdwardsY = constants::ED25519_BASEPOINT.compress_edwards();
+        let checked: CheckedKey<CompressedEdwardsY> =
+            UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap();
+
+        assert_eq!(key_bytes(&checked), key.to_bytes());
+        assert_eq!(checked.point().compress_edwards(), key);
+        assert_eq!(checked.policy(), KeyPolicy::Strict);
+    }
+
+    #[test]
+    fn torsioned_keys_fail_every_policy() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+        let key: UncheckedKey<CompressedEdwardsY> = p.compress_edwards().into();
+
+        assert!(key.check(KeyPolicy::PrimeOrder).is_none());
+        assert!(key.check(KeyPolicy::Strict).is_none());
+    }
+
+    #[test]
+    fn torsioned_keys_need_the_escape_hatch() {
+        let p: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1];
+        let key: UncheckedKey<CompressedEdwardsY> = p.compress_edwards().into();
+        let dangerous: DangerousTorsionedKey<CompressedEdwardsY> =
+            key.accept_with_torsion_dangerously().unwrap();
+
+        assert_eq!(dangerous.point().compress_edwards(), p.compress_edwards());
+        assert!(!dangerous.is_torsion_free());
+    }
+
+    #[test]
+    fn identity_is_only_accepted_by_the_lax_policy() {
+        let key: UncheckedKey<CompressedEdwardsY> = ExtendedPoint::identity().compress_edwards().into();
+        let ristretto: UncheckedKey<CompressedRistretto> = CompressedRistretto([0u8; 32]).into();
+
+        assert!(key.check(KeyPolicy::PrimeOrder).is_some());
+        assert!(key.check(KeyPolicy::Strict).is_none());
+        assert!(ristretto.check(KeyPolicy::PrimeOrder).is_some());
+        assert!(ristretto.check(KeyPolicy::Strict).is_none());
+    }
+
+    #[test]
+    fn problems_are_diagnosed() {
+        let identity: UncheckedKey<CompressedEdwardsY> = ExtendedPoint::identity().compress_edwards().into();
+        let torsioned: UncheckedKey<CompressedEdwardsY> = constants::EIGHT_TORSION[1].compress_edwards().into();
+        let good: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+
+        assert_eq!(identity.diagnose(), Some(KeyProblem::Identity));
+        assert_eq!(torsioned.diagnose(), Some(KeyProblem::Torsioned));
+        assert_eq!(good.diagnose(), None);
+    }
+
+    #[test]
+    fn rejections_come_with_their_problem() {
+        let identity: UncheckedKey<CompressedEdwardsY> = ExtendedPoint::identity().compress_edwards().into();
+        let good: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+
+        assert_eq!(identity.check_or_diagnose(KeyPolicy::Strict).err(), Some(KeyProblem::Identity));
+        assert!(identity.check_or_diagnose(KeyPolicy::PrimeOrder).is_ok());
+        assert_eq!(good.check_or_diagnose(KeyPolicy::Strict).ok(), good.check(KeyPolicy::Strict));
+        assert_eq!(UncheckedKey::new(CompressedEdwardsY([0x02; 32])).check_or_diagnose(KeyPolicy::PrimeOrder).err(),
+                   Some(KeyProblem::NotOnCurve));
+    }
+
+    #[test]
+    fn hardened_checks_give_checked_keys() {
+        let torsioned: UncheckedKey<CompressedEdwardsY> = constants::EIGHT_TORSION[1].compress_edwards().into();
+        let good: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+
+        assert_eq!(good.check_hardened(KeyPolicy::Strict), good.check(KeyPolicy::Strict));
+        assert!(torsioned.check_hardened(KeyPolicy::PrimeOrder).is_none());
+    }
+
+    #[test]
+    fn keys_deduplicate_by_encoding() {
+        let p: ExtendedPoint = constants::ED25519_BASEPOINT;
+        let q: ExtendedPoint = &p + &constants::EIGHT_TORSION[1];
+        let keys: [UncheckedKey<CompressedEdwardsY>; 3] = [
+            p.compress_edwards().into(),
+            q.compress_edwards().into(),
+            p.compress_edwards().into(),
+        ];
+
+        let unique: HashSet<UncheckedKey<CompressedEdwardsY>> = keys.iter().cloned().collect();
+        let sorted: BTreeSet<UncheckedKey<CompressedEdwardsY>> = keys.iter().cloned().collect();
+        let checked: HashSet<CheckedKey<CompressedEdwardsY>> = keys.iter()
+            .filter_map(|key| key.check(KeyPolicy::PrimeOrder)).collect();
+
+        // The torsioned variant is a different key, even though it is the
+        // same signer as far as a cofactored verifier is concerned.
+        assert_eq!(unique.len(), 2);
+        assert_eq!(sorted.len(), 2);
+        assert_eq!(checked.len(), 1);
+    }
+}
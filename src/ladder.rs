@@ -0,0 +1,101 @@
+// Telling whether a key has small order from its Montgomery u-coordinate
+// alone, as code with nothing but an X25519 ladder has to.
+//
+// The birational map to curve25519 sends an ed25519 point (x, y) to
+// u = (1 + y)/(1 - y), and the identity, where y = 1, to the point at
+// infinity.  The ladder's doubling works on projective (U : Z) without the
+// v-coordinate, so it can't tell P from -P, but order doesn't depend on the
+// sign anyway: P is in E[8] exactly when three doublings reach infinity,
+// that is, Z = 0.  Starting from (1 + y : 1 - y) avoids the inversion, and
+// handles the identity along with everything else.
+//
+// On a bare u-coordinate this can't tell the curve from its twist, whose
+// points of order 1, 2, and 4 it also reports as small; a u which is neither
+// isn't checked for at all.  That is what X25519 implementations want, but
+// for an ed25519 key `is_small_order` decompresses first, so that an
+// encoding which isn't a point is never reported as small.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::CompressedMontgomeryU;
+use curve25519_dalek::field::FieldElement;
+
+// a24 = (486662 - 2)/4 = 121665.
+static A24: [u8; 32] = [
+    0x41, 0xdb, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ];
+
+/// Whether the point with Montgomery u-coordinate `u`, on curve25519 or its
+/// twist, has order dividing 8.
+pub fn is_small_order_u(u: &CompressedMontgomeryU) -> bool {
+    is_small_order_projective(&FieldElement::from_bytes(&u.0), &FieldElement::one())
+}
+
+/// Whether `key` names a point of order dividing 8, checked by the ladder
+/// on its u-coordinate rather than by Edwards arithmetic.  An encoding which
+/// doesn't decompress is not a point, and so is not small-order.
+pub fn is_small_order(key: &CompressedEdwardsY) -> bool {
+    if key.decompress().is_none() {
+        return false; // the point was invalid
+    }
+
+    // The sign bit is ignored here, as it is by from_bytes.
+    let y: FieldElement = FieldElement::from_bytes(key.as_bytes());
+    let one: FieldElement = FieldElement::one();
+
+    is_small_order_projective(&(&one + &y), &(&one - &y))
+}
+
+// Whether three ladder doublings of (U : Z) reach infinity.
+fn is_small_order_projective(u: &FieldElement, z: &FieldElement) -> bool {
+    let (mut u, mut z): (FieldElement, FieldElement) = (*u, *z);
+
+    for _ in 0..3 {
+        let (u2, z2): (FieldElement, FieldElement) = double(&u, &z);
+        u = u2;
+        z = z2;
+    }
+    z.is_zero() == 1u8
+}
+
+// x-only doubling, as in RFC 7748 §5.
+fn double(u: &FieldElement, z: &FieldElement) -> (FieldElement, FieldElement) {
+    let a24: FieldElement = FieldElement::from_bytes(&A24);
+    let aa: FieldElement = (u + z).square();
+    let bb: FieldElement = (u - z).square();
+    let e: FieldElement = &aa - &bb;
+
+    (&aa * &bb, &e * &(&aa + &(&a24 * &e)))
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn agrees_with_edwards_arithmetic() {
+        let p: ExtendedPoint = &Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT;
+
+        for t in constants::EIGHT_TORSION.iter() {
+            assert!(is_small_order(&t.compress_edwards()));
+            assert!(!is_small_order(&(&p + t).compress_edwards()));
+        }
+        assert!(!is_small_order(&CompressedEdwardsY([0xff; 32])));
+    }
+
+    #[test]
+    fn u_coordinates() {
+        let mut one: [u8; 32] = [0u8; 32];
+        one[0] = 1;
+
+        // u = 0 has order 2, and u = 1 order 4 on the twist.
+        assert!(is_small_order_u(&CompressedMontgomeryU([0u8; 32])));
+        assert!(is_small_order_u(&CompressedMontgomeryU(one)));
+        assert!(!is_small_order_u(&constants::ED25519_BASEPOINT.compress_montgomery().unwrap()));
+    }
+}
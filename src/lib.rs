@@ -1,131 +1,182 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(feature = "bench", feature(test))]
 
+#[cfg(feature = "std")]
 extern crate core;
 
 #[cfg(all(test, feature = "bench"))]
 extern crate test;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "rand"))]
 extern crate rand;
 
 extern crate curve25519_dalek;
-
-use curve25519_dalek::constants;
-
-use curve25519_dalek::curve::CompressedEdwardsY;
-use curve25519_dalek::curve::ExtendedPoint;
-use curve25519_dalek::curve::Identity;
-use curve25519_dalek::curve::IsIdentity;
-
-use curve25519_dalek::decaf::CompressedDecaf;
-use curve25519_dalek::decaf::DecafPoint;
-
-
-// The public key for an ed25519 scheme is a compressed edwards point (the
-// Y-coordinate and the sign of X).
-
-pub fn mult_by_cofactor_and_validate(key: &CompressedEdwardsY) -> Option<ExtendedPoint> {
-    // decompression of Y in any sane curve25519 library should check validation
-    // by computing:
-    //
-    //    Z ← fe(1)
-    //    u ← Y² - Z
-    //    v ← dY² + Z
-    //    check ← sqrt(u/v)
-    //
-    // If `v` is nonzero and `check` is okay (meaning that `u/v` is square),
-    // then the point is valid.
-    let p: Option<ExtendedPoint> = key.decompress();
-    let q: ExtendedPoint;
-    
-    match p.is_some() {
-        true  => q = &p.unwrap() * &constants::l,
-        false => return None, // the point was invalid
-    }
-
-    // We need to check that p*l is the identity (the identity point
-    // is X:Y:Z:T == 0:1:1:0 since this shows there is no torsion
-    // component)
-    match q.is_identity() {
-        true  => return Some(q),
-        false => return None, // point * l was the identity
-    }
-}
-
-// Decaf decompression ensures both that the point is a valid point on
-// the curve and that it is within a prime-order group.
-pub fn decaf_decompress(key: &CompressedDecaf) -> Option<DecafPoint> {
-    let p: Option<DecafPoint>;
-
-    // The constant-time equality check for a decaf point is only
-    // defined for the compressed version, and works by byte comparison.
-    match *key == CompressedDecaf::identity() {
-        true  => return None, // the point was the identity
-        false => p = key.decompress(),
-    }
-
-    match p.is_some() {
-        true  => return p,
-        false => return None, // invalid decaf point
-    }
-}
-
-#[cfg(all(test, not(feature = "bench")))]
-mod test {
-    use super::*;
-
-    use rand::OsRng;
-
-    use curve25519_dalek::constants;
-    use curve25519_dalek::scalar::Scalar;
-
-    #[test]
-    fn current_design() {
-        let mut csprng: OsRng = OsRng::new().unwrap();
-        let a: Scalar = Scalar::random(&mut csprng);
-        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
-        let key: CompressedEdwardsY = p.compress_edwards();
-
-        let check = mult_by_cofactor_and_validate(&key);
-        assert!(check.is_some());
-    }
-
-    #[test]
-    fn with_decaf_instead() {
-        let mut csprng: OsRng = OsRng::new().unwrap();
-        let p: DecafPoint = DecafPoint::random(&mut csprng);
-        let key: CompressedDecaf = p.compress();
-
-        let check = decaf_decompress(&key);
-        assert!(check.is_some());
-    }
-}
-
-#[cfg(all(test, feature = "bench"))]
-mod bench {
-    use super::*;
-
-    use test::Bencher;
-    use rand::OsRng;
-
-    use curve25519_dalek::scalar::Scalar;
-
-    #[bench]
-    fn current_design(b: &mut Bencher) {
-        let mut csprng: OsRng = OsRng::new().unwrap();
-        let a: Scalar = Scalar::random(&mut csprng);
-        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
-        let key: CompressedEdwardsY = p.compress_edwards();
-
-        b.iter(| | mult_by_cofactor_and_validate(&key) )
-    }
-
-    #[bench]
-    fn with_decaf_instead(b: &mut Bencher) {
-        let mut csprng: OsRng = OsRng::new().unwrap();
-        let p: DecafPoint = DecafPoint::random(&mut csprng);
-        let key: CompressedDecaf = p.compress();
-
-        b.iter(| | decaf_decompress(&key) )
-    }
-}
+extern crate sha2;
+extern crate subtle;
+
+#[cfg(feature = "curve25519-dalek-4")]
+extern crate curve25519_dalek_4;
+#[cfg(feature = "curve448")]
+extern crate digest;
+#[cfg(any(feature = "blinding", feature = "curve448"))]
+extern crate sha3;
+
+#[cfg(feature = "ed25519-dalek")]
+extern crate ed25519_dalek;
+#[cfg(feature = "ed25519-dalek-1")]
+extern crate ed25519_dalek_1;
+#[cfg(feature = "fiat")]
+extern crate fiat_crypto;
+#[cfg(feature = "prometheus")]
+extern crate prometheus;
+#[cfg(feature = "ring")]
+extern crate ring;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "async")]
+extern crate tokio_util;
+#[cfg(feature = "tor-llcrypto")]
+extern crate tor_llcrypto;
+#[cfg(feature = "tracing")]
+#[macro_use]
+extern crate tracing;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+
+#[cfg(feature = "std")]
+pub mod adaptive;
+pub mod affine;
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "std")]
+pub mod authority;
+#[cfg(feature = "std")]
+pub mod backend;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "bindings")]
+pub mod bindings;
+#[cfg(feature = "blinding")]
+pub mod blinding;
+pub mod bridge;
+#[cfg(feature = "std")]
+pub mod corpus;
+#[cfg(feature = "curve448")]
+pub mod curve448;
+pub mod decaf;
+pub mod denylist;
+#[cfg(feature = "blinding")]
+pub mod descriptor;
+pub mod dual;
+#[cfg(feature = "std")]
+pub mod ed25519;
+pub mod edwards;
+pub mod elligator;
+pub mod entropy;
+#[cfg(feature = "audit")]
+pub mod equation;
+#[cfg(feature = "fiat")]
+pub mod fiat;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod explain;
+#[cfg(feature = "std")]
+pub mod family;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "forensics")]
+pub mod forensics;
+#[cfg(feature = "std")]
+pub mod formats;
+pub mod hardened;
+pub mod hash_to_curve;
+pub mod hooks;
+#[cfg(feature = "std")]
+pub mod incremental;
+#[cfg(feature = "inspect")]
+pub mod inspect;
+mod instrument;
+pub mod interop;
+#[cfg(feature = "std")]
+pub mod inversion;
+pub mod key;
+pub mod ladder;
+#[cfg(feature = "std")]
+pub mod manager;
+#[cfg(feature = "std")]
+pub mod merkle;
+#[cfg(feature = "prometheus")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod migration;
+#[cfg(feature = "std")]
+pub mod mutation;
+pub mod obfs4;
+#[cfg(feature = "unstable-offload")]
+pub mod offload;
+#[cfg(feature = "blinding")]
+pub mod onion;
+pub mod params;
+#[cfg(feature = "std")]
+pub mod pinning;
+pub mod policy;
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod quorum;
+#[cfg(feature = "std")]
+pub mod revocation;
+pub mod ristretto;
+#[cfg(feature = "std")]
+pub mod roles;
+pub mod roundtrip;
+#[cfg(all(test, feature = "std", not(feature = "bench")))]
+mod scenarios;
+#[cfg(feature = "std")]
+pub mod selftest;
+#[cfg(feature = "async")]
+pub mod service;
+#[cfg(feature = "std")]
+pub mod session;
+#[cfg(feature = "std")]
+pub mod slip10;
+#[cfg(all(test, feature = "std", not(feature = "bench")))]
+mod small_order;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "blinding")]
+pub mod srv;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod stream;
+#[cfg(feature = "timing")]
+pub mod timing;
+#[cfg(feature = "std")]
+pub mod tor;
+pub mod torsion;
+pub mod traits;
+#[cfg(feature = "std")]
+pub mod translog;
+pub mod vectors;
+#[cfg(feature = "std")]
+pub mod vrf;
+mod wipe;
+#[cfg(feature = "std")]
+pub mod xeddsa;
+
+// The free functions which began the crate, before it had modules of its
+// own, are still reachable from the root.
+pub use decaf::decaf_decompress;
+pub use decaf::decaf_decompress_with;
+#[cfg(feature = "std")]
+pub use decaf::decaf_compress_batch;
+#[cfg(feature = "std")]
+pub use decaf::decaf_decompress_batch;
+pub use decaf::decaf_from_uniform_bytes;
+pub use decaf::is_identity_encoding;
+pub use edwards::div_by_cofactor;
+pub use edwards::is_identity_encoding_edwards;
+pub use edwards::mul_by_pow_2;
+pub use edwards::mult_by_cofactor_and_validate;
+pub use edwards::validate_and_decompress;
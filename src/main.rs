@@ -0,0 +1,440 @@
+// A command-line front end to the validators, for auditing keys in bulk.
+//
+//    tor22006 known-hosts FILE...
+//    tor22006 audit FILE...
+//    tor22006 check-hs-dir DIR
+//    tor22006 check-descriptor FILE ADDRESS
+//    tor22006 divergence-corpus VALIDATOR VALIDATOR
+//    tor22006 fuzz-corpus DIR...
+//    tor22006 snapshot [FILE]
+//    tor22006 decaf-migration FILE...
+//
+// `known-hosts` prints one line per problem found, and exits with status 1
+// if there were any.  `audit` reads one ed25519 key per line, as hex or
+// base64, and prints statistics over all of them.  `check-hs-dir`, with the
+// `blinding` feature, checks the keys and hostname of an onion service
+// directory against each other, and `check-descriptor`, also with
+// `blinding`, checks a saved v3 descriptor for ADDRESS offline and prints
+// what it found; both likewise exit with status 1 on any problem.
+// `divergence-corpus` writes out every constructed encoding the two
+// validators disagree about, each given as BACKEND:POLICY, for a BACKEND of
+// native, decompress-only, fiat, or dalek4, and a POLICY of prime-order or
+// strict.  `fuzz-corpus` writes the seed corpus into each DIR, creating it
+// if need be.  `snapshot` prints every validator's verdicts on the snapshot
+// corpus, or, given the FILE of an earlier snapshot, every verdict which
+// has changed since, exiting with status 1 if any has.  `decaf-migration`
+// reads keys as `audit` does, and prints every one which reads differently
+// as decaf and as ristretto under the strict policy, with the ristretto
+// encoding it should be rewritten to, exiting with status 1 if there were
+// any.  Each exits with status 2 if its input couldn't be read, or
+// its output written, at all.
+
+extern crate curve25519_dalek;
+extern crate tor22006;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use tor22006::audit::AuditReport;
+use tor22006::audit::audit;
+use tor22006::backend::Backend;
+use tor22006::backend::Native;
+use tor22006::corpus::CorpusEntry;
+use tor22006::corpus::DecompressOnly;
+use tor22006::corpus::Validator;
+use tor22006::corpus::build_corpus;
+use tor22006::corpus::write_corpus;
+use tor22006::corpus::write_seed_corpus;
+use tor22006::encoding::decode_key;
+use tor22006::encoding::hex_encode;
+use tor22006::formats::known_hosts::HostKeyReport;
+use tor22006::formats::known_hosts::scan_known_hosts;
+use tor22006::key::KeyProblem;
+use tor22006::migration::MigrationDifference;
+use tor22006::migration::MigrationReport;
+use tor22006::migration::migration_differences;
+use tor22006::policy::KeyPolicy;
+use tor22006::snapshot::SnapshotChange;
+use tor22006::snapshot::compare_snapshot;
+use tor22006::snapshot::take_snapshot;
+#[cfg(feature = "blinding")]
+use tor22006::descriptor::DescriptorProblem;
+#[cfg(feature = "blinding")]
+use tor22006::descriptor::DescriptorReport;
+#[cfg(feature = "blinding")]
+use tor22006::descriptor::check_descriptor;
+#[cfg(feature = "blinding")]
+use tor22006::encoding::base64_encode;
+#[cfg(feature = "blinding")]
+use tor22006::formats::FormatError;
+#[cfg(feature = "blinding")]
+use tor22006::formats::tor_keys;
+#[cfg(feature = "blinding")]
+use tor22006::formats::tor_keys::ConsistencyError;
+#[cfg(feature = "blinding")]
+use tor22006::formats::tor_keys::TorSecretKey;
+#[cfg(feature = "blinding")]
+use tor22006::key::UncheckedKey;
+#[cfg(feature = "blinding")]
+use tor22006::onion::onion_address;
+#[cfg(feature = "blinding")]
+use tor22006::onion::parse_onion_address;
+
+static USAGE: &str = "usage: tor22006 known-hosts FILE...\n       tor22006 audit FILE...\n       tor22006 check-hs-dir DIR\n       tor22006 check-descriptor FILE ADDRESS\n       tor22006 divergence-corpus VALIDATOR VALIDATOR\n       tor22006 fuzz-corpus DIR...\n       tor22006 snapshot [FILE]\n       tor22006 decaf-migration FILE...";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let status: i32 = match args.first().map(|arg| arg.as_str()) {
+        Some("known-hosts") if args.len() > 1 => known_hosts(&args[1..]),
+        Some("audit") if args.len() > 1       => audit_files(&args[1..]),
+        #[cfg(feature = "blinding")]
+        Some("check-hs-dir") if args.len() == 2 => check_hs_dir(&args[1]),
+        #[cfg(feature = "blinding")]
+        Some("check-descriptor") if args.len() == 3 => check_descriptor_file(&args[1], &args[2]),
+        Some("divergence-corpus") if args.len() == 3 => divergence_corpus(&args[1], &args[2]),
+        Some("fuzz-corpus") if args.len() > 1 => fuzz_corpus(&args[1..]),
+        Some("snapshot") if args.len() <= 2   => snapshot(args.get(1)),
+        Some("decaf-migration") if args.len() > 1 => decaf_migration(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        },
+    };
+    process::exit(status);
+}
+
+fn read_file(path: &str) -> Option<String> {
+    let mut text: String = String::new();
+
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut text)) {
+        Ok(_)  => Some(text),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            None
+        },
+    }
+}
+
+#[cfg(feature = "blinding")]
+fn read_binary_file(path: &str) -> Option<Vec<u8>> {
+    let mut contents: Vec<u8> = Vec::new();
+
+    match File::open(path).and_then(|mut file| file.read_to_end(&mut contents)) {
+        Ok(_)  => Some(contents),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            None
+        },
+    }
+}
+
+fn describe(problem: KeyProblem) -> &'static str {
+    match problem {
+        KeyProblem::Malformed    => "malformed entry",
+        KeyProblem::NotOnCurve   => "key is not a point on the curve",
+        KeyProblem::Torsioned    => "key has a torsion component",
+        KeyProblem::NonCanonical => "key is not canonically encoded",
+        KeyProblem::Identity     => "key is the identity",
+    }
+}
+
+fn known_hosts(paths: &[String]) -> i32 {
+    let mut status: i32 = 0;
+
+    for path in paths {
+        let text: String = match read_file(path) {
+            Some(text) => text,
+            None       => return 2,
+        };
+        let reports: Vec<HostKeyReport> = scan_known_hosts(&text);
+
+        for report in reports.iter() {
+            if let Some(problem) = report.problem() {
+                println!("{}:{}: {}: {}", path, report.line(), report.hosts(), describe(problem));
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+fn audit_files(paths: &[String]) -> i32 {
+    let mut encodings: Vec<CompressedEdwardsY> = Vec::new();
+    let mut malformed: u64 = 0;
+
+    for path in paths {
+        let text: String = match read_file(path) {
+            Some(text) => text,
+            None       => return 2,
+        };
+
+        for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            match decode_key(line) {
+                Some(key) => encodings.push(key),
+                None      => malformed += 1,
+            }
+        }
+    }
+
+    let report: AuditReport = audit(&encodings);
+    let orders: &[u64; 4] = report.torsion_orders();
+
+    println!("keys:                   {}", report.total());
+    println!("malformed lines:        {}", malformed);
+    println!("exact duplicates:       {}", report.exact_duplicates());
+    println!("not on the curve:       {}", report.not_on_curve());
+    println!("non-canonical:          {}", report.non_canonical());
+    println!("identity:               {}", report.identity());
+    println!("torsioned:              {}", report.torsioned());
+    println!("torsion of order 2/4/8: {}/{}/{}", orders[1], orders[2], orders[3]);
+    println!("duplicates mod torsion: {}", report.duplicates_mod_torsion());
+    0
+}
+
+#[cfg(feature = "blinding")]
+fn describe_format_error(error: FormatError) -> &'static str {
+    match error {
+        FormatError::Malformed            => "not a Tor ed25519 key file",
+        FormatError::UnsupportedAlgorithm => "a key file of some other type",
+        FormatError::InvalidKey           => "key fails validation",
+    }
+}
+
+#[cfg(feature = "blinding")]
+fn check_hs_dir(dir: &str) -> i32 {
+    let path = |name: &str| Path::new(dir).join(name).to_string_lossy().into_owned();
+    let (secret_path, public_path, hostname_path) = (path("hs_ed25519_secret_key"),
+                                                     path("hs_ed25519_public_key"),
+                                                     path("hostname"));
+
+    let (secret, public, hostname) = match (read_binary_file(&secret_path),
+                                            read_binary_file(&public_path),
+                                            read_file(&hostname_path)) {
+        (Some(secret), Some(public), Some(hostname)) => (secret, public, hostname),
+        _                                            => return 2,
+    };
+    let mut problems: Vec<String> = Vec::new();
+
+    let secret: Option<TorSecretKey> = match tor_keys::parse_secret_key(&secret) {
+        Ok(secret) => Some(secret),
+        Err(e)     => { problems.push(format!("{}: {}", secret_path, describe_format_error(e))); None },
+    };
+    let public: Option<UncheckedKey<CompressedEdwardsY>> = match tor_keys::parse_public_key(&public) {
+        Ok(public) => Some(public),
+        Err(e)     => { problems.push(format!("{}: {}", public_path, describe_format_error(e))); None },
+    };
+
+    if let (Some(secret), Some(public)) = (secret.as_ref(), public.as_ref()) {
+        match tor_keys::check_keypair_consistency(secret.expanded(), public) {
+            Ok(()) => (),
+            Err(ConsistencyError::CorruptSecret) => {
+                problems.push(format!("{}: secret key is corrupt", secret_path));
+            },
+            Err(ConsistencyError::InvalidPublic(problem)) => {
+                problems.push(format!("{}: {}", public_path, describe(problem)));
+            },
+            Err(ConsistencyError::MismatchedPair) => {
+                problems.push(format!("{}: not the public key for {}", public_path, secret_path));
+            },
+        }
+    }
+
+    let mut address: Option<String> = None;
+    match (parse_onion_address(hostname.trim()), public) {
+        (None, _) => {
+            problems.push(format!("{}: not a v3 onion address", hostname_path));
+        },
+        (Some(named), Some(public)) => match named.check_or_diagnose(KeyPolicy::Strict) {
+            Ok(ref key) if named == public => address = Some(onion_address(key)),
+            Ok(_)                          => problems.push(format!("{}: names a key other than {}", hostname_path, public_path)),
+            Err(problem)                   => problems.push(format!("{}: {}", hostname_path, describe(problem))),
+        },
+        (Some(_), None) => (),
+    }
+
+    for problem in problems.iter() {
+        println!("{}", problem);
+    }
+    match (problems.is_empty(), address) {
+        (true, Some(address)) => { println!("{}: ok, {}", dir, address); 0 },
+        _                     => 1,
+    }
+}
+
+#[cfg(feature = "blinding")]
+fn describe_descriptor_problem(problem: DescriptorProblem) -> &'static str {
+    match problem {
+        DescriptorProblem::CertificateType         => "certificate is not for a descriptor signing key",
+        DescriptorProblem::MissingBlindedKey       => "certificate doesn't name the blinded key",
+        DescriptorProblem::InvalidBlindedKey       => "blinded key fails validation",
+        DescriptorProblem::InvalidSigningKey       => "descriptor signing key fails validation",
+        DescriptorProblem::WrongBlindedKey         => "blinded key is not derived from the address",
+        DescriptorProblem::BadCertificateSignature => "certificate signature doesn't verify",
+        DescriptorProblem::BadDescriptorSignature  => "descriptor signature doesn't verify",
+    }
+}
+
+#[cfg(feature = "blinding")]
+fn check_descriptor_file(path: &str, address: &str) -> i32 {
+    let text: String = match read_file(path) {
+        Some(text) => text,
+        None       => return 2,
+    };
+    let report: DescriptorReport = match check_descriptor(&text, address) {
+        Ok(report) => report,
+        Err(FormatError::InvalidKey) => {
+            eprintln!("{}: the key it names fails validation", address);
+            return 1;
+        },
+        Err(_) => {
+            eprintln!("{}: not a v3 descriptor, or {} is not a v3 onion address", path, address);
+            return 2;
+        },
+    };
+
+    println!("revision counter:       {}", report.revision_counter());
+    println!("lifetime:               {} minutes", report.lifetime());
+    println!("certificate expires:    hour {} since the epoch", report.expiration());
+    println!("descriptor signing key: {}", base64_encode(report.signing_key().as_bytes(), false));
+    match report.blinded_key() {
+        Some(key) => println!("blinded key:            {}", base64_encode(key.as_bytes(), false)),
+        None      => println!("blinded key:            none"),
+    }
+    match report.time_period() {
+        Some(period) => println!("time period:            {}", period),
+        None         => println!("time period:            not found"),
+    }
+    for &problem in report.problems().iter() {
+        println!("problem:                {}", describe_descriptor_problem(problem));
+    }
+
+    match report.is_valid() {
+        true  => 0,
+        false => 1,
+    }
+}
+
+fn parse_validator(spec: &str) -> Option<Validator<'static>> {
+    static NATIVE: Native = Native;
+    static DECOMPRESS_ONLY: DecompressOnly = DecompressOnly;
+    #[cfg(feature = "fiat")]
+    static FIAT: tor22006::backend::Fiat = tor22006::backend::Fiat;
+    #[cfg(feature = "curve25519-dalek-4")]
+    static DALEK4: tor22006::backend::Dalek4 = tor22006::backend::Dalek4;
+
+    let mut parts = spec.splitn(2, ':');
+    let backend: &'static dyn Backend = match parts.next() {
+        Some("native")          => &NATIVE,
+        Some("decompress-only") => &DECOMPRESS_ONLY,
+        #[cfg(feature = "fiat")]
+        Some("fiat")            => &FIAT,
+        #[cfg(feature = "curve25519-dalek-4")]
+        Some("dalek4")          => &DALEK4,
+        _                       => return None,
+    };
+    let policy: KeyPolicy = match parts.next() {
+        Some("prime-order") => KeyPolicy::PrimeOrder,
+        Some("strict")      => KeyPolicy::Strict,
+        _                   => return None,
+    };
+    Some(Validator::new(backend, policy))
+}
+
+fn divergence_corpus(first: &str, second: &str) -> i32 {
+    let (first, second): (Validator, Validator) = match (parse_validator(first), parse_validator(second)) {
+        (Some(first), Some(second)) => (first, second),
+        _ => {
+            eprintln!("a validator is BACKEND:POLICY, for a backend this build has, and prime-order or strict");
+            return 2;
+        },
+    };
+    let corpus: Vec<CorpusEntry> = build_corpus(&first, &second);
+
+    print!("{}", write_corpus(&first, &second, &corpus));
+    0
+}
+
+fn fuzz_corpus(dirs: &[String]) -> i32 {
+    for dir in dirs {
+        match write_seed_corpus(Path::new(dir)) {
+            Ok(count) => println!("{}: {} seeds", dir, count),
+            Err(e)    => {
+                eprintln!("{}: {}", dir, e);
+                return 2;
+            },
+        }
+    }
+    0
+}
+
+fn snapshot(path: Option<&String>) -> i32 {
+    let path: &String = match path {
+        Some(path) => path,
+        None       => {
+            print!("{}", take_snapshot());
+            return 0;
+        },
+    };
+    let text: String = match read_file(path) {
+        Some(text) => text,
+        None       => return 2,
+    };
+    let changes: Vec<SnapshotChange> = match compare_snapshot(&text) {
+        Ok(changes) => changes,
+        Err(_)      => {
+            eprintln!("{}: not a snapshot of this build's validators", path);
+            return 2;
+        },
+    };
+
+    for change in changes.iter() {
+        println!("{}: {} now {}", hex_encode(change.encoding()), change.validator(),
+                 if change.accepted() { "accepts" } else { "rejects" });
+    }
+    match changes.is_empty() {
+        true  => 0,
+        false => 1,
+    }
+}
+
+fn decaf_migration(paths: &[String]) -> i32 {
+    let mut encodings: Vec<[u8; 32]> = Vec::new();
+
+    for path in paths {
+        let text: String = match read_file(path) {
+            Some(text) => text,
+            None       => return 2,
+        };
+
+        for line in text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+            match decode_key(line) {
+                Some(key) => encodings.push(key.to_bytes()),
+                None      => eprintln!("{}: malformed line: {}", path, line),
+            }
+        }
+    }
+
+    let reports: Vec<MigrationReport> = migration_differences(&encodings, KeyPolicy::Strict);
+    for report in reports.iter() {
+        let difference: &str = match report.difference() {
+            MigrationDifference::DecafOnly         => "decaf only",
+            MigrationDifference::RistrettoOnly     => "ristretto only",
+            MigrationDifference::DifferentElements => "different elements",
+        };
+        match report.reencoded() {
+            Some(encoding) => println!("{}: {}: rewrite as {}", hex_encode(report.encoding()), difference,
+                                       hex_encode(encoding.as_bytes())),
+            None           => println!("{}: {}", hex_encode(report.encoding()), difference),
+        }
+    }
+    match reports.is_empty() {
+        true  => 0,
+        false => 1,
+    }
+}
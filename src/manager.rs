@@ -0,0 +1,355 @@
+// Validated keys, kept by what they are used for.
+//
+// A relay or a tool built on this crate ends up holding keys of several
+// kinds: ed25519 identity keys, the signing keys they certify, blinded onion
+// service keys, client authorization keys, and curve25519 ntor onion keys.
+// `KeyManager` validates each as the role requires before storing it, so
+// that nothing held under a role can fail the check it would have been given
+// on use.  Every ed25519 role is held to `KeyPolicy::Strict`, since each of
+// them verifies signatures; an ntor key must be a canonical u-coordinate of a
+// point in the prime-order subgroup, as a relay's own always is.
+//
+// Keys may carry an expiry, and revocations are honoured: a key is only
+// returned by `lookup` while it hasn't expired and isn't revoked.  The whole
+// set serializes to lines of text,
+//
+//    <role> <base64 key> [<expiry>]
+//    revoked <base64 revocation>
+//
+// and every key and revocation is checked again when it is read back.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::CompressedMontgomeryU;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::field::FieldElement;
+
+use encoding::base64_decode;
+use encoding::base64_encode;
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use revocation::Revocation;
+
+/// What a key is used for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyRole {
+    /// A relay's or service's long-term ed25519 identity.
+    Identity,
+    /// A medium-term ed25519 signing key, certified by an identity.
+    Signing,
+    /// A blinded ed25519 onion service key for one time period.
+    Blinded,
+    /// An ed25519 client authorization key.
+    Auth,
+    /// A curve25519 ntor onion key.
+    Ntor,
+}
+
+impl KeyRole {
+    /// The role's name in the serialized form.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            KeyRole::Identity => "identity",
+            KeyRole::Signing  => "signing",
+            KeyRole::Blinded  => "blinded",
+            KeyRole::Auth     => "auth",
+            KeyRole::Ntor     => "ntor",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<KeyRole> {
+        match name {
+            "identity" => Some(KeyRole::Identity),
+            "signing"  => Some(KeyRole::Signing),
+            "blinded"  => Some(KeyRole::Blinded),
+            "auth"     => Some(KeyRole::Auth),
+            "ntor"     => Some(KeyRole::Ntor),
+            _          => None,
+        }
+    }
+}
+
+/// A validated key of either kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoleKey {
+    /// Any of the ed25519 roles.
+    Ed25519(CheckedKey<CompressedEdwardsY>),
+    /// An ntor key.
+    Curve25519(CompressedMontgomeryU),
+}
+
+impl RoleKey {
+    /// The 32-byte encoding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        match *self {
+            RoleKey::Ed25519(ref key)    => key.encoding().as_bytes(),
+            RoleKey::Curve25519(ref key) => &key.0,
+        }
+    }
+}
+
+/// A key held by a `KeyManager`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManagedKey {
+    role: KeyRole,
+    key: RoleKey,
+    expires: Option<u64>,
+}
+
+impl ManagedKey {
+    /// What the key is used for.
+    pub fn role(&self) -> KeyRole {
+        self.role
+    }
+
+    /// The key itself.
+    pub fn key(&self) -> &RoleKey {
+        &self.key
+    }
+
+    /// When the key stops being valid, in seconds since the epoch, if ever.
+    pub fn expires(&self) -> Option<u64> {
+        self.expires
+    }
+}
+
+/// Validated keys, by role.
+#[derive(Clone, Debug, Default)]
+pub struct KeyManager {
+    keys: Vec<ManagedKey>,
+    revocations: Vec<Revocation>,
+}
+
+impl KeyManager {
+    /// An empty manager.
+    pub fn new() -> KeyManager {
+        KeyManager::default()
+    }
+
+    /// Validate `encoding` for `role` and store it, replacing the expiry if
+    /// it is already held under that role.
+    pub fn insert(&mut self, role: KeyRole, encoding: &[u8; 32], expires: Option<u64>) -> Result<(), KeyProblem> {
+        let key: RoleKey = check_for_role(role, encoding)?;
+
+        match self.keys.iter().position(|held| held.role == role && held.key == key) {
+            Some(i) => self.keys[i].expires = expires,
+            None    => self.keys.push(ManagedKey { role, key, expires }),
+        }
+        Ok(())
+    }
+
+    /// Honour `revocation` from now on.
+    pub fn revoke(&mut self, revocation: Revocation) {
+        if !self.revocations.contains(&revocation) {
+            self.revocations.push(revocation);
+        }
+    }
+
+    /// Every key held under `role` which has neither expired nor been
+    /// revoked at time `now`.
+    pub fn lookup(&self, role: KeyRole, now: u64) -> Vec<&ManagedKey> {
+        self.keys.iter().filter(|held| held.role == role && self.is_usable(held, now)).collect()
+    }
+
+    /// Whether `encoding` is held under `role`, and usable at time `now`.
+    pub fn contains(&self, role: KeyRole, encoding: &[u8; 32], now: u64) -> bool {
+        self.lookup(role, now).iter().any(|held| held.key.as_bytes() == encoding)
+    }
+
+    /// Drop every key which has expired or been revoked at time `now`,
+    /// returning how many there were.
+    pub fn expire(&mut self, now: u64) -> usize {
+        let before: usize = self.keys.len();
+        let keys: Vec<ManagedKey> = self.keys.iter().filter(|held| self.is_usable(held, now)).cloned().collect();
+
+        self.keys = keys;
+        before - self.keys.len()
+    }
+
+    /// How many keys are held, usable or not.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether no keys are held.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The serialized form.
+    pub fn to_text(&self) -> String {
+        let mut text: String = String::new();
+
+        for held in self.keys.iter() {
+            text.push_str(held.role.as_str());
+            text.push(' ');
+            text.push_str(&base64_encode(held.key.as_bytes(), false));
+            if let Some(expires) = held.expires {
+                text.push_str(&format!(" {}", expires));
+            }
+            text.push('\n');
+        }
+        for revocation in self.revocations.iter() {
+            text.push_str("revoked ");
+            text.push_str(&base64_encode(&revocation.to_bytes(), false));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Read back the serialized form, checking everything in it again.
+    /// Returns `None` if any line is malformed or holds a key which fails
+    /// validation for its role.
+    pub fn from_text(text: &str) -> Option<KeyManager> {
+        let mut manager: KeyManager = KeyManager::new();
+
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split(' ').collect();
+
+            if fields[0] == "revoked" && fields.len() == 2 {
+                let revocation: Revocation = match base64_decode(fields[1]) {
+                    Some(ref bytes) if bytes.len() == 104 => {
+                        let mut encoding: [u8; 104] = [0u8; 104];
+                        encoding.copy_from_slice(bytes);
+
+                        Revocation::from_bytes(&encoding)?
+                    },
+                    _ => return None,
+                };
+                manager.revoke(revocation);
+                continue;
+            }
+
+            let role: KeyRole = match KeyRole::from_str(fields[0]) {
+                Some(role) if fields.len() == 2 || fields.len() == 3 => role,
+                _                                                    => return None,
+            };
+            let mut encoding: [u8; 32] = [0u8; 32];
+            match base64_decode(fields[1]) {
+                Some(ref bytes) if bytes.len() == 32 => encoding.copy_from_slice(bytes),
+                _                                    => return None,
+            }
+            let expires: Option<u64> = match fields.get(2) {
+                Some(expires) => match expires.parse::<u64>() {
+                    Ok(expires) => Some(expires),
+                    Err(_)      => return None,
+                },
+                None => None,
+            };
+
+            if manager.insert(role, &encoding, expires).is_err() {
+                return None; // the key was invalid
+            }
+        }
+        Some(manager)
+    }
+
+    fn is_usable(&self, held: &ManagedKey, now: u64) -> bool {
+        if held.expires.is_some_and(|expires| now >= expires) {
+            return false;
+        }
+        match held.key {
+            RoleKey::Ed25519(ref key) => !self.revocations.iter().any(|revocation| revocation.revokes(key, now)),
+            RoleKey::Curve25519(_)    => true, // ntor keys can't sign a revocation
+        }
+    }
+}
+
+// Validate `encoding` as `role` requires.
+pub(crate) fn check_for_role(role: KeyRole, encoding: &[u8; 32]) -> Result<RoleKey, KeyProblem> {
+    if role != KeyRole::Ntor {
+        let key: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(CompressedEdwardsY(*encoding));
+
+        return key.check_or_diagnose(KeyPolicy::Strict).map(RoleKey::Ed25519);
+    }
+
+    let u: CompressedMontgomeryU = CompressedMontgomeryU(*encoding);
+
+    if FieldElement::from_bytes(encoding).to_bytes() != *encoding {
+        return Err(KeyProblem::NonCanonical);
+    }
+    // Either lift will do, since P is torsion-free if and only if -P is.
+    let p: ExtendedPoint = match u.decompress() {
+        Some(p) => p,
+        None    => return Err(KeyProblem::NotOnCurve),
+    };
+    if p.is_identity() {
+        return Err(KeyProblem::Identity);
+    }
+
+    match (&p * &constants::l).is_identity() {
+        true  => Ok(RoleKey::Curve25519(u)),
+        false => Err(KeyProblem::Torsioned),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::scalar::Scalar;
+
+    fn point(n: u64) -> ExtendedPoint {
+        &constants::ED25519_BASEPOINT * &Scalar::from_u64(n)
+    }
+
+    #[test]
+    fn roles_are_validated() {
+        let mut manager: KeyManager = KeyManager::new();
+        let torsioned: [u8; 32] = (&point(1) + &constants::EIGHT_TORSION[1]).compress_edwards().to_bytes();
+        let ntor: [u8; 32] = point(2).compress_montgomery().unwrap().to_bytes();
+
+        assert_eq!(manager.insert(KeyRole::Identity, &point(1).compress_edwards().to_bytes(), None), Ok(()));
+        assert_eq!(manager.insert(KeyRole::Signing, &torsioned, None), Err(KeyProblem::Torsioned));
+        assert_eq!(manager.insert(KeyRole::Ntor, &ntor, None), Ok(()));
+        assert_eq!(manager.insert(KeyRole::Ntor, &[0u8; 32], None), Err(KeyProblem::Torsioned));
+
+        // An ntor key is not an identity key, even if it happens to decode.
+        assert!(!manager.contains(KeyRole::Identity, &ntor, 0));
+        assert!(manager.contains(KeyRole::Ntor, &ntor, 0));
+        assert_eq!(manager.len(), 2);
+    }
+
+    #[test]
+    fn expiry_and_revocation() {
+        let mut manager: KeyManager = KeyManager::new();
+        let seed: [u8; 32] = [0x22; 32];
+        let revocation: Revocation = Revocation::create(&seed, 2000).unwrap();
+        let revoked: [u8; 32] = revocation.key().encoding().to_bytes();
+        let expiring: [u8; 32] = point(1).compress_edwards().to_bytes();
+
+        manager.insert(KeyRole::Signing, &revoked, None).unwrap();
+        manager.insert(KeyRole::Signing, &expiring, Some(1000)).unwrap();
+        manager.revoke(revocation);
+
+        assert_eq!(manager.lookup(KeyRole::Signing, 999).len(), 2);
+        assert_eq!(manager.lookup(KeyRole::Signing, 1000).len(), 1);
+        assert_eq!(manager.lookup(KeyRole::Signing, 2000).len(), 0);
+        assert_eq!(manager.expire(1500), 1);
+        assert_eq!(manager.len(), 1);
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        let mut manager: KeyManager = KeyManager::new();
+
+        manager.insert(KeyRole::Identity, &point(1).compress_edwards().to_bytes(), None).unwrap();
+        manager.insert(KeyRole::Blinded, &point(2).compress_edwards().to_bytes(), Some(1500000000)).unwrap();
+        manager.insert(KeyRole::Ntor, &point(3).compress_montgomery().unwrap().to_bytes(), None).unwrap();
+        manager.revoke(Revocation::create(&[0x22; 32], 2000).unwrap());
+
+        let text: String = manager.to_text();
+        let restored: KeyManager = KeyManager::from_text(&text).unwrap();
+
+        assert_eq!(restored.to_text(), text);
+        assert_eq!(restored.lookup(KeyRole::Blinded, 0)[0].expires(), Some(1500000000));
+
+        // A torsioned key written in by hand is caught on reading.
+        let torsioned: [u8; 32] = (&point(1) + &constants::EIGHT_TORSION[1]).compress_edwards().to_bytes();
+        let tampered: String = format!("{}identity {}\n", text, base64_encode(&torsioned, false));
+        assert!(KeyManager::from_text(&tampered).is_none());
+    }
+}
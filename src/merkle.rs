@@ -0,0 +1,275 @@
+// A Merkle tree over a set of validated keys, so that auditors can each
+// publish one hash for "the keys we validated at time T" and later show
+// that a given key was, or compare sets cheaply.
+//
+// The tree is RFC 9162's (and RFC 6962's), over the key encodings sorted
+// and with duplicates removed, so that the same set always gives the same
+// root however it was collected:
+//
+//    leaf(k)    = SHA-256(0x00 ‖ k)
+//    node(l, r) = SHA-256(0x01 ‖ l ‖ r)
+//
+// with a tree of n > 1 leaves split into the first 2^i < n and the rest, for
+// the largest such i.  An inclusion proof is the usual audit path, and is
+// checked with RFC 9162 §2.1.3.2, so that any CT-style verifier can check
+// one too; `translog` builds on the same hashes.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use key::CheckedKey;
+use traits::PrimeOrderEncoding;
+
+/// A commitment to a set of keys, as of some time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeySetCommitment {
+    leaves: Vec<[u8; 32]>,
+    encodings: Vec<Vec<u8>>,
+    root: [u8; 32],
+    timestamp: u64,
+}
+
+impl KeySetCommitment {
+    /// Commit to `keys`, which were validated at `timestamp`.
+    pub fn new<T: PrimeOrderEncoding>(keys: &[CheckedKey<T>], timestamp: u64) -> KeySetCommitment {
+        let mut encodings: Vec<Vec<u8>> = keys.iter().map(|key| key.encoding().as_slice().to_vec()).collect();
+
+        encodings.sort();
+        encodings.dedup();
+
+        let leaves: Vec<[u8; 32]> = encodings.iter().map(|encoding| leaf_hash(encoding)).collect();
+        let root: [u8; 32] = tree_hash(&leaves);
+
+        KeySetCommitment { leaves, encodings, root, timestamp }
+    }
+
+    /// The root of the tree.
+    pub fn root(&self) -> &[u8; 32] {
+        &self.root
+    }
+
+    /// When the keys were validated, in seconds since the epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// How many distinct keys there are.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// A proof that `encoding` is in the set, if it is.
+    pub fn prove(&self, encoding: &[u8]) -> Option<InclusionProof> {
+        let index: usize = match self.encodings.binary_search_by(|e| e.as_slice().cmp(encoding)) {
+            Ok(index) => index,
+            Err(_)    => return None, // the key isn't in the set
+        };
+
+        Some(InclusionProof { index: index as u64, size: self.leaves.len() as u64,
+                              path: audit_path(index, &self.leaves) })
+    }
+}
+
+/// A proof that one leaf is in a tree of a given size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    index: u64,
+    size: u64,
+    path: Vec<[u8; 32]>,
+}
+
+impl InclusionProof {
+    /// A proof received from elsewhere, for the leaf at `index` of a tree of
+    /// `size` leaves.
+    pub fn new(index: u64, size: u64, path: Vec<[u8; 32]>) -> InclusionProof {
+        InclusionProof { index, size, path }
+    }
+
+    /// The leaf's position.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The number of leaves in the tree.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The sibling hashes, from the leaf up.
+    pub fn path(&self) -> &[[u8; 32]] {
+        &self.path
+    }
+
+    /// Whether this proves that `encoding` is a leaf of the tree with `root`.
+    pub fn verify(&self, root: &[u8; 32], encoding: &[u8]) -> bool {
+        self.verify_leaf_hash(root, &leaf_hash(encoding))
+    }
+
+    /// As `verify`, for a leaf which has already been hashed.
+    pub fn verify_leaf_hash(&self, root: &[u8; 32], leaf: &[u8; 32]) -> bool {
+        if self.index >= self.size {
+            return false;
+        }
+
+        let (mut f, mut s): (u64, u64) = (self.index, self.size - 1);
+        let mut r: [u8; 32] = *leaf;
+
+        for p in self.path.iter() {
+            if s == 0 {
+                return false; // the path was too long
+            }
+            if f & 1 == 1 || f == s {
+                r = node_hash(p, &r);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                r = node_hash(&r, p);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+
+        s == 0 && r == *root
+    }
+}
+
+/// SHA-256(0x00 ‖ data).
+pub fn leaf_hash(data: &[u8]) -> [u8; 32] {
+    let mut h: Sha256 = Sha256::default();
+    let mut hash: [u8; 32] = [0u8; 32];
+
+    h.input(&[0x00]);
+    h.input(data);
+    hash.copy_from_slice(h.result().as_slice());
+    hash
+}
+
+/// SHA-256(0x01 ‖ left ‖ right).
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut h: Sha256 = Sha256::default();
+    let mut hash: [u8; 32] = [0u8; 32];
+
+    h.input(&[0x01]);
+    h.input(left);
+    h.input(right);
+    hash.copy_from_slice(h.result().as_slice());
+    hash
+}
+
+/// The root of the tree over `leaves`, which are already hashed.  The
+/// empty tree's root is SHA-256 of nothing.
+pub fn tree_hash(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves.len() {
+        0 => {
+            let mut hash: [u8; 32] = [0u8; 32];
+
+            hash.copy_from_slice(Sha256::default().result().as_slice());
+            hash
+        },
+        1 => leaves[0],
+        n => {
+            let k: usize = split(n);
+
+            node_hash(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..]))
+        },
+    }
+}
+
+// RFC 6962's PATH(m, D[n]).
+pub(crate) fn audit_path(m: usize, leaves: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let n: usize = leaves.len();
+
+    if n <= 1 {
+        return Vec::new();
+    }
+
+    let k: usize = split(n);
+    let mut path: Vec<[u8; 32]>;
+
+    if m < k {
+        path = audit_path(m, &leaves[..k]);
+        path.push(tree_hash(&leaves[k..]));
+    } else {
+        path = audit_path(m - k, &leaves[k..]);
+        path.push(tree_hash(&leaves[..k]));
+    }
+    path
+}
+
+// The largest power of two less than n, for n > 1.
+pub(crate) fn split(n: usize) -> usize {
+    let mut k: usize = 1;
+
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::CompressedEdwardsY;
+    use curve25519_dalek::scalar::Scalar;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    fn keys(count: u64) -> Vec<CheckedKey<CompressedEdwardsY>> {
+        (1..count + 1).map(|n| {
+            let key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * &Scalar::from_u64(n)).compress_edwards();
+
+            UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap()
+        }).collect()
+    }
+
+    #[test]
+    fn every_key_has_a_proof() {
+        for count in 1..12 {
+            let keys: Vec<CheckedKey<CompressedEdwardsY>> = keys(count);
+            let commitment: KeySetCommitment = KeySetCommitment::new(&keys, 1500000000);
+
+            for key in keys.iter() {
+                let proof: InclusionProof = commitment.prove(key.encoding().as_bytes()).unwrap();
+
+                assert!(proof.verify(commitment.root(), key.encoding().as_bytes()));
+                assert!(!proof.verify(commitment.root(), &[0u8; 32]));
+            }
+        }
+    }
+
+    #[test]
+    fn sets_commit_independently_of_order() {
+        let mut keys: Vec<CheckedKey<CompressedEdwardsY>> = keys(5);
+        let commitment: KeySetCommitment = KeySetCommitment::new(&keys, 0);
+
+        keys.reverse();
+        keys.push(keys[0].clone());
+        assert_eq!(KeySetCommitment::new(&keys, 0), commitment);
+        assert_eq!(commitment.len(), 5);
+        assert!(commitment.prove(&[0u8; 32]).is_none());
+    }
+
+    #[test]
+    fn proofs_are_bound_to_position() {
+        let keys: Vec<CheckedKey<CompressedEdwardsY>> = keys(7);
+        let commitment: KeySetCommitment = KeySetCommitment::new(&keys, 0);
+        let proof: InclusionProof = commitment.prove(keys[3].encoding().as_bytes()).unwrap();
+
+        let moved: InclusionProof = InclusionProof::new(proof.index() ^ 1, proof.size(), proof.path().to_vec());
+        let longer: InclusionProof = InclusionProof::new(proof.index(), proof.size(),
+                                                         [proof.path(), &[[0u8; 32]][..]].concat());
+
+        assert!(!moved.verify(commitment.root(), keys[3].encoding().as_bytes()));
+        assert!(!longer.verify(commitment.root(), keys[3].encoding().as_bytes()));
+    }
+}
@@ -0,0 +1,137 @@
+// Validation counters as Prometheus metrics, behind the `prometheus` feature.
+//
+// Two metric families are kept:
+//
+//    tor22006_key_checks_total{source, policy, outcome}
+//    tor22006_batch_duration_seconds{source}
+//
+// where `outcome` is "accepted" or the `KeyProblem` a key was rejected for,
+// in snake case.  They are registered into whatever `Registry` the service
+// already exports, so that alerting on a spike of, say, torsioned keys from
+// one source is a single query.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use prometheus::HistogramOpts;
+use prometheus::HistogramVec;
+use prometheus::IntCounterVec;
+use prometheus::Opts;
+use prometheus::Registry;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The metrics for one service.
+#[derive(Clone)]
+pub struct Metrics {
+    checks: IntCounterVec,
+    batch_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let checks: IntCounterVec = IntCounterVec::new(
+            Opts::new("tor22006_key_checks_total", "Public keys checked, by outcome."),
+            &["source", "policy", "outcome"]).expect("the metric is well-formed");
+        let batch_duration: HistogramVec = HistogramVec::new(
+            HistogramOpts::new("tor22006_batch_duration_seconds", "Time taken to check a batch of keys."),
+            &["source"]).expect("the metric is well-formed");
+
+        Metrics { checks, batch_duration }
+    }
+
+    /// Register these metrics with `registry`.
+    pub fn register(&self, registry: &Registry) -> ::prometheus::Result<()> {
+        registry.register(Box::new(self.checks.clone()))?;
+        registry.register(Box::new(self.batch_duration.clone()))
+    }
+
+    /// Count one outcome: `None` for a key which was accepted, or the
+    /// problem which got it rejected.
+    pub fn record(&self, source: &str, policy: KeyPolicy, outcome: Option<KeyProblem>) {
+        self.checks.with_label_values(&[source, policy_label(policy), outcome_label(outcome)]).inc();
+    }
+
+    /// Check `key` under `policy`, counting the outcome under `source`.
+    pub fn check(&self, source: &str, key: &UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+        -> Option<CheckedKey<CompressedEdwardsY>>
+    {
+        let checked: Result<CheckedKey<CompressedEdwardsY>, KeyProblem> = key.check_or_diagnose(policy);
+
+        self.record(source, policy, checked.as_ref().err().copied());
+        checked.ok()
+    }
+
+    /// Check every key in `keys`, counting each outcome and the time taken
+    /// for the whole batch.
+    pub fn check_batch(&self, source: &str, keys: &[UncheckedKey<CompressedEdwardsY>], policy: KeyPolicy)
+        -> Vec<Option<CheckedKey<CompressedEdwardsY>>>
+    {
+        let start: Instant = Instant::now();
+        let checked: Vec<Option<CheckedKey<CompressedEdwardsY>>> =
+            keys.iter().map(|key| self.check(source, key, policy)).collect();
+        let elapsed: Duration = start.elapsed();
+
+        self.batch_duration.with_label_values(&[source])
+            .observe(elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9);
+        checked
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+fn policy_label(policy: KeyPolicy) -> &'static str {
+    match policy {
+        KeyPolicy::PrimeOrder => "prime_order",
+        KeyPolicy::Strict     => "strict",
+    }
+}
+
+fn outcome_label(outcome: Option<KeyProblem>) -> &'static str {
+    match outcome {
+        None          => "accepted",
+        Some(problem) => problem.as_str(),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use prometheus::Encoder;
+    use prometheus::TextEncoder;
+
+    use curve25519_dalek::constants;
+
+    #[test]
+    fn counters_are_exported() {
+        let registry: Registry = Registry::new();
+        let metrics: Metrics = Metrics::new();
+        metrics.register(&registry).unwrap();
+
+        let keys: [UncheckedKey<CompressedEdwardsY>; 2] = [
+            constants::ED25519_BASEPOINT.compress_edwards().into(),
+            constants::EIGHT_TORSION[1].compress_edwards().into(),
+        ];
+        metrics.check_batch("consensus", &keys, KeyPolicy::Strict);
+
+        let mut text: Vec<u8> = Vec::new();
+        TextEncoder::new().encode(&registry.gather(), &mut text).unwrap();
+        let text: String = String::from_utf8(text).unwrap();
+
+        assert!(text.contains(
+            "tor22006_key_checks_total{outcome=\"accepted\",policy=\"strict\",source=\"consensus\"} 1"));
+        assert!(text.contains(
+            "tor22006_key_checks_total{outcome=\"torsioned\",policy=\"strict\",source=\"consensus\"} 1"));
+        assert!(text.contains("tor22006_batch_duration_seconds_count{source=\"consensus\"} 1"));
+    }
+}
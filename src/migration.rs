@@ -0,0 +1,136 @@
+// Evidence for moving tor tooling from curve25519-dalek's decaf encoding to
+// ristretto255.
+//
+// Both name elements of the same prime-order group, 2·E modulo the
+// 4-torsion, but they encode them differently, so 32 bytes stored as one
+// mean something else, or nothing, read as the other.  `dual_run` reads an
+// encoding both ways, under a `KeyPolicy`, and reports it if only one path
+// accepts it or if both do but name different elements; for anything decaf
+// accepts it also gives the ristretto encoding of the same element, which is
+// what a stored key has to be rewritten to.  A migration can only read old
+// keys as ristretto in place once `migration_differences` over every stored
+// key comes back empty, which for real keys it essentially never will.
+
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::decaf::CompressedDecaf;
+use curve25519_dalek::decaf::DecafPoint;
+
+use edwards::mul_by_pow_2;
+use policy::KeyPolicy;
+use ristretto::CompressedRistretto;
+use ristretto::RistrettoPoint;
+use traits::PrimeOrderEncoding;
+
+/// How the two paths differ on an encoding.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MigrationDifference {
+    /// Only the decaf path accepted it.
+    DecafOnly,
+    /// Only the ristretto path accepted it.
+    RistrettoOnly,
+    /// Both accepted it, as different elements.
+    DifferentElements,
+}
+
+/// An encoding the two paths differ on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MigrationReport {
+    encoding: [u8; 32],
+    difference: MigrationDifference,
+    reencoded: Option<CompressedRistretto>,
+}
+
+impl MigrationReport {
+    /// The encoding.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// How the paths differ on it.
+    pub fn difference(&self) -> MigrationDifference {
+        self.difference
+    }
+
+    /// The ristretto encoding of the element decaf read, if decaf accepted
+    /// the encoding.
+    pub fn reencoded(&self) -> Option<CompressedRistretto> {
+        self.reencoded
+    }
+}
+
+/// Read `encoding` as both decaf and ristretto under `policy`, and report it
+/// if they differ.
+pub fn dual_run(encoding: &[u8; 32], policy: KeyPolicy) -> Option<MigrationReport> {
+    let (decaf, ristretto): (Option<DecafPoint>, Option<RistrettoPoint>) = match policy {
+        KeyPolicy::PrimeOrder => (CompressedDecaf(*encoding).validate(), CompressedRistretto(*encoding).validate()),
+        KeyPolicy::Strict     => (CompressedDecaf(*encoding).validate_strict(),
+                                  CompressedRistretto(*encoding).validate_strict()),
+    };
+    let difference: MigrationDifference = match (decaf, ristretto) {
+        (None, None)       => return None,
+        (Some(_), None)    => MigrationDifference::DecafOnly,
+        (None, Some(_))    => MigrationDifference::RistrettoOnly,
+        (Some(d), Some(r)) => match same_element(&d.0, &r.0) {
+            true  => return None,
+            false => MigrationDifference::DifferentElements,
+        },
+    };
+
+    Some(MigrationReport {
+        encoding: *encoding,
+        difference,
+        reencoded: decaf.map(|d| RistrettoPoint(d.0).compress()),
+    })
+}
+
+/// Every one of `encodings` which the two paths differ on under `policy`,
+/// in order.
+pub fn migration_differences(encodings: &[[u8; 32]], policy: KeyPolicy) -> Vec<MigrationReport> {
+    encodings.iter().filter_map(|encoding| dual_run(encoding, policy)).collect()
+}
+
+// Whether two points are the same element of 2·E modulo the 4-torsion.
+fn same_element(p: &ExtendedPoint, q: &ExtendedPoint) -> bool {
+    mul_by_pow_2(&(p - q), 2).is_identity()
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn points() -> Vec<ExtendedPoint> {
+        (1..16u64).map(|k| &Scalar::from_u64(k * 22006) * &constants::ED25519_BASEPOINT).collect()
+    }
+
+    #[test]
+    fn decaf_keys_must_be_reencoded() {
+        let encodings: Vec<[u8; 32]> = points().iter().map(|p| DecafPoint(*p).compress().0).collect();
+        let reports: Vec<MigrationReport> = migration_differences(&encodings, KeyPolicy::Strict);
+
+        // Not one decaf key means the same thing read as ristretto.
+        assert_eq!(reports.len(), encodings.len());
+        for (report, p) in reports.iter().zip(points().iter()) {
+            assert!(report.difference() != MigrationDifference::RistrettoOnly);
+
+            let reencoded: RistrettoPoint = report.reencoded().unwrap().decompress().unwrap();
+            assert!(same_element(&reencoded.0, p));
+        }
+    }
+
+    #[test]
+    fn ristretto_keys_are_reported_too() {
+        let encodings: Vec<[u8; 32]> = points().iter().map(|p| RistrettoPoint(*p).compress().0).collect();
+        let reports: Vec<MigrationReport> = migration_differences(&encodings, KeyPolicy::PrimeOrder);
+
+        assert_eq!(reports.len(), encodings.len());
+        for report in reports.iter() {
+            assert!(report.difference() != MigrationDifference::DecafOnly);
+        }
+        assert_eq!(dual_run(&[0u8; 32], KeyPolicy::PrimeOrder), None); // the identity, both ways
+        assert_eq!(dual_run(&[0u8; 32], KeyPolicy::Strict), None);
+    }
+}
@@ -0,0 +1,241 @@
+// Checking that the validators agree with each other, and with what is
+// known about the curve, on encodings a bit or two away from valid ones.
+//
+// An encoding one flipped bit from a valid key is where a classification
+// bug is most likely to hide: a sign bit misread, a carry dropped while
+// reducing y, a check skipped for some coset of the torsion.  `mutations`
+// lists flips of every single bit, and of every pair of bits within a byte
+// or involving the sign bit: 1400 mutants of each encoding, rather than the
+// 32896 with every pair, but covering the pairs likely to interact.
+// `check_mutants` classifies the mutants of each encoding with every
+// validator the crate has, and reports every invariant a classification
+// breaks:
+//
+//  - `UncheckedKey::check`, `PrimeOrderEncoding::validate` and
+//    `mult_by_cofactor_and_validate` all agree, for each policy they have;
+//  - the verdicts are the ones `UncheckedKey::diagnose` implies, and no key
+//    passes `Strict` without passing `PrimeOrder`;
+//  - flipping only the sign bit, which negates x, never moves a point into
+//    or out of the prime-order subgroup;
+//  - every backend given agrees with `UncheckedKey::check`.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use backend::Backend;
+use edwards::mult_by_cofactor_and_validate;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use traits::PrimeOrderEncoding;
+
+/// Which bits of an encoding were flipped, numbering bit `i` of byte `j` as
+/// `8 * j + i`, so that the sign bit is 255.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// One bit.
+    Single(usize),
+    /// Two different bits, the lower first.
+    Double(usize, usize),
+}
+
+impl Mutation {
+    /// `encoding` with the bits flipped.
+    pub fn apply(&self, encoding: &[u8; 32]) -> [u8; 32] {
+        let mut mutant: [u8; 32] = *encoding;
+
+        match *self {
+            Mutation::Single(i)    => flip(&mut mutant, i),
+            Mutation::Double(i, j) => { flip(&mut mutant, i); flip(&mut mutant, j); },
+        }
+        mutant
+    }
+}
+
+/// An invariant which a mutant broke.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    original: [u8; 32],
+    mutation: Mutation,
+    invariant: &'static str,
+}
+
+impl Violation {
+    /// The encoding which was mutated.
+    pub fn original(&self) -> &[u8; 32] {
+        &self.original
+    }
+
+    /// How it was mutated.
+    pub fn mutation(&self) -> Mutation {
+        self.mutation
+    }
+
+    /// The mutant.
+    pub fn mutant(&self) -> [u8; 32] {
+        self.mutation.apply(&self.original)
+    }
+
+    /// What was wrong with its classification.
+    pub fn invariant(&self) -> &'static str {
+        self.invariant
+    }
+}
+
+/// Every mutation described above, the single flips first.
+pub fn mutations() -> Vec<Mutation> {
+    let mut mutations: Vec<Mutation> = (0..256).map(Mutation::Single).collect();
+
+    for i in 0..256 {
+        for j in i + 1..256 {
+            if i / 8 == j / 8 || j == 255 {
+                mutations.push(Mutation::Double(i, j));
+            }
+        }
+    }
+    mutations
+}
+
+/// Classify every mutant of every one of `encodings` under `mutations`,
+/// usually all of `mutations()`, and return each invariant broken, checking
+/// `backends` against `UncheckedKey::check` as well.
+pub fn check_mutants(encodings: &[[u8; 32]], mutations: &[Mutation], backends: &[&dyn Backend]) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = Vec::new();
+
+    for original in encodings.iter() {
+        let original_order: Option<bool> = prime_order(original);
+
+        for &mutation in mutations.iter() {
+            let mut broken = |invariant: &'static str| violations.push(Violation {
+                original: *original, mutation, invariant,
+            });
+            let mutant: [u8; 32] = mutation.apply(original);
+
+            for invariant in classify(&mutant, backends) {
+                broken(invariant);
+            }
+            if mutation == Mutation::Single(255) {
+                if let (Some(before), Some(after)) = (original_order, prime_order(&mutant)) {
+                    if before != after {
+                        broken("negating x changed subgroup membership");
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
+
+// Every invariant the verdicts on `encoding` break.
+fn classify(encoding: &[u8; 32], backends: &[&dyn Backend]) -> Vec<&'static str> {
+    let mut broken: Vec<&'static str> = Vec::new();
+    let key: CompressedEdwardsY = CompressedEdwardsY(*encoding);
+    let unchecked: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(key);
+    let problem: Option<KeyProblem> = unchecked.diagnose();
+    let prime_order: bool = unchecked.check(KeyPolicy::PrimeOrder).is_some();
+    let strict: bool = unchecked.check(KeyPolicy::Strict).is_some();
+
+    if key.validate().is_some() != prime_order {
+        broken.push("validate disagrees with check under PrimeOrder");
+    }
+    if key.validate_strict().is_some() != strict {
+        broken.push("validate_strict disagrees with check under Strict");
+    }
+    if mult_by_cofactor_and_validate(&key).is_some() != prime_order {
+        broken.push("mult_by_cofactor_and_validate disagrees with check under PrimeOrder");
+    }
+    if strict && !prime_order {
+        broken.push("passed Strict but not PrimeOrder");
+    }
+    if strict != problem.is_none() {
+        broken.push("diagnose disagrees with check under Strict");
+    }
+    match problem {
+        Some(KeyProblem::NonCanonical) | Some(KeyProblem::Identity) | None => if !prime_order {
+            broken.push("diagnose disagrees with check under PrimeOrder");
+        },
+        Some(_) => if prime_order {
+            broken.push("diagnose disagrees with check under PrimeOrder");
+        },
+    }
+    for backend in backends.iter() {
+        if backend.accepts(encoding, KeyPolicy::PrimeOrder) != prime_order ||
+            backend.accepts(encoding, KeyPolicy::Strict) != strict {
+            broken.push("a backend disagrees with check");
+        }
+    }
+    broken
+}
+
+// Whether `encoding` is in the prime-order subgroup, if it is a point.
+fn prime_order(encoding: &[u8; 32]) -> Option<bool> {
+    match UncheckedKey::new(CompressedEdwardsY(*encoding)).diagnose() {
+        Some(KeyProblem::NotOnCurve) | Some(KeyProblem::Malformed) => None,
+        Some(KeyProblem::Torsioned)                                => Some(false),
+        _                                                          => Some(true),
+    }
+}
+
+fn flip(encoding: &mut [u8; 32], bit: usize) {
+    encoding[bit / 8] ^= 1 << (bit % 8);
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use backend::Native;
+
+    fn valid() -> Vec<[u8; 32]> {
+        [1u64, 22006].iter()
+            .map(|&k| (&Scalar::from_u64(k) * &constants::ED25519_BASEPOINT).compress_edwards().to_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn mutations_are_distinct() {
+        let encoding: [u8; 32] = valid()[0];
+        let mut mutants: Vec<[u8; 32]> = mutations().iter().map(|mutation| mutation.apply(&encoding)).collect();
+
+        assert_eq!(mutants.len(), 256 + 32 * 28 + 255 - 7);
+        mutants.sort();
+        mutants.dedup();
+        assert_eq!(mutants.len(), mutations().len());
+        assert!(!mutants.contains(&encoding));
+    }
+
+    #[test]
+    fn validators_agree_on_mutants() {
+        let violations: Vec<Violation> = check_mutants(&valid()[..1], &mutations(), &[&Native]);
+        let torsion: [u8; 32] = constants::EIGHT_TORSION[1].compress_edwards().to_bytes();
+
+        assert!(violations.is_empty(), "{:?}", violations.first());
+        assert!(check_mutants(&[valid()[1], torsion], &mutations()[..256], &[]).is_empty());
+    }
+
+    // Gets every key with the sign bit set wrong under `Strict`.
+    struct SignBlind;
+
+    impl Backend for SignBlind {
+        fn name(&self) -> &'static str {
+            "sign-blind"
+        }
+
+        fn accepts(&self, encoding: &[u8; 32], policy: KeyPolicy) -> bool {
+            Native.accepts(encoding, policy) != (policy == KeyPolicy::Strict && encoding[31] & 0x80 != 0)
+        }
+    }
+
+    #[test]
+    fn disagreements_are_reported() {
+        let original: [u8; 32] = valid()[0];
+        let violations: Vec<Violation> = check_mutants(&[original], &mutations()[..256], &[&SignBlind]);
+
+        assert!(!violations.is_empty());
+        assert!(violations.iter().all(|violation| violation.invariant() == "a backend disagrees with check"));
+        assert!(violations.iter().all(|violation| violation.mutant()[31] & 0x80 != original[31] & 0x80));
+        assert!(violations.iter().any(|violation| violation.mutation() == Mutation::Single(255)));
+    }
+}
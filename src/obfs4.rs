@@ -0,0 +1,116 @@
+// obfs4 hides its ntor public keys on the wire as Elligator2 representatives.
+// A bridge (or a client) receiving a representative has to recover the
+// Montgomery u-coordinate before it can do anything with it, and it is at that
+// point that the same validation question as for ed25519 keys comes up.
+
+use curve25519_dalek::curve::CompressedMontgomeryU;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::field::FieldElement;
+
+use edwards::mul_by_pow_2;
+use elligator::elligator2;
+
+/// Recover the public key from a 32-byte obfs4 representative, rejecting
+/// only a key of small order.
+///
+/// Representatives only carry 254 bits of information.  obfs4 fills the top
+/// two bits of the last byte with random padding, so they are cleared here
+/// before decoding; the remaining value is always below p, so there is no
+/// non-canonical representative to reject.
+///
+/// A current obfs4 peer's key is not in the prime-order subgroup: to keep
+/// representatives indistinguishable from random, obfs4proxy adds a random
+/// point of small order to its public key before computing one, and the
+/// other side's clamped X25519 scalar, a multiple of 8, cancels it again.
+/// So the torsion component is only checked once the cofactor is cleared,
+/// and a key is refused, with `None`, only when nothing is left of it, that
+/// is, when it has small order and every shared secret with it is zero.
+pub fn obfs4_representative_to_public_key(representative: &[u8; 32]) -> Option<CompressedMontgomeryU> {
+    let mut cleansed: [u8; 32] = *representative;
+    cleansed[31] &= 0x3f;
+
+    let r: FieldElement = FieldElement::from_bytes(&cleansed);
+    let u: CompressedMontgomeryU = CompressedMontgomeryU(elligator2(&r).to_bytes());
+
+    // The sign of the Edwards x-coordinate is lost in the u-coordinate, but
+    // [8]P is the identity if and only if [8](-P) is, so either lift will do.
+    let p: ExtendedPoint = u.decompress()?;
+
+    match mul_by_pow_2(&p, 3).is_identity() {
+        true  => None, // the point has small order
+        false => Some(u),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    // Generated with an independent big-integer implementation of the map.
+    static REPRESENTATIVE: [u8; 32] = [
+        0xe7, 0x7b, 0x9a, 0x9a, 0xe9, 0xe3, 0x0b, 0x0d,
+        0xbd, 0xb6, 0xf5, 0x10, 0xa2, 0x64, 0xef, 0x9d,
+        0xe7, 0x81, 0x50, 0x1d, 0x7b, 0x6b, 0x92, 0xae,
+        0x89, 0xeb, 0x05, 0x9c, 0x5a, 0xb7, 0x43, 0x1b ];
+    static PUBLIC_KEY: [u8; 32] = [
+        0x7a, 0xeb, 0x9f, 0xad, 0x21, 0xb7, 0x56, 0xef,
+        0xce, 0x25, 0xe4, 0x54, 0xb9, 0xe1, 0xe0, 0x76,
+        0x9b, 0x9a, 0x36, 0x86, 0x6e, 0xf0, 0xd3, 0xbd,
+        0x86, 0xce, 0x42, 0xce, 0x4d, 0x00, 0xd7, 0x7b ];
+
+    #[test]
+    fn valid_representative() {
+        let key = obfs4_representative_to_public_key(&REPRESENTATIVE);
+
+        assert_eq!(key, Some(CompressedMontgomeryU(PUBLIC_KEY)));
+    }
+
+    #[test]
+    fn high_bits_are_ignored() {
+        let mut padded: [u8; 32] = REPRESENTATIVE;
+        padded[31] |= 0xc0;
+
+        let key = obfs4_representative_to_public_key(&padded);
+
+        assert_eq!(key, Some(CompressedMontgomeryU(PUBLIC_KEY)));
+    }
+
+    // A representative of [k]B + T, for T of order 8, as obfs4proxy makes
+    // one: r with r² = -(u + A)/(2u), which the map sends back to u, and
+    // which fits in 254 bits.
+    fn torsioned_representative() -> ([u8; 32], CompressedMontgomeryU) {
+        for k in 1..64u64 {
+            let p: ExtendedPoint = &(&Scalar::from_u64(k) * &constants::ED25519_BASEPOINT) +
+                                   &constants::EIGHT_TORSION[1];
+            let u: FieldElement = FieldElement::from_bytes(&p.compress_montgomery().unwrap().to_bytes());
+            let (square, r): (u8, FieldElement) =
+                FieldElement::sqrt_ratio(&-&(&u + &constants::A), &(&u + &u));
+
+            for candidate in [r, -&r].iter() {
+                if square == 1 && candidate.to_bytes()[31] & 0xc0 == 0 {
+                    return (candidate.to_bytes(), CompressedMontgomeryU(u.to_bytes()));
+                }
+            }
+        }
+        panic!("no representable key");
+    }
+
+    #[test]
+    fn torsioned_representatives_are_accepted() {
+        let (representative, u): ([u8; 32], CompressedMontgomeryU) = torsioned_representative();
+        let p: ExtendedPoint = u.decompress().unwrap();
+
+        assert!(!(&p * &constants::l).is_identity());
+        assert_eq!(obfs4_representative_to_public_key(&representative), Some(u));
+    }
+
+    #[test]
+    fn small_order_representatives_are_rejected() {
+        // r = 0 maps to u = 0, the point of order 2.
+        assert!(obfs4_representative_to_public_key(&[0u8; 32]).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+// An interface for validating very large corpora of keys on an accelerator
+// such as a GPU, behind the `unstable-offload` feature, without having to
+// trust it.  There is no GPU backend here, only the interface, and it may
+// change once there is one.
+//
+// A `Device` takes a whole batch of encodings and returns a verdict for
+// each.  `validate_offloaded` hands it a batch, then redoes a random
+// sample of the batch on the CPU with `backend::Native`, and refuses the
+// whole batch if the device got any of the sample wrong, or returned the
+// wrong number of verdicts.  A device which is wrong about a fraction f of
+// keys gets through a sample of n with probability about (1 - f)^n, so the
+// sample size is the caller's trade between speed and assurance.
+//
+// No device ships with the crate: binding OpenCL or CUDA needs a toolchain
+// and drivers which the crate's own builds and tests can't assume, and a
+// kernel nobody can run would be untested field arithmetic.  A wrapper for
+// one implements `Device` outside the crate, and gets the cross-checking
+// here for free.
+
+use backend::Backend;
+use backend::Native;
+use entropy::EntropySource;
+use policy::KeyPolicy;
+
+/// An accelerator which validates batches of keys.
+pub trait Device {
+    /// A short name for reports.
+    fn name(&self) -> &'static str;
+
+    /// Whether each of `encodings` passes under `policy`, in order.
+    fn validate_batch(&self, encodings: &[[u8; 32]], policy: KeyPolicy) -> Vec<bool>;
+}
+
+/// Why a device's verdicts on a batch were refused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OffloadError {
+    /// The device returned this many verdicts, not one per key.
+    WrongLength(usize),
+    /// The device's verdict on the key at this index disagreed with the CPU.
+    Mismatch(usize),
+}
+
+/// Validate `encodings` under `policy` on `device`, checking `sample` keys
+/// of the batch, chosen with `entropy`, against the CPU.  Keys may be
+/// sampled more than once, and a sample at least as large as the batch
+/// checks every key instead.
+pub fn validate_offloaded<D, E>(device: &D, encodings: &[[u8; 32]], policy: KeyPolicy,
+                                sample: usize, entropy: &mut E) -> Result<Vec<bool>, OffloadError>
+    where D: Device, E: EntropySource
+{
+    let verdicts: Vec<bool> = device.validate_batch(encodings, policy);
+
+    if verdicts.len() != encodings.len() {
+        return Err(OffloadError::WrongLength(verdicts.len()));
+    }
+
+    let check = |i: usize| match Native.accepts(&encodings[i], policy) == verdicts[i] {
+        true  => Ok(()),
+        false => Err(OffloadError::Mismatch(i)),
+    };
+
+    if sample >= encodings.len() {
+        (0..encodings.len()).try_for_each(check)?;
+        return Ok(verdicts);
+    }
+    for _ in 0..sample {
+        let mut bytes: [u8; 8] = [0u8; 8];
+        entropy.fill_bytes(&mut bytes);
+
+        check((u64_from_le_bytes(&bytes) % encodings.len() as u64) as usize)?;
+    }
+    Ok(verdicts)
+}
+
+fn u64_from_le_bytes(bytes: &[u8; 8]) -> u64 {
+    bytes.iter().rev().fold(0, |n, &byte| (n << 8) | byte as u64)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use backend::adversarial_encodings;
+    use entropy::OsEntropy;
+
+    // The CPU, posing as a device, and getting every key wrong from
+    // `wrong_from` on, or dropping the last verdict.
+    struct Faulty {
+        wrong_from: usize,
+        truncate: bool,
+    }
+
+    impl Device for Faulty {
+        fn name(&self) -> &'static str {
+            "faulty"
+        }
+
+        fn validate_batch(&self, encodings: &[[u8; 32]], policy: KeyPolicy) -> Vec<bool> {
+            let mut verdicts: Vec<bool> = encodings.iter().enumerate()
+                .map(|(i, encoding)| Native.accepts(encoding, policy) != (i >= self.wrong_from))
+                .collect();
+
+            if self.truncate {
+                verdicts.pop();
+            }
+            verdicts
+        }
+    }
+
+    #[test]
+    fn sound_devices_pass() {
+        let encodings: Vec<[u8; 32]> = adversarial_encodings();
+        let device: Faulty = Faulty { wrong_from: encodings.len(), truncate: false };
+        let expected: Vec<bool> = encodings.iter().map(|encoding| Native.accepts(encoding, KeyPolicy::Strict)).collect();
+
+        assert_eq!(validate_offloaded(&device, &encodings, KeyPolicy::Strict, 8, &mut OsEntropy::new()), Ok(expected));
+        assert_eq!(validate_offloaded(&device, &[], KeyPolicy::Strict, 8, &mut OsEntropy::new()), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn faulty_devices_are_caught() {
+        let encodings: Vec<[u8; 32]> = adversarial_encodings();
+        let last: usize = encodings.len() - 1;
+
+        assert_eq!(validate_offloaded(&Faulty { wrong_from: last, truncate: false }, &encodings, KeyPolicy::PrimeOrder,
+                                      encodings.len(), &mut OsEntropy::new()),
+                   Err(OffloadError::Mismatch(last)));
+        assert!(validate_offloaded(&Faulty { wrong_from: 0, truncate: false }, &encodings, KeyPolicy::PrimeOrder,
+                                   1, &mut OsEntropy::new()).is_err());
+        assert_eq!(validate_offloaded(&Faulty { wrong_from: encodings.len(), truncate: true }, &encodings,
+                                      KeyPolicy::PrimeOrder, 0, &mut OsEntropy::new()),
+                   Err(OffloadError::WrongLength(last)));
+    }
+}
@@ -0,0 +1,284 @@
+// v3 onion addresses, as in rend-spec-v3 §6, behind the `blinding` feature:
+//
+//    onion_address = base32(PUBKEY ‖ CHECKSUM ‖ VERSION) ‖ ".onion"
+//    CHECKSUM = SHA3-256(".onion checksum" ‖ PUBKEY ‖ VERSION)[..2]
+//
+// where PUBKEY is the service's ed25519 identity key and VERSION is 0x03.
+// An address names its key exactly, so a torsioned or otherwise invalid key
+// has an address as good as any other; parsing one only undoes the encoding,
+// and the key which comes out still has to be checked.
+// `adversarial_onion_addresses` makes such addresses, for testing that other
+// address handling does check.
+//
+// A key P and each P + T, for T in E[8], are the same signer to a cofactored
+// verifier, but have eight different addresses.  `torsion_variant_addresses`
+// gives all eight for a valid address, for testing that an HSDir or client
+// treats the other seven as the same service, or refuses them, rather than
+// as seven more.
+
+use sha3::Digest;
+use sha3::Sha3_256;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use backend::adversarial_encodings;
+use encoding::base32_decode;
+use encoding::base32_encode;
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+static CHECKSUM_STRING: &[u8] = b".onion checksum";
+static SUFFIX: &str = ".onion";
+const VERSION: u8 = 3;
+
+/// An onion address, well-formed and with a correct checksum, for a key
+/// which must be rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdversarialAddress {
+    address: String,
+    key: CompressedEdwardsY,
+    problem: KeyProblem,
+}
+
+impl AdversarialAddress {
+    /// The address, with its ".onion" suffix.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The key it names.
+    pub fn key(&self) -> &CompressedEdwardsY {
+        &self.key
+    }
+
+    /// What is wrong with the key.
+    pub fn problem(&self) -> KeyProblem {
+        self.problem
+    }
+}
+
+/// The v3 onion address of the service whose identity key is `key`.
+pub fn onion_address(key: &CheckedKey<CompressedEdwardsY>) -> String {
+    encode_address(key.encoding())
+}
+
+/// An address for every point of small order, every prime-order point with
+/// a torsion component, and every non-canonical encoding of a point, among
+/// `backend::adversarial_encodings`.  Encodings which aren't points at all
+/// are left out, since a client can't get further with those than
+/// decompressing them.
+pub fn adversarial_onion_addresses() -> Vec<AdversarialAddress> {
+    adversarial_encodings().into_iter().filter_map(|bytes| {
+        let key: CompressedEdwardsY = CompressedEdwardsY(bytes);
+
+        match UncheckedKey::new(key).diagnose() {
+            Some(KeyProblem::NotOnCurve) | None => None,
+            Some(problem) => Some(AdversarialAddress { address: encode_address(&key), key, problem }),
+        }
+    }).collect()
+}
+
+/// The identity key named by a v3 onion address, with or without its
+/// ".onion" suffix.  Returns `None` if the address is malformed, is for some
+/// other version, or has the wrong checksum.
+pub fn parse_onion_address(address: &str) -> Option<UncheckedKey<CompressedEdwardsY>> {
+    let encoded: &str = match address.ends_with(SUFFIX) {
+        true  => &address[..address.len() - SUFFIX.len()],
+        false => address,
+    };
+    let blob: Vec<u8> = base32_decode(encoded)?;
+
+    if blob.len() != 35 || blob[34] != VERSION {
+        return None;
+    }
+
+    let mut bytes: [u8; 32] = [0u8; 32];
+    bytes.copy_from_slice(&blob[..32]);
+    let key: CompressedEdwardsY = CompressedEdwardsY(bytes);
+
+    match blob[32..34] == checksum(&key) {
+        true  => Some(UncheckedKey::new(key)),
+        false => None, // a mistyped address
+    }
+}
+
+/// Parse a v3 onion address as for `parse_onion_address`, and check the key
+/// it names under `policy`.
+pub fn check_onion_address(address: &str, policy: KeyPolicy) -> Option<CheckedKey<CompressedEdwardsY>> {
+    parse_onion_address(address)?.check(policy)
+}
+
+/// The addresses of P + T for each T in E[8], in the order of
+/// `constants::EIGHT_TORSION`, where P is the key `address` names, so that
+/// the first is `address` itself, with its ".onion" suffix.  Returns `None`
+/// unless `address` passes `check_onion_address` under `KeyPolicy::Strict`.
+pub fn torsion_variant_addresses(address: &str) -> Option<Vec<String>> {
+    let key: CheckedKey<CompressedEdwardsY> = check_onion_address(address, KeyPolicy::Strict)?;
+
+    Some(constants::EIGHT_TORSION.iter()
+         .map(|t| encode_address(&(key.point() + t).compress_edwards()))
+         .collect())
+}
+
+fn encode_address(key: &CompressedEdwardsY) -> String {
+    let mut blob: Vec<u8> = key.as_bytes().to_vec();
+    blob.extend_from_slice(&checksum(key));
+    blob.push(VERSION);
+
+    base32_encode(&blob) + SUFFIX
+}
+
+fn checksum(key: &CompressedEdwardsY) -> [u8; 2] {
+    let mut h: Sha3_256 = Sha3_256::default();
+    let mut checksum: [u8; 2] = [0u8; 2];
+
+    h.input(CHECKSUM_STRING);
+    h.input(key.as_bytes());
+    h.input(&[VERSION]);
+    checksum.copy_from_slice(&h.result().as_slice()[..2]);
+    checksum
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::scalar::Scalar;
+
+    use encoding::hex_decode;
+    use torsion::eq_mod_torsion;
+
+    // The Tor Project's own onion service, whose key is torproject.org's.
+    static TORPROJECT: &str = "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion";
+    static TORPROJECT_KEY: &str = "d1b38b83a83b3ed918c5bb69dd444ad56bc8d5835a914de73447474e5f02591b";
+
+    fn key_from_hex(hex: &str) -> CompressedEdwardsY {
+        let mut bytes: [u8; 32] = [0u8; 32];
+
+        bytes.copy_from_slice(&hex_decode(hex).unwrap());
+        CompressedEdwardsY(bytes)
+    }
+
+    fn key() -> CheckedKey<CompressedEdwardsY> {
+        let p: CompressedEdwardsY = (&Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT).compress_edwards();
+
+        UncheckedKey::new(p).check(KeyPolicy::Strict).unwrap()
+    }
+
+    #[test]
+    fn addresses_round_trip() {
+        let address: String = onion_address(&key());
+
+        assert_eq!(address.len(), 62);
+        assert!(address.ends_with(".onion"));
+        assert_eq!(check_onion_address(&address, KeyPolicy::Strict), Some(key()));
+        assert_eq!(parse_onion_address(&address[..56]), Some(UncheckedKey::new(*key().encoding())));
+    }
+
+    #[test]
+    fn published_addresses_decode_to_their_keys() {
+        // The first is test_build_address's, in tor's test_hs_common.c.
+        let vectors: [(&str, &str); 2] = [
+            ("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+             "25njqamcweflpvkl73j4szahhihoc4xt3ktcgjnpaingr5yhkenl5sid.onion"),
+            (TORPROJECT_KEY, TORPROJECT),
+        ];
+
+        for &(key, address) in vectors.iter() {
+            let key: CheckedKey<CompressedEdwardsY> = UncheckedKey::new(key_from_hex(key)).check(KeyPolicy::Strict).unwrap();
+
+            assert_eq!(parse_onion_address(address), Some(UncheckedKey::new(*key.encoding())));
+            assert_eq!(onion_address(&key), address);
+            assert_eq!(check_onion_address(&address[..56], KeyPolicy::Strict), Some(key));
+        }
+    }
+
+    #[test]
+    fn mistyped_addresses_are_rejected() {
+        let address: String = onion_address(&key());
+        let mut mistyped: Vec<u8> = address.clone().into_bytes();
+        mistyped[3] = if mistyped[3] == b'a' { b'b' } else { b'a' };
+
+        assert!(parse_onion_address(&String::from_utf8(mistyped).unwrap()).is_none());
+        assert!(parse_onion_address(&address[..55]).is_none());
+        assert!(parse_onion_address("").is_none());
+    }
+
+    #[test]
+    fn adversarial_addresses_parse_but_fail() {
+        let addresses: Vec<AdversarialAddress> = adversarial_onion_addresses();
+
+        for problem in [KeyProblem::Identity, KeyProblem::Torsioned, KeyProblem::NonCanonical].iter() {
+            assert!(addresses.iter().any(|address| address.problem() == *problem), "no {:?}", problem);
+        }
+        for address in addresses.iter() {
+            assert_eq!(parse_onion_address(address.address()), Some(UncheckedKey::new(*address.key())));
+            assert!(check_onion_address(address.address(), KeyPolicy::Strict).is_none());
+            if address.problem() == KeyProblem::Torsioned {
+                assert!(check_onion_address(address.address(), KeyPolicy::PrimeOrder).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn adversarial_addresses_match_known_answers() {
+        // Computed from rend-spec-v3 with Python's hashlib and base64: the
+        // identity, a point of order 8, and the identity with its sign bit set.
+        let vectors: [(&str, &str, KeyProblem); 3] = [
+            ("0100000000000000000000000000000000000000000000000000000000000000",
+             "aeaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaahmjqd.onion", KeyProblem::Identity),
+            ("c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a",
+             "y4lwu4b5jxme7or4bn3a2edhb4vcau72fq44zrsoy76xpevman5hhfqd.onion", KeyProblem::Torsioned),
+            ("0100000000000000000000000000000000000000000000000000000000000080",
+             "aeaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaacabdzyd.onion", KeyProblem::NonCanonical),
+        ];
+        let addresses: Vec<AdversarialAddress> = adversarial_onion_addresses();
+
+        for &(key, address, problem) in vectors.iter() {
+            let expected: AdversarialAddress = AdversarialAddress { address: address.to_string(), key: key_from_hex(key), problem };
+
+            assert!(addresses.contains(&expected), "{:?}", expected);
+        }
+    }
+
+    #[test]
+    fn torsion_variants_are_distinct_addresses_for_one_signer() {
+        let address: String = onion_address(&key());
+        let variants: Vec<String> = torsion_variant_addresses(&address[..56]).unwrap();
+        let mut distinct: Vec<String> = variants.clone();
+
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 8);
+        assert_eq!(variants[0], address);
+        for variant in variants[1..].iter() {
+            let unchecked: UncheckedKey<CompressedEdwardsY> = parse_onion_address(variant).unwrap();
+
+            assert_eq!(unchecked.diagnose(), Some(KeyProblem::Torsioned));
+            assert!(eq_mod_torsion(unchecked.encoding(), key().encoding()));
+        }
+        assert!(torsion_variant_addresses(&variants[1]).is_none());
+    }
+
+    #[test]
+    fn torsion_variants_of_a_published_address() {
+        // Computed for the Tor Project's key with a separate implementation
+        // of Edwards addition in Python, adding each of
+        // `constants::EIGHT_TORSION` in turn.
+        let expected: [&str; 8] = [
+            TORPROJECT,
+            "bagvrqglweibwo27d675ayb6roysfzxj43n7udpqtf6u5n5jlixuqnad.onion",
+            "3iy77gvbh43r7xp3jomknxmp6ohs45ns4qhrr2pqw7n3tndwydvyptad.onion",
+            "bsh6wr2i2t6ktnrku5yjrwfh7fdmje6rx67nq6h2zddpp2d7pfcqscyd.onion",
+            "drghi7cxytasnzz2islcfo5vfkkdokt4uvxlegglxc4ldih5u3smzsqd.onion",
+            "4xzkopzuj3x6jrfa4bac7h6borhn2giwdesal4qpm2blcscwuxic2uad.onion",
+            "cphaazk6ydeoaiqewrtvsitqbryndcsndpyoofqpjasems4jh4kmp2ad.onion",
+            "4fybjofxfmbvmsovlchwoj2ya24tw3boibaspbyfg44qqf4aq25bihyd.onion",
+        ];
+
+        assert_eq!(torsion_variant_addresses(TORPROJECT).unwrap(), expected);
+    }
+}
@@ -0,0 +1,161 @@
+// The parameters of ed25519's group, and the scalar arithmetic experiments
+// keep needing, so that none of it has to be taken from curve25519-dalek's
+// internals (its field elements need `yolocrypto`) or rebuilt by hand.
+//
+// The curve is -x² + y² = 1 + dx²y² over GF(p), p = 2^255 - 19, whose points
+// form a group of order 8l: the cofactor 8 times the prime
+//
+//    l = 2^252 + 27742317777372353535851937790883648493.
+//
+// Scalars are reduced mod l, and a scalar is canonical when its 32 bytes are
+// already below l, as RFC 8032 requires of the S half of a signature.  Hashes
+// become scalars by reducing all 64 bytes of a SHA-512 output, as ed25519's
+// challenge and nonce do, which makes the result as good as uniform.
+
+use sha2::Digest;
+use sha2::Sha512;
+
+use curve25519_dalek::scalar::Scalar;
+
+use wipe::wipe;
+
+/// l, the order of the prime-order subgroup.
+pub static L: Scalar = Scalar([
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+    0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10 ]);
+
+/// The cofactor, 8.
+pub const COFACTOR: u8 = 8;
+
+/// log2 of the cofactor, the number of doublings which clear it.
+pub const COFACTOR_LOG2: u32 = 3;
+
+/// d = -121665/121666 mod p, as little-endian bytes of the reduced value.
+pub static D: [u8; 32] = [
+    0xa3, 0x78, 0x59, 0x13, 0xca, 0x4d, 0xeb, 0x75,
+    0xab, 0xd8, 0x41, 0x41, 0x4d, 0x0a, 0x70, 0x00,
+    0x98, 0xe8, 0x79, 0x77, 0x79, 0x40, 0xc7, 0x8c,
+    0x73, 0xfe, 0x6f, 0x2b, 0xee, 0x6c, 0x03, 0x52 ];
+
+/// `bytes`, read as a little-endian integer, mod l.
+pub fn reduce_mod_l(bytes: &[u8; 32]) -> Scalar {
+    let mut wide: [u8; 64] = [0u8; 64];
+    wide[..32].copy_from_slice(bytes);
+    let reduced: Scalar = Scalar::reduce(&wide);
+
+    wipe(&mut wide);
+    reduced
+}
+
+/// The 64 bytes of `bytes`, as from a hash, read as a little-endian integer,
+/// mod l.
+pub fn reduce_wide_mod_l(bytes: &[u8; 64]) -> Scalar {
+    Scalar::reduce(bytes)
+}
+
+/// SHA-512 of the concatenation of `parts`, mod l.
+pub fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut h: Sha512 = Sha512::default();
+    let mut wide: [u8; 64] = [0u8; 64];
+
+    for part in parts {
+        h.input(part);
+    }
+    wide.copy_from_slice(h.result().as_slice());
+    let reduced: Scalar = Scalar::reduce(&wide);
+
+    wipe(&mut wide);
+    reduced
+}
+
+/// Whether `bytes` is a little-endian integer below l.
+pub fn is_canonical_scalar(bytes: &[u8; 32]) -> bool {
+    reduce_mod_l(bytes).as_bytes() == bytes
+}
+
+/// ab mod l, whether or not a and b were reduced, as for a clamped secret
+/// scalar.
+pub fn mul_then_reduce(a: &Scalar, b: &Scalar) -> Scalar {
+    Scalar::multiply_add(a, b, &Scalar::zero())
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::IsIdentity;
+
+    #[test]
+    fn parameters_agree_with_dalek() {
+        assert_eq!(L.as_bytes(), constants::l.as_bytes());
+        assert_eq!(D, constants::d.to_bytes());
+        assert_eq!(1 << COFACTOR_LOG2, COFACTOR);
+        for t in constants::EIGHT_TORSION.iter() {
+            assert!(::edwards::mul_by_pow_2(t, COFACTOR_LOG2).is_identity());
+        }
+    }
+
+    #[test]
+    fn scalars_are_reduced() {
+        let mut l_plus_one: [u8; 32] = L.0;
+        l_plus_one[0] += 1;
+
+        assert!(!is_canonical_scalar(L.as_bytes()));
+        assert!(!is_canonical_scalar(&l_plus_one));
+        assert!(is_canonical_scalar(Scalar::from_u64(22006).as_bytes()));
+        assert_eq!(reduce_mod_l(&l_plus_one), Scalar::from_u64(1));
+        assert_eq!(reduce_mod_l(L.as_bytes()), Scalar::zero());
+
+        let mut wide: [u8; 64] = [0u8; 64];
+        wide[..32].copy_from_slice(&l_plus_one);
+        assert_eq!(reduce_wide_mod_l(&wide), Scalar::from_u64(1));
+    }
+
+    #[test]
+    fn wide_reduction_vectors() {
+        let all_ones: [u8; 64] = [0xff; 64];
+        let mut counting: [u8; 64] = [0u8; 64];
+        for (i, byte) in counting.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        // 2^512 - 1 mod l.
+        assert_eq!(reduce_wide_mod_l(&all_ones).0, [
+            0x00, 0x0f, 0x9c, 0x44, 0xe3, 0x11, 0x06, 0xa4, 0x47, 0x93, 0x85, 0x68, 0xa7, 0x1b, 0x0e, 0xd0,
+            0x65, 0xbe, 0xf5, 0x17, 0xd2, 0x73, 0xec, 0xce, 0x3d, 0x9a, 0x30, 0x7c, 0x1b, 0x41, 0x99, 0x03 ]);
+        assert_eq!(reduce_wide_mod_l(&counting).0, [
+            0x7a, 0x3c, 0x62, 0x82, 0xf0, 0x2d, 0x37, 0xa0, 0x50, 0x23, 0xb6, 0x0d, 0x54, 0x28, 0xe6, 0xcc,
+            0x59, 0x61, 0xd4, 0xc3, 0x12, 0x21, 0x93, 0x7a, 0xda, 0xe0, 0xb5, 0x74, 0xe4, 0xd0, 0x72, 0x05 ]);
+    }
+
+    #[test]
+    fn rfc8032_challenge() {
+        // k = SHA-512(R ‖ A ‖ M) mod l for the first test of RFC 8032 §7.1,
+        // whose message is empty.
+        let r: [u8; 32] = [
+            0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72, 0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+            0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74, 0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55 ];
+        let a: [u8; 32] = [
+            0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+            0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a ];
+
+        assert_eq!(hash_to_scalar(&[&r, &a, b""]).0, [
+            0x86, 0xea, 0xbc, 0x8e, 0x4c, 0x96, 0x19, 0x3d, 0x29, 0x05, 0x04, 0xe7, 0xc6, 0x00, 0xdf, 0x6c,
+            0xf8, 0xd8, 0x25, 0x61, 0x31, 0xec, 0x2c, 0x13, 0x8a, 0x3e, 0x7e, 0x16, 0x2e, 0x52, 0x54, 0x04 ]);
+    }
+
+    #[test]
+    fn products_are_reduced() {
+        // [0xff; 31] ‖ 0x7f is 2^255 - 1, far above l.
+        let mut big: [u8; 32] = [0xff; 32];
+        big[31] = 0x7f;
+        let big: Scalar = Scalar(big);
+
+        assert_eq!(mul_then_reduce(&Scalar::from_u64(3), &Scalar::from_u64(7)), Scalar::from_u64(21));
+        assert_eq!(mul_then_reduce(&big, &Scalar::from_u64(1)), reduce_mod_l(big.as_bytes()));
+        assert_eq!(mul_then_reduce(&L, &Scalar::from_u64(22006)), Scalar::zero());
+    }
+}
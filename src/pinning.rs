@@ -0,0 +1,159 @@
+// A fixed set of trusted keys, for tooling which must accept only the keys
+// of a known authority set and nothing else, however valid.
+//
+// This is the opposite of `denylist`: a key is accepted only if it passes
+// its policy and is one of the pinned keys.  Membership is tested against
+// every pinned key every time, with the same constant-time comparison, so
+// that how long a check takes doesn't say which pinned key, if any, a
+// candidate matched or came closest to.  The serialized form is one key per
+// line, in unpadded base64, and every key is checked again when read back.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use subtle::arrays_equal;
+
+use encoding::base64_decode;
+use encoding::base64_encode;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// A set of explicitly trusted keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PinnedKeys {
+    keys: Vec<CheckedKey<CompressedEdwardsY>>,
+}
+
+impl PinnedKeys {
+    /// An empty set, which accepts nothing.
+    pub fn new() -> PinnedKeys {
+        PinnedKeys::default()
+    }
+
+    /// Pin `key`, returning false if it was already pinned.
+    pub fn insert(&mut self, key: CheckedKey<CompressedEdwardsY>) -> bool {
+        match self.contains(key.encoding()) {
+            1 => false,
+            _ => { self.keys.push(key); true },
+        }
+    }
+
+    /// Whether `encoding` is pinned, in constant time: 1 if it is, and 0 if
+    /// not.
+    pub fn contains(&self, encoding: &CompressedEdwardsY) -> u8 {
+        let mut found: u8 = 0;
+
+        for key in self.keys.iter() {
+            found |= arrays_equal(encoding.as_bytes(), key.encoding().as_bytes());
+        }
+        found
+    }
+
+    /// Check `key` under `policy`, and accept it only if it is pinned.
+    pub fn check(&self, key: &UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+        -> Option<CheckedKey<CompressedEdwardsY>>
+    {
+        let checked: CheckedKey<CompressedEdwardsY> = key.check(policy)?;
+
+        match self.contains(checked.encoding()) {
+            1 => Some(checked),
+            _ => None, // the key is valid, but not pinned
+        }
+    }
+
+    /// The pinned keys, in the order they were pinned.
+    pub fn keys(&self) -> &[CheckedKey<CompressedEdwardsY>] {
+        &self.keys
+    }
+
+    /// How many keys are pinned.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether no keys are pinned.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// One key per line, in unpadded base64.
+    pub fn to_text(&self) -> String {
+        let mut text: String = String::new();
+
+        for key in self.keys.iter() {
+            text.push_str(&base64_encode(key.encoding().as_bytes(), false));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Read back the serialized form, checking every key under `policy`.
+    /// Returns `None` if any line is malformed or holds a key which fails.
+    pub fn from_text(text: &str, policy: KeyPolicy) -> Option<PinnedKeys> {
+        let mut pinned: PinnedKeys = PinnedKeys::new();
+
+        for line in text.lines() {
+            let mut encoding: [u8; 32] = [0u8; 32];
+            match base64_decode(line) {
+                Some(ref bytes) if bytes.len() == 32 => encoding.copy_from_slice(bytes),
+                _                                    => return None,
+            }
+
+            let key: CheckedKey<CompressedEdwardsY> = UncheckedKey::new(CompressedEdwardsY(encoding)).check(policy)?;
+            pinned.insert(key);
+        }
+        Some(pinned)
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn key(n: u64) -> CompressedEdwardsY {
+        (&Scalar::from_u64(n) * &constants::ED25519_BASEPOINT).compress_edwards()
+    }
+
+    fn pinned() -> PinnedKeys {
+        let mut pinned: PinnedKeys = PinnedKeys::new();
+
+        for n in 1..4 {
+            assert!(pinned.insert(UncheckedKey::new(key(n)).check(KeyPolicy::Strict).unwrap()));
+        }
+        pinned
+    }
+
+    #[test]
+    fn only_pinned_keys_are_accepted() {
+        let pinned: PinnedKeys = pinned();
+
+        assert_eq!(pinned.contains(&key(2)), 1);
+        assert_eq!(pinned.contains(&key(4)), 0);
+        assert!(pinned.check(&UncheckedKey::new(key(3)), KeyPolicy::Strict).is_some());
+        assert!(pinned.check(&UncheckedKey::new(key(4)), KeyPolicy::Strict).is_none());
+        assert!(PinnedKeys::new().check(&UncheckedKey::new(key(1)), KeyPolicy::Strict).is_none());
+    }
+
+    #[test]
+    fn pins_are_not_repeated() {
+        let mut pinned: PinnedKeys = pinned();
+
+        assert!(!pinned.insert(UncheckedKey::new(key(1)).check(KeyPolicy::Strict).unwrap()));
+        assert_eq!(pinned.len(), 3);
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let pinned: PinnedKeys = pinned();
+        let text: String = pinned.to_text();
+
+        assert_eq!(PinnedKeys::from_text(&text, KeyPolicy::Strict), Some(pinned));
+        assert_eq!(PinnedKeys::from_text("not base64!\n", KeyPolicy::Strict), None);
+
+        let torsioned: String = base64_encode(constants::EIGHT_TORSION[1].compress_edwards().as_bytes(), false);
+        assert_eq!(PinnedKeys::from_text(&torsioned, KeyPolicy::PrimeOrder), None);
+    }
+}
@@ -0,0 +1,65 @@
+// Policy switches shared by the key checks and verifiers for every curve.
+//
+// Implementations disagree about how to treat torsion: RFC 8032 permits
+// either checking the verification equation as is or multiplying it through
+// by the cofactor first, and the two give different answers exactly when a
+// torsion component is present.  Keeping the choice in one type, rather than
+// one per curve, means a result measured on one curve carries over directly
+// to the other.  The same goes for the identity, which every encoding has
+// and which each policy either accepts on every curve or rejects on every
+// curve.
+
+/// Which verification equation to check, and how much to demand of the
+/// encoded points along the way.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VerificationProfile {
+    /// Check [c][S]B = [c]R + [c][k]A, where c is the curve's cofactor, so
+    /// that torsion components in R and A are ignored.
+    Cofactored,
+    /// Check [S]B = R + [k]A exactly, so that a torsion component in R or A
+    /// causes a failure for some, but not all, messages.
+    Cofactorless,
+    /// Check the cofactorless equation, and first reject any R or A which is
+    /// not the canonical encoding of a point in the prime-order subgroup.
+    Strict,
+}
+
+/// What a public key must satisfy before it is handed to protocol code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum KeyPolicy {
+    /// The key must name an element of the prime-order group.
+    PrimeOrder,
+    /// As for `PrimeOrder`, and additionally the encoding must be canonical
+    /// and must not name the identity.
+    Strict,
+}
+
+impl KeyPolicy {
+    /// What this policy does with a key naming the identity.
+    pub fn identity_policy(&self) -> IdentityPolicy {
+        match *self {
+            KeyPolicy::PrimeOrder => IdentityPolicy::Accept,
+            KeyPolicy::Strict     => IdentityPolicy::Reject,
+        }
+    }
+}
+
+/// What to do with a key naming the identity, which is in the prime-order
+/// group but is nobody's public key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IdentityPolicy {
+    /// Accept it, as l·P = O for P = O.
+    Accept,
+    /// Reject it.
+    Reject,
+}
+
+/// Which point validating a key hands back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PointOutput {
+    /// The decompressed point P itself.
+    Decompressed,
+    /// The cofactor-cleared [8]P, as a protocol which clears the cofactor
+    /// of whatever it is given would compute.
+    CofactorCleared,
+}
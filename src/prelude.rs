@@ -0,0 +1,40 @@
+// The types and functions most code validating keys needs, for importing
+// all at once with `use tor22006::prelude::*;`.
+//
+// The API is arranged by what is being validated: `edwards` for ed25519
+// keys as Edwards points, `decaf` and `ristretto` for the two prime-order
+// encodings, `policy` for the switches every one of them takes, and `batch`
+// for many keys at once.  `key` wraps them all in types recording whether a
+// key has been checked, and `traits` lets code be written once for every
+// encoding.  Everything here is re-exported from those, and nothing more
+// specialised, such as a particular protocol's key format, is.
+
+pub use curve25519_dalek::curve::CompressedEdwardsY;
+pub use curve25519_dalek::curve::ExtendedPoint;
+pub use curve25519_dalek::decaf::CompressedDecaf;
+pub use curve25519_dalek::decaf::DecafPoint;
+
+#[cfg(feature = "std")]
+pub use batch::validate_deduplicated;
+pub use decaf::decaf_decompress;
+pub use decaf::decaf_decompress_with;
+pub use edwards::mult_by_cofactor_and_validate;
+pub use edwards::validate_and_decompress;
+pub use entropy::EntropySource;
+pub use key::CheckedKey;
+pub use key::KeyProblem;
+pub use key::UncheckedKey;
+pub use policy::IdentityPolicy;
+pub use policy::KeyPolicy;
+pub use policy::PointOutput;
+pub use policy::VerificationProfile;
+pub use ristretto::CompressedRistretto;
+pub use ristretto::RistrettoPoint;
+pub use torsion::TorsionFreePoint;
+pub use traits::CofactorGroup;
+pub use traits::PrimeOrderEncoding;
+#[cfg(feature = "std")]
+pub use traits::validate_batch;
+pub use traits::validate_batch_into;
+#[cfg(feature = "std")]
+pub use traits::validate_strict_batch;
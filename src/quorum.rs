@@ -0,0 +1,176 @@
+// Checking a k-of-n quorum of signatures, as a client checks the directory
+// authorities' signatures on a consensus.
+//
+// A `Quorum` is a set of distinct validated keys and a threshold.  Checking
+// a set of signatures against it reports, for each key in the quorum,
+// whether one of the signatures verified under it, whether every one
+// claiming to be from it failed, or whether none claimed to be; signatures
+// from keys outside the quorum are only counted.  A key can only be counted
+// once however many signatures it made, which is what stops a single
+// authority from reaching the threshold on its own.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::verify;
+use key::CheckedKey;
+use policy::VerificationProfile;
+
+/// What became of one key in a quorum.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SignerStatus {
+    /// A signature verified under this key.
+    Verified,
+    /// There were signatures under this key, but none of them verified.
+    Invalid,
+    /// There was no signature under this key.
+    Missing,
+}
+
+/// The outcome of checking signatures against a quorum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuorumReport {
+    statuses: Vec<SignerStatus>,
+    threshold: usize,
+    unknown: usize,
+}
+
+impl QuorumReport {
+    /// The status of each key, in the order the quorum was built with.
+    pub fn statuses(&self) -> &[SignerStatus] {
+        &self.statuses
+    }
+
+    /// How many keys verified.
+    pub fn verified(&self) -> usize {
+        self.statuses.iter().filter(|&&status| status == SignerStatus::Verified).count()
+    }
+
+    /// How many signatures were from keys outside the quorum.
+    pub fn unknown_signers(&self) -> usize {
+        self.unknown
+    }
+
+    /// Whether at least the threshold number of keys verified.
+    pub fn reached(&self) -> bool {
+        self.verified() >= self.threshold
+    }
+}
+
+/// A set of keys, of which some number must sign.
+pub struct Quorum {
+    keys: Vec<CheckedKey<CompressedEdwardsY>>,
+    threshold: usize,
+}
+
+impl Quorum {
+    /// A quorum requiring `threshold` of `keys`.  Returns `None` if a key
+    /// appears twice, or the threshold is zero or more than there are keys.
+    pub fn new(keys: Vec<CheckedKey<CompressedEdwardsY>>, threshold: usize) -> Option<Quorum> {
+        if threshold == 0 || threshold > keys.len() {
+            return None; // the threshold could never, or would always, be reached
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].contains(key) {
+                return None; // the key would be counted twice
+            }
+        }
+
+        Some(Quorum { keys, threshold })
+    }
+
+    /// The number of keys which must sign.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Check each of `signatures`, which are paired with the key they claim
+    /// to be under, on `message`.
+    pub fn verify(&self, message: &[u8], signatures: &[(CompressedEdwardsY, [u8; 64])],
+                  profile: VerificationProfile) -> QuorumReport {
+        let mut statuses: Vec<SignerStatus> = vec![SignerStatus::Missing; self.keys.len()];
+        let mut unknown: usize = 0;
+
+        for (signer, signature) in signatures.iter() {
+            let i: usize = match self.keys.iter().position(|key| key.encoding() == signer) {
+                Some(i) => i,
+                None    => { unknown += 1; continue; },
+            };
+            if statuses[i] == SignerStatus::Verified {
+                continue;
+            }
+            statuses[i] = match verify(signer, message, signature, profile) {
+                true  => SignerStatus::Verified,
+                false => SignerStatus::Invalid,
+            };
+        }
+
+        QuorumReport { statuses, threshold: self.threshold, unknown }
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    // RFC 8032 §7.1, test 1.
+    static PUBLIC_KEY: [u8; 32] = [
+        0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7,
+        0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+        0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+        0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a ];
+    static SIGNATURE: [u8; 64] = [
+        0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72,
+        0x90, 0x86, 0xe2, 0xcc, 0x80, 0x6e, 0x82, 0x8a,
+        0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+        0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55,
+        0x5f, 0xb8, 0x82, 0x15, 0x90, 0xa3, 0x3b, 0xac,
+        0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+        0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24,
+        0x65, 0x51, 0x41, 0x43, 0x8e, 0x7a, 0x10, 0x0b ];
+
+    fn checked(encoding: CompressedEdwardsY) -> CheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(encoding).check(KeyPolicy::Strict).unwrap()
+    }
+
+    fn quorum(threshold: usize) -> Quorum {
+        let other: CompressedEdwardsY = (&Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT).compress_edwards();
+
+        Quorum::new(vec![checked(CompressedEdwardsY(PUBLIC_KEY)), checked(other)], threshold).unwrap()
+    }
+
+    #[test]
+    fn thresholds() {
+        let signatures: [(CompressedEdwardsY, [u8; 64]); 1] = [(CompressedEdwardsY(PUBLIC_KEY), SIGNATURE)];
+        let report: QuorumReport = quorum(1).verify(b"", &signatures, VerificationProfile::Strict);
+
+        assert_eq!(report.statuses(), &[SignerStatus::Verified, SignerStatus::Missing]);
+        assert!(report.reached());
+        assert!(!quorum(2).verify(b"", &signatures, VerificationProfile::Strict).reached());
+        assert_eq!(quorum(1).verify(b"x", &signatures, VerificationProfile::Strict).statuses(),
+                   &[SignerStatus::Invalid, SignerStatus::Missing]);
+    }
+
+    #[test]
+    fn signers_count_once() {
+        let key: CompressedEdwardsY = CompressedEdwardsY(PUBLIC_KEY);
+        let signatures: [(CompressedEdwardsY, [u8; 64]); 3] = [
+            (key, [0u8; 64]),
+            (key, SIGNATURE),
+            (constants::ED25519_BASEPOINT.compress_edwards(), SIGNATURE),
+        ];
+        let report: QuorumReport = quorum(2).verify(b"", &signatures, VerificationProfile::Strict);
+
+        assert_eq!(report.verified(), 1);
+        assert_eq!(report.unknown_signers(), 1);
+        assert!(!report.reached());
+        assert!(Quorum::new(vec![checked(key), checked(key)], 1).is_none());
+        assert!(Quorum::new(vec![checked(key)], 0).is_none());
+        assert!(Quorum::new(vec![checked(key)], 2).is_none());
+    }
+}
@@ -0,0 +1,139 @@
+// Self-signed revocation statements: "this key is revoked as of time T",
+// signed by the key itself.
+//
+// A revocation is 104 bytes,
+//
+//    KEY ‖ INT_8(T) ‖ SIG
+//
+// where T is in seconds since the epoch and SIG is a strict Ed25519
+// signature under KEY on
+//
+//    "ed25519 key revocation" ‖ 0x00 ‖ KEY ‖ INT_8(T)
+//
+// The prefix keeps a revocation from ever being valid as a signature on
+// anything else the key signs.  Since only the holder of the secret key can
+// make one, a revocation can be trusted from anywhere, and parsing it checks
+// both the key, strictly, and the signature, so that a `Revocation` in hand
+// is always genuine.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::public_key_from_seed;
+use ed25519::sign;
+use ed25519::verify;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+static REVOCATION_STRING: &[u8] = b"ed25519 key revocation\x00";
+
+/// A verified revocation of a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Revocation {
+    key: CheckedKey<CompressedEdwardsY>,
+    revoked_at: u64,
+    signature: [u8; 64],
+}
+
+impl Revocation {
+    /// Revoke the key for the secret `seed` as of `revoked_at`.  Returns
+    /// `None` only if the key itself would not pass validation.
+    pub fn create(seed: &[u8; 32], revoked_at: u64) -> Option<Revocation> {
+        let key: CheckedKey<CompressedEdwardsY> = UncheckedKey::new(public_key_from_seed(seed))
+                                                             .check(KeyPolicy::Strict)?;
+        let signature: [u8; 64] = sign(seed, &signed_message(key.encoding(), revoked_at));
+
+        Some(Revocation { key, revoked_at, signature })
+    }
+
+    /// Parse and verify a revocation.
+    pub fn from_bytes(bytes: &[u8; 104]) -> Option<Revocation> {
+        let mut encoding: [u8; 32] = [0u8; 32];
+        let mut signature: [u8; 64] = [0u8; 64];
+        let mut revoked_at: [u8; 8] = [0u8; 8];
+
+        encoding.copy_from_slice(&bytes[..32]);
+        revoked_at.copy_from_slice(&bytes[32..40]);
+        signature.copy_from_slice(&bytes[40..]);
+        let revoked_at: u64 = u64::from_be_bytes(revoked_at);
+
+        let key: CheckedKey<CompressedEdwardsY> = UncheckedKey::new(CompressedEdwardsY(encoding))
+                                                             .check(KeyPolicy::Strict)?;
+
+        match verify(key.encoding(), &signed_message(key.encoding(), revoked_at), &signature,
+                     VerificationProfile::Strict) {
+            true  => Some(Revocation { key, revoked_at, signature }),
+            false => None, // the signature was invalid
+        }
+    }
+
+    /// The 104-byte encoding.
+    pub fn to_bytes(&self) -> [u8; 104] {
+        let mut bytes: [u8; 104] = [0u8; 104];
+
+        bytes[..32].copy_from_slice(self.key.encoding().as_bytes());
+        bytes[32..40].copy_from_slice(&self.revoked_at.to_be_bytes());
+        bytes[40..].copy_from_slice(&self.signature);
+        bytes
+    }
+
+    /// The revoked key.
+    pub fn key(&self) -> &CheckedKey<CompressedEdwardsY> {
+        &self.key
+    }
+
+    /// When the key stopped being valid, in seconds since the epoch.
+    pub fn revoked_at(&self) -> u64 {
+        self.revoked_at
+    }
+
+    /// Whether this revokes `key` at time `now`.
+    pub fn revokes(&self, key: &CheckedKey<CompressedEdwardsY>, now: u64) -> bool {
+        self.key == *key && now >= self.revoked_at
+    }
+}
+
+fn signed_message(key: &CompressedEdwardsY, revoked_at: u64) -> Vec<u8> {
+    let mut message: Vec<u8> = Vec::with_capacity(REVOCATION_STRING.len() + 40);
+
+    message.extend_from_slice(REVOCATION_STRING);
+    message.extend_from_slice(key.as_bytes());
+    message.extend_from_slice(&revoked_at.to_be_bytes());
+    message
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    static SEED: [u8; 32] = [0x22; 32];
+
+    #[test]
+    fn revocations_round_trip() {
+        let revocation: Revocation = Revocation::create(&SEED, 1500000000).unwrap();
+        let parsed: Revocation = Revocation::from_bytes(&revocation.to_bytes()).unwrap();
+
+        assert_eq!(parsed, revocation);
+        assert!(parsed.revokes(revocation.key(), 1500000000));
+        assert!(!parsed.revokes(revocation.key(), 1499999999));
+
+        let other: Revocation = Revocation::create(&[0x23; 32], 0).unwrap();
+        assert!(!parsed.revokes(other.key(), 1500000000));
+    }
+
+    #[test]
+    fn forgeries_are_rejected() {
+        let bytes: [u8; 104] = Revocation::create(&SEED, 1500000000).unwrap().to_bytes();
+
+        // Moving the revocation time, or replaying the signature under
+        // another key.
+        let mut later: [u8; 104] = bytes;
+        later[39] ^= 1;
+        assert!(Revocation::from_bytes(&later).is_none());
+
+        let mut moved: [u8; 104] = bytes;
+        moved[..32].copy_from_slice(Revocation::create(&[0x23; 32], 0).unwrap().key().encoding().as_bytes());
+        assert!(Revocation::from_bytes(&moved).is_none());
+    }
+}
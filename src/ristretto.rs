@@ -0,0 +1,348 @@
+// ristretto255, as specified in RFC 9496.
+//
+// curve25519-dalek only ships the older decaf-flavoured encoding, so this
+// module builds the ristretto encoding, decoding, and one-way map directly on
+// top of its field arithmetic.  Like decaf, the ristretto group is built from
+// the even points 2·E modulo the 4-torsion, which is a group of prime order l,
+// so every ristretto encoding that decodes at all names a point in a
+// prime-order group.
+
+use core::fmt::Debug;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::field::FieldElement;
+
+use subtle::arrays_equal;
+use subtle::CTAssignable;
+use subtle::CTEq;
+use subtle::CTNegatable;
+
+// 1/sqrt(a - d)
+static INVSQRT_A_MINUS_D: [u8; 32] = [
+    0xea, 0x40, 0x5d, 0x80, 0xaa, 0xfd, 0xc8, 0x99,
+    0xbe, 0x72, 0x41, 0x5a, 0x17, 0x16, 0x2f, 0x9d,
+    0x40, 0xd8, 0x01, 0xfe, 0x91, 0x7b, 0xc2, 0x16,
+    0xa2, 0xfc, 0xaf, 0xcf, 0x05, 0x89, 0x6c, 0x78 ];
+
+// sqrt(a·d - 1), the negative square root as in the RFC
+static SQRT_AD_MINUS_ONE: [u8; 32] = [
+    0x1b, 0x2e, 0x7b, 0x49, 0xa0, 0xf6, 0x97, 0x7e,
+    0xbd, 0x54, 0x78, 0x1b, 0x0c, 0x8e, 0x9d, 0xaf,
+    0xfd, 0xd1, 0xf5, 0x31, 0xc9, 0xfc, 0x3c, 0x0f,
+    0xac, 0x48, 0x83, 0x2b, 0xbf, 0x31, 0x69, 0x37 ];
+
+// 1 - d²
+static ONE_MINUS_D_SQ: [u8; 32] = [
+    0x76, 0xc1, 0x5f, 0x94, 0xc1, 0x09, 0x7c, 0xe2,
+    0x0f, 0x35, 0x5e, 0xcd, 0x38, 0xa1, 0x81, 0x2c,
+    0xe4, 0xdf, 0x70, 0xbe, 0xdd, 0xab, 0x94, 0x99,
+    0xd7, 0xe0, 0xb3, 0xb2, 0xa8, 0x72, 0x90, 0x02 ];
+
+// (d - 1)²
+static D_MINUS_ONE_SQ: [u8; 32] = [
+    0x20, 0x4d, 0xed, 0x44, 0xaa, 0x5a, 0xad, 0x31,
+    0x99, 0x19, 0x1e, 0xb0, 0x2c, 0x4a, 0x9e, 0xd2,
+    0xeb, 0x4e, 0x9b, 0x52, 0x2f, 0xd3, 0xdc, 0x4c,
+    0x41, 0x22, 0x6c, 0xf6, 0x7a, 0xb3, 0x68, 0x59 ];
+
+/// A ristretto255 point encoding.
+///
+/// Equality, hashing, and ordering are all by the encoding bytes.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct CompressedRistretto(pub [u8; 32]);
+
+impl Debug for CompressedRistretto {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "CompressedRistretto: {:?}", self.as_bytes())
+    }
+}
+
+impl CompressedRistretto {
+    /// View this `CompressedRistretto` as an array of bytes.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Attempt to decode to a `RistrettoPoint`, returning `None` if the
+    /// encoding is non-canonical, negative, or not the encoding of any
+    /// point.
+    pub fn decompress(&self) -> Option<RistrettoPoint> {
+        let s: FieldElement = FieldElement::from_bytes(self.as_bytes());
+
+        let s_is_canonical: u8 = arrays_equal(&s.to_bytes(), self.as_bytes());
+        let s_is_negative: u8 = s.is_negative_ed25519();
+
+        if s_is_canonical & (1u8 ^ s_is_negative) != 1u8 {
+            return None;
+        }
+
+        let one: FieldElement = FieldElement::one();
+        let ss: FieldElement = s.square();
+        let u1: FieldElement = &one - &ss;
+        let u2: FieldElement = &one + &ss;
+        let u2_sqr: FieldElement = u2.square();
+
+        let v: FieldElement = &(-&(&constants::d * &u1.square())) - &u2_sqr;
+        let (was_square, invsqrt) = sqrt_ratio_m1(&one, &(&v * &u2_sqr));
+
+        let den_x: FieldElement = &invsqrt * &u2;
+        let den_y: FieldElement = &(&invsqrt * &den_x) * &v;
+
+        let mut x: FieldElement = &(&s + &s) * &den_x;
+        let x_is_negative: u8 = x.is_negative_ed25519();
+        x.conditional_negate(x_is_negative);
+        let y: FieldElement = &u1 * &den_y;
+        let t: FieldElement = &x * &y;
+
+        match was_square & (1u8 ^ t.is_negative_ed25519()) & y.is_nonzero() {
+            1u8 => Some(RistrettoPoint(ExtendedPoint{ X: x, Y: y, Z: one, T: t })),
+            _   => None,
+        }
+    }
+}
+
+/// An element of the ristretto255 group, represented by any point of its
+/// coset in the curve.
+#[derive(Copy, Clone)]
+pub struct RistrettoPoint(pub ExtendedPoint);
+
+impl RistrettoPoint {
+    /// Encode this point, choosing the same encoding for every
+    /// representative of the coset.
+    pub fn compress(&self) -> CompressedRistretto {
+        let invsqrt_a_minus_d: FieldElement = FieldElement::from_bytes(&INVSQRT_A_MINUS_D);
+
+        let mut x: FieldElement = self.0.X;
+        let mut y: FieldElement = self.0.Y;
+        let z: &FieldElement = &self.0.Z;
+        let t: &FieldElement = &self.0.T;
+
+        let u1: FieldElement = &(z + &y) * &(z - &y);
+        let u2: FieldElement = &x * &y;
+        let (_, invsqrt) = sqrt_ratio_m1(&FieldElement::one(), &(&u1 * &u2.square()));
+
+        let den1: FieldElement = &invsqrt * &u1;
+        let den2: FieldElement = &invsqrt * &u2;
+        let z_inv: FieldElement = &(&den1 * &den2) * t;
+
+        let ix: FieldElement = &x * &constants::SQRT_M1;
+        let iy: FieldElement = &y * &constants::SQRT_M1;
+        let enchanted_denominator: FieldElement = &den1 * &invsqrt_a_minus_d;
+
+        let rotate: u8 = (t * &z_inv).is_negative_ed25519();
+        let mut den_inv: FieldElement = den2;
+
+        x.conditional_assign(&iy, rotate);
+        y.conditional_assign(&ix, rotate);
+        den_inv.conditional_assign(&enchanted_denominator, rotate);
+
+        let x_z_inv_is_negative: u8 = (&x * &z_inv).is_negative_ed25519();
+        y.conditional_negate(x_z_inv_is_negative);
+
+        let mut s: FieldElement = &den_inv * &(z - &y);
+        let s_is_negative: u8 = s.is_negative_ed25519();
+        s.conditional_negate(s_is_negative);
+
+        CompressedRistretto(s.to_bytes())
+    }
+
+    /// Derive a point from 64 uniformly random bytes, by applying the
+    /// ristretto one-way map to each half and adding the results.
+    ///
+    /// The output is indistinguishable from a uniformly random group element
+    /// with unknown discrete log, so this is the right way to turn a seed (or
+    /// a 512-bit hash) into test points.
+    pub fn from_uniform_bytes(bytes: &[u8; 64]) -> RistrettoPoint {
+        let mut r_1: [u8; 32] = [0u8; 32];
+        let mut r_2: [u8; 32] = [0u8; 32];
+
+        r_1.copy_from_slice(&bytes[..32]);
+        r_2.copy_from_slice(&bytes[32..]);
+
+        // from_bytes ignores the high bit, which is what the RFC asks for.
+        let p_1: ExtendedPoint = one_way_map(&FieldElement::from_bytes(&r_1));
+        let p_2: ExtendedPoint = one_way_map(&FieldElement::from_bytes(&r_2));
+
+        RistrettoPoint(&p_1 + &p_2)
+    }
+}
+
+impl CTEq for RistrettoPoint {
+    // Two representatives are in the same coset iff x₁y₂ = y₁x₂ or
+    // y₁y₂ = x₁x₂.
+    fn ct_eq(&self, other: &RistrettoPoint) -> u8 {
+        let x1y2: FieldElement = &self.0.X * &other.0.Y;
+        let y1x2: FieldElement = &self.0.Y * &other.0.X;
+        let y1y2: FieldElement = &self.0.Y * &other.0.Y;
+        let x1x2: FieldElement = &self.0.X * &other.0.X;
+
+        x1y2.ct_eq(&y1x2) | y1y2.ct_eq(&x1x2)
+    }
+}
+
+impl PartialEq for RistrettoPoint {
+    fn eq(&self, other: &RistrettoPoint) -> bool {
+        self.ct_eq(other) == 1u8
+    }
+}
+
+impl Eq for RistrettoPoint {}
+
+impl Debug for RistrettoPoint {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        write!(f, "RistrettoPoint: {:?}", self.compress())
+    }
+}
+
+// SQRT_RATIO_M1 from RFC 9496 §4.2: the non-negative square root of u/v, or
+// of i·u/v when u/v is not square.  Unlike FieldElement::sqrt_ratio, this
+// always returns a root of something, which the one-way map relies upon.
+fn sqrt_ratio_m1(u: &FieldElement, v: &FieldElement) -> (u8, FieldElement) {
+    let v3: FieldElement = &v.square() * v;
+    let v7: FieldElement = &v3.square() * v;
+    let mut r: FieldElement = &(u * &v3) * &(u * &v7).pow_p58();
+    let check: FieldElement = v * &r.square();
+
+    let minus_u: FieldElement = -u;
+    let correct_sign_sqrt: u8 = check.ct_eq(u);
+    let flipped_sign_sqrt: u8 = check.ct_eq(&minus_u);
+    let flipped_sign_sqrt_i: u8 = check.ct_eq(&(&minus_u * &constants::SQRT_M1));
+
+    let r_prime: FieldElement = &constants::SQRT_M1 * &r;
+    r.conditional_assign(&r_prime, flipped_sign_sqrt | flipped_sign_sqrt_i);
+
+    let r_is_negative: u8 = r.is_negative_ed25519();
+    r.conditional_negate(r_is_negative);
+
+    (correct_sign_sqrt | flipped_sign_sqrt, r)
+}
+
+// The ristretto flavour of Elligator2, RFC 9496 §4.3.4.
+fn one_way_map(t: &FieldElement) -> ExtendedPoint {
+    let one: FieldElement = FieldElement::one();
+    let minus_one: FieldElement = FieldElement::minus_one();
+    let d: &FieldElement = &constants::d;
+
+    let r: FieldElement = &constants::SQRT_M1 * &t.square();
+    let u: FieldElement = &(&r + &one) * &FieldElement::from_bytes(&ONE_MINUS_D_SQ);
+    let v: FieldElement = &(&minus_one - &(&r * d)) * &(&r + d);
+
+    let (was_square, mut s) = sqrt_ratio_m1(&u, &v);
+
+    let mut s_prime: FieldElement = &s * t;
+    let s_prime_is_positive: u8 = 1u8 ^ s_prime.is_negative_ed25519();
+    s_prime.conditional_negate(s_prime_is_positive);
+
+    let mut c: FieldElement = minus_one;
+    s.conditional_assign(&s_prime, 1u8 ^ was_square);
+    c.conditional_assign(&r, 1u8 ^ was_square);
+
+    let n: FieldElement = &(&(&c * &(&r - &one)) * &FieldElement::from_bytes(&D_MINUS_ONE_SQ)) - &v;
+
+    let w0: FieldElement = &(&s + &s) * &v;
+    let w1: FieldElement = &n * &FieldElement::from_bytes(&SQRT_AD_MINUS_ONE);
+    let w2: FieldElement = &one - &s.square();
+    let w3: FieldElement = &one + &s.square();
+
+    ExtendedPoint{ X: &w0 * &w3, Y: &w2 * &w1, Z: &w1 * &w3, T: &w0 * &w2 }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::curve::Identity;
+    use curve25519_dalek::scalar::Scalar;
+
+    // Encodings of 0·B through 3·B, from RFC 9496 Appendix A.1.
+    static MULTIPLES_OF_BASEPOINT: [[u8; 32]; 4] = [
+        [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+         0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        [0xe2, 0xf2, 0xae, 0x0a, 0x6a, 0xbc, 0x4e, 0x71,
+         0xa8, 0x84, 0xa9, 0x61, 0xc5, 0x00, 0x51, 0x5f,
+         0x58, 0xe3, 0x0b, 0x6a, 0xa5, 0x82, 0xdd, 0x8d,
+         0xb6, 0xa6, 0x59, 0x45, 0xe0, 0x8d, 0x2d, 0x76],
+        [0x6a, 0x49, 0x32, 0x10, 0xf7, 0x49, 0x9c, 0xd1,
+         0x7f, 0xec, 0xb5, 0x10, 0xae, 0x0c, 0xea, 0x23,
+         0xa1, 0x10, 0xe8, 0xd5, 0xb9, 0x01, 0xf8, 0xac,
+         0xad, 0xd3, 0x09, 0x5c, 0x73, 0xa3, 0xb9, 0x19],
+        [0x94, 0x74, 0x1f, 0x5d, 0x5d, 0x52, 0x75, 0x5e,
+         0xce, 0x4f, 0x23, 0xf0, 0x44, 0xee, 0x27, 0xd5,
+         0xd1, 0xea, 0x1e, 0x2b, 0xd1, 0x96, 0xb4, 0x62,
+         0x16, 0x6b, 0x16, 0x15, 0x2a, 0x9d, 0x02, 0x59] ];
+
+    // SHA-512("Ristretto is traditionally a short shot of espresso coffee")
+    // and the point it maps to, from RFC 9496 Appendix A.3.
+    static UNIFORM_BYTES: [u8; 64] = [
+        0x5d, 0x1b, 0xe0, 0x9e, 0x3d, 0x0c, 0x82, 0xfc,
+        0x53, 0x81, 0x12, 0x49, 0x0e, 0x35, 0x70, 0x19,
+        0x79, 0xd9, 0x9e, 0x06, 0xca, 0x3e, 0x2b, 0x5b,
+        0x54, 0xbf, 0xfe, 0x8b, 0x4d, 0xc7, 0x72, 0xc1,
+        0x4d, 0x98, 0xb6, 0x96, 0xa1, 0xbb, 0xfb, 0x5c,
+        0xa3, 0x2c, 0x43, 0x6c, 0xc6, 0x1c, 0x16, 0x56,
+        0x37, 0x90, 0x30, 0x6c, 0x79, 0xea, 0xca, 0x77,
+        0x05, 0x66, 0x8b, 0x47, 0xdf, 0xfe, 0x5b, 0xb6 ];
+    static UNIFORM_POINT: [u8; 32] = [
+        0x30, 0x66, 0xf8, 0x2a, 0x1a, 0x74, 0x7d, 0x45,
+        0x12, 0x0d, 0x17, 0x40, 0xf1, 0x43, 0x58, 0x53,
+        0x1a, 0x8f, 0x04, 0xbb, 0xff, 0xe6, 0xa8, 0x19,
+        0xf8, 0x6d, 0xfe, 0x50, 0xf4, 0x4a, 0x0a, 0x46 ];
+
+    #[test]
+    fn encodes_small_multiples_of_basepoint() {
+        let mut p: ExtendedPoint = ExtendedPoint::identity();
+
+        for expected in MULTIPLES_OF_BASEPOINT.iter() {
+            assert_eq!(RistrettoPoint(p).compress().as_bytes(), expected);
+            p = &p + &constants::ED25519_BASEPOINT;
+        }
+    }
+
+    #[test]
+    fn decode_then_encode_is_the_identity() {
+        for encoding in MULTIPLES_OF_BASEPOINT.iter() {
+            let key: CompressedRistretto = CompressedRistretto(*encoding);
+            let p: RistrettoPoint = key.decompress().unwrap();
+
+            assert_eq!(p.compress(), key);
+        }
+    }
+
+    #[test]
+    fn cosets_share_an_encoding() {
+        let a: Scalar = Scalar::from_u64(1337);
+        let p: ExtendedPoint = &a * &constants::ED25519_BASEPOINT;
+
+        // The 4-torsion points are the even-indexed ones.
+        for i in [0, 2, 4, 6].iter() {
+            let q: RistrettoPoint = RistrettoPoint(&p + &constants::EIGHT_TORSION[*i]);
+
+            assert_eq!(q.compress(), RistrettoPoint(p).compress());
+            assert_eq!(q, RistrettoPoint(p));
+        }
+    }
+
+    #[test]
+    fn rejects_negative_and_non_canonical_encodings() {
+        let mut one: [u8; 32] = [0u8; 32];
+        one[0] = 1;
+
+        // p itself, i.e. a non-canonical encoding of zero
+        let mut p: [u8; 32] = [0xff; 32];
+        p[0] = 0xed;
+        p[31] = 0x7f;
+
+        assert!(CompressedRistretto(one).decompress().is_none());
+        assert!(CompressedRistretto(p).decompress().is_none());
+    }
+
+    #[test]
+    fn from_uniform_bytes_vector() {
+        let p: RistrettoPoint = RistrettoPoint::from_uniform_bytes(&UNIFORM_BYTES);
+
+        assert_eq!(p.compress().as_bytes(), &UNIFORM_POINT);
+    }
+}
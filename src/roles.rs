@@ -0,0 +1,186 @@
+// Keys typed by the role Tor uses them in, so that an API can say which
+// role it needs and have the compiler hold callers to it.
+//
+// Each type is built only through the checks `KeyManager` gives its role:
+// `IdentityKey`, `SigningKey` and `BlindedKey` are ed25519 keys under
+// `KeyPolicy::Strict`, so a blinded key in particular is always torsion-free,
+// and an `NtorKey` is a canonical Montgomery u-coordinate of a point in the
+// prime-order subgroup.  Nothing converts one role into another, so a
+// function verifying a certificate can take the `IdentityKey` which signed
+// it and the `SigningKey` it certifies, and can't be handed them the wrong
+// way round, or a key which has never been checked at all.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::CompressedMontgomeryU;
+
+#[cfg(feature = "blinding")]
+use blinding::blind_public_key;
+use ed25519::verify;
+use key::CheckedKey;
+use key::KeyProblem;
+use manager::check_for_role;
+use manager::KeyRole;
+use manager::RoleKey;
+use policy::VerificationProfile;
+
+/// A relay's or service's long-term ed25519 identity key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdentityKey(CheckedKey<CompressedEdwardsY>);
+
+/// A medium-term ed25519 signing key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SigningKey(CheckedKey<CompressedEdwardsY>);
+
+/// A blinded ed25519 onion service key, for one time period.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlindedKey(CheckedKey<CompressedEdwardsY>);
+
+/// A curve25519 ntor onion key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NtorKey(CompressedMontgomeryU);
+
+impl IdentityKey {
+    /// Check `encoding` as an identity key.
+    pub fn from_bytes(encoding: &[u8; 32]) -> Result<IdentityKey, KeyProblem> {
+        check_ed25519(KeyRole::Identity, encoding).map(IdentityKey)
+    }
+
+    /// The checked key.
+    pub fn key(&self) -> &CheckedKey<CompressedEdwardsY> {
+        &self.0
+    }
+
+    /// The 32-byte encoding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.0.encoding().as_bytes()
+    }
+
+    /// Whether `signature` on `message` verifies under this key.
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64], profile: VerificationProfile) -> bool {
+        verify(self.0.encoding(), message, signature, profile)
+    }
+
+    /// This key blinded for the given time period, as an onion service's
+    /// identity is.
+    #[cfg(feature = "blinding")]
+    pub fn blind(&self, period_number: u64, period_length: u64) -> Result<BlindedKey, KeyProblem> {
+        BlindedKey::from_bytes(blind_public_key(&self.0, period_number, period_length).as_bytes())
+    }
+}
+
+impl SigningKey {
+    /// Check `encoding` as a signing key.
+    pub fn from_bytes(encoding: &[u8; 32]) -> Result<SigningKey, KeyProblem> {
+        check_ed25519(KeyRole::Signing, encoding).map(SigningKey)
+    }
+
+    /// The checked key.
+    pub fn key(&self) -> &CheckedKey<CompressedEdwardsY> {
+        &self.0
+    }
+
+    /// The 32-byte encoding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.0.encoding().as_bytes()
+    }
+
+    /// Whether `signature` on `message` verifies under this key.
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64], profile: VerificationProfile) -> bool {
+        verify(self.0.encoding(), message, signature, profile)
+    }
+}
+
+impl BlindedKey {
+    /// Check `encoding` as a blinded key.
+    pub fn from_bytes(encoding: &[u8; 32]) -> Result<BlindedKey, KeyProblem> {
+        check_ed25519(KeyRole::Blinded, encoding).map(BlindedKey)
+    }
+
+    /// The checked key.
+    pub fn key(&self) -> &CheckedKey<CompressedEdwardsY> {
+        &self.0
+    }
+
+    /// The 32-byte encoding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.0.encoding().as_bytes()
+    }
+
+    /// Whether `signature` on `message` verifies under this key.
+    pub fn verify(&self, message: &[u8], signature: &[u8; 64], profile: VerificationProfile) -> bool {
+        verify(self.0.encoding(), message, signature, profile)
+    }
+}
+
+impl NtorKey {
+    /// Check `encoding` as an ntor key.
+    pub fn from_bytes(encoding: &[u8; 32]) -> Result<NtorKey, KeyProblem> {
+        match check_for_role(KeyRole::Ntor, encoding) {
+            Ok(RoleKey::Curve25519(u)) => Ok(NtorKey(u)),
+            Ok(RoleKey::Ed25519(_))    => unreachable!(), // ntor keys are always curve25519
+            Err(problem)               => Err(problem),
+        }
+    }
+
+    /// The u-coordinate.
+    pub fn u(&self) -> &CompressedMontgomeryU {
+        &self.0
+    }
+
+    /// The 32-byte encoding.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &(self.0).0
+    }
+}
+
+fn check_ed25519(role: KeyRole, encoding: &[u8; 32]) -> Result<CheckedKey<CompressedEdwardsY>, KeyProblem> {
+    match check_for_role(role, encoding) {
+        Ok(RoleKey::Ed25519(key))  => Ok(key),
+        Ok(RoleKey::Curve25519(_)) => unreachable!(), // only ntor keys are curve25519
+        Err(problem)               => Err(problem),
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::ExtendedPoint;
+    use curve25519_dalek::curve::Identity;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn point(k: u64) -> ExtendedPoint {
+        &Scalar::from_u64(k) * &constants::ED25519_BASEPOINT
+    }
+
+    #[test]
+    fn each_role_is_checked_as_the_manager_checks_it() {
+        let p: [u8; 32] = point(22006).compress_edwards().to_bytes();
+        let torsioned: [u8; 32] = (&point(22006) + &constants::EIGHT_TORSION[1]).compress_edwards().to_bytes();
+        let identity: [u8; 32] = ExtendedPoint::identity().compress_edwards().to_bytes();
+
+        assert_eq!(IdentityKey::from_bytes(&p).unwrap().as_bytes(), &p);
+        assert_eq!(SigningKey::from_bytes(&p).unwrap().as_bytes(), &p);
+        assert_eq!(BlindedKey::from_bytes(&p).unwrap().as_bytes(), &p);
+        assert_eq!(BlindedKey::from_bytes(&torsioned), Err(KeyProblem::Torsioned));
+        assert_eq!(IdentityKey::from_bytes(&identity), Err(KeyProblem::Identity));
+    }
+
+    #[test]
+    fn ntor_keys_use_the_montgomery_checks() {
+        let u: [u8; 32] = point(2).compress_montgomery().unwrap().to_bytes();
+
+        assert_eq!(NtorKey::from_bytes(&u).unwrap().as_bytes(), &u);
+        assert_eq!(NtorKey::from_bytes(&[0u8; 32]), Err(KeyProblem::Torsioned));
+    }
+
+    #[cfg(feature = "blinding")]
+    #[test]
+    fn identities_blind_to_blinded_keys() {
+        let identity: IdentityKey = IdentityKey::from_bytes(&point(22006).compress_edwards().to_bytes()).unwrap();
+        let blinded: BlindedKey = identity.blind(17000, 1440).unwrap();
+
+        assert_eq!(blinded.as_bytes(), blind_public_key(identity.key(), 17000, 1440).as_bytes());
+    }
+}
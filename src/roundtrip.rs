@@ -0,0 +1,120 @@
+// Whether an encoding survives decompressing and compressing again
+// unchanged, which is to say whether it is in the image of curve25519-dalek's
+// compression.
+//
+// That image is exactly the canonical encodings: y reduced mod p, and the
+// sign bit clear when x = 0.  Several interoperability bugs come down to one
+// side re-encoding a key it was given and the other comparing, hashing or
+// storing the bytes it was sent, so this is worth asking on its own, under
+// any policy.  `KeyPolicy::Strict` already asks it as part of "canonicity";
+// `RoundTrip` adds it to the pipeline in `hooks` under `PrimeOrder` too,
+// rejecting a key whose encoding doesn't survive straight after
+// "decompress".
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use hooks::Control;
+use hooks::StageHooks;
+
+/// Whether `encoding` decompresses, and compresses back to the same bytes.
+pub fn roundtrip_check(encoding: &CompressedEdwardsY) -> bool {
+    match encoding.decompress() {
+        Some(p) => p.compress_edwards() == *encoding,
+        None    => false, // not a point, so in no image at all
+    }
+}
+
+/// Hooks which require every key to pass `roundtrip_check`, run around
+/// some other hooks.
+#[derive(Clone, Debug)]
+pub struct RoundTrip<H: StageHooks> {
+    inner: H,
+}
+
+impl<H: StageHooks> RoundTrip<H> {
+    /// Run `inner` as well, as if it were the only hooks.
+    pub fn new(inner: H) -> RoundTrip<H> {
+        RoundTrip { inner }
+    }
+
+    /// The hooks run inside these.
+    pub fn into_inner(self) -> H {
+        self.inner
+    }
+}
+
+impl<H: StageHooks> StageHooks for RoundTrip<H> {
+    fn before(&mut self, stage: &'static str, encoding: &[u8]) -> Control {
+        self.inner.before(stage, encoding)
+    }
+
+    fn after(&mut self, stage: &'static str, encoding: &[u8], passed: bool) -> Control {
+        let control: Control = self.inner.after(stage, encoding, passed);
+
+        if control == Control::Abort || stage != "decompress" || !passed {
+            return control;
+        }
+
+        let mut bytes: [u8; 32] = [0u8; 32];
+        bytes.copy_from_slice(encoding);
+
+        match roundtrip_check(&CompressedEdwardsY(bytes)) {
+            true  => Control::Continue,
+            false => Control::Abort, // a non-canonical encoding
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use hooks::validate_with_hooks;
+    use policy::KeyPolicy;
+
+    // The identity, with y = 1 + p, and a prime-order point.
+    fn encodings() -> (CompressedEdwardsY, CompressedEdwardsY) {
+        let mut unreduced: [u8; 32] = [0xff; 32];
+        unreduced[0] = 0xee;
+        unreduced[31] = 0x7f;
+
+        (CompressedEdwardsY(unreduced), constants::ED25519_BASEPOINT.compress_edwards())
+    }
+
+    // Counts the stages it sees.
+    struct Counter(usize);
+
+    impl StageHooks for Counter {
+        fn after(&mut self, _stage: &'static str, _encoding: &[u8], _passed: bool) -> Control {
+            self.0 += 1;
+            Control::Continue
+        }
+    }
+
+    #[test]
+    fn only_canonical_encodings_round_trip() {
+        let (unreduced, basepoint) = encodings();
+        let mut flipped: [u8; 32] = constants::EIGHT_TORSION[0].compress_edwards().to_bytes();
+        flipped[31] |= 0x80;
+
+        assert!(roundtrip_check(&basepoint));
+        assert!(roundtrip_check(&constants::EIGHT_TORSION[1].compress_edwards()));
+        assert!(!roundtrip_check(&unreduced));
+        assert!(!roundtrip_check(&CompressedEdwardsY(flipped)));
+        assert!(!roundtrip_check(&CompressedEdwardsY([0x02; 32])));
+    }
+
+    #[test]
+    fn stage_rejects_under_any_policy() {
+        let (unreduced, basepoint) = encodings();
+
+        assert!(validate_with_hooks(&unreduced, KeyPolicy::PrimeOrder, &mut ()).is_some());
+        assert!(validate_with_hooks(&unreduced, KeyPolicy::PrimeOrder, &mut RoundTrip::new(())).is_none());
+
+        let mut hooks: RoundTrip<Counter> = RoundTrip::new(Counter(0));
+        assert!(validate_with_hooks(&basepoint, KeyPolicy::PrimeOrder, &mut hooks).is_some());
+        assert_eq!(hooks.into_inner().0, 2);
+    }
+}
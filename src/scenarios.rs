@@ -0,0 +1,207 @@
+// The cases discussed on tor#22006, each as a test named for it, with what
+// every rule proposed there makes of it.  The rules for keys are
+//
+//    decompress   anything which decompresses, as tor accepted keys when
+//                 the ticket was opened
+//    PrimeOrder   `KeyPolicy::PrimeOrder`, the point must be in the
+//                 prime-order subgroup
+//    Strict       `KeyPolicy::Strict`, as PrimeOrder, canonically encoded,
+//                 and not the identity
+//
+// and for signatures the three `VerificationProfile`s, where Cofactorless is
+// what tor verifies with.  The verdicts, "yes" for accepted and "--" for
+// rejected, are:
+//
+//                                          decompress   PrimeOrder   Strict
+//    a relay identity A + T, T ≠ 0         yes          --           --
+//    a relay identity T of order 8         yes          --           --
+//    the identity, canonically encoded     yes          yes          --
+//    the identity, encoded as y = p + 1    yes          yes          --
+//    the 8 onion addresses of A + E[8]     8            1            1
+//
+//                                          Cofactorless Cofactored   Strict
+//    a signature by the key A + T          1 in 8       yes          --
+//    a signature (R + T, S) under A        --           yes          --
+//    (identity, 0) under T of order 8      1 in 8       yes          --
+//
+// where "1 in 8" means for exactly those messages whose challenge k is a
+// multiple of 8, since what cofactorless verification checks is [k]T = 0.
+// Those are the two failures the ticket is about: keys which are one
+// signer's but not one encoding, and signatures which verifiers disagree
+// about depending on the message.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::scalar::Scalar;
+
+use backend::Backend;
+use backend::Native;
+use corpus::DecompressOnly;
+use ed25519::verify;
+use params::hash_to_scalar;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+// The key scenarios' secret scalar a, so that A = [a]B.
+fn secret() -> Scalar {
+    Scalar::from_u64(22006)
+}
+
+fn public() -> ExtendedPoint {
+    &secret() * &constants::ED25519_BASEPOINT
+}
+
+// The point of order 8 used throughout.
+fn torsion() -> ExtendedPoint {
+    constants::EIGHT_TORSION[1]
+}
+
+// Whether decompress, PrimeOrder, and Strict accept `key`.
+fn key_verdicts(key: &CompressedEdwardsY) -> [bool; 3] {
+    [
+        DecompressOnly.accepts(key.as_bytes(), KeyPolicy::Strict),
+        Native.accepts(key.as_bytes(), KeyPolicy::PrimeOrder),
+        Native.accepts(key.as_bytes(), KeyPolicy::Strict),
+    ]
+}
+
+// Whether Cofactorless, Cofactored, and Strict accept `signature`.
+fn signature_verdicts(key: &CompressedEdwardsY, message: &[u8], signature: &[u8; 64]) -> [bool; 3] {
+    [
+        verify(key, message, signature, VerificationProfile::Cofactorless),
+        verify(key, message, signature, VerificationProfile::Cofactored),
+        verify(key, message, signature, VerificationProfile::Strict),
+    ]
+}
+
+// k = H(R ‖ A ‖ M), and whether it is a multiple of 8.
+fn challenge_is_multiple_of_8(r: &CompressedEdwardsY, key: &CompressedEdwardsY, message: &[u8]) -> bool {
+    hash_to_scalar(&[r.as_bytes(), key.as_bytes(), message]).as_bytes()[0] & 7 == 0
+}
+
+// A signature on `message` under `key`, made with the secret a and, as R,
+// [r]B + `r_torsion`, where `key` is A plus some torsion.
+fn sign_as(key: &CompressedEdwardsY, r_torsion: &ExtendedPoint, message: &[u8]) -> [u8; 64] {
+    let r: Scalar = hash_to_scalar(&[b"scenario nonce", message]);
+    let r_key: CompressedEdwardsY = (&(&r * &constants::ED25519_BASEPOINT) + r_torsion).compress_edwards();
+    let k: Scalar = hash_to_scalar(&[r_key.as_bytes(), key.as_bytes(), message]);
+    let mut signature: [u8; 64] = [0u8; 64];
+
+    signature[..32].copy_from_slice(r_key.as_bytes());
+    signature[32..].copy_from_slice(Scalar::multiply_add(&k, &secret(), &r).as_bytes());
+    signature
+}
+
+fn messages() -> Vec<Vec<u8>> {
+    (0..64u8).map(|i| vec![i]).collect()
+}
+
+/// A relay publishes A + T as its identity.  Everyone who verifies its
+/// signatures cofactored takes it for the same relay as A, but as a key it
+/// is a different identity, so a relay can appear in the consensus once
+/// per torsion point.
+#[test]
+fn torsioned_identity_key() {
+    let key: CompressedEdwardsY = (&public() + &torsion()).compress_edwards();
+
+    assert_eq!(key_verdicts(&key), [true, false, false]);
+    assert_eq!(key_verdicts(&public().compress_edwards()), [true, true, true]);
+}
+
+/// A relay publishes a point of small order as its identity.  Under the
+/// cofactored equation every signature (R, S) with [8]R = [8][S]B verifies,
+/// whatever the message, so such a key has no owner.
+#[test]
+fn small_order_identity_key() {
+    assert_eq!(key_verdicts(&torsion().compress_edwards()), [true, false, false]);
+}
+
+/// The identity, canonically and as y = p + 1.  It is in the prime-order
+/// subgroup, so only Strict refuses it, and it is why PrimeOrder is not
+/// enough by itself.
+#[test]
+fn identity_key() {
+    let mut unreduced: [u8; 32] = [0xff; 32];
+    unreduced[0] = 0xee;
+    unreduced[31] = 0x7f;
+
+    assert_eq!(key_verdicts(&ExtendedPoint::identity().compress_edwards()), [true, true, false]);
+    assert_eq!(key_verdicts(&CompressedEdwardsY(unreduced)), [true, true, false]);
+}
+
+/// One onion service key A has eight addresses, one for each A + T, which
+/// any cofactored verifier would take for the same service, and an HSDir
+/// would store as eight.
+#[cfg(feature = "blinding")]
+#[test]
+fn equivalent_key_onion_addresses() {
+    use key::CheckedKey;
+    use key::UncheckedKey;
+    use onion::check_onion_address;
+    use onion::onion_address;
+    use onion::parse_onion_address;
+    use onion::torsion_variant_addresses;
+
+    let key: CheckedKey<CompressedEdwardsY> =
+        UncheckedKey::new(public().compress_edwards()).check(KeyPolicy::Strict).unwrap();
+    let addresses: Vec<String> = torsion_variant_addresses(&onion_address(&key)).unwrap();
+    let accepted = |policy: Option<KeyPolicy>| addresses.iter().filter(|address| match policy {
+        Some(policy) => check_onion_address(address, policy).is_some(),
+        None         => parse_onion_address(address).is_some(),
+    }).count();
+
+    assert_eq!([accepted(None), accepted(Some(KeyPolicy::PrimeOrder)), accepted(Some(KeyPolicy::Strict))], [8, 1, 1]);
+}
+
+/// The owner of A signs as A + T.  [S]B - [k](A + T) - R is -[k]T, so a
+/// cofactorless verifier accepts exactly when 8 divides k, and a cofactored
+/// one always does: two implementations of "ed25519" split on which of
+/// these signatures are valid.
+#[test]
+fn cofactored_and_cofactorless_disagree_on_a_torsioned_key() {
+    let key: CompressedEdwardsY = (&public() + &torsion()).compress_edwards();
+    let mut cofactorless: usize = 0;
+
+    for message in messages().iter() {
+        let signature: [u8; 64] = sign_as(&key, &ExtendedPoint::identity(), message);
+        let mut r: [u8; 32] = [0u8; 32];
+        r.copy_from_slice(&signature[..32]);
+        let divisible: bool = challenge_is_multiple_of_8(&CompressedEdwardsY(r), &key, message);
+
+        assert_eq!(signature_verdicts(&key, message, &signature), [divisible, true, false]);
+        cofactorless += divisible as usize;
+    }
+    assert!(cofactorless > 0 && cofactorless < messages().len());
+}
+
+/// A valid key, but R carries torsion.  [S]B - [k]A - R is -T whatever the
+/// message, so only a cofactored verifier accepts, and anyone can make such
+/// a signature from an honest one, which is malleability.
+#[test]
+fn cofactored_accepts_a_torsioned_r() {
+    let key: CompressedEdwardsY = public().compress_edwards();
+
+    for message in messages().iter() {
+        let signature: [u8; 64] = sign_as(&key, &torsion(), message);
+
+        assert_eq!(signature_verdicts(&key, message, &signature), [false, true, false]);
+    }
+}
+
+/// (R, S) = (identity, 0) under a key T of order 8 needs no secret at all,
+/// and verifies cofactorless whenever 8 divides k.
+#[test]
+fn forged_signature_under_a_small_order_key() {
+    let key: CompressedEdwardsY = torsion().compress_edwards();
+    let identity: CompressedEdwardsY = ExtendedPoint::identity().compress_edwards();
+    let mut signature: [u8; 64] = [0u8; 64];
+    signature[..32].copy_from_slice(identity.as_bytes());
+
+    for message in messages().iter() {
+        let divisible: bool = challenge_is_multiple_of_8(&identity, &key, message);
+
+        assert_eq!(signature_verdicts(&key, message, &signature), [divisible, true, false]);
+    }
+}
@@ -0,0 +1,148 @@
+// A power-on self-test, for modules which must show that their key checks
+// still give the known answers before they are used.
+//
+// `selftest` runs the compiled-in known answers of `vectors`, each with the
+// verdict it must get under each policy, through every `Backend` this build
+// has, and reports every answer which was wrong.  A module should refuse to
+// check keys at all unless the report passed.
+
+use backend::Backend;
+use backend::Native;
+use policy::KeyPolicy;
+use vectors::known_answers;
+
+#[cfg(feature = "curve25519-dalek-4")]
+use backend::Dalek4;
+#[cfg(feature = "fiat")]
+use backend::Fiat;
+
+/// A known answer which a backend got wrong.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestFailure {
+    backend: &'static str,
+    encoding: [u8; 32],
+    policy: KeyPolicy,
+    expected: bool,
+}
+
+impl SelfTestFailure {
+    /// The backend's name.
+    pub fn backend(&self) -> &'static str {
+        self.backend
+    }
+
+    /// The encoding it was given.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// The policy it was checked under.
+    pub fn policy(&self) -> KeyPolicy {
+        self.policy
+    }
+
+    /// Whether it should have been accepted.  The backend said the opposite.
+    pub fn expected(&self) -> bool {
+        self.expected
+    }
+}
+
+/// What the self-test found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestReport {
+    backends: Vec<&'static str>,
+    answers: usize,
+    failures: Vec<SelfTestFailure>,
+}
+
+impl SelfTestReport {
+    /// Whether every backend gave every known answer.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// The backends tested.
+    pub fn backends(&self) -> &[&'static str] {
+        &self.backends
+    }
+
+    /// How many answers were checked, over every backend and policy.
+    pub fn answers(&self) -> usize {
+        self.answers
+    }
+
+    /// The wrong answers.
+    pub fn failures(&self) -> &[SelfTestFailure] {
+        &self.failures
+    }
+}
+
+/// Run the known answers through every backend in this build.
+pub fn selftest() -> SelfTestReport {
+    let mut report: SelfTestReport = SelfTestReport { backends: Vec::new(), answers: 0, failures: Vec::new() };
+
+    run(&Native, &mut report);
+    #[cfg(feature = "fiat")]
+    run(&Fiat, &mut report);
+    #[cfg(feature = "curve25519-dalek-4")]
+    run(&Dalek4, &mut report);
+
+    report
+}
+
+fn run<B: Backend>(backend: &B, report: &mut SelfTestReport) {
+    report.backends.push(backend.name());
+
+    for answer in known_answers().iter() {
+        for &policy in [KeyPolicy::PrimeOrder, KeyPolicy::Strict].iter() {
+            report.answers += 1;
+
+            if backend.accepts(answer.encoding(), policy) != answer.expected(policy) {
+                report.failures.push(SelfTestFailure {
+                    backend: backend.name(),
+                    encoding: *answer.encoding(),
+                    policy,
+                    expected: answer.expected(policy),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // Accepts everything.
+    struct Broken;
+
+    impl Backend for Broken {
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+
+        fn accepts(&self, _: &[u8; 32], _: KeyPolicy) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn every_backend_passes() {
+        let report: SelfTestReport = selftest();
+
+        assert!(report.passed(), "{:?}", report.failures());
+        assert_eq!(report.backends()[0], "curve25519-dalek-0.9");
+        assert_eq!(report.answers(), 2 * known_answers().len() * report.backends().len());
+    }
+
+    #[test]
+    fn wrong_answers_are_reported() {
+        let mut report: SelfTestReport = SelfTestReport { backends: Vec::new(), answers: 0, failures: Vec::new() };
+        run(&Broken, &mut report);
+
+        assert!(!report.passed());
+        // Every answer which should have been a rejection.
+        assert_eq!(report.failures().len(), 20);
+        assert!(report.failures().iter().all(|failure| failure.backend() == "broken" && !failure.expected()));
+    }
+}
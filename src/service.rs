@@ -0,0 +1,492 @@
+// Key validation as a tokio service, behind the `async` feature.
+//
+// Checking a key costs two scalar multiplications, which is long enough to
+// stall a reactor when keys arrive in bulk, so an `AsyncValidator` takes
+// requests over a bounded mpsc channel and deals them out in turn to a
+// fixed set of worker threads, each with a deque of its own, taking no
+// more from the channel while the workers have `DEPTH` apiece waiting.  A
+// worker checks the oldest request in its own deque, and once that is
+// empty steals the newest from another's, so a worker whose share of a
+// batch went quickly takes over the rest of a slower one's rather than
+// sitting idle.  Each request says where its verdict should go: to a
+// oneshot channel for a single key, or, tagged with an id, down an mpsc
+// channel shared by a whole batch.
+//
+// Verdicts down a shared channel arrive as their checks finish;
+// `OrderedVerdicts` puts them back in id order for callers which need that.
+// A producer which wants to slow down rather than be refused can watch
+// `AsyncValidator::capacity`, which reaches zero when the queue is full.
+//
+// The crate is written against the 2015 edition, which has no `async`, so
+// the dispatcher is a hand-written future; callers in newer editions just
+// `.await` the channels as usual.
+
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::thread;
+
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::task;
+use tokio_util::sync::PollSemaphore;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The outcome of checking one key.
+pub type Verdict = Option<CheckedKey<CompressedEdwardsY>>;
+
+enum Reply {
+    Oneshot(oneshot::Sender<Verdict>),
+    Stream(u64, mpsc::UnboundedSender<(u64, Verdict)>),
+}
+
+/// A key to check, and where to send the verdict.
+pub struct ValidationRequest {
+    key: UncheckedKey<CompressedEdwardsY>,
+    policy: KeyPolicy,
+    reply: Reply,
+}
+
+impl ValidationRequest {
+    /// A request whose verdict arrives on the returned receiver.
+    pub fn new(key: UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+        -> (ValidationRequest, oneshot::Receiver<Verdict>)
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        (ValidationRequest { key, policy, reply: Reply::Oneshot(sender) }, receiver)
+    }
+
+    /// A request whose verdict is sent down `results`, tagged with `id`.
+    /// Verdicts arrive in the order the checks finish, not the order they
+    /// were sent.
+    pub fn streaming(id: u64, key: UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy,
+                     results: &mpsc::UnboundedSender<(u64, Verdict)>) -> ValidationRequest
+    {
+        ValidationRequest { key, policy, reply: Reply::Stream(id, results.clone()) }
+    }
+
+    fn run(self) {
+        let verdict: Verdict = self.key.check(self.policy);
+
+        // A requester which has gone away no longer wants the answer.
+        match self.reply {
+            Reply::Oneshot(sender)     => { let _ = sender.send(verdict); },
+            Reply::Stream(id, results) => { let _ = results.send((id, verdict)); },
+        }
+    }
+}
+
+/// A handle on a running validation service.  The service stops once every
+/// clone of its sender has been dropped and the queue has drained.
+#[derive(Clone)]
+pub struct AsyncValidator {
+    sender: mpsc::Sender<ValidationRequest>,
+}
+
+impl AsyncValidator {
+    /// Start a service on the current tokio runtime, queueing at most
+    /// `queue_len` requests and checking them on `workers` threads.  The
+    /// threads exit once the service has stopped and their deques are empty.
+    ///
+    /// Panics if called outside a runtime, if either bound is zero, or if
+    /// the threads can't be started.
+    pub fn spawn(queue_len: usize, workers: usize) -> AsyncValidator {
+        assert!(workers > 0);
+
+        let (sender, receiver) = mpsc::channel(queue_len);
+        let pool: Arc<Pool> = Arc::new(Pool {
+            deques: (0..workers).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: Mutex::new(Queued { jobs: 0, closed: false }),
+            work: Condvar::new(),
+        });
+
+        for worker in 0..workers {
+            let pool: Arc<Pool> = pool.clone();
+
+            thread::Builder::new().name(format!("tor22006-worker-{}", worker))
+                .spawn(move || {
+                    while let Some((request, slot)) = pool.take(worker) {
+                        request.run();
+                        drop(slot);
+                    }
+                })
+                .unwrap();
+        }
+
+        let dispatcher: Dispatcher = Dispatcher {
+            requests: receiver,
+            slots: PollSemaphore::new(Arc::new(Semaphore::new(workers * DEPTH))),
+            slot: None,
+            pool,
+            next: 0,
+        };
+        task::spawn(dispatcher);
+
+        AsyncValidator { sender }
+    }
+
+    /// The channel requests are accepted on, for callers which want to wait
+    /// for room in the queue with `send(..).await`.
+    pub fn sender(&self) -> mpsc::Sender<ValidationRequest> {
+        self.sender.clone()
+    }
+
+    /// How many more requests the queue has room for now.
+    pub fn capacity(&self) -> usize {
+        self.sender.capacity()
+    }
+
+    /// Whether the queue is full, so that `try_validate` would fail.
+    pub fn is_saturated(&self) -> bool {
+        self.capacity() == 0
+    }
+
+    /// Queue `key` to be checked under `policy`, without waiting.  Fails,
+    /// handing the key back, if the queue is full or the service has
+    /// stopped.
+    pub fn try_validate(&self, key: UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+        -> Result<oneshot::Receiver<Verdict>, UncheckedKey<CompressedEdwardsY>>
+    {
+        let (request, receiver) = ValidationRequest::new(key, policy);
+
+        match self.sender.try_send(request) {
+            Ok(())     => Ok(receiver),
+            Err(error) => Err(match error {
+                mpsc::error::TrySendError::Full(request)   => request.key,
+                mpsc::error::TrySendError::Closed(request) => request.key,
+            }),
+        }
+    }
+}
+
+/// Verdicts from a streaming batch, in id order.  The ids are expected to
+/// count up from the one given, as they do when a batch is numbered as it's
+/// sent.  If the channel closes with one missing, because its request was
+/// dropped unchecked, the verdicts stop there rather than skip it, so that
+/// a caller given fewer than it sent knows the first lost is the one after
+/// the last it was given.
+pub struct OrderedVerdicts {
+    results: mpsc::UnboundedReceiver<(u64, Verdict)>,
+    next: u64,
+    pending: BTreeMap<u64, Verdict>,
+}
+
+impl OrderedVerdicts {
+    /// Reorder `results`, starting from the id `first`.
+    pub fn new(results: mpsc::UnboundedReceiver<(u64, Verdict)>, first: u64) -> OrderedVerdicts {
+        OrderedVerdicts { results, next: first, pending: BTreeMap::new() }
+    }
+
+    /// The next verdict in order, or `None` once there are no more.
+    pub fn recv(&mut self) -> Recv<'_> {
+        Recv { verdicts: self }
+    }
+
+    /// As `recv`, for callers driving their own futures.
+    pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<Option<(u64, Verdict)>> {
+        loop {
+            if let Some(verdict) = self.pending.remove(&self.next) {
+                self.next += 1;
+                return Poll::Ready(Some((self.next - 1, verdict)));
+            }
+
+            match self.results.poll_recv(cx) {
+                Poll::Ready(Some((id, verdict))) => { self.pending.insert(id, verdict); },
+                Poll::Ready(None)                => return Poll::Ready(None), // every sender is gone
+                Poll::Pending                    => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// The future `OrderedVerdicts::recv` returns.
+pub struct Recv<'a> {
+    verdicts: &'a mut OrderedVerdicts,
+}
+
+impl<'a> Future for Recv<'a> {
+    type Output = Option<(u64, Verdict)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<(u64, Verdict)>> {
+        self.get_mut().verdicts.poll_recv(cx)
+    }
+}
+
+/// How many requests each worker may have waiting in its deque, so that
+/// there is something to steal.
+const DEPTH: usize = 4;
+
+// A request, and the slot it holds until it has been checked.
+type Job = (ValidationRequest, OwnedSemaphorePermit);
+
+// The workers' deques, and a count of the jobs in them which don't yet have
+// a worker coming for them, for idle workers to wait on.
+struct Pool {
+    deques: Vec<Mutex<VecDeque<Job>>>,
+    queued: Mutex<Queued>,
+    work: Condvar,
+}
+
+struct Queued {
+    jobs: usize,
+    closed: bool,
+}
+
+impl Pool {
+    fn push(&self, worker: usize, job: Job) {
+        self.deques[worker].lock().unwrap().push_back(job);
+        self.queued.lock().unwrap().jobs += 1;
+        self.work.notify_one();
+    }
+
+    fn close(&self) {
+        self.queued.lock().unwrap().closed = true;
+        self.work.notify_all();
+    }
+
+    // The next job for `worker`, or `None` once the pool is closed and
+    // empty.  A job is claimed from the count before it is looked for, and
+    // was pushed before it was counted, so the search always ends; it can
+    // take more than one pass if other workers take jobs this one passed
+    // over as empty.
+    fn take(&self, worker: usize) -> Option<Job> {
+        {
+            let mut queued = self.queued.lock().unwrap();
+
+            while queued.jobs == 0 && !queued.closed {
+                queued = self.work.wait(queued).unwrap();
+            }
+            if queued.jobs == 0 {
+                return None; // closed, and drained
+            }
+            queued.jobs -= 1;
+        }
+
+        let workers: usize = self.deques.len();
+        loop {
+            // The oldest job in this worker's own deque, or else the newest
+            // in another's.
+            if let Some(job) = self.deques[worker].lock().unwrap().pop_front() {
+                return Some(job);
+            }
+            for other in (1..workers).map(|k| (worker + k) % workers) {
+                if let Some(job) = self.deques[other].lock().unwrap().pop_back() {
+                    return Some(job);
+                }
+            }
+            thread::yield_now();
+        }
+    }
+}
+
+// Takes a slot, then a request, and deals both to the next worker in turn;
+// the slot is given back when the check finishes.  The workers are told to
+// stop once the dispatcher is dropped, whether because every sender has
+// gone or because the runtime has.
+struct Dispatcher {
+    requests: mpsc::Receiver<ValidationRequest>,
+    slots: PollSemaphore,
+    slot: Option<OwnedSemaphorePermit>,
+    pool: Arc<Pool>,
+    next: usize,
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.pool.close();
+    }
+}
+
+impl Future for Dispatcher {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this: &mut Dispatcher = self.get_mut();
+
+        loop {
+            if this.slot.is_none() {
+                this.slot = match this.slots.poll_acquire(cx) {
+                    Poll::Ready(Some(slot)) => Some(slot),
+                    Poll::Ready(None)       => return Poll::Ready(()), // never closed
+                    Poll::Pending           => return Poll::Pending,
+                };
+            }
+
+            let request: ValidationRequest = match this.requests.poll_recv(cx) {
+                Poll::Ready(Some(request)) => request,
+                Poll::Ready(None)          => return Poll::Ready(()), // every sender is gone
+                Poll::Pending              => return Poll::Pending,
+            };
+            let slot: OwnedSemaphorePermit = this.slot.take().unwrap();
+
+            this.pool.push(this.next, (request, slot));
+            this.next = (this.next + 1) % this.pool.deques.len();
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use tokio::runtime::Runtime;
+
+    use curve25519_dalek::constants;
+
+    fn runtime() -> Runtime {
+        tokio::runtime::Builder::new_multi_thread().worker_threads(2).build().unwrap()
+    }
+
+    #[test]
+    fn single_keys_get_oneshot_verdicts() {
+        let runtime: Runtime = runtime();
+        let _context = runtime.enter();
+        let validator: AsyncValidator = AsyncValidator::spawn(4, 2);
+
+        let good: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+        let bad: UncheckedKey<CompressedEdwardsY> = constants::EIGHT_TORSION[1].compress_edwards().into();
+
+        let good_verdict = validator.try_validate(good, KeyPolicy::Strict).ok().unwrap();
+        let bad_verdict = validator.try_validate(bad, KeyPolicy::Strict).ok().unwrap();
+
+        assert!(runtime.block_on(good_verdict).unwrap().is_some());
+        assert!(runtime.block_on(bad_verdict).unwrap().is_none());
+    }
+
+    #[test]
+    fn batches_stream_tagged_verdicts() {
+        let runtime: Runtime = runtime();
+        let _context = runtime.enter();
+        let validator: AsyncValidator = AsyncValidator::spawn(16, 3);
+        let (results, mut verdicts) = mpsc::unbounded_channel();
+
+        for id in 0..10u64 {
+            let key: UncheckedKey<CompressedEdwardsY> = match id % 2 {
+                0 => constants::ED25519_BASEPOINT.compress_edwards().into(),
+                _ => constants::EIGHT_TORSION[3].compress_edwards().into(),
+            };
+            assert!(validator.sender().try_send(ValidationRequest::streaming(id, key, KeyPolicy::Strict,
+                                                                             &results)).is_ok());
+        }
+        drop(results);
+
+        let mut seen: Vec<(u64, bool)> = Vec::new();
+        while let Some((id, verdict)) = runtime.block_on(verdicts.recv()) {
+            seen.push((id, verdict.is_some()));
+        }
+        seen.sort();
+
+        assert_eq!(seen, (0..10u64).map(|id| (id, id % 2 == 0)).collect::<Vec<(u64, bool)>>());
+    }
+
+    #[test]
+    fn idle_workers_steal_the_newest_job() {
+        let pool: Pool = Pool {
+            deques: (0..2).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: Mutex::new(Queued { jobs: 0, closed: false }),
+            work: Condvar::new(),
+        };
+        let slots: Arc<Semaphore> = Arc::new(Semaphore::new(3));
+        let (results, _verdicts) = mpsc::unbounded_channel();
+        let key: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+        let id = |job: Option<Job>| match job.unwrap().0.reply {
+            Reply::Stream(id, _) => id,
+            Reply::Oneshot(_)    => panic!("streaming requests only"),
+        };
+
+        for id in 0..3u64 {
+            let slot: OwnedSemaphorePermit = slots.clone().try_acquire_owned().unwrap();
+            pool.push(0, (ValidationRequest::streaming(id, key, KeyPolicy::Strict, &results), slot));
+        }
+        pool.close();
+
+        assert_eq!(id(pool.take(1)), 2);
+        assert_eq!(id(pool.take(0)), 0);
+        assert_eq!(id(pool.take(1)), 1);
+        assert!(pool.take(0).is_none());
+        assert!(pool.take(1).is_none());
+    }
+
+    #[test]
+    fn verdicts_can_be_put_back_in_order() {
+        let runtime: Runtime = runtime();
+        let _context = runtime.enter();
+        let validator: AsyncValidator = AsyncValidator::spawn(32, 4);
+        let (results, verdicts) = mpsc::unbounded_channel();
+        let mut verdicts: OrderedVerdicts = OrderedVerdicts::new(verdicts, 100);
+
+        for id in 100..120u64 {
+            let key: UncheckedKey<CompressedEdwardsY> = match id % 3 {
+                0 => constants::EIGHT_TORSION[2].compress_edwards().into(),
+                _ => constants::ED25519_BASEPOINT.compress_edwards().into(),
+            };
+            if id != 110 {
+                assert!(validator.sender().try_send(ValidationRequest::streaming(id, key, KeyPolicy::Strict,
+                                                                                 &results)).is_ok());
+            }
+        }
+        drop(results);
+
+        let mut seen: Vec<(u64, bool)> = Vec::new();
+        while let Some((id, verdict)) = runtime.block_on(verdicts.recv()) {
+            seen.push((id, verdict.is_some()));
+        }
+
+        // 110 was never sent, so the verdicts stop short of it.
+        assert_eq!(seen, (100..110u64).map(|id| (id, id % 3 != 0)).collect::<Vec<(u64, bool)>>());
+    }
+
+    #[test]
+    fn ordered_verdicts_stop_at_a_lost_one() {
+        let runtime: Runtime = runtime();
+        let (results, verdicts) = mpsc::unbounded_channel();
+        let mut verdicts: OrderedVerdicts = OrderedVerdicts::new(verdicts, 0);
+        let lost: mpsc::UnboundedSender<(u64, Verdict)> = results.clone();
+
+        assert!(results.send((1, None)).is_ok());
+        assert!(results.send((0, None)).is_ok());
+        assert!(results.send((3, None)).is_ok());
+        assert_eq!(runtime.block_on(verdicts.recv()), Some((0, None)));
+        assert_eq!(runtime.block_on(verdicts.recv()), Some((1, None)));
+
+        // The sender which was to send 2 goes away without it.
+        drop(lost);
+        drop(results);
+
+        assert_eq!(runtime.block_on(verdicts.recv()), None);
+        assert_eq!(runtime.block_on(verdicts.recv()), None);
+    }
+
+    #[test]
+    fn full_queues_push_back() {
+        // Nothing runs on this runtime until it is blocked on, so the queue
+        // doesn't drain by itself.
+        let runtime: Runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let _context = runtime.enter();
+        let validator: AsyncValidator = AsyncValidator::spawn(2, 1);
+        let key: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+
+        assert_eq!(validator.capacity(), 2);
+        let first = validator.try_validate(key, KeyPolicy::Strict).ok().unwrap();
+        let second = validator.try_validate(key, KeyPolicy::Strict).ok().unwrap();
+
+        assert!(validator.is_saturated());
+        assert_eq!(validator.try_validate(key, KeyPolicy::Strict).err(), Some(key));
+        assert!(runtime.block_on(first).unwrap().is_some());
+        assert!(runtime.block_on(second).unwrap().is_some());
+    }
+}
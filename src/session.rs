@@ -0,0 +1,234 @@
+// Collecting everything a caller needs checked, and checking it all at the
+// end, rather than managing batches by hand.
+//
+// A `VerifierSession` takes signatures, as (key, message, signature), and
+// keys on their own, in any order, and hands back an index for each.
+// `finalize` then checks everything in one pass, choosing how per item:
+//
+//  - each distinct key is validated once, however often it was added;
+//  - a key with at least `EXPAND_THRESHOLD` signatures, which is also
+//    torsion-free, has an `ExpandedPublicKey` built for it, whose table pays
+//    for itself over that many verifications;
+//  - every other signature goes into one `verify_batch`, and if the batch
+//    fails it is halved, and each half retried, until the failures are
+//    found, so a few bad signatures among many cost a few extra batches
+//    rather than verifying everything alone.
+//
+// Under `Cofactorless` there is no batch to be had, so the signatures which
+// aren't expanded are verified one at a time.  Either way, the verdicts are
+// exactly those `verify` and `UncheckedKey::check` would give.
+
+use std::collections::HashMap;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::verify;
+use ed25519::verify_batch;
+use ed25519::ExpandedPublicKey;
+use entropy::EntropySource;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+
+/// How many signatures under one key make building its table worthwhile.
+pub const EXPAND_THRESHOLD: usize = 8;
+
+enum Item<'a> {
+    Key(CompressedEdwardsY),
+    Signature(CompressedEdwardsY, &'a [u8], [u8; 64]),
+}
+
+/// Signatures and keys waiting to be checked together.
+pub struct VerifierSession<'a> {
+    profile: VerificationProfile,
+    policy: KeyPolicy,
+    items: Vec<Item<'a>>,
+}
+
+impl<'a> VerifierSession<'a> {
+    /// A session verifying signatures under `profile`, and validating keys
+    /// under `policy`.
+    pub fn new(profile: VerificationProfile, policy: KeyPolicy) -> VerifierSession<'a> {
+        VerifierSession { profile, policy, items: Vec::new() }
+    }
+
+    /// Add `signature` on `message` under `key`, returning the index of its
+    /// verdict.
+    pub fn add_signature(&mut self, key: &CompressedEdwardsY, message: &'a [u8], signature: &[u8; 64]) -> usize {
+        self.items.push(Item::Signature(*key, message, *signature));
+        self.items.len() - 1
+    }
+
+    /// Add `key` to be validated, returning the index of its verdict.
+    pub fn add_key(&mut self, key: &CompressedEdwardsY) -> usize {
+        self.items.push(Item::Key(*key));
+        self.items.len() - 1
+    }
+
+    /// How many items have been added.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether nothing has been added.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Check everything, returning a verdict for each item in the order it
+    /// was added, with batch coefficients drawn from `entropy`.
+    pub fn finalize<E: EntropySource>(self, entropy: &mut E) -> Vec<bool> {
+        let mut verdicts: Vec<bool> = vec![false; self.items.len()];
+        let mut keys: HashMap<[u8; 32], bool> = HashMap::new();
+        let mut signers: HashMap<[u8; 32], Vec<usize>> = HashMap::new();
+
+        for (i, item) in self.items.iter().enumerate() {
+            match *item {
+                Item::Key(ref key) => {
+                    let policy: KeyPolicy = self.policy;
+                    verdicts[i] = *keys.entry(key.to_bytes())
+                        .or_insert_with(|| UncheckedKey::new(*key).check(policy).is_some());
+                },
+                Item::Signature(ref key, _, _) => signers.entry(key.to_bytes()).or_default().push(i),
+            }
+        }
+
+        let mut batched: Vec<usize> = Vec::new();
+        for (key, indices) in signers.iter() {
+            let checked: Option<CheckedKey<CompressedEdwardsY>> = match indices.len() >= EXPAND_THRESHOLD {
+                true  => UncheckedKey::new(CompressedEdwardsY(*key)).check(KeyPolicy::PrimeOrder),
+                false => None, // too few signatures to pay for a table
+            };
+            match checked {
+                Some(checked) => {
+                    let expanded: ExpandedPublicKey = ExpandedPublicKey::new(&checked);
+                    for &i in indices.iter() {
+                        if let Item::Signature(_, message, ref signature) = self.items[i] {
+                            verdicts[i] = expanded.verify(message, signature, self.profile);
+                        }
+                    }
+                },
+                None => batched.extend_from_slice(indices),
+            }
+        }
+
+        batched.sort();
+        let mut triples: (Vec<CompressedEdwardsY>, Vec<&[u8]>, Vec<[u8; 64]>) = (Vec::new(), Vec::new(), Vec::new());
+        for &i in batched.iter() {
+            if let Item::Signature(key, message, signature) = self.items[i] {
+                triples.0.push(key);
+                triples.1.push(message);
+                triples.2.push(signature);
+            }
+        }
+
+        let mut results: Vec<bool> = vec![false; batched.len()];
+        match self.profile {
+            VerificationProfile::Cofactorless => {
+                for (j, result) in results.iter_mut().enumerate() {
+                    *result = verify(&triples.0[j], triples.1[j], &triples.2[j], self.profile);
+                }
+            },
+            _ => bisect(&triples.0, &triples.1, &triples.2, self.profile, entropy, &mut results),
+        }
+        for (&i, &result) in batched.iter().zip(results.iter()) {
+            verdicts[i] = result;
+        }
+        verdicts
+    }
+}
+
+// Fill `results` with the verdict on each signature, verifying them as one
+// batch and, if it fails, each half of it in turn.
+fn bisect<E: EntropySource>(keys: &[CompressedEdwardsY],
+                            messages: &[&[u8]],
+                            signatures: &[[u8; 64]],
+                            profile: VerificationProfile,
+                            entropy: &mut E,
+                            results: &mut [bool]) {
+    match keys.len() {
+        0 => return,
+        1 => {
+            results[0] = verify(&keys[0], messages[0], &signatures[0], profile);
+            return;
+        },
+        _ => (),
+    }
+    if verify_batch(keys, messages, signatures, profile, entropy) {
+        for result in results.iter_mut() {
+            *result = true;
+        }
+        return;
+    }
+
+    let half: usize = keys.len() / 2;
+    let (left, right): (&mut [bool], &mut [bool]) = results.split_at_mut(half);
+    bisect(&keys[..half], &messages[..half], &signatures[..half], profile, entropy, left);
+    bisect(&keys[half..], &messages[half..], &signatures[half..], profile, entropy, right);
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use ed25519::public_key_from_seed;
+    use ed25519::sign;
+    use entropy::OsEntropy;
+
+    #[test]
+    fn sessions_agree_with_verify() {
+        let seeds: Vec<[u8; 32]> = (0..12u8).map(|i| [i; 32]).collect();
+        let keys: Vec<CompressedEdwardsY> = seeds.iter().map(public_key_from_seed).collect();
+        let torsioned: CompressedEdwardsY =
+            (&keys[0].decompress().unwrap() + &constants::EIGHT_TORSION[1]).compress_edwards();
+        let messages: Vec<[u8; 1]> = (0..40u8).map(|i| [i]).collect();
+        let profiles: [VerificationProfile; 3] = [
+            VerificationProfile::Cofactored,
+            VerificationProfile::Cofactorless,
+            VerificationProfile::Strict,
+        ];
+
+        for &profile in profiles.iter() {
+            let mut session: VerifierSession = VerifierSession::new(profile, KeyPolicy::Strict);
+            let mut expected: Vec<bool> = Vec::new();
+
+            // Ten signatures under the first key, enough to expand it, with
+            // one of them bad, then one under each other key, with two bad.
+            for (j, message) in messages.iter().enumerate() {
+                let signer: usize = match j < 10 {
+                    true  => 0,
+                    false => j % 12,
+                };
+                let mut signature: [u8; 64] = sign(&seeds[signer], message);
+                if j == 4 || j == 13 || j == 30 {
+                    signature[40] ^= 1;
+                }
+
+                assert_eq!(session.add_signature(&keys[signer], message, &signature), expected.len());
+                expected.push(verify(&keys[signer], message, &signature, profile));
+            }
+            for key in [keys[3], torsioned, CompressedEdwardsY([0x02; 32]), keys[3]].iter() {
+                assert_eq!(session.add_key(key), expected.len());
+                expected.push(UncheckedKey::new(*key).check(KeyPolicy::Strict).is_some());
+            }
+            let signature: [u8; 64] = sign(&seeds[0], b"");
+            session.add_signature(&torsioned, b"", &signature);
+            expected.push(verify(&torsioned, b"", &signature, profile));
+
+            assert_eq!(session.len(), expected.len());
+            assert_eq!(expected.iter().filter(|&&verdict| !verdict).count(), 6);
+            assert_eq!(session.finalize(&mut OsEntropy::new()), expected);
+        }
+    }
+
+    #[test]
+    fn empty_sessions_finalize() {
+        let session: VerifierSession = VerifierSession::new(VerificationProfile::Strict, KeyPolicy::Strict);
+
+        assert!(session.is_empty());
+        assert!(session.finalize(&mut OsEntropy::new()).is_empty());
+    }
+}
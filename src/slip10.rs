@@ -0,0 +1,231 @@
+// SLIP-0010 hierarchical derivation of Ed25519 keys, as used by wallets.
+//
+// Only hardened derivation exists for Ed25519: each child secret key is the
+// left half of HMAC-SHA512(chain code, 0x00 || parent key || index), and its
+// chain code is the right half.  The public key is then computed from the
+// secret key exactly as for any other Ed25519 seed, which means the
+// clamped scalar is multiplied by the basepoint and the result always lies
+// in the prime-order subgroup.  A derived key should therefore never fail
+// validation; checking it anyway catches keys which were not derived the way
+// the wallet claims.
+
+use sha2::Digest;
+use sha2::Sha512;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use ed25519::public_key_from_seed;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use wipe::wipe;
+
+/// Indices at or above this are hardened.
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// A secret key together with the chain code its children are derived with.
+#[derive(Clone)]
+pub struct ExtendedSecretKey {
+    key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for ExtendedSecretKey {
+    fn drop(&mut self) {
+        wipe(&mut self.key);
+        wipe(&mut self.chain_code);
+    }
+}
+
+impl ExtendedSecretKey {
+    /// Use a secret key and chain code obtained elsewhere.
+    pub fn new(key: [u8; 32], chain_code: [u8; 32]) -> ExtendedSecretKey {
+        ExtendedSecretKey { key, chain_code }
+    }
+
+    /// Derive the master key from a wallet seed.
+    pub fn from_seed(seed: &[u8]) -> ExtendedSecretKey {
+        split(hmac_sha512(b"ed25519 seed", &[seed]))
+    }
+
+    /// The secret key, which is an Ed25519 seed.
+    pub fn key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    /// The chain code this key's children are derived with.
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    /// Derive the child at `index`, or return `None` if `index` is not
+    /// hardened.
+    pub fn derive_child(&self, index: u32) -> Option<ExtendedSecretKey> {
+        if index < HARDENED {
+            return None; // Ed25519 has no non-hardened derivation
+        }
+
+        let index_bytes: [u8; 4] = [(index >> 24) as u8, (index >> 16) as u8,
+                                    (index >> 8) as u8, index as u8];
+
+        Some(split(hmac_sha512(&self.chain_code, &[&[0u8], &self.key, &index_bytes])))
+    }
+
+    /// Derive the descendant at `path`, a list of indices as from
+    /// `parse_path`.
+    pub fn derive_path(&self, path: &[u32]) -> Option<ExtendedSecretKey> {
+        let mut key: ExtendedSecretKey = self.clone();
+
+        for &index in path {
+            key = key.derive_child(index)?;
+        }
+        Some(key)
+    }
+
+    /// The public key for this secret key.
+    pub fn public_key(&self) -> CompressedEdwardsY {
+        public_key_from_seed(&self.key)
+    }
+}
+
+/// Parse a path such as `m/44'/0'/1'` into its indices.  Hardened indices
+/// may be marked with `'`, `h` or `H`.
+pub fn parse_path(path: &str) -> Option<Vec<u32>> {
+    let mut components = path.split('/');
+
+    if components.next() != Some("m") {
+        return None;
+    }
+
+    components.map(|component| {
+        let (digits, hardened): (&str, bool) = match component.chars().last() {
+            Some('\'') | Some('h') | Some('H') => (&component[..component.len() - 1], true),
+            _ => (component, false),
+        };
+
+        match digits.parse::<u32>() {
+            Ok(index) if index < HARDENED && !digits.starts_with('+') => {
+                Some(if hardened { index | HARDENED } else { index })
+            },
+            _ => None,
+        }
+    }).collect()
+}
+
+/// Derive the public key at `path` from a wallet seed, and check it under
+/// `policy`.
+///
+/// Returns `None` if the path is unparseable or has a non-hardened index,
+/// or if the derived key somehow fails validation.
+pub fn derive_and_check(seed: &[u8], path: &str, policy: KeyPolicy)
+    -> Option<CheckedKey<CompressedEdwardsY>>
+{
+    let indices: Vec<u32> = parse_path(path)?;
+
+    match ExtendedSecretKey::from_seed(seed).derive_path(&indices) {
+        Some(key) => UncheckedKey::new(key.public_key()).check(policy),
+        None      => None,
+    }
+}
+
+fn split(mut output: [u8; 64]) -> ExtendedSecretKey {
+    let mut key: [u8; 32] = [0u8; 32];
+    let mut chain_code: [u8; 32] = [0u8; 32];
+
+    key.copy_from_slice(&output[..32]);
+    chain_code.copy_from_slice(&output[32..]);
+    wipe(&mut output);
+
+    ExtendedSecretKey { key, chain_code }
+}
+
+// HMAC-SHA512 (RFC 2104) over the concatenation of `data`.
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> [u8; 64] {
+    let mut block: [u8; 128] = [0u8; 128];
+
+    if key.len() > 128 {
+        let mut h: Sha512 = Sha512::default();
+        h.input(key);
+        block[..64].copy_from_slice(h.result().as_slice());
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Sha512 = Sha512::default();
+    let mut outer: Sha512 = Sha512::default();
+    let mut pad: [u8; 128] = [0u8; 128];
+
+    for i in 0..128 {
+        pad[i] = block[i] ^ 0x36;
+    }
+    inner.input(&pad);
+    for part in data {
+        inner.input(part);
+    }
+
+    for i in 0..128 {
+        pad[i] = block[i] ^ 0x5c;
+    }
+    outer.input(&pad);
+    outer.input(inner.result().as_slice());
+    wipe(&mut block);
+    wipe(&mut pad);
+
+    let mut mac: [u8; 64] = [0u8; 64];
+    mac.copy_from_slice(outer.result().as_slice());
+    mac
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    // SLIP-0010 test vector 1 for ed25519.
+    static SEED: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f ];
+
+    static MASTER_CHAIN_CODE: [u8; 32] = [
+        0x90, 0x04, 0x6a, 0x93, 0xde, 0x53, 0x80, 0xa7,
+        0x2b, 0x5e, 0x45, 0x01, 0x07, 0x48, 0x56, 0x7d,
+        0x5e, 0xa0, 0x2b, 0xbf, 0x65, 0x22, 0xf9, 0x79,
+        0xe0, 0x5c, 0x0d, 0x8d, 0x8c, 0xa9, 0xff, 0xfb ];
+
+    static MASTER_PUBLIC_KEY: [u8; 32] = [
+        0xa4, 0xb2, 0x85, 0x6b, 0xfe, 0xc5, 0x10, 0xab,
+        0xab, 0x89, 0x75, 0x3f, 0xac, 0x1a, 0xc0, 0xe1,
+        0x11, 0x23, 0x64, 0xe7, 0xd2, 0x50, 0x54, 0x59,
+        0x63, 0xf1, 0x35, 0xf2, 0xa3, 0x31, 0x88, 0xed ];
+
+    // m/0'/1'/2'
+    static CHILD_PUBLIC_KEY: [u8; 32] = [
+        0xae, 0x98, 0x73, 0x65, 0x66, 0xd3, 0x0e, 0xd0,
+        0xe9, 0xd2, 0xf4, 0x48, 0x6a, 0x64, 0xbc, 0x95,
+        0x74, 0x0d, 0x89, 0xc7, 0xdb, 0x33, 0xf5, 0x21,
+        0x21, 0xf8, 0xea, 0x8f, 0x76, 0xff, 0x0f, 0xc1 ];
+
+    #[test]
+    fn slip10_vector() {
+        let master: ExtendedSecretKey = ExtendedSecretKey::from_seed(&SEED);
+
+        assert_eq!(master.chain_code(), &MASTER_CHAIN_CODE);
+        assert_eq!(master.public_key(), CompressedEdwardsY(MASTER_PUBLIC_KEY));
+        assert_eq!(derive_and_check(&SEED, "m/0'/1h/2H", KeyPolicy::Strict).unwrap().encoding(),
+                   &CompressedEdwardsY(CHILD_PUBLIC_KEY));
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(parse_path("m"), Some(vec![]));
+        assert_eq!(parse_path("m/44'/0"), Some(vec![44 | HARDENED, 0]));
+        assert_eq!(parse_path("m/2147483648'"), None);
+        assert_eq!(parse_path("44'/0'"), None);
+        assert_eq!(parse_path("m//0'"), None);
+    }
+
+    #[test]
+    fn non_hardened_indices_are_refused() {
+        assert!(derive_and_check(&SEED, "m/0'/1", KeyPolicy::PrimeOrder).is_none());
+    }
+}
@@ -0,0 +1,159 @@
+// Every small-order ed25519 encoding, run through every validator in the
+// crate, so that the claims made about them elsewhere are checked rather than
+// just stated.
+//
+// E[8] has eight points, but more than eight encodings: the sign bit of x
+// can be set when x = 0, and y can be encoded as y + p when that still fits
+// in 255 bits, which is only the case for y = 0 and y = 1.  That makes
+// fourteen encodings in all, which are enumerated here from the torsion
+// points themselves, along with B + T for each T in E[8].
+//
+// What the validators make of them is this, where "any" means the encoding
+// is accepted and "--" that it is rejected:
+//
+//                              identity     identity         other      B + T,
+//                              canonical    non-canonical    E[8]       T ≠ 0
+//
+//    decompress                any          any              any        any
+//    mult_by_cofactor_and_     any          any              --         --
+//        validate, validate,
+//        KeyPolicy::PrimeOrder
+//    validate_strict,          --           --               --         --
+//        KeyPolicy::Strict
+//    ed25519_to_ristretto      any          --               --         --
+//    !ladder::is_small_order   --           --               --         any
+//    diagnose                  Identity     NonCanonical     Torsioned  Torsioned
+//
+// and B itself passes everything.  So the policies disagree only about the
+// identity, and only `Strict` rejects every small-order encoding outright.
+//
+// Verification is checked with the signature (R, S) = (identity, 0), which
+// satisfies the cofactored equation for every message under every
+// small-order key.  The cofactored profile accepts it under all fourteen;
+// the strict profile rejects it under every key but the canonical identity,
+// which, unlike `KeyPolicy::Strict`, it doesn't refuse.
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::Identity;
+use curve25519_dalek::curve::IsIdentity;
+
+use bridge::ed25519_to_ristretto;
+use ed25519::verify;
+use edwards::mult_by_cofactor_and_validate;
+use key::KeyProblem;
+use key::UncheckedKey;
+use ladder;
+use policy::KeyPolicy;
+use policy::VerificationProfile;
+use roundtrip::roundtrip_check;
+use traits::PrimeOrderEncoding;
+
+// Every encoding which decompresses to a point of E[8].
+fn small_order_encodings() -> Vec<CompressedEdwardsY> {
+    let mut encodings: Vec<CompressedEdwardsY> = Vec::new();
+
+    for t in constants::EIGHT_TORSION.iter() {
+        let canonical: [u8; 32] = t.compress_edwards().to_bytes();
+        let mut negative: [u8; 32] = canonical;
+        negative[31] ^= 0x80;
+
+        encodings.push(CompressedEdwardsY(canonical));
+        encodings.push(CompressedEdwardsY(negative));
+    }
+
+    // y = p and y = p + 1, for y = 0 and y = 1, with either sign.
+    for &low in [0xed, 0xee].iter() {
+        for &high in [0x7f, 0xff].iter() {
+            let mut bytes: [u8; 32] = [0xff; 32];
+            bytes[0] = low;
+            bytes[31] = high;
+
+            encodings.push(CompressedEdwardsY(bytes));
+        }
+    }
+
+    // Negating x is a no-op or another point of E[8], so some of the above
+    // are repeats.
+    encodings.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+    encodings.dedup();
+    encodings
+}
+
+fn is_identity(key: &CompressedEdwardsY) -> bool {
+    key.decompress().unwrap().is_identity()
+}
+
+// Whether each validator accepts `key`, in the order of the rows above.
+fn verdicts(key: &CompressedEdwardsY) -> [bool; 8] {
+    let unchecked: UncheckedKey<CompressedEdwardsY> = UncheckedKey::new(*key);
+
+    [
+        key.decompress().is_some(),
+        mult_by_cofactor_and_validate(key).is_some(),
+        key.validate().is_some(),
+        unchecked.check(KeyPolicy::PrimeOrder).is_some(),
+        key.validate_strict().is_some(),
+        unchecked.check(KeyPolicy::Strict).is_some(),
+        ed25519_to_ristretto(key).is_some(),
+        !ladder::is_small_order(key),
+    ]
+}
+
+#[test]
+fn there_are_fourteen_small_order_encodings() {
+    let encodings: Vec<CompressedEdwardsY> = small_order_encodings();
+
+    assert_eq!(encodings.len(), 14);
+    assert_eq!(encodings.iter().filter(|key| roundtrip_check(key)).count(), 8);
+    assert_eq!(encodings.iter().filter(|key| is_identity(key)).count(), 4);
+
+    for key in encodings.iter() {
+        assert!(key.decompress().unwrap().is_small_order());
+    }
+}
+
+#[test]
+fn small_order_matrix() {
+    for key in small_order_encodings().iter() {
+        let (expected, problem): ([bool; 8], KeyProblem) = match (is_identity(key), roundtrip_check(key)) {
+            (true, true)  => ([true, true, true, true, false, false, true, false], KeyProblem::Identity),
+            (true, false) => ([true, true, true, true, false, false, false, false], KeyProblem::NonCanonical),
+            (false, _)    => ([true, false, false, false, false, false, false, false], KeyProblem::Torsioned),
+        };
+
+        assert_eq!(verdicts(key), expected, "{:?}", key);
+        assert_eq!(UncheckedKey::new(*key).diagnose(), Some(problem), "{:?}", key);
+        assert!(UncheckedKey::new(*key).accept_with_torsion_dangerously().is_some());
+    }
+}
+
+#[test]
+fn torsioned_basepoint_matrix() {
+    for (i, t) in constants::EIGHT_TORSION.iter().enumerate() {
+        let key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT + t).compress_edwards();
+        let (expected, problem): ([bool; 8], Option<KeyProblem>) = match i {
+            0 => ([true; 8], None),
+            _ => ([true, false, false, false, false, false, false, true], Some(KeyProblem::Torsioned)),
+        };
+
+        assert_eq!(verdicts(&key), expected, "B + T[{}]", i);
+        assert_eq!(UncheckedKey::new(key).diagnose(), problem, "B + T[{}]", i);
+    }
+}
+
+#[test]
+fn small_order_signatures() {
+    let mut signature: [u8; 64] = [0u8; 64];
+    signature[..32].copy_from_slice(ExtendedPoint::identity().compress_edwards().as_bytes());
+
+    for key in small_order_encodings().iter() {
+        let strict: bool = is_identity(key) && roundtrip_check(key);
+
+        for message in [&b""[..], &b"a"[..], &b"tor22006"[..]].iter() {
+            assert!(verify(key, message, &signature, VerificationProfile::Cofactored), "{:?}", key);
+            assert_eq!(verify(key, message, &signature, VerificationProfile::Strict), strict, "{:?}", key);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// Recording what every validator decides about a fixed corpus, so that an
+// upgrade of curve25519-dalek, or any other change underneath, can't change
+// which keys are accepted without a test failing.
+//
+// The corpus is `corpus::seed_corpus`, the encodings validators are known
+// to get wrong, and 64 more derived from SHA-256, arbitrary but fixed, for
+// the ordinary cases.  `take_snapshot` writes one line per encoding,
+//
+//    <hex encoding> <verdicts>
+//
+// with a 1 or 0 for each validator in `VALIDATORS`, in order, under a
+// header naming them.  The snapshot taken on curve25519-dalek 0.9 is kept
+// in `snapshots/`, and a test compares the current verdicts against it; a
+// change which is meant to alter the acceptance set has to update the
+// snapshot, and so shows up in review.
+
+use core::str::Lines;
+use core::str::Split;
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::decaf::CompressedDecaf;
+
+use corpus::seed_corpus;
+use edwards::mult_by_cofactor_and_validate;
+use encoding::hex_decode;
+use encoding::hex_encode;
+use formats::FormatError;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+use ristretto::CompressedRistretto;
+use traits::PrimeOrderEncoding;
+
+/// Whether a validator accepts an encoding.
+pub type Verdict = fn(&[u8; 32]) -> bool;
+
+/// Every validator a snapshot records, by name.
+pub static VALIDATORS: [(&str, Verdict); 7] = [
+    ("edwards-prime-order", edwards_prime_order),
+    ("edwards-strict", edwards_strict),
+    ("mult-by-cofactor", mult_by_cofactor),
+    ("decaf-prime-order", decaf_prime_order),
+    ("decaf-strict", decaf_strict),
+    ("ristretto-prime-order", ristretto_prime_order),
+    ("ristretto-strict", ristretto_strict),
+];
+
+fn edwards_prime_order(encoding: &[u8; 32]) -> bool {
+    UncheckedKey::new(CompressedEdwardsY(*encoding)).check(KeyPolicy::PrimeOrder).is_some()
+}
+
+fn edwards_strict(encoding: &[u8; 32]) -> bool {
+    UncheckedKey::new(CompressedEdwardsY(*encoding)).check(KeyPolicy::Strict).is_some()
+}
+
+fn mult_by_cofactor(encoding: &[u8; 32]) -> bool {
+    mult_by_cofactor_and_validate(&CompressedEdwardsY(*encoding)).is_some()
+}
+
+fn decaf_prime_order(encoding: &[u8; 32]) -> bool {
+    CompressedDecaf(*encoding).validate().is_some()
+}
+
+fn decaf_strict(encoding: &[u8; 32]) -> bool {
+    CompressedDecaf(*encoding).validate_strict().is_some()
+}
+
+fn ristretto_prime_order(encoding: &[u8; 32]) -> bool {
+    CompressedRistretto(*encoding).validate().is_some()
+}
+
+fn ristretto_strict(encoding: &[u8; 32]) -> bool {
+    CompressedRistretto(*encoding).validate_strict().is_some()
+}
+
+/// A verdict which differs from the one a snapshot recorded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotChange {
+    encoding: [u8; 32],
+    validator: &'static str,
+    accepted: bool,
+}
+
+impl SnapshotChange {
+    /// The encoding.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// The validator whose verdict changed.
+    pub fn validator(&self) -> &'static str {
+        self.validator
+    }
+
+    /// Whether it accepts the encoding now.  The snapshot said the opposite.
+    pub fn accepted(&self) -> bool {
+        self.accepted
+    }
+}
+
+/// The encodings a snapshot covers, in order.
+pub fn snapshot_corpus() -> Vec<[u8; 32]> {
+    let mut encodings: Vec<[u8; 32]> = seed_corpus().into_iter().map(|(encoding, _)| encoding).collect();
+
+    for i in 0..64u8 {
+        let mut h: Sha256 = Sha256::default();
+        let mut encoding: [u8; 32] = [0u8; 32];
+
+        h.input(b"tor22006 snapshot");
+        h.input(&[i]);
+        encoding.copy_from_slice(h.result().as_slice());
+        encodings.push(encoding);
+    }
+    encodings
+}
+
+/// A snapshot of every validator's verdicts, in the format described above.
+pub fn take_snapshot() -> String {
+    let mut text: String = header();
+
+    for encoding in snapshot_corpus().iter() {
+        text.push_str(&hex_encode(encoding));
+        text.push(' ');
+        text.extend(VALIDATORS.iter().map(|&(_, accepts)| if accepts(encoding) { '1' } else { '0' }));
+        text.push('\n');
+    }
+    text
+}
+
+/// Every verdict which differs from those recorded in `snapshot`.  Fails if
+/// `snapshot` isn't in the format `take_snapshot` writes, for the same
+/// validators.
+pub fn compare_snapshot(snapshot: &str) -> Result<Vec<SnapshotChange>, FormatError> {
+    let mut lines: Lines = snapshot.lines();
+    let mut changes: Vec<SnapshotChange> = Vec::new();
+
+    if lines.next() != header().lines().next() {
+        return Err(FormatError::Malformed); // some other set of validators
+    }
+    for line in lines {
+        let mut fields: Split<char> = line.split(' ');
+        let (hex, verdicts): (&str, &str) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(hex), Some(verdicts), None) => (hex, verdicts),
+            _                                 => return Err(FormatError::Malformed),
+        };
+        let mut encoding: [u8; 32] = [0u8; 32];
+        match hex_decode(hex) {
+            Some(ref bytes) if bytes.len() == 32 => encoding.copy_from_slice(bytes),
+            _                                    => return Err(FormatError::Malformed),
+        }
+        if verdicts.len() != VALIDATORS.len() || verdicts.bytes().any(|verdict| verdict != b'0' && verdict != b'1') {
+            return Err(FormatError::Malformed);
+        }
+
+        for (&(name, accepts), recorded) in VALIDATORS.iter().zip(verdicts.bytes()) {
+            let accepted: bool = accepts(&encoding);
+
+            if accepted != (recorded == b'1') {
+                changes.push(SnapshotChange { encoding, validator: name, accepted });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+fn header() -> String {
+    let names: Vec<&str> = VALIDATORS.iter().map(|&(name, _)| name).collect();
+
+    format!("# validators: {}\n", names.join(" "))
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    static COMMITTED: &str = include_str!("snapshots/curve25519-dalek-0.9.txt");
+
+    #[test]
+    fn committed_snapshot_still_holds() {
+        assert_eq!(compare_snapshot(COMMITTED), Ok(Vec::new()));
+        assert_eq!(COMMITTED, take_snapshot());
+    }
+
+    #[test]
+    fn changes_are_reported() {
+        let snapshot: String = take_snapshot();
+        let mut lines: Vec<String> = snapshot.lines().map(String::from).collect();
+        let recorded: bool = &lines[1][65..66] == "1";
+
+        lines[1].replace_range(65..66, if recorded { "0" } else { "1" });
+        let changes: Vec<SnapshotChange> = compare_snapshot(&lines.join("\n")).unwrap();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].validator(), "edwards-prime-order");
+        assert_eq!(changes[0].accepted(), recorded);
+        assert_eq!(hex_encode(changes[0].encoding()), &lines[1][..64]);
+        assert_eq!(compare_snapshot(&snapshot[1..]), Err(FormatError::Malformed));
+        assert_eq!(compare_snapshot(&(snapshot.clone() + "00 1111111\n")), Err(FormatError::Malformed));
+    }
+}
@@ -0,0 +1,334 @@
+// Tor's shared-random commit and reveal values, from proposal 250, parsed
+// and checked before anything is derived from them.  Behind the `blinding`
+// feature, alongside the code which consumes the result.
+//
+// Each directory authority picks 32 random bytes RN, and in its votes first
+// commits to them and later reveals them:
+//
+//    REVEAL = base64(TIMESTAMP ‖ SHA3-256(RN))
+//    COMMIT = base64(TIMESTAMP ‖ SHA3-256(REVEAL))
+//
+// on lines of the form
+//
+//    shared-rand-commit 1 sha3-256 <identity> <COMMIT> [<REVEAL>]
+//
+// Once the reveals are in, the shared random value is
+//
+//    SRV = SHA3-256("shared-random" ‖ INT_8(n) ‖ INT_4(1) ‖ HASHED_REVEALS ‖ PREVIOUS_SRV)
+//    HASHED_REVEALS = SHA3-256(ID_a ‖ REVEAL_a ‖ ID_b ‖ REVEAL_b ‖ ...)
+//
+// over the n reveals, sorted by identity, with PREVIOUS_SRV all zero if
+// there was none.  The authority is named here by its ed25519 identity, as
+// unpadded base64, rather than the RSA fingerprint tor writes, so that it
+// can be validated like every other key in this crate; ID is then the 32
+// bytes of the key.  A commit is only accepted from an authority the caller
+// knows, with a strictly valid identity, and with a reveal, if there is
+// one, which matches it.
+
+use sha2::Digest;
+use sha3::Sha3_256;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use encoding::base64_decode;
+use encoding::base64_encode;
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// Why a commit, reveal, or shared random value was rejected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SrvError {
+    /// The line was not in the expected format.
+    Malformed,
+    /// The line was for a protocol version or hash other than 1 and sha3-256.
+    UnsupportedAlgorithm,
+    /// The authority's identity was not a valid key.
+    InvalidIdentity,
+    /// The identity was valid, but not one of the authorities.
+    UnknownAuthority,
+    /// The reveal did not match the commit.
+    RevealMismatch,
+    /// Two commits were from the same authority.
+    DuplicateAuthority,
+    /// No commit had a reveal, so there was nothing to compute from.
+    NoReveals,
+}
+
+/// An authority's commitment, with its reveal if it has made one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    identity: CheckedKey<CompressedEdwardsY>,
+    timestamp: u64,
+    hashed_reveal: [u8; 32],
+    reveal: Option<String>,
+}
+
+impl Commit {
+    /// Parse a `shared-rand-commit` line, without its trailing newline, from
+    /// one of `authorities`.
+    pub fn parse(line: &str, authorities: &[CheckedKey<CompressedEdwardsY>]) -> Result<Commit, SrvError> {
+        let fields: Vec<&str> = line.split(' ').collect();
+
+        if fields.len() < 5 || fields.len() > 6 || fields[0] != "shared-rand-commit" {
+            return Err(SrvError::Malformed);
+        }
+        if fields[1] != "1" || fields[2] != "sha3-256" {
+            return Err(SrvError::UnsupportedAlgorithm);
+        }
+
+        let identity: CheckedKey<CompressedEdwardsY> = match base64_decode(fields[3]) {
+            Some(ref bytes) if bytes.len() == 32 => {
+                let mut key: [u8; 32] = [0u8; 32];
+                key.copy_from_slice(bytes);
+
+                match UncheckedKey::new(CompressedEdwardsY(key)).check(KeyPolicy::Strict) {
+                    Some(identity) => identity,
+                    None           => return Err(SrvError::InvalidIdentity),
+                }
+            },
+            _ => return Err(SrvError::Malformed),
+        };
+        if !authorities.contains(&identity) {
+            return Err(SrvError::UnknownAuthority);
+        }
+
+        let (timestamp, hashed_reveal): (u64, [u8; 32]) = match decode_value(fields[4]) {
+            Some(value) => value,
+            None        => return Err(SrvError::Malformed),
+        };
+        let reveal: Option<String> = match fields.get(5) {
+            Some(reveal) => {
+                let (revealed_at, _): (u64, [u8; 32]) = match decode_value(reveal) {
+                    Some(value) => value,
+                    None        => return Err(SrvError::Malformed),
+                };
+                if revealed_at != timestamp || sha3_256(&[reveal.as_bytes()]) != hashed_reveal {
+                    return Err(SrvError::RevealMismatch);
+                }
+                Some(reveal.to_string())
+            },
+            None => None,
+        };
+
+        Ok(Commit { identity, timestamp, hashed_reveal, reveal })
+    }
+
+    /// The authority which made the commitment.
+    pub fn identity(&self) -> &CheckedKey<CompressedEdwardsY> {
+        &self.identity
+    }
+
+    /// When the random value was chosen, in seconds since the epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// SHA3-256 of the reveal, which is what was committed to.
+    pub fn hashed_reveal(&self) -> &[u8; 32] {
+        &self.hashed_reveal
+    }
+
+    /// The reveal, as it appeared, if it has been made.
+    pub fn reveal(&self) -> Option<&str> {
+        self.reveal.as_deref()
+    }
+}
+
+/// A shared random value, with the number of reveals it was computed from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SharedRandomValue {
+    reveals: u64,
+    value: [u8; 32],
+}
+
+impl SharedRandomValue {
+    /// Compute the shared random value from `commits`, following `previous`
+    /// if there was one.  Commits without a reveal are skipped.
+    pub fn compute(commits: &[Commit], previous: Option<&SharedRandomValue>)
+        -> Result<SharedRandomValue, SrvError>
+    {
+        let mut revealed: Vec<(&CheckedKey<CompressedEdwardsY>, &str)> = Vec::new();
+
+        for (i, commit) in commits.iter().enumerate() {
+            if commits[..i].iter().any(|earlier| earlier.identity() == commit.identity()) {
+                return Err(SrvError::DuplicateAuthority);
+            }
+            if let Some(reveal) = commit.reveal() {
+                revealed.push((commit.identity(), reveal));
+            }
+        }
+        if revealed.is_empty() {
+            return Err(SrvError::NoReveals);
+        }
+        revealed.sort();
+
+        let mut parts: Vec<&[u8]> = Vec::with_capacity(2 * revealed.len());
+        for &(identity, reveal) in revealed.iter() {
+            parts.push(identity.encoding().as_bytes());
+            parts.push(reveal.as_bytes());
+        }
+        let hashed_reveals: [u8; 32] = sha3_256(&parts);
+        let reveals: u64 = revealed.len() as u64;
+
+        let value: [u8; 32] = sha3_256(&[
+            b"shared-random",
+            &reveals.to_be_bytes(),
+            &[0, 0, 0, 1],
+            &hashed_reveals,
+            previous.map(|previous| &previous.value).unwrap_or(&[0u8; 32]),
+        ]);
+
+        Ok(SharedRandomValue { reveals, value })
+    }
+
+    /// Parse a `shared-rand-current-value` or `shared-rand-previous-value`
+    /// line from a consensus, without its trailing newline.
+    pub fn parse(line: &str) -> Result<SharedRandomValue, SrvError> {
+        let fields: Vec<&str> = line.split(' ').collect();
+
+        if fields.len() != 3 ||
+            (fields[0] != "shared-rand-current-value" && fields[0] != "shared-rand-previous-value") {
+            return Err(SrvError::Malformed);
+        }
+        let reveals: u64 = match fields[1].parse::<u64>() {
+            Ok(reveals) if fields[1] == reveals.to_string() => reveals,
+            _                                              => return Err(SrvError::Malformed),
+        };
+
+        match base64_decode(fields[2]) {
+            Some(ref bytes) if bytes.len() == 32 && base64_encode(bytes, true) == fields[2] => {
+                let mut value: [u8; 32] = [0u8; 32];
+                value.copy_from_slice(bytes);
+
+                Ok(SharedRandomValue { reveals, value })
+            },
+            _ => Err(SrvError::Malformed),
+        }
+    }
+
+    /// How many reveals went into the value.
+    pub fn reveals(&self) -> u64 {
+        self.reveals
+    }
+
+    /// The value itself.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.value
+    }
+}
+
+// TIMESTAMP ‖ H, from its padded base64.  Any other encoding of the same
+// bytes is refused, since the reveal is hashed as text.
+fn decode_value(text: &str) -> Option<(u64, [u8; 32])> {
+    let bytes: Vec<u8> = match base64_decode(text) {
+        Some(ref bytes) if bytes.len() == 40 && base64_encode(bytes, true) == text => bytes.clone(),
+        _                                                                         => return None,
+    };
+    let mut timestamp: [u8; 8] = [0u8; 8];
+    let mut hash: [u8; 32] = [0u8; 32];
+
+    timestamp.copy_from_slice(&bytes[..8]);
+    hash.copy_from_slice(&bytes[8..]);
+
+    Some((u64::from_be_bytes(timestamp), hash))
+}
+
+fn sha3_256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut h: Sha3_256 = Sha3_256::default();
+    let mut digest: [u8; 32] = [0u8; 32];
+
+    for part in parts.iter() {
+        h.input(part);
+    }
+    digest.copy_from_slice(h.result().as_slice());
+    digest
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use tor::identity_string;
+
+    fn authority(n: u64) -> CheckedKey<CompressedEdwardsY> {
+        let key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * &Scalar::from_u64(n)).compress_edwards();
+
+        UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap()
+    }
+
+    // The commit and reveal an authority would publish for `random`.
+    fn commit_and_reveal(timestamp: u64, random: &[u8; 32]) -> (String, String) {
+        let mut reveal: Vec<u8> = timestamp.to_be_bytes().to_vec();
+        reveal.extend_from_slice(&sha3_256(&[random]));
+        let reveal: String = base64_encode(&reveal, true);
+
+        let mut commit: Vec<u8> = timestamp.to_be_bytes().to_vec();
+        commit.extend_from_slice(&sha3_256(&[reveal.as_bytes()]));
+
+        (base64_encode(&commit, true), reveal)
+    }
+
+    fn line(identity: &CheckedKey<CompressedEdwardsY>, commit: &str, reveal: &str) -> String {
+        format!("shared-rand-commit 1 sha3-256 {} {} {}", identity_string(identity), commit, reveal)
+    }
+
+    #[test]
+    fn commits_and_reveals() {
+        let authorities: [CheckedKey<CompressedEdwardsY>; 2] = [authority(1), authority(2)];
+        let (commit, reveal): (String, String) = commit_and_reveal(1500000000, &[7u8; 32]);
+        let (_, other): (String, String) = commit_and_reveal(1500000000, &[8u8; 32]);
+
+        let parsed: Commit = Commit::parse(&line(&authorities[0], &commit, &reveal), &authorities).unwrap();
+        assert_eq!(parsed.timestamp(), 1500000000);
+        assert_eq!(parsed.reveal(), Some(reveal.as_str()));
+        assert!(Commit::parse(line(&authorities[0], &commit, "").trim_end(), &authorities)
+                    .unwrap().reveal().is_none());
+
+        assert_eq!(Commit::parse(&line(&authorities[0], &commit, &other), &authorities),
+                   Err(SrvError::RevealMismatch));
+        assert_eq!(Commit::parse(&line(&authority(3), &commit, &reveal), &authorities),
+                   Err(SrvError::UnknownAuthority));
+        assert_eq!(Commit::parse(&line(&authorities[0], &commit, &reveal).replace(" 1 ", " 2 "), &authorities),
+                   Err(SrvError::UnsupportedAlgorithm));
+        assert_eq!(Commit::parse(&line(&authorities[0], &commit, reveal.trim_end_matches('=')), &authorities),
+                   Err(SrvError::Malformed));
+    }
+
+    #[test]
+    fn torsioned_identities_are_rejected() {
+        let torsioned: CompressedEdwardsY = (authority(1).point() + &constants::EIGHT_TORSION[1]).compress_edwards();
+        let (commit, reveal): (String, String) = commit_and_reveal(1500000000, &[7u8; 32]);
+        let text: String = format!("shared-rand-commit 1 sha3-256 {} {} {}",
+                                   base64_encode(torsioned.as_bytes(), false), commit, reveal);
+
+        assert_eq!(Commit::parse(&text, &[authority(1)]), Err(SrvError::InvalidIdentity));
+    }
+
+    #[test]
+    fn shared_random_values() {
+        let authorities: [CheckedKey<CompressedEdwardsY>; 2] = [authority(1), authority(2)];
+        let commits: Vec<Commit> = authorities.iter().enumerate().map(|(i, identity)| {
+            let (commit, reveal): (String, String) = commit_and_reveal(1500000000, &[i as u8; 32]);
+
+            Commit::parse(&line(identity, &commit, &reveal), &authorities).unwrap()
+        }).collect();
+        let reversed: Vec<Commit> = commits.iter().rev().cloned().collect();
+
+        let srv: SharedRandomValue = SharedRandomValue::compute(&commits, None).unwrap();
+        assert_eq!(srv.reveals(), 2);
+        assert_eq!(SharedRandomValue::compute(&reversed, None), Ok(srv));
+        assert!(SharedRandomValue::compute(&commits, Some(&srv)) != Ok(srv));
+
+        let text: String = format!("shared-rand-current-value 2 {}", base64_encode(srv.as_bytes(), true));
+        assert_eq!(SharedRandomValue::parse(&text), Ok(srv));
+        assert_eq!(SharedRandomValue::parse(&text.replace(" 2 ", " 02 ")), Err(SrvError::Malformed));
+
+        let twice: Vec<Commit> = vec![commits[0].clone(), commits[0].clone()];
+        assert_eq!(SharedRandomValue::compute(&twice, None), Err(SrvError::DuplicateAuthority));
+        assert_eq!(SharedRandomValue::compute(&[], None), Err(SrvError::NoReveals));
+    }
+}
@@ -0,0 +1,146 @@
+// Counting validation outcomes over a long run.
+//
+// Every outcome is counted under the source it came from (a label of the
+// caller's choosing, such as "consensus" or "known_hosts"), the policy the
+// key was checked under, and the problem found with it, if any.  A `Stats`
+// can be shared between threads, and read or reset at any point without
+// stopping the run.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use key::CheckedKey;
+use key::KeyProblem;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+type Counts = BTreeMap<(String, KeyPolicy, Option<KeyProblem>), u64>;
+
+/// Counts of validation outcomes, which may be updated from many threads.
+#[derive(Debug, Default)]
+pub struct Stats {
+    counts: Mutex<Counts>,
+}
+
+impl Stats {
+    /// No counts at all.
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Count one outcome: `None` for a key which was accepted, or the
+    /// problem which got it rejected.
+    pub fn record(&self, source: &str, policy: KeyPolicy, outcome: Option<KeyProblem>) {
+        *self.lock().entry((source.to_string(), policy, outcome)).or_insert(0) += 1;
+    }
+
+    /// Check `key` under `policy`, counting the outcome under `source`.
+    pub fn check(&self, source: &str, key: &UncheckedKey<CompressedEdwardsY>, policy: KeyPolicy)
+        -> Option<CheckedKey<CompressedEdwardsY>>
+    {
+        let checked: Result<CheckedKey<CompressedEdwardsY>, KeyProblem> = key.check_or_diagnose(policy);
+
+        self.record(source, policy, checked.as_ref().err().copied());
+        checked.ok()
+    }
+
+    /// The counts so far.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { counts: self.lock().clone() }
+    }
+
+    /// Start counting again from zero, returning the counts up to now.
+    pub fn reset(&self) -> Snapshot {
+        let mut counts: MutexGuard<Counts> = self.lock();
+
+        Snapshot { counts: std::mem::take(&mut *counts) }
+    }
+
+    // A thread which panicked while holding the lock can at worst have lost
+    // one increment, so there is no reason to stop counting.
+    fn lock<'a>(&'a self) -> MutexGuard<'a, Counts> {
+        self.counts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The counts from a `Stats` at one moment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    counts: Counts,
+}
+
+impl Snapshot {
+    /// The number of outcomes for which `filter`, given the source, policy
+    /// and outcome, returns true.
+    pub fn count<F>(&self, filter: F) -> u64
+        where F: Fn(&str, KeyPolicy, Option<KeyProblem>) -> bool
+    {
+        self.counts.iter()
+            .filter(|&(&(ref source, policy, outcome), _)| filter(source, policy, outcome))
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    /// The number of keys accepted.
+    pub fn accepted(&self) -> u64 {
+        self.count(|_, _, outcome| outcome.is_none())
+    }
+
+    /// The number of keys rejected.
+    pub fn rejected(&self) -> u64 {
+        self.count(|_, _, outcome| outcome.is_some())
+    }
+
+    /// The number of keys rejected for `problem`.
+    pub fn rejected_for(&self, problem: KeyProblem) -> u64 {
+        self.count(|_, _, outcome| outcome == Some(problem))
+    }
+
+    /// Every non-zero count, ordered by source, then policy, then outcome.
+    pub fn entries(&self) -> Vec<(&str, KeyPolicy, Option<KeyProblem>, u64)> {
+        self.counts.iter()
+            .map(|(&(ref source, policy, outcome), &n)| (source.as_str(), policy, outcome, n))
+            .collect()
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    #[test]
+    fn outcomes_are_counted_by_source_policy_and_problem() {
+        let stats: Stats = Stats::new();
+        let good: UncheckedKey<CompressedEdwardsY> = constants::ED25519_BASEPOINT.compress_edwards().into();
+        let torsioned: UncheckedKey<CompressedEdwardsY> =
+            (&constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1]).compress_edwards().into();
+
+        assert!(stats.check("consensus", &good, KeyPolicy::Strict).is_some());
+        assert!(stats.check("consensus", &torsioned, KeyPolicy::Strict).is_none());
+        assert!(stats.check("known_hosts", &torsioned, KeyPolicy::PrimeOrder).is_none());
+        stats.record("known_hosts", KeyPolicy::PrimeOrder, Some(KeyProblem::Malformed));
+
+        let snapshot: Snapshot = stats.snapshot();
+
+        assert_eq!(snapshot.accepted(), 1);
+        assert_eq!(snapshot.rejected(), 3);
+        assert_eq!(snapshot.rejected_for(KeyProblem::Torsioned), 2);
+        assert_eq!(snapshot.count(|source, _, _| source == "known_hosts"), 2);
+        assert_eq!(snapshot.count(|_, policy, _| policy == KeyPolicy::Strict), 2);
+        assert_eq!(snapshot.entries()[0], ("consensus", KeyPolicy::Strict, None, 1));
+    }
+
+    #[test]
+    fn reset_hands_back_the_old_counts() {
+        let stats: Stats = Stats::new();
+        stats.record("consensus", KeyPolicy::Strict, None);
+
+        assert_eq!(stats.reset().accepted(), 1);
+        assert_eq!(stats.snapshot(), Snapshot::default());
+    }
+}
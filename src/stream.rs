@@ -0,0 +1,168 @@
+// Validating keys as they arrive down a channel, for crawlers which find
+// keys a few at a time, on many threads, and would otherwise have to gather
+// them into slices themselves.
+//
+// `validate_stream` receives encodings until every sender has hung up, and
+// sends an outcome for each, in the order they were received.  Whatever is
+// already waiting when it wakes is taken as one batch, of up to
+// `STREAM_BATCH` keys, in which each distinct encoding is checked once, as
+// `batch::validate_deduplicated` does, so a producer sending faster than the
+// checks run gets larger batches and more duplicates caught, while one
+// sending slowly never waits on a batch to fill.  The senders are
+// `std::sync::mpsc` ones, so any number of threads can share them by
+// cloning.
+
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use key::CheckedKey;
+use key::UncheckedKey;
+use policy::KeyPolicy;
+
+/// The most keys `validate_stream` takes into one batch.
+pub const STREAM_BATCH: usize = 256;
+
+/// The result of checking one key from a stream.
+#[derive(Clone, Debug)]
+pub struct ValidationOutcome {
+    encoding: [u8; 32],
+    checked: Option<CheckedKey<CompressedEdwardsY>>,
+}
+
+impl ValidationOutcome {
+    /// The encoding which was checked.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// The checked key, if it passed.
+    pub fn checked(&self) -> Option<&CheckedKey<CompressedEdwardsY>> {
+        self.checked.as_ref()
+    }
+
+    /// Whether it passed.
+    pub fn is_valid(&self) -> bool {
+        self.checked.is_some()
+    }
+}
+
+/// Check every encoding received on `keys` under `policy`, sending the
+/// outcomes down `outcomes` in the order the keys arrived, until every
+/// sender of `keys` has been dropped, or the receiver of `outcomes` has.
+/// Returns how many outcomes were sent.
+pub fn validate_stream(keys: Receiver<[u8; 32]>, outcomes: Sender<ValidationOutcome>, policy: KeyPolicy) -> usize {
+    let mut batch: Vec<[u8; 32]> = Vec::with_capacity(STREAM_BATCH);
+    let mut sent: usize = 0;
+
+    while let Ok(first) = keys.recv() {
+        batch.push(first);
+        while batch.len() < STREAM_BATCH {
+            match keys.try_recv() {
+                Ok(encoding) => batch.push(encoding),
+                Err(_)       => break, // nothing more waiting, for now
+            }
+        }
+
+        for outcome in check_batch(&batch, policy) {
+            if outcomes.send(outcome).is_err() {
+                return sent; // nobody is listening any more
+            }
+            sent += 1;
+        }
+        batch.clear();
+    }
+    sent
+}
+
+// Check each distinct encoding in `batch` once, returning an outcome for
+// every one, in order.
+fn check_batch(batch: &[[u8; 32]], policy: KeyPolicy) -> Vec<ValidationOutcome> {
+    let mut seen: HashMap<[u8; 32], Option<CheckedKey<CompressedEdwardsY>>> = HashMap::new();
+
+    batch.iter()
+        .map(|encoding| ValidationOutcome {
+            encoding: *encoding,
+            checked: seen.entry(*encoding)
+                .or_insert_with(|| UncheckedKey::new(CompressedEdwardsY(*encoding)).check(policy))
+                .clone(),
+        })
+        .collect()
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use std::sync::mpsc;
+    use std::thread;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn encodings() -> Vec<[u8; 32]> {
+        (0..40u64)
+            .map(|i| match i % 4 {
+                0 => (&constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1]).compress_edwards().to_bytes(),
+                1 => [0x02; 32],
+                _ => (&Scalar::from_u64(i % 5 + 1) * &constants::ED25519_BASEPOINT).compress_edwards().to_bytes(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn outcomes_arrive_in_order() {
+        let (key_sender, keys) = mpsc::channel();
+        let (outcome_sender, outcomes) = mpsc::channel();
+        let validator: thread::JoinHandle<usize> =
+            thread::spawn(move || validate_stream(keys, outcome_sender, KeyPolicy::Strict));
+
+        for encoding in encodings() {
+            key_sender.send(encoding).unwrap();
+        }
+        drop(key_sender);
+
+        let received: Vec<ValidationOutcome> = outcomes.iter().collect();
+        assert_eq!(validator.join().unwrap(), encodings().len());
+        assert_eq!(received.len(), encodings().len());
+        for (outcome, encoding) in received.iter().zip(encodings().iter()) {
+            assert_eq!(outcome.encoding(), encoding);
+            assert_eq!(outcome.is_valid(), UncheckedKey::new(CompressedEdwardsY(*encoding))
+                       .check(KeyPolicy::Strict).is_some());
+        }
+        assert_eq!(received.iter().filter(|outcome| outcome.is_valid()).count(), 20);
+    }
+
+    #[test]
+    fn many_producers_share_one_stream() {
+        let (key_sender, keys) = mpsc::channel();
+        let (outcome_sender, outcomes) = mpsc::channel();
+        let producers: Vec<thread::JoinHandle<()>> = (0..4)
+            .map(|_| {
+                let key_sender: Sender<[u8; 32]> = key_sender.clone();
+
+                thread::spawn(move || for encoding in encodings() { key_sender.send(encoding).unwrap(); })
+            })
+            .collect();
+        drop(key_sender);
+
+        assert_eq!(validate_stream(keys, outcome_sender, KeyPolicy::PrimeOrder), 4 * encodings().len());
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(outcomes.iter().filter(|outcome| outcome.is_valid()).count(), 4 * 20);
+    }
+
+    #[test]
+    fn stops_when_nobody_listens() {
+        let (key_sender, keys) = mpsc::channel();
+        let (outcome_sender, outcomes) = mpsc::channel::<ValidationOutcome>();
+
+        key_sender.send([0x02; 32]).unwrap();
+        drop(outcomes);
+
+        assert_eq!(validate_stream(keys, outcome_sender, KeyPolicy::Strict), 0);
+    }
+}
@@ -0,0 +1,174 @@
+// Timing each stage of validation, to see where the cost of strict
+// validation goes on a real workload.  Built with the `timing` feature, and
+// even then off until `enable` is called, so that an ordinary build pays
+// nothing and a timing build pays only for a flag check.
+//
+// The stages are those the pipeline reports to `instrument`: "decompress",
+// "torsion", "canonicity" (strict only), and "check" for the whole of
+// `UncheckedKey::check`.  Every duration goes into a process-wide histogram
+// for its stage, with one bucket per power of two nanoseconds, and the
+// stages of the most recent check on each thread are kept as they were.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static HISTOGRAMS: Mutex<BTreeMap<&'static str, Histogram>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    static CURRENT: RefCell<Vec<(&'static str, Duration)>> = const { RefCell::new(Vec::new()) };
+    static LAST: RefCell<Vec<(&'static str, Duration)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Durations, counted in buckets by powers of two nanoseconds: bucket i
+/// counts durations of at least 2^i and less than 2^(i+1) nanoseconds,
+/// except that bucket 0 also counts those under 1ns and bucket 31 all
+/// those longer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+    buckets: [u64; 32],
+    total: Duration,
+}
+
+impl Histogram {
+    /// How many durations have been counted.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Their sum.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// The buckets.
+    pub fn buckets(&self) -> &[u64; 32] {
+        &self.buckets
+    }
+
+    /// An upper bound on the `q`th quantile, for `q` between 0 and 1: the
+    /// top of the bucket it falls in.
+    pub fn quantile(&self, q: f64) -> Duration {
+        let target: u64 = (q * self.count() as f64).ceil().max(1.0) as u64;
+        let mut seen: u64 = 0;
+
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return Duration::from_nanos((1u64 << (i + 1)) - 1);
+            }
+        }
+        Duration::from_nanos((1u64 << 32) - 1)
+    }
+
+    fn add(&mut self, duration: Duration) {
+        let nanos: u64 = duration.as_secs().saturating_mul(1_000_000_000) + duration.subsec_nanos() as u64;
+        let bucket: usize = (63 - nanos.max(1).leading_zeros() as usize).min(31);
+
+        self.buckets[bucket] += 1;
+        self.total += duration;
+    }
+}
+
+/// Start or stop timing.
+pub fn enable(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Every stage's histogram so far.
+pub fn snapshot() -> BTreeMap<&'static str, Histogram> {
+    lock().clone()
+}
+
+/// Start again from empty histograms, returning them as they were.
+pub fn reset() -> BTreeMap<&'static str, Histogram> {
+    std::mem::take(&mut *lock())
+}
+
+/// Each stage of the last check made on this thread, in order, with what
+/// it took.
+pub fn last_call() -> Vec<(&'static str, Duration)> {
+    LAST.with(|last| last.borrow().clone())
+}
+
+pub(crate) fn start() -> Option<Instant> {
+    match ENABLED.load(Ordering::Relaxed) {
+        true  => Some(Instant::now()),
+        false => None,
+    }
+}
+
+pub(crate) fn record(stage: &'static str, started: Option<Instant>) {
+    let elapsed: Duration = match started {
+        Some(started) => started.elapsed(),
+        None          => return, // timing is off
+    };
+
+    lock().entry(stage).or_insert_with(Histogram::default).add(elapsed);
+
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+
+        // Decompression is always first, so it starts a new call.
+        if stage == "decompress" {
+            current.clear();
+        }
+        current.push((stage, elapsed));
+        if stage == "check" {
+            LAST.with(|last| *last.borrow_mut() = std::mem::take(&mut *current));
+        }
+    });
+}
+
+// As in `stats`, a panic elsewhere can at worst have lost one duration.
+fn lock<'a>() -> MutexGuard<'a, BTreeMap<&'static str, Histogram>> {
+    HISTOGRAMS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::CompressedEdwardsY;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    #[test]
+    fn stages_are_timed() {
+        enable(true);
+        UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards()).check(KeyPolicy::Strict).unwrap();
+
+        let stages: Vec<&'static str> = last_call().iter().map(|&(stage, _)| stage).collect();
+        assert_eq!(stages, vec!["decompress", "torsion", "canonicity", "check"]);
+
+        // A rejected key stops early, and so has fewer stages.
+        UncheckedKey::new(CompressedEdwardsY([0x02; 32])).check(KeyPolicy::Strict);
+        assert_eq!(last_call().len(), 2);
+
+        let histograms: BTreeMap<&'static str, Histogram> = snapshot();
+        assert!(histograms["check"].count() >= 2);
+        assert!(histograms["torsion"].quantile(1.0) >= histograms["torsion"].quantile(0.5));
+    }
+
+    #[test]
+    fn buckets() {
+        let mut histogram: Histogram = Histogram::default();
+
+        histogram.add(Duration::from_nanos(1));
+        histogram.add(Duration::from_nanos(1000));
+        histogram.add(Duration::from_secs(1 << 20));
+
+        assert_eq!(histogram.buckets()[0], 1);
+        assert_eq!(histogram.buckets()[9], 1);
+        assert_eq!(histogram.buckets()[31], 1);
+        assert_eq!(histogram.quantile(0.5), Duration::from_nanos(1023));
+    }
+}
@@ -0,0 +1,68 @@
+// How Tor names ed25519 relay identities.
+//
+// Directory documents print an ed25519 identity as unpadded base64 of the
+// 32-byte key, as in the `id ed25519` lines of microdescriptors and votes.
+// Monitoring tools which want a fixed-length digest instead, the way the RSA
+// identity is shown as a SHA-1 fingerprint, use unpadded base64 of the
+// key's SHA-256.  Both are only computed from keys which have passed
+// validation, so that nothing reported under them could be rejected by a
+// relay.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+
+use encoding::base64_encode;
+use key::CheckedKey;
+
+/// The unpadded base64 SHA-256 digest of a relay's ed25519 identity key.
+pub fn fingerprint(key: &CheckedKey<CompressedEdwardsY>) -> String {
+    fingerprint_bytes(key.encoding().as_bytes())
+}
+
+// The same digest, for encodings which haven't been validated (or aren't
+// ed25519 at all), so that logs can name a key without printing it.
+pub(crate) fn fingerprint_bytes(encoding: &[u8]) -> String {
+    let mut h: Sha256 = Sha256::default();
+    h.input(encoding);
+
+    base64_encode(h.result().as_slice(), false)
+}
+
+/// The identity key itself, as unpadded base64, in the form it appears on
+/// `id ed25519` lines in directory documents.
+pub fn identity_string(key: &CheckedKey<CompressedEdwardsY>) -> String {
+    base64_encode(key.encoding().as_bytes(), false)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    fn basepoint_key() -> CheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(constants::ED25519_BASEPOINT.compress_edwards())
+            .check(KeyPolicy::Strict).unwrap()
+    }
+
+    #[test]
+    fn identity_string_is_unpadded_base64() {
+        // The basepoint is y = 4/5, i.e. 0x58 followed by 0x66s.
+        assert_eq!(identity_string(&basepoint_key()),
+                   "WGZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmZmY");
+    }
+
+    #[test]
+    fn fingerprint_is_a_sha256_digest() {
+        let fp: String = fingerprint(&basepoint_key());
+
+        assert_eq!(fp.len(), 43);
+        assert!(!fp.ends_with('='));
+        assert!(fp != identity_string(&basepoint_key()));
+    }
+}
@@ -0,0 +1,268 @@
+// Comparing ed25519 keys while ignoring their torsion components, and
+// telling whether a point has one.
+//
+// A cofactored verifier (see `VerificationProfile::Cofactored`) accepts
+// exactly the same signatures under P and under P + T for any T in E[8], so
+// for it the two keys are the same signer.  Byte-wise equality of encodings
+// doesn't capture that; checking 8·P = 8·Q does.
+//
+// The prime-order subgroup is closed under addition, negation, and scalar
+// multiplication, so a `TorsionFreePoint` is checked once, when it is made,
+// and arithmetic on it hands back more of them without checking again.
+//
+// Code which keeps raw points instead, believing them torsion-free, can say
+// so with `ensure_validated!(point)`, which checks in debug builds and
+// compiles to nothing in release ones.
+
+use core::ops::Add;
+use core::ops::Mul;
+use core::ops::Neg;
+use core::ops::Sub;
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::scalar::Scalar;
+
+use curve25519_dalek::constants;
+
+use subtle::arrays_equal;
+use subtle::CTEq;
+
+use edwards::mul_by_pow_2;
+use key::CheckedKey;
+use traits::CofactorGroup;
+
+/// A point in the prime-order subgroup.
+///
+/// This may be the identity, which `KeyPolicy::Strict` rejects as a key, so
+/// a result computed from `Strict` keys is only known to be torsion-free.
+#[derive(Copy, Clone, Debug)]
+pub struct TorsionFreePoint(ExtendedPoint);
+
+impl TorsionFreePoint {
+    /// Return `point` if it is torsion-free.
+    pub fn new(point: ExtendedPoint) -> Option<TorsionFreePoint> {
+        match is_torsion_free(&point) {
+            true  => Some(TorsionFreePoint(point)),
+            false => None, // the point had a torsion component
+        }
+    }
+
+    /// The basepoint, which generates the subgroup.
+    pub fn basepoint() -> TorsionFreePoint {
+        TorsionFreePoint(constants::ED25519_BASEPOINT)
+    }
+
+    /// The point itself.
+    pub fn point(&self) -> &ExtendedPoint {
+        &self.0
+    }
+
+    /// The canonical encoding of the point.
+    pub fn compress(&self) -> CompressedEdwardsY {
+        self.0.compress_edwards()
+    }
+}
+
+// A checked key's point has already been found torsion-free.
+impl<'a> From<&'a CheckedKey<CompressedEdwardsY>> for TorsionFreePoint {
+    fn from(key: &'a CheckedKey<CompressedEdwardsY>) -> TorsionFreePoint {
+        TorsionFreePoint(*key.point())
+    }
+}
+
+impl PartialEq for TorsionFreePoint {
+    fn eq(&self, other: &TorsionFreePoint) -> bool {
+        self.0.ct_eq(&other.0) == 1u8
+    }
+}
+
+impl Eq for TorsionFreePoint {}
+
+impl<'b> Add<&'b TorsionFreePoint> for &TorsionFreePoint {
+    type Output = TorsionFreePoint;
+
+    fn add(self, other: &'b TorsionFreePoint) -> TorsionFreePoint {
+        TorsionFreePoint(&self.0 + &other.0)
+    }
+}
+
+impl<'b> Sub<&'b TorsionFreePoint> for &TorsionFreePoint {
+    type Output = TorsionFreePoint;
+
+    fn sub(self, other: &'b TorsionFreePoint) -> TorsionFreePoint {
+        TorsionFreePoint(&self.0 - &other.0)
+    }
+}
+
+impl Neg for &TorsionFreePoint {
+    type Output = TorsionFreePoint;
+
+    fn neg(self) -> TorsionFreePoint {
+        TorsionFreePoint(-&self.0)
+    }
+}
+
+impl<'b> Mul<&'b Scalar> for &TorsionFreePoint {
+    type Output = TorsionFreePoint;
+
+    fn mul(self, scalar: &'b Scalar) -> TorsionFreePoint {
+        TorsionFreePoint(&self.0 * scalar)
+    }
+}
+
+impl<'b> Mul<&'b TorsionFreePoint> for &Scalar {
+    type Output = TorsionFreePoint;
+
+    fn mul(self, point: &'b TorsionFreePoint) -> TorsionFreePoint {
+        TorsionFreePoint(&point.0 * self)
+    }
+}
+
+/// Return true if `point` lies in the prime-order subgroup.
+pub fn is_torsion_free(point: &ExtendedPoint) -> bool {
+    ct_is_torsion_free(point) == 1u8
+}
+
+/// As `is_torsion_free`, but returning `1u8` or `0u8`, in constant time.
+///
+/// This compares [l]P against the identity projectively, as X = 0 and
+/// Y = Z, which saves the inversion that compressing [l]P would cost.
+pub fn ct_is_torsion_free(point: &ExtendedPoint) -> u8 {
+    let q: ExtendedPoint = point * &constants::l;
+
+    q.X.is_zero() & q.Y.ct_eq(&q.Z)
+}
+
+/// Return true if `a` and `b` both decode, and the points differ only by an
+/// element of E[8].
+pub fn eq_mod_torsion(a: &CompressedEdwardsY, b: &CompressedEdwardsY) -> bool {
+    ct_eq_mod_torsion(a, b) == 1u8
+}
+
+/// As `eq_mod_torsion`, but returning `1u8` or `0u8`, and comparing the
+/// points in constant time.
+///
+/// Decompression may still take a different amount of time for encodings
+/// which are not points at all, so only the comparison itself is protected.
+pub fn ct_eq_mod_torsion(a: &CompressedEdwardsY, b: &CompressedEdwardsY) -> u8 {
+    let p: ExtendedPoint = match a.decompress() {
+        Some(p) => p,
+        None    => return 0u8, // the point was invalid
+    };
+    let q: ExtendedPoint = match b.decompress() {
+        Some(q) => q,
+        None    => return 0u8, // the point was invalid
+    };
+
+    arrays_equal(mul_by_pow_2(&p, 3).compress_edwards().as_bytes(),
+                 mul_by_pow_2(&q, 3).compress_edwards().as_bytes())
+}
+
+/// Panic if `point` is not in the prime-order subgroup, when this crate is
+/// built with debug assertions, and do nothing otherwise.
+pub fn debug_validate<P: CofactorGroup>(point: &P) {
+    debug_assert!(point.is_torsion_free(), "a point with a torsion component was assumed torsion-free");
+}
+
+/// As `debug_validate`, but following the debug assertions of the crate it
+/// is used in, and not evaluating `point` at all without them.
+#[macro_export]
+macro_rules! ensure_validated {
+    ($point:expr) => {
+        debug_assert!($crate::traits::CofactorGroup::is_torsion_free(&$point),
+                      "{} has a torsion component", stringify!($point))
+    };
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::curve::IsIdentity;
+    use curve25519_dalek::scalar::Scalar;
+
+    #[test]
+    fn every_torsion_variant_is_equivalent() {
+        let p: ExtendedPoint = &Scalar::from_u64(42) * &constants::ED25519_BASEPOINT;
+        let key: CompressedEdwardsY = p.compress_edwards();
+
+        for t in constants::EIGHT_TORSION.iter() {
+            let variant: CompressedEdwardsY = (&p + t).compress_edwards();
+
+            assert!(eq_mod_torsion(&key, &variant));
+            assert_eq!(ct_eq_mod_torsion(&variant, &key), 1u8);
+        }
+    }
+
+    #[test]
+    fn only_the_prime_order_subgroup_is_torsion_free() {
+        let p: ExtendedPoint = &Scalar::from_u64(42) * &constants::ED25519_BASEPOINT;
+
+        for (i, t) in constants::EIGHT_TORSION.iter().enumerate() {
+            let q: ExtendedPoint = &p + t;
+
+            assert_eq!(is_torsion_free(&q), i == 0);
+            assert_eq!(ct_is_torsion_free(&q), (&q * &constants::l).is_identity() as u8);
+        }
+    }
+
+    #[test]
+    fn different_signers_are_not_equivalent() {
+        let p: CompressedEdwardsY = (&Scalar::from_u64(42) * &constants::ED25519_BASEPOINT).compress_edwards();
+        let q: CompressedEdwardsY = (&Scalar::from_u64(43) * &constants::ED25519_BASEPOINT).compress_edwards();
+
+        assert!(!eq_mod_torsion(&p, &q));
+    }
+
+    #[test]
+    fn arithmetic_stays_torsion_free() {
+        let b: TorsionFreePoint = TorsionFreePoint::basepoint();
+        let p: TorsionFreePoint = &b * &Scalar::from_u64(42);
+        let q: TorsionFreePoint = &Scalar::from_u64(43) * &b;
+        let results: [TorsionFreePoint; 4] = [&p + &q, &q - &p, -&p, &(&p - &q) * &Scalar::from_u64(8)];
+
+        assert!(&q - &p == b);
+        assert!(&p + &(-&p) == &b * &Scalar::zero());
+        for r in results.iter() {
+            assert!(is_torsion_free(r.point()));
+        }
+        assert!(TorsionFreePoint::new(&constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[1]).is_none());
+    }
+
+    #[test]
+    fn invalid_encodings_are_never_equal() {
+        // y = 2 is not the y-coordinate of any point.
+        let mut bytes: [u8; 32] = [0u8; 32];
+        bytes[0] = 2;
+        let key: CompressedEdwardsY = CompressedEdwardsY(bytes);
+
+        assert!(key.decompress().is_none());
+        assert!(!eq_mod_torsion(&key, &key));
+    }
+
+    #[test]
+    fn torsion_free_points_pass_debug_checks() {
+        let p: ExtendedPoint = &Scalar::from_u64(42) * &constants::ED25519_BASEPOINT;
+
+        debug_validate(&p);
+        ensure_validated!(p);
+        ensure_validated!(&p + &p);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "has a torsion component")]
+    fn torsioned_points_fail_debug_checks() {
+        let q: ExtendedPoint = &constants::ED25519_BASEPOINT + &constants::EIGHT_TORSION[2];
+
+        ensure_validated!(q);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "assumed torsion-free")]
+    fn torsioned_points_fail_debug_validate() {
+        debug_validate(&constants::EIGHT_TORSION[1]);
+    }
+}
@@ -0,0 +1,358 @@
+// Traits which let validation code be written once for every curve and
+// encoding in the crate.
+//
+// There are really only two questions to ask of a public key: does the
+// encoding decode to an element of a prime-order group at all, and, for the
+// Edwards point types underneath, does a point carry a torsion component.
+// `PrimeOrderEncoding` answers the first and `CofactorGroup` the second.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+#[cfg(feature = "std")]
+use curve25519_dalek::curve::IsIdentity;
+use curve25519_dalek::decaf::CompressedDecaf;
+use curve25519_dalek::decaf::DecafPoint;
+
+use ristretto::CompressedRistretto;
+use ristretto::RistrettoPoint;
+
+use decaf::decaf_decompress_with;
+use hooks;
+#[cfg(feature = "std")]
+use inversion::compress_edwards_batch;
+use policy::KeyPolicy;
+use torsion;
+
+#[cfg(feature = "curve448")]
+use curve448;
+
+/// A group of order h·l, for a small cofactor h and a large prime l.
+pub trait CofactorGroup: Sized {
+    /// The cofactor h.
+    fn cofactor() -> u8;
+
+    /// Compute h·P.
+    fn mult_by_cofactor(&self) -> Self;
+
+    /// Return true if this point lies in the prime-order subgroup.
+    fn is_torsion_free(&self) -> bool;
+
+    /// Return true if this point has order dividing h.
+    fn is_small_order(&self) -> bool;
+}
+
+/// A public key encoding which should name an element of a prime-order
+/// group.
+pub trait PrimeOrderEncoding: Sized {
+    /// What a valid encoding decodes to.
+    type Point;
+
+    /// View the encoding as bytes, exactly as it appeared on the wire.
+    fn as_slice(&self) -> &[u8];
+
+    /// Decode this encoding, returning `None` unless it names an element of
+    /// the prime-order group.
+    fn validate(&self) -> Option<Self::Point>;
+
+    /// As `validate`, but also reject non-canonical encodings and the
+    /// identity.
+    fn validate_strict(&self) -> Option<Self::Point>;
+
+    /// As `validate_strict` for each of `keys`, in order.  Encodings whose
+    /// strict checks have a step which can be shared across a batch
+    /// override this.
+    #[cfg(feature = "std")]
+    fn validate_strict_batch(keys: &[Self]) -> Vec<Option<Self::Point>> {
+        keys.iter().map(|key| key.validate_strict()).collect()
+    }
+}
+
+impl CofactorGroup for ExtendedPoint {
+    fn cofactor() -> u8 {
+        8
+    }
+
+    fn mult_by_cofactor(&self) -> ExtendedPoint {
+        ExtendedPoint::mult_by_cofactor(self)
+    }
+
+    fn is_torsion_free(&self) -> bool {
+        torsion::is_torsion_free(self)
+    }
+
+    fn is_small_order(&self) -> bool {
+        ExtendedPoint::is_small_order(self)
+    }
+}
+
+impl PrimeOrderEncoding for CompressedEdwardsY {
+    type Point = ExtendedPoint;
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn validate(&self) -> Option<ExtendedPoint> {
+        hooks::validate_with_hooks(self, KeyPolicy::PrimeOrder, &mut ())
+    }
+
+    fn validate_strict(&self) -> Option<ExtendedPoint> {
+        hooks::validate_with_hooks(self, KeyPolicy::Strict, &mut ())
+    }
+
+    // The canonicity check compresses each point again, which costs an
+    // inversion of Z apiece, so those are done with one inversion for the
+    // whole batch.  The stages aren't reported to `instrument`.
+    #[cfg(feature = "std")]
+    fn validate_strict_batch(keys: &[CompressedEdwardsY]) -> Vec<Option<ExtendedPoint>> {
+        let points: Vec<Option<ExtendedPoint>> = keys.iter().map(|key| match key.decompress() {
+            Some(p) if p.is_torsion_free() => Some(p),
+            _                              => None, // invalid, or with a torsion component
+        }).collect();
+        let valid: Vec<ExtendedPoint> = points.iter().filter_map(|p| *p).collect();
+        let mut compressed = compress_edwards_batch(&valid).into_iter();
+
+        points.into_iter().zip(keys.iter()).map(|(p, key)| match p {
+            Some(p) => match compressed.next() == Some(*key) && !p.is_identity() {
+                true  => Some(p),
+                false => None, // non-canonical encoding, or the identity
+            },
+            None => None,
+        }).collect()
+    }
+}
+
+impl PrimeOrderEncoding for CompressedDecaf {
+    type Point = DecafPoint;
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn validate(&self) -> Option<DecafPoint> {
+        decaf_decompress_with(self, KeyPolicy::PrimeOrder.identity_policy())
+    }
+
+    // Strictness changes only what is done with the identity here.
+    fn validate_strict(&self) -> Option<DecafPoint> {
+        decaf_decompress_with(self, KeyPolicy::Strict.identity_policy())
+    }
+}
+
+impl PrimeOrderEncoding for CompressedRistretto {
+    type Point = RistrettoPoint;
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn validate(&self) -> Option<RistrettoPoint> {
+        self.decompress()
+    }
+
+    // Ristretto decoding already rejects non-canonical encodings, and the
+    // identity is the only element encoded as all zeroes.
+    fn validate_strict(&self) -> Option<RistrettoPoint> {
+        match self.0 == [0u8; 32] {
+            true  => None, // the point was the identity
+            false => self.validate(),
+        }
+    }
+}
+
+#[cfg(feature = "curve448")]
+impl CofactorGroup for curve448::edwards::ExtendedPoint {
+    fn cofactor() -> u8 {
+        4
+    }
+
+    fn mult_by_cofactor(&self) -> curve448::edwards::ExtendedPoint {
+        curve448::edwards::ExtendedPoint::mult_by_cofactor(self)
+    }
+
+    fn is_torsion_free(&self) -> bool {
+        self.scalar_mult(&curve448::edwards::L).is_identity()
+    }
+
+    fn is_small_order(&self) -> bool {
+        curve448::edwards::ExtendedPoint::is_small_order(self)
+    }
+}
+
+#[cfg(feature = "curve448")]
+impl PrimeOrderEncoding for curve448::edwards::CompressedEdwardsY {
+    type Point = curve448::edwards::ExtendedPoint;
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn validate(&self) -> Option<curve448::edwards::ExtendedPoint> {
+        curve448::mult_by_cofactor_and_validate(self)
+    }
+
+    // Ed448 decompression already rejects non-canonical encodings.
+    fn validate_strict(&self) -> Option<curve448::edwards::ExtendedPoint> {
+        match self.validate() {
+            Some(p) => match p.is_identity() {
+                true  => None, // the point was the identity
+                false => Some(p),
+            },
+            None    => None,
+        }
+    }
+}
+
+#[cfg(feature = "curve448")]
+impl PrimeOrderEncoding for curve448::decaf::CompressedDecaf {
+    type Point = curve448::decaf::DecafPoint;
+
+    fn as_slice(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    fn validate(&self) -> Option<curve448::decaf::DecafPoint> {
+        curve448::decaf448_decompress_with(self, KeyPolicy::PrimeOrder.identity_policy())
+    }
+
+    fn validate_strict(&self) -> Option<curve448::decaf::DecafPoint> {
+        curve448::decaf448_decompress_with(self, KeyPolicy::Strict.identity_policy())
+    }
+}
+
+/// Validate every key in `keys`, in order.
+///
+/// Each key costs what `validate` does: decompression's one expensive step
+/// is the exponentiation inside `sqrt_ratio`, which dalek already uses to
+/// divide by v as well, so there is no separate inversion for Montgomery's
+/// trick to share, and no trick shares the exponentiation itself, since
+/// (u₁u₂)^e gives neither u₁^e nor u₂^e without another exponentiation.
+/// Only strict validation re-compresses, and so has an inversion to share:
+/// see `validate_strict_batch`.
+#[cfg(feature = "std")]
+pub fn validate_batch<E: PrimeOrderEncoding>(keys: &[E]) -> Vec<Option<E::Point>> {
+    keys.iter().map(|key| key.validate()).collect()
+}
+
+/// Validate every key in `keys` strictly, in order, as `validate_strict`
+/// would.  For Edwards keys, the inversions the canonicity check needs are
+/// batched with `inversion::batch_invert`.
+#[cfg(feature = "std")]
+pub fn validate_strict_batch<E: PrimeOrderEncoding>(keys: &[E]) -> Vec<Option<E::Point>> {
+    E::validate_strict_batch(keys)
+}
+
+/// Validate every key in `keys`, in order, into `out`, without allocating.
+/// Only the first `min(keys.len(), out.len())` keys are validated, and that
+/// number is returned, so a caller with a fixed-size buffer can validate a
+/// long list a buffer at a time.  The stack used is that of one `validate`,
+/// however many keys there are; as for `validate_batch`, there is no
+/// inversion to share between them.
+pub fn validate_batch_into<E: PrimeOrderEncoding>(keys: &[E], out: &mut [Option<E::Point>]) -> usize {
+    let n: usize = keys.len().min(out.len());
+
+    for (key, point) in keys[..n].iter().zip(out[..n].iter_mut()) {
+        *point = key.validate();
+    }
+    n
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::Identity;
+    use curve25519_dalek::scalar::Scalar;
+
+    fn count_valid<E: PrimeOrderEncoding>(keys: &[E]) -> usize {
+        validate_batch(keys).iter().filter(|p| p.is_some()).count()
+    }
+
+    #[test]
+    fn edwards_keys() {
+        let p: ExtendedPoint = &Scalar::from_u64(1999) * &constants::ED25519_BASEPOINT;
+        let keys: [CompressedEdwardsY; 2] = [
+            p.compress_edwards(),
+            (&p + &constants::EIGHT_TORSION[1]).compress_edwards(),
+        ];
+
+        assert_eq!(count_valid(&keys), 1);
+        assert!(p.is_torsion_free());
+        assert!(constants::EIGHT_TORSION[3].is_small_order());
+        assert!(!(&p + &constants::EIGHT_TORSION[3]).is_torsion_free());
+    }
+
+    #[test]
+    fn prime_order_encodings() {
+        let p: ExtendedPoint = &Scalar::from_u64(1999) * &constants::ED25519_BASEPOINT;
+
+        assert_eq!(count_valid(&[DecafPoint(p).compress(), CompressedDecaf::identity()]), 2);
+        assert_eq!(count_valid(&[RistrettoPoint(p).compress(), CompressedRistretto([0xff; 32])]), 1);
+    }
+
+    #[test]
+    fn identity_is_treated_alike() {
+        let edwards: CompressedEdwardsY = ExtendedPoint::identity().compress_edwards();
+        let decaf: CompressedDecaf = CompressedDecaf::identity();
+        let ristretto: CompressedRistretto = CompressedRistretto([0u8; 32]);
+
+        assert!(edwards.validate().is_some() && edwards.validate_strict().is_none());
+        assert!(decaf.validate().is_some() && decaf.validate_strict().is_none());
+        assert!(ristretto.validate().is_some() && ristretto.validate_strict().is_none());
+    }
+
+    #[test]
+    fn strict_batches_agree_with_validate_strict() {
+        let p: ExtendedPoint = &Scalar::from_u64(1999) * &constants::ED25519_BASEPOINT;
+        // p + 1, which encodes y = 1, the identity, again.
+        let mut unreduced: [u8; 32] = [0xff; 32];
+        unreduced[0] = 0xee;
+        unreduced[31] = 0x7f;
+        let keys: [CompressedEdwardsY; 6] = [
+            p.compress_edwards(),
+            (&p + &constants::EIGHT_TORSION[1]).compress_edwards(),
+            ExtendedPoint::identity().compress_edwards(),
+            CompressedEdwardsY(unreduced),
+            CompressedEdwardsY([0x02; 32]),
+            (&p + &p).compress_edwards(),
+        ];
+        let expected: Vec<Option<ExtendedPoint>> = keys.iter().map(|key| key.validate_strict()).collect();
+        let batch: Vec<Option<ExtendedPoint>> = validate_strict_batch(&keys);
+
+        assert_eq!(batch.iter().map(|p| p.map(|p| p.compress_edwards())).collect::<Vec<_>>(),
+                   expected.iter().map(|p| p.map(|p| p.compress_edwards())).collect::<Vec<_>>());
+        assert_eq!(batch.iter().filter(|p| p.is_some()).count(), 2);
+        assert!(validate_strict_batch::<CompressedEdwardsY>(&[]).is_empty());
+
+        let decaf: [CompressedDecaf; 1] = [CompressedDecaf([0u8; 32])];
+        assert_eq!(validate_strict_batch(&decaf).len(), 1);
+    }
+
+    #[test]
+    fn batches_into_buffers() {
+        let p: ExtendedPoint = &Scalar::from_u64(1999) * &constants::ED25519_BASEPOINT;
+        let keys: [CompressedEdwardsY; 3] = [
+            p.compress_edwards(),
+            (&p + &constants::EIGHT_TORSION[1]).compress_edwards(),
+            CompressedEdwardsY([0xff; 32]),
+        ];
+        let mut out: [Option<ExtendedPoint>; 2] = [None, None];
+
+        assert_eq!(validate_batch_into(&keys, &mut out), 2);
+        assert_eq!(out.iter().map(|p| p.is_some()).collect::<Vec<bool>>(), vec![true, false]);
+        assert_eq!(validate_batch_into(&keys[2..], &mut out), 1);
+        assert!(out[0].is_none());
+    }
+
+    #[cfg(feature = "curve448")]
+    #[test]
+    fn curve448_keys() {
+        let b: curve448::edwards::ExtendedPoint = curve448::edwards::ExtendedPoint::basepoint();
+
+        assert_eq!(count_valid(&[b.compress_edwards()]), 1);
+        assert_eq!(count_valid(&[curve448::decaf::DecafPoint(b).compress()]), 1);
+        assert!(b.is_torsion_free());
+        assert_eq!(<curve448::edwards::ExtendedPoint as CofactorGroup>::cofactor(), 4);
+    }
+}
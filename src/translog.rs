@@ -0,0 +1,290 @@
+// An append-only, CT-style log of the keys a key observatory has seen and
+// accepted, with the proofs that let anyone check it was never rewritten.
+//
+// Each entry is a validated key with the time it was seen, and its leaf is
+//
+//    leaf = SHA-256(0x00 ‖ INT_8(seen at) ‖ key)
+//
+// in the RFC 9162 tree of `merkle`.  The log publishes a `TreeHead`, its
+// size and root, from time to time.  An inclusion proof shows that an entry
+// is in the tree under a head, and a consistency proof shows that a later
+// head's tree extends an earlier one's, entry for entry, so a monitor which
+// checks one between every pair of heads it sees knows that nothing it saw
+// accepted has since been altered or removed.  Both are verified with the
+// algorithms of RFC 9162 §2.1.3.2 and §2.1.4.2.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use key::CheckedKey;
+use merkle::InclusionProof;
+use merkle::audit_path;
+use merkle::node_hash;
+use merkle::split;
+use merkle::tree_hash;
+use traits::PrimeOrderEncoding;
+
+/// The size and root of the log at some point.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TreeHead {
+    size: u64,
+    root: [u8; 32],
+}
+
+impl TreeHead {
+    /// A tree head received from the log.
+    pub fn new(size: u64, root: [u8; 32]) -> TreeHead {
+        TreeHead { size, root }
+    }
+
+    /// The number of entries.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The root of the tree over them.
+    pub fn root(&self) -> &[u8; 32] {
+        &self.root
+    }
+}
+
+/// A proof that one tree extends another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    first: u64,
+    second: u64,
+    path: Vec<[u8; 32]>,
+}
+
+impl ConsistencyProof {
+    /// A proof received from the log, that its tree of `second` entries
+    /// extends its tree of `first`.
+    pub fn new(first: u64, second: u64, path: Vec<[u8; 32]>) -> ConsistencyProof {
+        ConsistencyProof { first, second, path }
+    }
+
+    /// The hashes making up the proof.
+    pub fn path(&self) -> &[[u8; 32]] {
+        &self.path
+    }
+
+    /// Whether this proves that the tree under `new` extends the one under
+    /// `old`.
+    pub fn verify(&self, old: &TreeHead, new: &TreeHead) -> bool {
+        if old.size != self.first || new.size != self.second || self.first > self.second {
+            return false;
+        }
+        if self.first == self.second {
+            return self.path.is_empty() && old.root == new.root;
+        }
+        if self.first == 0 {
+            // Everything extends the empty tree.
+            return self.path.is_empty() && old.root == tree_hash(&[]);
+        }
+
+        let mut path: Vec<[u8; 32]> = self.path.clone();
+        if self.first.is_power_of_two() {
+            path.insert(0, old.root);
+        }
+        if path.is_empty() {
+            return false;
+        }
+
+        let (mut f, mut s): (u64, u64) = (self.first - 1, self.second - 1);
+        while f & 1 == 1 {
+            f >>= 1;
+            s >>= 1;
+        }
+
+        let mut fr: [u8; 32] = path[0];
+        let mut sr: [u8; 32] = path[0];
+
+        for c in path[1..].iter() {
+            if s == 0 {
+                return false; // the path was too long
+            }
+            if f & 1 == 1 || f == s {
+                fr = node_hash(c, &fr);
+                sr = node_hash(c, &sr);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                sr = node_hash(&sr, c);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+
+        fr == old.root && sr == new.root && s == 0
+    }
+}
+
+/// The leaf hash for `encoding`, seen at `seen_at`, for checking an
+/// `InclusionProof` against a `TreeHead`.
+pub fn entry_hash(encoding: &[u8], seen_at: u64) -> [u8; 32] {
+    let mut h: Sha256 = Sha256::default();
+    let mut hash: [u8; 32] = [0u8; 32];
+
+    h.input(&[0x00]);
+    h.input(&seen_at.to_be_bytes());
+    h.input(encoding);
+    hash.copy_from_slice(h.result().as_slice());
+    hash
+}
+
+/// The log itself, as the observatory keeps it.
+#[derive(Clone, Debug, Default)]
+pub struct KeyLog {
+    leaves: Vec<[u8; 32]>,
+}
+
+impl KeyLog {
+    /// An empty log.
+    pub fn new() -> KeyLog {
+        KeyLog::default()
+    }
+
+    /// Record that `key` was seen and accepted at `seen_at`, returning the
+    /// entry's index.
+    pub fn append<T: PrimeOrderEncoding>(&mut self, key: &CheckedKey<T>, seen_at: u64) -> u64 {
+        self.leaves.push(entry_hash(key.encoding().as_slice(), seen_at));
+        self.leaves.len() as u64 - 1
+    }
+
+    /// The current tree head.
+    pub fn head(&self) -> TreeHead {
+        TreeHead { size: self.leaves.len() as u64, root: tree_hash(&self.leaves) }
+    }
+
+    /// A proof that the entry at `index` is in the tree of the first `size`
+    /// entries.
+    pub fn prove_inclusion(&self, index: u64, size: u64) -> Option<InclusionProof> {
+        if index >= size || size > self.leaves.len() as u64 {
+            return None;
+        }
+
+        Some(InclusionProof::new(index, size, audit_path(index as usize, &self.leaves[..size as usize])))
+    }
+
+    /// A proof that the tree of the first `second` entries extends that of
+    /// the first `first`.
+    pub fn prove_consistency(&self, first: u64, second: u64) -> Option<ConsistencyProof> {
+        if first > second || second > self.leaves.len() as u64 {
+            return None;
+        }
+        let path: Vec<[u8; 32]> = match first {
+            0 => Vec::new(),
+            _ => subproof(first as usize, &self.leaves[..second as usize], true),
+        };
+
+        Some(ConsistencyProof { first, second, path })
+    }
+}
+
+// RFC 6962's SUBPROOF(m, D[n], b).
+fn subproof(m: usize, leaves: &[[u8; 32]], whole: bool) -> Vec<[u8; 32]> {
+    let n: usize = leaves.len();
+
+    if m == n {
+        return match whole {
+            true  => Vec::new(),
+            false => vec![tree_hash(leaves)],
+        };
+    }
+
+    let k: usize = split(n);
+    let mut path: Vec<[u8; 32]>;
+
+    if m <= k {
+        path = subproof(m, &leaves[..k], whole);
+        path.push(tree_hash(&leaves[k..]));
+    } else {
+        path = subproof(m - k, &leaves[k..], false);
+        path.push(tree_hash(&leaves[..k]));
+    }
+    path
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::CompressedEdwardsY;
+    use curve25519_dalek::scalar::Scalar;
+
+    use key::UncheckedKey;
+    use policy::KeyPolicy;
+
+    fn log(count: u64) -> (KeyLog, Vec<CheckedKey<CompressedEdwardsY>>) {
+        let mut log: KeyLog = KeyLog::new();
+        let keys: Vec<CheckedKey<CompressedEdwardsY>> = (1..count + 1).map(|n| {
+            let key: CompressedEdwardsY = (&constants::ED25519_BASEPOINT * &Scalar::from_u64(n)).compress_edwards();
+
+            UncheckedKey::new(key).check(KeyPolicy::Strict).unwrap()
+        }).collect();
+
+        for (i, key) in keys.iter().enumerate() {
+            log.append(key, 1500000000 + i as u64);
+        }
+        (log, keys)
+    }
+
+    fn head(log: &KeyLog, size: u64) -> TreeHead {
+        TreeHead::new(size, tree_hash(&log.leaves[..size as usize]))
+    }
+
+    #[test]
+    fn every_pair_of_heads_is_consistent() {
+        let (log, _): (KeyLog, Vec<CheckedKey<CompressedEdwardsY>>) = log(10);
+
+        for second in 0..11 {
+            for first in 0..second + 1 {
+                let proof: ConsistencyProof = log.prove_consistency(first, second).unwrap();
+
+                assert!(proof.verify(&head(&log, first), &head(&log, second)), "{} {}", first, second);
+            }
+        }
+    }
+
+    #[test]
+    fn rewritten_logs_are_caught() {
+        let (log, keys): (KeyLog, Vec<CheckedKey<CompressedEdwardsY>>) = log(7);
+        let (mut rewritten, _): (KeyLog, Vec<CheckedKey<CompressedEdwardsY>>) = log_with_changed_entry(&keys);
+        rewritten.append(&keys[0], 0);
+
+        let old: TreeHead = head(&log, 5);
+        for second in 6..8 {
+            let proof: ConsistencyProof = rewritten.prove_consistency(5, second).unwrap();
+
+            assert!(!proof.verify(&old, &head(&rewritten, second)));
+        }
+        assert!(!log.prove_consistency(3, 7).unwrap().verify(&head(&log, 4), &log.head()));
+    }
+
+    // The same keys, with the third seen at a different time.
+    fn log_with_changed_entry(keys: &[CheckedKey<CompressedEdwardsY>]) -> (KeyLog, Vec<CheckedKey<CompressedEdwardsY>>) {
+        let mut log: KeyLog = KeyLog::new();
+
+        for (i, key) in keys.iter().enumerate() {
+            log.append(key, 1500000000 + i as u64 + if i == 2 { 1 } else { 0 });
+        }
+        (log, keys.to_vec())
+    }
+
+    #[test]
+    fn entries_are_included() {
+        let (log, keys): (KeyLog, Vec<CheckedKey<CompressedEdwardsY>>) = log(6);
+        let head: TreeHead = log.head();
+
+        for (i, key) in keys.iter().enumerate() {
+            let proof: InclusionProof = log.prove_inclusion(i as u64, head.size()).unwrap();
+            let entry: [u8; 32] = entry_hash(key.encoding().as_bytes(), 1500000000 + i as u64);
+
+            assert!(proof.verify_leaf_hash(head.root(), &entry));
+            assert!(!proof.verify_leaf_hash(head.root(), &entry_hash(key.encoding().as_bytes(), 0)));
+        }
+    }
+}
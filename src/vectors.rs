@@ -0,0 +1,190 @@
+// A minimal set of known answers, compiled in, for smoke-testing bindings
+// and other implementations against this crate.
+//
+// Each vector is an Edwards encoding with the verdict it must get under each
+// `KeyPolicy`: a valid key, all eight points of small order, the identity
+// encoded non-canonically as y = p + 1, a valid key with a torsion component,
+// and an encoding which is not a point.  `selftest` runs these through every
+// backend.
+
+use policy::KeyPolicy;
+
+/// One encoding, and what each policy must say about it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KnownAnswer {
+    name: &'static str,
+    encoding: [u8; 32],
+    prime_order: bool,
+    strict: bool,
+}
+
+impl KnownAnswer {
+    /// A short description.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The encoding.
+    pub fn encoding(&self) -> &[u8; 32] {
+        &self.encoding
+    }
+
+    /// Whether it must pass under `policy`.
+    pub fn expected(&self, policy: KeyPolicy) -> bool {
+        match policy {
+            KeyPolicy::PrimeOrder => self.prime_order,
+            KeyPolicy::Strict     => self.strict,
+        }
+    }
+}
+
+static KNOWN_ANSWERS: [KnownAnswer; 12] = [
+    KnownAnswer {
+        name: "[22006]B",
+        encoding: [
+            0x70, 0x25, 0x9b, 0x90, 0x4c, 0xc2, 0xce, 0x39,
+            0x16, 0xea, 0x87, 0x3d, 0x24, 0xef, 0x3e, 0x6d,
+            0x63, 0x03, 0xd5, 0x57, 0xc4, 0xe5, 0x17, 0x53,
+            0xe9, 0x29, 0xa6, 0x9d, 0xe3, 0x0f, 0xcd, 0x4b ],
+        prime_order: true,
+        strict: true,
+    },
+    KnownAnswer {
+        name: "the identity",
+        encoding: [
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ],
+        prime_order: true,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "a point of order 8",
+        encoding: [
+            0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f,
+            0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10, 0x67, 0x0f,
+            0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6,
+            0x4e, 0xc7, 0xfd, 0x77, 0x92, 0xac, 0x03, 0x7a ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "a point of order 4",
+        encoding: [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80 ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "a point of order 8",
+        encoding: [
+            0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0,
+            0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98, 0xf0,
+            0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39,
+            0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53, 0xfc, 0x05 ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "the point of order 2",
+        encoding: [
+            0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "a point of order 8",
+        encoding: [
+            0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0,
+            0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98, 0xf0,
+            0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39,
+            0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53, 0xfc, 0x85 ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "a point of order 4",
+        encoding: [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "a point of order 8",
+        encoding: [
+            0xc7, 0x17, 0x6a, 0x70, 0x3d, 0x4d, 0xd8, 0x4f,
+            0xba, 0x3c, 0x0b, 0x76, 0x0d, 0x10, 0x67, 0x0f,
+            0x2a, 0x20, 0x53, 0xfa, 0x2c, 0x39, 0xcc, 0xc6,
+            0x4e, 0xc7, 0xfd, 0x77, 0x92, 0xac, 0x03, 0xfa ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "the identity, encoded as y = p + 1",
+        encoding: [
+            0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f ],
+        prime_order: true,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "[22006]B plus a point of order 8",
+        encoding: [
+            0x64, 0x50, 0xd4, 0xf0, 0x71, 0xe7, 0xcf, 0x58,
+            0x1a, 0x76, 0xe9, 0x28, 0x47, 0xb1, 0xce, 0xcc,
+            0x9b, 0x77, 0x0a, 0x6d, 0xf1, 0x15, 0xbd, 0xcf,
+            0xfb, 0xb9, 0x4f, 0x3c, 0xd9, 0x11, 0x5f, 0x75 ],
+        prime_order: false,
+        strict: false,
+    },
+    KnownAnswer {
+        name: "not a point",
+        encoding: [0x02; 32],
+        prime_order: false,
+        strict: false,
+    },
+];
+
+/// Every vector.
+pub fn known_answers() -> &'static [KnownAnswer] {
+    &KNOWN_ANSWERS
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::curve::CompressedEdwardsY;
+
+    use key::UncheckedKey;
+
+    #[test]
+    fn vectors_agree_with_check() {
+        for vector in known_answers().iter() {
+            for &policy in [KeyPolicy::PrimeOrder, KeyPolicy::Strict].iter() {
+                assert_eq!(UncheckedKey::new(CompressedEdwardsY(*vector.encoding())).check(policy).is_some(),
+                           vector.expected(policy), "{} under {:?}", vector.name(), policy);
+            }
+        }
+    }
+
+    #[test]
+    fn every_small_order_point_is_included() {
+        for t in constants::EIGHT_TORSION.iter() {
+            assert!(known_answers().iter().any(|vector| vector.encoding() == t.compress_edwards().as_bytes()));
+        }
+    }
+}
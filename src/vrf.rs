@@ -0,0 +1,279 @@
+// ECVRF proof verification over edwards25519, per RFC 9381, for the
+// ECVRF-EDWARDS25519-SHA512-TAI and -ELL2 suites.
+//
+// A proof π = Γ ‖ c ‖ s under a public key Y for an input α is checked by
+//
+//    H  ← encode_to_curve(Y, α)
+//    U  ← [s]B - [c]Y
+//    V  ← [s]H - [c]Γ
+//    c′ ← the first 16 bytes of SHA-512(suite ‖ 0x02 ‖ Y ‖ H ‖ Γ ‖ U ‖ V ‖ 0x00)
+//
+// and accepting when c = c′, in which case the output is
+// β = SHA-512(suite ‖ 0x03 ‖ [8]Γ ‖ 0x00).  The suites differ only in how H
+// is found: TAI tries SHA-512(suite ‖ 0x01 ‖ Y ‖ α ‖ ctr ‖ 0x00) for
+// ctr = 0, 1, ... as an encoding until one decodes, and ELL2 uses RFC 9380's
+// nonuniform Elligator2 map.
+//
+// The RFC leaves validating Y optional, and without it a small-order Y lets
+// whoever chose it produce proofs for more than one β.  Here the key must be
+// a `CheckedKey`, so there is no way to skip it.
+
+use sha2::Digest;
+use sha2::Sha512;
+
+use curve25519_dalek::constants;
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+use curve25519_dalek::curve::vartime;
+use curve25519_dalek::scalar::Scalar;
+
+use ed25519::scalar_from_canonical_bytes;
+use edwards::mul_by_pow_2;
+use hash_to_curve::encode_to_curve;
+use key::CheckedKey;
+
+/// The two edwards25519 suites.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Suite {
+    /// ECVRF-EDWARDS25519-SHA512-TAI, suite_string 0x03.
+    Tai,
+    /// ECVRF-EDWARDS25519-SHA512-ELL2, suite_string 0x04.
+    Ell2,
+}
+
+impl Suite {
+    fn suite_string(&self) -> u8 {
+        match *self {
+            Suite::Tai  => 0x03,
+            Suite::Ell2 => 0x04,
+        }
+    }
+}
+
+/// Verify the 80-byte `proof` for `alpha` under `key`, returning the 64-byte
+/// VRF output β if it is valid.
+pub fn verify(key: &CheckedKey<CompressedEdwardsY>, alpha: &[u8], proof: &[u8; 80], suite: Suite)
+    -> Option<[u8; 64]>
+{
+    let mut gamma_bytes: [u8; 32] = [0u8; 32];
+    let mut c_bytes: [u8; 32] = [0u8; 32];
+    let mut s_bytes: [u8; 32] = [0u8; 32];
+
+    gamma_bytes.copy_from_slice(&proof[..32]);
+    c_bytes[..16].copy_from_slice(&proof[32..48]);
+    s_bytes.copy_from_slice(&proof[48..]);
+
+    let gamma: ExtendedPoint = string_to_point(&gamma_bytes)?;
+    let s: Scalar = scalar_from_canonical_bytes(&s_bytes)?;
+    let c: Scalar = Scalar(c_bytes);
+    let y: &ExtendedPoint = key.point();
+
+    let h: ExtendedPoint = encode(key.encoding(), alpha, suite)?;
+    let u: ExtendedPoint = vartime::k_fold_scalar_mult(&[s, -&c], &[constants::ED25519_BASEPOINT, *y]);
+    let v: ExtendedPoint = vartime::k_fold_scalar_mult(&[s, -&c], &[h, gamma]);
+
+    match challenge(suite, &[*y, h, gamma, u, v]) == c {
+        true  => Some(proof_to_hash(suite, &gamma)),
+        false => None, // the proof was invalid
+    }
+}
+
+/// The output β for `proof`, without checking it.  Only meaningful once
+/// `verify` has accepted the proof, which returns the same value.
+pub fn proof_to_hash(suite: Suite, gamma: &ExtendedPoint) -> [u8; 64] {
+    let mut h: Sha512 = Sha512::default();
+    let mut beta: [u8; 64] = [0u8; 64];
+
+    h.input(&[suite.suite_string(), 0x03]);
+    h.input(mul_by_pow_2(gamma, 3).compress_edwards().as_bytes());
+    h.input(&[0x00]);
+    beta.copy_from_slice(h.result().as_slice());
+    beta
+}
+
+// H for `alpha` under the public key `salt`.
+fn encode(salt: &CompressedEdwardsY, alpha: &[u8], suite: Suite) -> Option<ExtendedPoint> {
+    match suite {
+        Suite::Tai  => encode_try_and_increment(salt, alpha),
+        Suite::Ell2 => {
+            let mut dst: [u8; 40] = [0u8; 40];
+            dst[..39].copy_from_slice(b"ECVRF_edwards25519_XMD:SHA-512_ELL2_NU_");
+            dst[39] = suite.suite_string();
+
+            let mut msg: Vec<u8> = Vec::with_capacity(32 + alpha.len());
+            msg.extend_from_slice(salt.as_bytes());
+            msg.extend_from_slice(alpha);
+
+            Some(encode_to_curve(&msg, &dst))
+        },
+    }
+}
+
+// ECVRF_encode_to_curve_try_and_increment, §5.4.1.1.
+fn encode_try_and_increment(salt: &CompressedEdwardsY, alpha: &[u8]) -> Option<ExtendedPoint> {
+    for ctr in 0..=255u8 {
+        let mut h: Sha512 = Sha512::default();
+        let mut candidate: [u8; 32] = [0u8; 32];
+
+        h.input(&[Suite::Tai.suite_string(), 0x01]);
+        h.input(salt.as_bytes());
+        h.input(alpha);
+        h.input(&[ctr, 0x00]);
+        candidate.copy_from_slice(&h.result().as_slice()[..32]);
+
+        if let Some(p) = string_to_point(&candidate) {
+            return Some(mul_by_pow_2(&p, 3));
+        }
+    }
+    None
+}
+
+// ECVRF_challenge_generation, §5.4.3.
+fn challenge(suite: Suite, points: &[ExtendedPoint; 5]) -> Scalar {
+    let mut h: Sha512 = Sha512::default();
+    let mut c: [u8; 32] = [0u8; 32];
+
+    h.input(&[suite.suite_string(), 0x02]);
+    for p in points.iter() {
+        h.input(p.compress_edwards().as_bytes());
+    }
+    h.input(&[0x00]);
+    c[..16].copy_from_slice(&h.result().as_slice()[..16]);
+
+    Scalar(c)
+}
+
+// RFC 8032 decoding, which unlike `decompress` rejects y ≥ p, and x = 0
+// with the sign bit set.
+fn string_to_point(bytes: &[u8; 32]) -> Option<ExtendedPoint> {
+    let p: ExtendedPoint = CompressedEdwardsY(*bytes).decompress()?;
+
+    match p.compress_edwards().as_bytes() == bytes {
+        true  => Some(p),
+        false => None, // the encoding was not canonical
+    }
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use ed25519::ExpandedSecretKey;
+    use ed25519::public_key_from_seed;
+    use key::UncheckedKey;
+    use params::reduce_mod_l;
+    use policy::KeyPolicy;
+
+    // RFC 8032 §7.1, test 1, which RFC 9381 uses too.
+    static SEED: [u8; 32] = [
+        0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60,
+        0xba, 0x84, 0x4a, 0xf4, 0x92, 0xec, 0x2c, 0xc4,
+        0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19,
+        0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60 ];
+
+    // ECVRF_prove, §5.1, with the nonce from §5.4.2.2.
+    fn prove(seed: &[u8; 32], alpha: &[u8], suite: Suite) -> [u8; 80] {
+        let expanded: ExpandedSecretKey = ExpandedSecretKey::from_seed(seed);
+        let key: CompressedEdwardsY = expanded.public_key();
+        let x: Scalar = reduce_mod_l(expanded.scalar());
+
+        let y: ExtendedPoint = key.decompress().unwrap();
+        let h_point: ExtendedPoint = encode(&key, alpha, suite).unwrap();
+        let gamma: ExtendedPoint = &h_point * &x;
+
+        let mut h: Sha512 = Sha512::default();
+        h.input(expanded.prefix());
+        h.input(h_point.compress_edwards().as_bytes());
+        let k: Scalar = Scalar::from_hash(h);
+
+        let c: Scalar = challenge(suite, &[y, h_point, gamma,
+                                           &constants::ED25519_BASEPOINT * &k, &h_point * &k]);
+        let s: Scalar = Scalar::multiply_add(&c, &x, &k);
+
+        let mut proof: [u8; 80] = [0u8; 80];
+        proof[..32].copy_from_slice(gamma.compress_edwards().as_bytes());
+        proof[32..48].copy_from_slice(&c.as_bytes()[..16]);
+        proof[48..].copy_from_slice(s.as_bytes());
+        proof
+    }
+
+    fn checked_key() -> CheckedKey<CompressedEdwardsY> {
+        UncheckedKey::new(public_key_from_seed(&SEED)).check(KeyPolicy::Strict).unwrap()
+    }
+
+    #[test]
+    fn proofs_verify() {
+        let key: CheckedKey<CompressedEdwardsY> = checked_key();
+
+        for &suite in [Suite::Tai, Suite::Ell2].iter() {
+            let proof: [u8; 80] = prove(&SEED, b"tor22006", suite);
+            let beta: [u8; 64] = verify(&key, b"tor22006", &proof, suite).unwrap();
+
+            assert_eq!(beta, proof_to_hash(suite, &string_to_point(&{
+                let mut gamma: [u8; 32] = [0u8; 32];
+                gamma.copy_from_slice(&proof[..32]);
+                gamma
+            }).unwrap()));
+            assert!(verify(&key, b"tor22007", &proof, suite).is_none());
+        }
+        // A proof under one suite is not one under the other.
+        assert!(verify(&key, b"tor22006", &prove(&SEED, b"tor22006", Suite::Tai), Suite::Ell2).is_none());
+    }
+
+    #[test]
+    fn tampered_proofs_fail() {
+        let key: CheckedKey<CompressedEdwardsY> = checked_key();
+        let proof: [u8; 80] = prove(&SEED, b"", Suite::Tai);
+
+        for &i in [0, 33, 50].iter() {
+            let mut tampered: [u8; 80] = proof;
+            tampered[i] ^= 1;
+
+            assert!(verify(&key, b"", &tampered, Suite::Tai).is_none());
+        }
+
+        // s + l, which is congruent but not reduced.
+        let mut unreduced: [u8; 80] = proof;
+        let mut carry: u16 = 0;
+        for i in 0..32 {
+            let sum: u16 = proof[48 + i] as u16 + constants::l.as_bytes()[i] as u16 + carry;
+            unreduced[48 + i] = sum as u8;
+            carry = sum >> 8;
+        }
+        assert!(verify(&key, b"", &unreduced, Suite::Tai).is_none());
+    }
+
+    #[test]
+    fn rfc9381_vectors() {
+        // RFC 9381 appendix B, the first edwards25519 example of each suite: the
+        // key above, and α empty.
+        let tai: [u8; 80] = [
+            0x86, 0x57, 0x10, 0x66, 0x90, 0xb5, 0x52, 0x62,
+            0x45, 0xa9, 0x2b, 0x00, 0x3b, 0xb0, 0x79, 0xcc,
+            0xd1, 0xa9, 0x21, 0x30, 0x47, 0x76, 0x71, 0xf6,
+            0xfc, 0x01, 0xad, 0x16, 0xf2, 0x6f, 0x72, 0x3f,
+            0x26, 0xf8, 0xa5, 0x7c, 0xca, 0xed, 0x74, 0xee,
+            0x1b, 0x19, 0x0b, 0xed, 0x1f, 0x47, 0x9d, 0x97,
+            0x27, 0xd2, 0xd0, 0xf9, 0xb0, 0x05, 0xa6, 0xe4,
+            0x56, 0xa3, 0x5d, 0x4f, 0xb0, 0xda, 0xab, 0x12,
+            0x68, 0xa1, 0xb0, 0xdb, 0x10, 0x83, 0x6d, 0x98,
+            0x26, 0xa5, 0x28, 0xca, 0x76, 0x56, 0x78, 0x05 ];
+        let ell2: [u8; 80] = [
+            0x7d, 0x9c, 0x63, 0x3f, 0xfe, 0xee, 0x27, 0x34,
+            0x92, 0x64, 0xcf, 0x5c, 0x66, 0x75, 0x79, 0xfc,
+            0x58, 0x3b, 0x4b, 0xda, 0x63, 0xab, 0x71, 0xd0,
+            0x01, 0xf8, 0x9c, 0x10, 0x00, 0x3a, 0xb4, 0x6f,
+            0x14, 0xad, 0xf9, 0xa3, 0xcd, 0x8b, 0x84, 0x12,
+            0xd9, 0x03, 0x85, 0x31, 0xe8, 0x65, 0xc3, 0x41,
+            0xca, 0xfa, 0x73, 0x58, 0x9b, 0x02, 0x3d, 0x14,
+            0x31, 0x1c, 0x33, 0x1a, 0x9a, 0xd1, 0x5f, 0xf2,
+            0xfb, 0x37, 0x83, 0x1e, 0x00, 0xf0, 0xac, 0xaa,
+            0x6d, 0x73, 0xbc, 0x99, 0x97, 0xb0, 0x65, 0x01 ];
+        let key: CheckedKey<CompressedEdwardsY> = checked_key();
+
+        assert_eq!(&prove(&SEED, b"", Suite::Tai)[..], &tai[..]);
+        assert_eq!(&prove(&SEED, b"", Suite::Ell2)[..], &ell2[..]);
+        assert!(verify(&key, b"", &tai, Suite::Tai).is_some());
+        assert!(verify(&key, b"", &ell2, Suite::Ell2).is_some());
+    }
+}
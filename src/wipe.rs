@@ -0,0 +1,54 @@
+// Wiping buffers which held secret-derived data, with the `zeroize`
+// feature, and doing nothing without it.
+//
+// With the feature, each of these is wiped once it is no longer needed:
+//
+//    ed25519        `ExpandedSecretKey`, on drop, the hash it is expanded
+//                   from, the nonce in `sign`, and the random coefficients
+//                   of `verify_batch`
+//    blinding       the blinding factor h, everywhere it is used here
+//    slip10         `ExtendedSecretKey`, on drop, and the HMAC pads and
+//                   outputs its derivation goes through
+//    formats        the seed copied out of a PKCS#8 document, and
+//                   `TorSecretKey`, on drop
+//    params         the buffers scalars are reduced and hashed through
+//
+// The hash states these pass through can't be reached to be wiped, and a
+// `Scalar` or array returned to the caller is the caller's to wipe.
+
+#[cfg(feature = "std")]
+use curve25519_dalek::scalar::Scalar;
+
+/// Overwrite `bytes` with zeroes, in a way the compiler won't elide.
+#[cfg(feature = "zeroize")]
+pub(crate) fn wipe(bytes: &mut [u8]) {
+    ::zeroize::Zeroize::zeroize(bytes)
+}
+
+#[cfg(not(feature = "zeroize"))]
+#[inline(always)]
+pub(crate) fn wipe(_bytes: &mut [u8]) {}
+
+/// Overwrite `scalar` with zero.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub(crate) fn wipe_scalar(scalar: &mut Scalar) {
+    wipe(&mut scalar.0)
+}
+
+#[cfg(all(test, feature = "zeroize", not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffers_are_wiped() {
+        let mut bytes: [u8; 64] = [0xa5; 64];
+        let mut scalar: Scalar = Scalar::from_u64(22006);
+
+        wipe(&mut bytes);
+        wipe_scalar(&mut scalar);
+
+        assert_eq!(&bytes[..], &[0u8; 64][..]);
+        assert_eq!(scalar.0, [0u8; 32]);
+    }
+}
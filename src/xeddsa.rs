@@ -0,0 +1,138 @@
+// XEd25519 signatures, as Signal makes them, verified against a curve25519
+// key which has already been through the checks an ntor key gets.
+//
+// XEdDSA signs with a Montgomery key u by taking the Edwards point with
+// that u and the sign bit clear as the public key A, and the secret as
+// whichever of ±k has [a]B = A, and otherwise signs exactly as ed25519
+// does, with
+//
+//    h = SHA-512(R ‖ A ‖ M) mod l,   S = r + ha mod l
+//
+// so a signature is verified by converting u to A and checking an ed25519
+// signature under it.  Only u is published, so the sign of A is not the
+// signer's to choose, and nothing converts until `NtorKey::from_bytes` has
+// refused a u which is unreduced, not on the curve, the identity, or has a
+// torsion component: A then has none either, and the choice of profile
+// only decides what is demanded of R.
+//
+// The XEdDSA specification verifies cofactorless, but compares R with the
+// encoding of [S]B - [h]A rather than comparing points, so it also rejects
+// an unreduced R, and since A is torsion-free here, that accepts what
+// `VerificationProfile::Strict` does.  It requires S < 2^253 where every
+// profile here requires S < l, which only refuses signatures no honest
+// signer makes.
+
+use curve25519_dalek::curve::CompressedEdwardsY;
+use curve25519_dalek::curve::ExtendedPoint;
+
+use ed25519::verify;
+use policy::VerificationProfile;
+use roles::NtorKey;
+
+/// The Edwards key XEdDSA signatures under `key` are made with: the point
+/// with u-coordinate `key` whose sign bit is clear.
+pub fn xeddsa_public_key(key: &NtorKey) -> CompressedEdwardsY {
+    let p: ExtendedPoint = match key.u().decompress() {
+        Some(p) => p,
+        None    => unreachable!(), // an NtorKey always decompresses
+    };
+    let mut encoding: [u8; 32] = p.compress_edwards().to_bytes();
+    encoding[31] &= 0x7f;
+
+    CompressedEdwardsY(encoding)
+}
+
+/// Whether `signature` is an XEd25519 signature on `message` under `key`,
+/// checked under `profile`.
+pub fn verify_xeddsa(key: &NtorKey, message: &[u8], signature: &[u8; 64], profile: VerificationProfile) -> bool {
+    verify(&xeddsa_public_key(key), message, signature, profile)
+}
+
+#[cfg(all(test, not(feature = "bench")))]
+mod test {
+    use super::*;
+
+    use curve25519_dalek::constants;
+    use curve25519_dalek::scalar::Scalar;
+
+    use key::KeyProblem;
+    use params::hash_to_scalar;
+
+    static PROFILES: [VerificationProfile; 3] = [
+        VerificationProfile::Cofactored,
+        VerificationProfile::Cofactorless,
+        VerificationProfile::Strict,
+    ];
+
+    // The Montgomery key for the secret k, and an XEdDSA signature on
+    // `message` with it, with a deterministic nonce in place of the
+    // specification's random one.
+    fn sign(k: u64, message: &[u8]) -> (NtorKey, [u8; 64]) {
+        let k: Scalar = Scalar::from_u64(k);
+        let e: ExtendedPoint = &k * &constants::ED25519_BASEPOINT;
+        let key: NtorKey = NtorKey::from_bytes(&e.compress_montgomery().unwrap().to_bytes()).unwrap();
+
+        let a: Scalar = match e.compress_edwards().as_bytes()[31] >> 7 {
+            0 => k,
+            _ => -&k, // so that [a]B has its sign bit clear
+        };
+        let public: CompressedEdwardsY = (&a * &constants::ED25519_BASEPOINT).compress_edwards();
+        let r: Scalar = hash_to_scalar(&[a.as_bytes(), message]);
+        let r_key: CompressedEdwardsY = (&r * &constants::ED25519_BASEPOINT).compress_edwards();
+        let h: Scalar = hash_to_scalar(&[r_key.as_bytes(), public.as_bytes(), message]);
+        let mut signature: [u8; 64] = [0u8; 64];
+
+        signature[..32].copy_from_slice(r_key.as_bytes());
+        signature[32..].copy_from_slice(Scalar::multiply_add(&h, &a, &r).as_bytes());
+        (key, signature)
+    }
+
+    #[test]
+    fn signatures_verify_under_every_profile() {
+        let mut negated: usize = 0;
+
+        for k in 1..9u64 {
+            let (key, signature): (NtorKey, [u8; 64]) = sign(k, b"tor22006");
+            let e: CompressedEdwardsY = (&Scalar::from_u64(k) * &constants::ED25519_BASEPOINT).compress_edwards();
+
+            negated += (e != xeddsa_public_key(&key)) as usize;
+            for &profile in PROFILES.iter() {
+                assert!(verify_xeddsa(&key, b"tor22006", &signature, profile), "k = {}", k);
+                assert!(!verify_xeddsa(&key, b"tor22007", &signature, profile), "k = {}", k);
+            }
+        }
+        // Both signs of A turned up, so both ways of choosing a did.
+        assert!(negated > 0 && negated < 8);
+    }
+
+    #[test]
+    fn public_keys_have_their_sign_bit_clear() {
+        for k in 1..9u64 {
+            let (key, _): (NtorKey, [u8; 64]) = sign(k, b"");
+            let public: CompressedEdwardsY = xeddsa_public_key(&key);
+
+            assert_eq!(public.as_bytes()[31] >> 7, 0);
+            assert_eq!(public.decompress().unwrap().compress_montgomery().unwrap(), *key.u());
+        }
+    }
+
+    #[test]
+    fn signatures_with_the_sign_bit_in_s_are_rejected() {
+        // Older Signal code carried the sign of A in the top bit of S.
+        let (key, mut signature): (NtorKey, [u8; 64]) = sign(22006, b"tor22006");
+        signature[63] |= 0x80;
+
+        for &profile in PROFILES.iter() {
+            assert!(!verify_xeddsa(&key, b"tor22006", &signature, profile));
+        }
+    }
+
+    #[test]
+    fn torsioned_keys_never_convert() {
+        let torsioned: ExtendedPoint = &(&Scalar::from_u64(22006) * &constants::ED25519_BASEPOINT) +
+                                       &constants::EIGHT_TORSION[1];
+        let u: [u8; 32] = torsioned.compress_montgomery().unwrap().to_bytes();
+
+        assert_eq!(NtorKey::from_bytes(&u), Err(KeyProblem::Torsioned));
+    }
+}